@@ -0,0 +1,390 @@
+use clap::{Args, Parser, Subcommand};
+use grpc_bridge_core::fuzz::FuzzParams;
+use grpc_bridge_core::grpcurl::GrpcurlConfig;
+use grpc_bridge_core::payload::format::{self, PayloadFormat};
+use grpc_bridge_core::payload::json_output::{Int64Format, JsonOptions};
+use grpc_bridge_core::payload::raw;
+use grpc_bridge_core::storage::Storage;
+use grpc_bridge_core::{secrets, Bridge, BridgeError, BridgeResult, RunParams};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// gRPC Bridge without the window: shares proto roots, environments, collections and history
+/// with the desktop app.
+#[derive(Parser)]
+#[command(name = "grpc-bridge-cli", version)]
+struct Cli {
+    /// Database to use instead of the desktop app's.
+    #[arg(long, global = true)]
+    db: Option<PathBuf>,
+    /// grpcurl binary to use instead of the one found on PATH.
+    #[arg(long, global = true)]
+    grpcurl: Option<String>,
+    /// Hex SHA-256 the grpcurl binary must match.
+    #[arg(long, global = true)]
+    grpcurl_sha256: Option<String>,
+    /// Print results as JSON.
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Register a proto root (if needed) and index its .proto files.
+    Scan { path: PathBuf },
+    /// List indexed services and methods.
+    List {
+        /// Proto root id or path.
+        #[arg(long)]
+        root: Option<String>,
+    },
+    /// Print a JSON Schema (draft 2020-12) for a message type.
+    JsonSchema {
+        /// Fully qualified message name, e.g. `shop.v1.Order`.
+        message: String,
+    },
+    /// Decode protobuf bytes without a schema, like `protoc --decode_raw`.
+    DecodeRaw {
+        /// The bytes as base64 or hex (see `--format`), or `@file` to read them raw from a file.
+        data: String,
+        /// How `data` is written: binary_base64 or hex.
+        #[arg(long, default_value = "binary_base64")]
+        format: PayloadFormat,
+        /// Also decode the bytes as this indexed message type, e.g. `shop.v1.Order`.
+        #[arg(long)]
+        message: Option<String>,
+    },
+    /// Send a unary request.
+    Call {
+        target: String,
+        /// `package.Service/Method`.
+        method: String,
+        /// Request body as JSON, or `@file` to read it from a file.
+        #[arg(short, long, default_value = "{}")]
+        data: String,
+        /// Proto root id or path; its files are used as import path.
+        #[arg(long)]
+        root: Option<String>,
+        /// Proto file relative to the root; may be repeated.
+        #[arg(long = "proto")]
+        proto_files: Vec<String>,
+        /// Check the payload against the indexed input message before sending it.
+        #[arg(long)]
+        validate: bool,
+        /// Format of `--data`: json, text, binary_base64 or hex. Other formats than JSON need
+        /// the method to be indexed.
+        #[arg(long, default_value = "json")]
+        payload_format: PayloadFormat,
+        /// Format to print the response in: json, text, binary_base64 or hex.
+        #[arg(long, default_value = "json")]
+        response_format: PayloadFormat,
+        /// Print unset fields at their default value.
+        #[arg(long)]
+        emit_defaults: bool,
+        /// Name fields as in the .proto file instead of lowerCamelCase.
+        #[arg(long)]
+        proto_names: bool,
+        /// Print enum values as numbers.
+        #[arg(long)]
+        enums_as_numbers: bool,
+        /// How to print 64-bit integers: string, number, or lossless (numbers up to 2^53, strings beyond).
+        #[arg(long, default_value = "string")]
+        int64: Int64Format,
+        #[command(flatten)]
+        conn: Connection,
+    },
+    /// Send mutated payloads to a method and save the ones that break the server to a collection.
+    Fuzz {
+        target: String,
+        /// `package.Service/Method`; must be indexed.
+        method: String,
+        /// Proto root id or path; its files are used as import path.
+        #[arg(long)]
+        root: Option<String>,
+        /// Proto file relative to the root; may be repeated.
+        #[arg(long = "proto")]
+        proto_files: Vec<String>,
+        /// Seed of the base payload; random by default.
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Send at most this many cases.
+        #[arg(long)]
+        max_cases: Option<usize>,
+        /// Collection id or name for the reproducers; a new collection by default.
+        #[arg(long)]
+        collection: Option<String>,
+        #[command(flatten)]
+        conn: Connection,
+    },
+    /// Run a saved collection (or one folder of it) and check its assertions.
+    RunCollection {
+        /// Collection id or name.
+        collection: String,
+        /// Folder id, name or `/` separated path inside the collection.
+        #[arg(long)]
+        folder: Option<String>,
+        /// Write a JUnit report to this file.
+        #[arg(long)]
+        junit: Option<PathBuf>,
+        /// Environment id or name; defaults to the active environment.
+        #[arg(long)]
+        env: Option<String>,
+    },
+    /// Query a server through gRPC reflection: services, or the description of a symbol.
+    Reflect {
+        target: String,
+        symbol: Option<String>,
+        #[command(flatten)]
+        conn: Connection,
+    },
+}
+
+#[derive(Args)]
+struct Connection {
+    /// Metadata as `Key: Value`; may be repeated.
+    #[arg(short = 'H', long = "header")]
+    headers: Vec<String>,
+    /// Force TLS (default: implied by the target scheme).
+    #[arg(long, conflicts_with = "plaintext")]
+    tls: bool,
+    #[arg(long)]
+    plaintext: bool,
+    /// Environment id or name; defaults to the active environment.
+    #[arg(long)]
+    env: Option<String>,
+}
+
+impl Connection {
+    fn tls(&self) -> Option<bool> {
+        if self.tls { Some(true) } else if self.plaintext { Some(false) } else { None }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    tracing_subscriber::fmt().with_env_filter(tracing_subscriber::EnvFilter::from_default_env()).with_writer(std::io::stderr).init();
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Returns whether the command succeeded; errors mean it could not run at all.
+async fn run(cli: Cli) -> BridgeResult<bool> {
+    let db = cli.db.or_else(Storage::default_path).ok_or_else(|| BridgeError::Storage("no app data directory".into()))?;
+    let storage = Storage::open(&db)?;
+    let secrets = secrets::open_default(db.parent(), secrets::default_key_dir().as_deref());
+    let bridge = Bridge::load(storage, secrets, None)?;
+    // flags override the saved config for this run only
+    let saved = bridge.engine().grpcurl_config();
+    bridge.engine().set_grpcurl_config(GrpcurlConfig {
+        path: cli.grpcurl.or(saved.path),
+        expected_sha256: cli.grpcurl_sha256.or(saved.expected_sha256),
+    });
+    let json = cli.json;
+    match cli.command {
+        Command::Scan { path } => {
+            let path = path.canonicalize().map_err(|e| BridgeError::InvalidArgument(format!("{}: {}", path.display(), e)))?;
+            let path = path.to_string_lossy().to_string();
+            let root_id = match bridge.index().roots().into_iter().find(|r| r.path == path) {
+                Some(root) => root.id,
+                None => bridge.index().register_root(&path)?,
+            };
+            let scan = bridge.index().scan_root(&root_id).await?;
+            if json {
+                print_json(&serde_json::json!({ "root_id": root_id, "files": scan.files, "services": scan.services }));
+            } else {
+                println!("{} ({} files, {} services)", root_id, scan.files.len(), scan.services.len());
+            }
+        }
+        Command::List { root } => {
+            let root_id = root.map(|r| find_root(&bridge, &r)).transpose()?;
+            let services = bridge.index().services(root_id.as_deref());
+            if json { print_json(&services); }
+            for svc in services.iter().filter(|_| !json) {
+                println!("{}", svc.fq_service);
+                for m in &svc.methods {
+                    println!("  {}({}) returns ({}){}", m.name, m.input_type, m.output_type, if m.streaming { " [streaming]" } else { "" });
+                }
+            }
+        }
+        Command::JsonSchema { message } => print_json(&bridge.index().json_schema(&message)?),
+        Command::DecodeRaw { data, format, message } => {
+            let bytes = match data.strip_prefix('@') {
+                Some(file) => std::fs::read(file).map_err(|e| BridgeError::InvalidArgument(format!("{}: {}", file, e)))?,
+                None => format::decode_binary(format, &data).map_err(|reason| BridgeError::PayloadFormat { format: format.to_string(), reason })?,
+            };
+            let decoding = bridge.index().decode_raw(&bytes, message.as_deref())?;
+            if json {
+                print_json(&decoding);
+            } else if let Some(decoded) = &decoding.decoded {
+                print_json(decoded);
+            } else {
+                print!("{}", raw::print(&decoding.fields));
+                if !decoding.candidates.is_empty() { eprintln!("also decodes as: {}", decoding.candidates.join(", ")); }
+            }
+        }
+        Command::Call { target, method, data, root, proto_files, validate, payload_format, response_format, emit_defaults, proto_names, enums_as_numbers, int64, conn } => {
+            let (service, method) = split_method(&method)?;
+            let payload = match data.strip_prefix('@') {
+                Some(file) => std::fs::read_to_string(file).map_err(|e| BridgeError::InvalidArgument(format!("{}: {}", file, e)))?,
+                None => data,
+            };
+            let params = RunParams {
+                target,
+                service: service.to_string(),
+                method: method.to_string(),
+                payload,
+                proto_files,
+                root_id: root.map(|r| find_root(&bridge, &r)).transpose()?,
+                headers: Some(conn.headers.clone()),
+                tls: conn.tls(),
+                environment_id: conn.env.as_deref().map(|e| find_environment(&bridge, e)).transpose()?,
+                extractions: Vec::new(),
+                validate,
+                payload_format,
+                response_format,
+                json_options: JsonOptions { emit_defaults, proto_names, enums_as_numbers, int64 },
+            };
+            let done = bridge.call(params).await?;
+            let outcome = done.result?;
+            if json {
+                print_json(&serde_json::json!({
+                    "status": outcome.status,
+                    "parsed": done.parsed,
+                    "raw": done.raw,
+                    "headers": done.headers,
+                    "trailers": done.trailers,
+                    "took_ms": done.took_ms,
+                    "history_id": done.history_id,
+                    "rendered": done.rendered,
+                    "render_error": done.render_error,
+                    "error": outcome.error().map(|e| e.to_string())
+                }));
+            } else if let Some(err) = outcome.error() {
+                eprintln!("{}: {}", outcome.status, err);
+            } else {
+                if let Some(e) = &done.render_error { eprintln!("response shown as JSON: {}", e); }
+                println!("{}", done.rendered.as_deref().unwrap_or(&done.raw));
+            }
+            return Ok(outcome.is_ok());
+        }
+        Command::Fuzz { target, method, root, proto_files, seed, max_cases, collection, conn } => {
+            let (service, method) = split_method(&method)?;
+            let params = FuzzParams {
+                target,
+                service: service.to_string(),
+                method: method.to_string(),
+                proto_files,
+                root_id: root.map(|r| find_root(&bridge, &r)).transpose()?,
+                headers: Some(conn.headers.clone()),
+                tls: conn.tls(),
+                environment_id: conn.env.as_deref().map(|e| find_environment(&bridge, e)).transpose()?,
+                seed,
+                max_cases,
+                collection_id: collection.map(|c| find_collection(&bridge, &c)).transpose()?,
+            };
+            let report = bridge.fuzz(params, |case| {
+                if !json { println!("{} {} ({}, {} ms)", if case.finding { "FOUND" } else { "ok" }, case.mutation, case.status, case.took_ms); }
+            }).await?;
+            if json {
+                print_json(&report);
+            } else {
+                for f in &report.findings { println!("{} {}: {}", f.status, f.mutation, f.message); }
+                println!("{} cases, {} findings, seed {}, {} ms", report.cases, report.findings.len(), report.seed, report.took_ms);
+                if report.stopped_early { println!("stopped early: the target stopped accepting connections"); }
+                if let Some(id) = &report.collection_id { println!("reproducers saved to collection {}", id); }
+            }
+            return Ok(report.findings.is_empty());
+        }
+        Command::RunCollection { collection, folder, junit, env } => {
+            let collection_id = find_collection(&bridge, &collection)?;
+            let folder_id = folder.map(|f| find_folder(&bridge, &collection_id, &f)).transpose()?;
+            let environment_id = env.as_deref().map(|e| find_environment(&bridge, e)).transpose()?;
+            let summary = bridge.run_collection(&collection_id, folder_id.as_deref(), environment_id, |step| {
+                if json { return; }
+                let name = if step.folder.is_empty() { step.name.clone() } else { format!("{}/{}", step.folder, step.name) };
+                println!("{} {} ({}, {} ms)", if step.passed { "PASS" } else { "FAIL" }, name, step.status, step.took_ms);
+                if let Some(e) = &step.error { println!("    {}", e); }
+                for a in step.assertions.iter().filter(|a| !a.passed) {
+                    println!("    {}", a.message.as_deref().unwrap_or("assertion failed"));
+                }
+            }).await?;
+            if let Some(path) = junit {
+                std::fs::write(&path, summary.to_junit()).map_err(|e| BridgeError::Storage(format!("{}: {}", path.display(), e)))?;
+            }
+            if json { print_json(&summary); } else { println!("{} passed, {} failed, {} ms", summary.passed, summary.failed, summary.took_ms); }
+            return Ok(summary.failed == 0);
+        }
+        Command::Reflect { target, symbol, conn } => {
+            let environment_id = conn.env.as_deref().map(|e| find_environment(&bridge, e)).transpose()?;
+            let out = bridge.reflect(&target, conn.tls(), &conn.headers, environment_id, symbol.as_deref()).await?;
+            if json { print_json(&out.lines().collect::<Vec<_>>()); } else { print!("{}", out); }
+        }
+    }
+    Ok(true)
+}
+
+fn print_json<T: serde::Serialize>(value: &T) {
+    println!("{}", serde_json::to_string_pretty(value).unwrap_or_default());
+}
+
+fn split_method(method: &str) -> BridgeResult<(&str, &str)> {
+    method.rsplit_once('/').ok_or_else(|| BridgeError::InvalidArgument(format!("expected package.Service/Method, got {:?}", method)))
+}
+
+fn find_root(bridge: &Bridge, id_or_path: &str) -> BridgeResult<String> {
+    let canonical = Path::new(id_or_path).canonicalize().ok().map(|p| p.to_string_lossy().to_string());
+    bridge.index().roots().into_iter()
+        .find(|r| r.id == id_or_path || r.path == id_or_path || Some(&r.path) == canonical.as_ref())
+        .map(|r| r.id)
+        .ok_or_else(|| BridgeError::RootNotFound(id_or_path.to_string()))
+}
+
+fn find_environment(bridge: &Bridge, id_or_name: &str) -> BridgeResult<String> {
+    let entries = bridge.storage().list_environments()?.into_iter().map(|e| (e.id, e.name)).collect();
+    find_by_id_or_name("environments", id_or_name, entries, BridgeError::EnvironmentNotFound)
+}
+
+fn find_collection(bridge: &Bridge, id_or_name: &str) -> BridgeResult<String> {
+    let entries = bridge.storage().list_collections()?.into_iter().map(|t| (t.collection.id, t.collection.name)).collect();
+    find_by_id_or_name("collections", id_or_name, entries, BridgeError::CollectionNotFound)
+}
+
+/// Picks one of `(id, name)` `entries`. Names may repeat (each fuzz run adds a "Fuzz findings"
+/// collection), so an exact id wins and a name shared by several entries is rejected with
+/// their ids.
+fn find_by_id_or_name(kind: &str, id_or_name: &str, entries: Vec<(String, String)>, not_found: fn(String) -> BridgeError) -> BridgeResult<String> {
+    if entries.iter().any(|(id, _)| id == id_or_name) { return Ok(id_or_name.to_string()); }
+    let ids: Vec<String> = entries.into_iter().filter(|(_, name)| name == id_or_name).map(|(id, _)| id).collect();
+    match &ids[..] {
+        [id] => Ok(id.clone()),
+        [] => Err(not_found(id_or_name.to_string())),
+        _ => Err(BridgeError::InvalidArgument(format!("{:?} matches several {}, pass one of their ids: {}", id_or_name, kind, ids.join(", ")))),
+    }
+}
+
+/// Folder names may repeat, even among siblings, so a name or path that matches several folders
+/// is rejected with the list of matches.
+fn find_folder(bridge: &Bridge, collection_id: &str, id_or_name: &str) -> BridgeResult<String> {
+    let tree = bridge.storage().collection_tree(collection_id)?;
+    if tree.folders.iter().any(|f| f.id == id_or_name) { return Ok(id_or_name.to_string()); }
+    let path = id_or_name.trim_matches('/');
+    let by_path: Vec<_> = tree.folders.iter().filter(|f| tree.folder_path(&f.id).is_ok_and(|p| p == path)).collect();
+    let found = if by_path.is_empty() { tree.folders.iter().filter(|f| f.name == path).collect() } else { by_path };
+    match found[..] {
+        [f] => Ok(f.id.clone()),
+        [] => Err(BridgeError::FolderNotFound(id_or_name.to_string())),
+        _ => {
+            let listing: Vec<String> = found.iter().map(|f| format!("{} ({})", f.id, tree.folder_path(&f.id).unwrap_or_default())).collect();
+            Err(BridgeError::InvalidArgument(format!("{:?} matches several folders, pass one of their ids: {}", id_or_name, listing.join(", "))))
+        }
+    }
+}
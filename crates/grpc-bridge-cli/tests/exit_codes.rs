@@ -0,0 +1,141 @@
+#![cfg(unix)]
+
+use grpc_bridge_core::payload::format::PayloadFormat;
+use grpc_bridge_core::storage::{SavedRequest, Storage};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A grpcurl that answers `{}` for targets on `up.test` and fails to dial everything else.
+const FAKE_GRPCURL: &str = r#"#!/bin/sh
+if [ "$1" = "-version" ]; then echo "grpcurl v1.9.1"; exit 0; fi
+cat >/dev/null
+prev=""; addr=""
+for a in "$@"; do [ "$prev" = "--" ] && addr="$a"; prev="$a"; done
+case "$addr" in
+    up.test:*) printf 'Response headers received:\n(empty)\n\nResponse contents:\n{}\n\nResponse trailers received:\n(empty)\nSent 1 request and received 1 response\n' ;;
+    *) echo "Failed to dial target host \"$addr\": connection refused" >&2; exit 1 ;;
+esac
+"#;
+
+struct Fixture {
+    dir: tempfile::TempDir,
+    grpcurl: PathBuf,
+}
+
+impl Fixture {
+    /// A database with collection `smoke`: `api/up` (passes), `api/down`, `other/up` (named
+    /// like the folder inside `api`) and two empty folders both at `api/twin`.
+    fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let grpcurl = dir.path().join("grpcurl");
+        std::fs::write(&grpcurl, FAKE_GRPCURL).unwrap();
+        std::fs::set_permissions(&grpcurl, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let storage = Storage::open(&dir.path().join("bridge.sqlite3")).unwrap();
+        let smoke = storage.create_collection("smoke").unwrap();
+        let api = storage.create_folder(&smoke.id, None, "api").unwrap();
+        let up = storage.create_folder(&smoke.id, Some(&api.id), "up").unwrap();
+        let down = storage.create_folder(&smoke.id, Some(&api.id), "down").unwrap();
+        storage.create_folder(&smoke.id, Some(&api.id), "twin").unwrap();
+        storage.create_folder(&smoke.id, Some(&api.id), "twin").unwrap();
+        let other = storage.create_folder(&smoke.id, None, "other").unwrap();
+        storage.create_folder(&smoke.id, Some(&other.id), "up").unwrap();
+        for (folder, target) in [(&up.id, "up.test:1"), (&down.id, "down.test:1")] {
+            storage.save_request(&request(&smoke.id, folder, target)).unwrap();
+        }
+        Self { dir, grpcurl }
+    }
+
+    fn db(&self) -> PathBuf { self.dir.path().join("bridge.sqlite3") }
+
+    /// Exit code of the CLI run against the fixture database.
+    fn run(&self, args: &[&str]) -> i32 {
+        let out = Command::new(env!("CARGO_BIN_EXE_grpc-bridge-cli"))
+            .arg("--db").arg(self.db())
+            .arg("--grpcurl").arg(&self.grpcurl)
+            .args(args)
+            .env("XDG_CONFIG_HOME", self.dir.path().join("config"))
+            .output()
+            .unwrap();
+        out.status.code().unwrap()
+    }
+}
+
+fn request(collection_id: &str, folder_id: &str, target: &str) -> SavedRequest {
+    SavedRequest {
+        id: String::new(),
+        collection_id: collection_id.into(),
+        folder_id: Some(folder_id.into()),
+        name: "ping".into(),
+        root_id: None,
+        fq_service: "health.v1.Health".into(),
+        method: "Check".into(),
+        target: target.into(),
+        headers: Vec::new(),
+        payload: "{}".into(),
+        proto_files: Vec::new(),
+        tls: Some(false),
+        extractions: Vec::new(),
+        assertions: Vec::new(),
+        payload_format: PayloadFormat::Json,
+        response_format: PayloadFormat::Json,
+        json_options: Default::default(),
+    }
+}
+
+fn folder_id(db: &Path, path: &str) -> String {
+    let tree = Storage::open(db).unwrap().list_collections().unwrap().remove(0);
+    tree.folders.iter().find(|f| tree.folder_path(&f.id).unwrap() == path).unwrap().id.clone()
+}
+
+#[test]
+fn calls_exit_with_0_on_success_and_1_on_failure() {
+    let f = Fixture::new();
+    assert_eq!(f.run(&["call", "up.test:1", "health.v1.Health/Check"]), 0);
+    assert_eq!(f.run(&["call", "down.test:1", "health.v1.Health/Check"]), 1);
+}
+
+#[test]
+fn collection_runs_exit_with_1_when_a_step_fails() {
+    let f = Fixture::new();
+    assert_eq!(f.run(&["run-collection", "smoke"]), 1);
+    assert_eq!(f.run(&["run-collection", "smoke", "--folder", "api/up"]), 0);
+    assert_eq!(f.run(&["run-collection", "smoke", "--folder", "down"]), 1);
+}
+
+#[test]
+fn folders_are_found_by_id_name_or_path() {
+    let f = Fixture::new();
+    let up = folder_id(&f.db(), "api/up");
+    assert_eq!(f.run(&["run-collection", "smoke", "--folder", &up]), 0);
+    assert_eq!(f.run(&["run-collection", "smoke", "--folder", "/api/up/"]), 0);
+    // `up` exists in `api` and in `other`
+    assert_eq!(f.run(&["run-collection", "smoke", "--folder", "up"]), 2);
+    assert_eq!(f.run(&["run-collection", "smoke", "--folder", "other/up"]), 0);
+    assert_eq!(f.run(&["run-collection", "smoke", "--folder", "api/twin"]), 2);
+}
+
+#[test]
+fn collections_and_environments_sharing_a_name_need_an_id() {
+    let f = Fixture::new();
+    let storage = Storage::open(&f.db()).unwrap();
+    let smoke = storage.list_collections().unwrap().remove(0).collection.id;
+    storage.create_collection("smoke").unwrap();
+    let dev = storage.create_environment("dev").unwrap();
+    storage.create_environment("dev").unwrap();
+    drop(storage);
+    assert_eq!(f.run(&["run-collection", "smoke"]), 2);
+    assert_eq!(f.run(&["run-collection", &smoke, "--folder", "api/up"]), 0);
+    assert_eq!(f.run(&["call", "up.test:1", "health.v1.Health/Check", "--env", "dev"]), 2);
+    assert_eq!(f.run(&["call", "up.test:1", "health.v1.Health/Check", "--env", &dev.id]), 0);
+}
+
+#[test]
+fn commands_that_cannot_run_exit_with_2() {
+    let f = Fixture::new();
+    assert_eq!(f.run(&["run-collection", "nope"]), 2);
+    assert_eq!(f.run(&["run-collection", "smoke", "--folder", "nope"]), 2);
+    assert_eq!(f.run(&["call", "up.test:1", "no-slash"]), 2);
+    assert_eq!(f.run(&["call", "up.test:1", "health.v1.Health/Check", "--env", "nope"]), 2);
+}
@@ -0,0 +1,149 @@
+use crate::call::CallOutcome;
+use crate::extract::metadata_value;
+use crate::jsonpath::{self, JsonPath};
+use crate::payload::rules;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A check on a response, stored with a saved request and evaluated by `run_collection`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Assertion {
+    /// gRPC status by name (`OK`, `NOT_FOUND`, `NotFound`) or number (`5`).
+    Status { code: String },
+    /// The first match of `path` equals `value`. Numbers also match their string form, since
+    /// 64-bit integers are strings in protobuf JSON.
+    JsonEquals { path: String, value: Value },
+    /// The first match of `path`, as text, matches the regular expression `pattern`.
+    JsonMatches { path: String, pattern: String },
+    LatencyBelow { ms: u64 },
+    /// A response header or trailer with this key is present.
+    MetadataPresent { key: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssertionResult {
+    pub assertion: Assertion,
+    pub passed: bool,
+    /// What was found, for failures.
+    pub message: Option<String>,
+}
+
+const STATUS_CODES: [&str; 17] = [
+    "OK", "CANCELLED", "UNKNOWN", "INVALID_ARGUMENT", "DEADLINE_EXCEEDED", "NOT_FOUND", "ALREADY_EXISTS",
+    "PERMISSION_DENIED", "RESOURCE_EXHAUSTED", "FAILED_PRECONDITION", "ABORTED", "OUT_OF_RANGE",
+    "UNIMPLEMENTED", "INTERNAL", "UNAVAILABLE", "DATA_LOSS", "UNAUTHENTICATED",
+];
+
+/// `NotFound`, `NOT_FOUND`, `not_found` and `5` all become `notfound`.
+fn normalize_status(code: &str) -> String {
+    let code = code.trim();
+    let name = code.parse::<usize>().ok().and_then(|n| STATUS_CODES.get(n).copied()).unwrap_or(code);
+    name.chars().filter(|c| *c != '_').collect::<String>().to_ascii_lowercase()
+}
+
+pub fn has_status_assertion(assertions: &[Assertion]) -> bool {
+    assertions.iter().any(|a| matches!(a, Assertion::Status { .. }))
+}
+
+pub fn evaluate(assertions: &[Assertion], outcome: &CallOutcome) -> Vec<AssertionResult> {
+    let body = outcome.parsed();
+    assertions.iter().map(|a| {
+        let check = check(a, outcome, body.as_ref());
+        AssertionResult { assertion: a.clone(), passed: check.is_ok(), message: check.err() }
+    }).collect()
+}
+
+fn check(assertion: &Assertion, outcome: &CallOutcome, body: Option<&Value>) -> Result<(), String> {
+    let first = |path: &str| -> Result<Value, String> {
+        let p = JsonPath::parse(path)?;
+        let body = body.ok_or("response body is not JSON")?;
+        p.first(body).cloned().ok_or_else(|| format!("{} matched nothing", path))
+    };
+    match assertion {
+        Assertion::Status { code } => {
+            if normalize_status(code) == normalize_status(&outcome.status) { Ok(()) }
+            else { Err(format!("expected status {}, got {}", code, outcome.status)) }
+        }
+        Assertion::JsonEquals { path, value } => {
+            let found = first(path)?;
+            if found == *value || jsonpath::to_text(&found) == jsonpath::to_text(value) { Ok(()) }
+            else { Err(format!("{}: expected {}, got {}", path, value, found)) }
+        }
+        Assertion::JsonMatches { path, pattern } => {
+            let re = rules::pattern(pattern).map_err(|e| format!("invalid pattern {:?}: {}", pattern, e))?;
+            let found = jsonpath::to_text(&first(path)?);
+            if re.is_match(&found) { Ok(()) } else { Err(format!("{}: {:?} does not match {:?}", path, found, pattern)) }
+        }
+        Assertion::LatencyBelow { ms } => {
+            if outcome.took_ms < u128::from(*ms) { Ok(()) }
+            else { Err(format!("took {} ms, limit {} ms", outcome.took_ms, ms)) }
+        }
+        Assertion::MetadataPresent { key } => {
+            if metadata_value(&outcome.headers, key).or_else(|| metadata_value(&outcome.trailers, key)).is_some() { Ok(()) }
+            else { Err(format!("no header or trailer {:?}", key)) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn outcome(status: &str, body: Value) -> CallOutcome {
+        CallOutcome {
+            status: status.into(),
+            messages: vec![body.to_string()],
+            headers: vec![("x-request-id".into(), "r1".into())],
+            trailers: Vec::new(),
+            stderr: String::new(),
+            exit_code: Some(if status == "OK" { 0 } else { 1 }),
+            took_ms: 40,
+        }
+    }
+
+    fn messages(assertions: &[Assertion], outcome: &CallOutcome) -> Vec<Option<String>> {
+        evaluate(assertions, outcome).into_iter().map(|r| { assert_eq!(r.passed, r.message.is_none()); r.message }).collect()
+    }
+
+    #[test]
+    fn status_names_and_numbers_are_interchangeable() {
+        assert_eq!(normalize_status("5"), "notfound");
+        assert_eq!(normalize_status(" NOT_FOUND "), normalize_status("NotFound"));
+        assert_eq!(normalize_status("99"), "99");
+        let not_found = outcome("NotFound", json!({}));
+        let status = |code: &str| Assertion::Status { code: code.into() };
+        assert_eq!(messages(&[status("5"), status("NOT_FOUND"), status("not_found")], &not_found), [None, None, None]);
+        assert_eq!(messages(&[status("OK")], &not_found), [Some("expected status OK, got NotFound".into())]);
+    }
+
+    #[test]
+    fn numbers_equal_their_string_form() {
+        let body = outcome("OK", json!({ "id": "7", "count": 3, "name": "ada" }));
+        let equals = |path: &str, value: Value| Assertion::JsonEquals { path: path.into(), value };
+        assert_eq!(messages(&[equals("$.id", json!(7)), equals("$.count", json!("3")), equals("$.name", json!("ada"))], &body), [None, None, None]);
+        assert_eq!(messages(&[equals("$.id", json!(8))], &body), [Some("$.id: expected 8, got \"7\"".into())]);
+        assert_eq!(messages(&[equals("$.missing", json!(1))], &body), [Some("$.missing matched nothing".into())]);
+    }
+
+    #[test]
+    fn patterns_match_text_and_invalid_ones_fail_the_assertion() {
+        let body = outcome("OK", json!({ "email": "ada@example.com", "id": 42 }));
+        let matches = |path: &str, pattern: &str| Assertion::JsonMatches { path: path.into(), pattern: pattern.into() };
+        assert_eq!(messages(&[matches("$.email", "@example\\.com$"), matches("$.id", "^4[0-9]$")], &body), [None, None]);
+        assert_eq!(messages(&[matches("$.email", "^bob")], &body), [Some("$.email: \"ada@example.com\" does not match \"^bob\"".into())]);
+        let invalid = messages(&[matches("$.email", "(")], &body).remove(0).unwrap();
+        assert!(invalid.starts_with("invalid pattern \"(\": unclosed group"), "{}", invalid);
+    }
+
+    #[test]
+    fn latency_and_metadata_are_checked() {
+        let body = outcome("OK", json!({}));
+        assert_eq!(messages(&[Assertion::LatencyBelow { ms: 50 }, Assertion::MetadataPresent { key: "X-Request-Id".into() }], &body), [None, None]);
+        assert_eq!(messages(&[Assertion::LatencyBelow { ms: 40 }, Assertion::MetadataPresent { key: "x-trace".into() }], &body), [
+            Some("took 40 ms, limit 40 ms".into()),
+            Some("no header or trailer \"x-trace\"".into()),
+        ]);
+    }
+}
@@ -0,0 +1,621 @@
+use crate::call::{CallEngine, CallOutcome};
+use crate::error::{BridgeError, BridgeResult};
+use crate::extract::{self, ExtractionRule};
+use crate::fuzz::{self, FuzzCase, FuzzFinding, FuzzParams, FuzzReport};
+use crate::grpcurl::{self, GrpcurlConfig};
+use crate::payload::format::{self, PayloadFormat};
+use crate::payload::json_output::{self, JsonOptions};
+use crate::payload::mutate;
+use crate::proto_index::ProtoIndex;
+use crate::redact::{self, RedactionConfig, Redactor};
+use crate::runner::{RunSummary, StepResult};
+use crate::secrets::SecretStore;
+use crate::storage::{self, HistoryEntry, SavedRequest, Storage};
+use crate::target::Target;
+use crate::template;
+use crate::workspace::Workspace;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Settings key of the user's [`RedactionConfig`].
+const REDACTION_KEY: &str = "redaction";
+/// Settings key of the user's [`GrpcurlConfig`].
+const GRPCURL_KEY: &str = "grpcurl";
+
+/// A unary call as sent by the UI or the CLI. `target`, `headers` and `payload` may contain
+/// template placeholders.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RunParams {
+    pub target: String,
+    pub service: String,
+    pub method: String,
+    pub payload: String,
+    pub proto_files: Vec<String>,
+    #[serde(alias = "rootId")] pub root_id: Option<String>,
+    pub headers: Option<Vec<String>>, // "Key: Value"
+    pub tls: Option<bool>, // overrides the default implied by the target scheme
+    pub environment_id: Option<String>, // defaults to the active environment
+    #[serde(default)] pub extractions: Vec<ExtractionRule>, // applied to the environment after a successful call
+    #[serde(default)] pub validate: bool, // refuse to send a payload that does not match the input message
+    #[serde(default)] pub payload_format: PayloadFormat, // converted to JSON with the indexed schema before sending
+    #[serde(default)] pub response_format: PayloadFormat, // how `CompletedCall::rendered` shows the response
+    #[serde(default)] pub json_options: JsonOptions, // how `CompletedCall::parsed` and `raw` write the response
+}
+
+impl From<SavedRequest> for RunParams {
+    fn from(req: SavedRequest) -> Self {
+        RunParams {
+            target: req.target,
+            service: req.fq_service,
+            method: req.method,
+            payload: req.payload,
+            proto_files: req.proto_files,
+            root_id: req.root_id,
+            headers: Some(req.headers),
+            tls: req.tls,
+            environment_id: None,
+            extractions: req.extractions,
+            validate: false,
+            payload_format: req.payload_format,
+            response_format: req.response_format,
+            json_options: req.json_options,
+        }
+    }
+}
+
+/// A validated call: placeholders substituted and grpcurl arguments built.
+pub struct PreparedCall {
+    /// With the rendered target.
+    pub params: RunParams,
+    target: Target,
+    tls: bool,
+    headers: Vec<String>,
+    payload: String,
+    args: Vec<String>,
+    /// Includes the secrets substituted into this call.
+    pub redactor: Redactor,
+    environment_id: Option<String>,
+}
+
+/// What a finished call produced. `result` is untouched; the other fields are redacted and
+/// safe to display.
+pub struct CompletedCall {
+    pub result: BridgeResult<CallOutcome>,
+    pub raw: String,
+    pub parsed: Option<serde_json::Value>,
+    pub headers: grpcurl::Metadata,
+    pub trailers: grpcurl::Metadata,
+    pub took_ms: u128,
+    pub history_id: Option<i64>,
+    pub extracted: Vec<String>,
+    pub extraction_errors: Vec<String>,
+    /// The response messages in the call's `response_format`, one per line or block; `None`
+    /// for JSON, which `raw` already is.
+    pub rendered: Option<String>,
+    /// Why the response could not be rendered in `response_format` or with `json_options`.
+    pub render_error: Option<String>,
+    /// The call's redactor plus the values extracted into secrets.
+    pub redactor: Redactor,
+}
+
+/// A [`ProtoIndex`], a [`CallEngine`] and a [`Workspace`] wired together: sends requests with
+/// environment placeholders filled in, records them in history and runs collections. Shared by
+/// the Tauri app and `grpc-bridge-cli`.
+pub struct Bridge {
+    index: ProtoIndex,
+    engine: CallEngine,
+    workspace: Workspace,
+}
+
+impl Bridge {
+    /// Opens the index and workspace persisted in `storage`. `sidecar_dir` is where a bundled
+    /// grpcurl is looked for before `PATH`.
+    pub fn load(storage: Storage, secrets: Box<dyn SecretStore>, sidecar_dir: Option<PathBuf>) -> BridgeResult<Self> {
+        let storage = Arc::new(storage);
+        let engine = CallEngine::new(sidecar_dir);
+        if let Some(config) = storage.get_setting(REDACTION_KEY)? { engine.set_redaction_config(config); }
+        if let Some(config) = storage.get_setting(GRPCURL_KEY)? { engine.set_grpcurl_config(config); }
+        Ok(Self {
+            index: ProtoIndex::load(storage.clone())?,
+            engine,
+            workspace: Workspace::new(storage, secrets),
+        })
+    }
+
+    pub fn index(&self) -> &ProtoIndex { &self.index }
+
+    pub fn engine(&self) -> &CallEngine { &self.engine }
+
+    pub fn workspace(&self) -> &Workspace { &self.workspace }
+
+    /// History, collections and environments live here.
+    pub fn storage(&self) -> &Storage { self.workspace.storage() }
+
+    /// Applies `config` to every later call and keeps it for the next start.
+    pub fn set_redaction_config(&self, config: RedactionConfig) -> BridgeResult<()> {
+        self.storage().set_setting(REDACTION_KEY, &config)?;
+        self.engine.set_redaction_config(config);
+        Ok(())
+    }
+
+    /// Uses `config` to find and verify grpcurl from now on and keeps it for the next start.
+    pub fn set_grpcurl_config(&self, config: GrpcurlConfig) -> BridgeResult<()> {
+        self.storage().set_setting(GRPCURL_KEY, &config)?;
+        self.engine.set_grpcurl_config(config);
+        Ok(())
+    }
+
+    /// Validates `params`, substitutes placeholders and builds the grpcurl arguments. Fails
+    /// early when grpcurl itself is unusable.
+    pub async fn prepare_call(&self, mut params: RunParams) -> BridgeResult<PreparedCall> {
+        let root_dir = params.root_id.as_deref().and_then(|rid| self.index.root_path(rid));
+        let rendered = self.render_params(&params, root_dir.as_deref().map(Path::new))?;
+        params.target = rendered.target;
+        let target = Target::parse(&params.target)
+            .map_err(|e| BridgeError::InvalidTarget { target: params.target.clone(), reason: e.to_string() })?;
+        let tls = params.tls.unwrap_or(target.tls);
+        let payload = match params.payload_format {
+            PayloadFormat::Json => rendered.payload,
+            _ if rendered.payload.trim().is_empty() => String::new(),
+            format => {
+                let (schema, message) = self.index.method_input(params.root_id.as_deref(), &params.service, &params.method)?;
+                format::to_json(&schema, &message, format, &rendered.payload)
+                    .map_err(|reason| BridgeError::PayloadFormat { format: format.to_string(), reason })?
+            }
+        };
+        let payload = grpcurl::effective_payload(&payload).to_string();
+        if params.validate { self.check_payload(&params, &payload)?; }
+        self.engine.resolve_grpcurl().await?;
+        let args = grpcurl::build_args(&grpcurl::CallSpec {
+            target: &target,
+            tls,
+            import_path: root_dir.as_deref(),
+            proto_files: &params.proto_files,
+            headers: &rendered.headers,
+            service: &params.service,
+            method: &params.method,
+        })?;
+        let redactor = self.engine.redactor(rendered.secrets);
+        Ok(PreparedCall { params, target, tls, headers: rendered.headers, payload, args, redactor, environment_id: rendered.environment_id })
+    }
+
+    /// Refuses payloads with schema errors. Methods missing from the index (e.g. only known
+    /// through reflection) cannot be checked and are let through.
+    fn check_payload(&self, params: &RunParams, payload: &str) -> BridgeResult<()> {
+        match self.index.validate_payload(params.root_id.as_deref(), &params.service, &params.method, payload) {
+            Ok(issues) if issues.is_empty() => Ok(()),
+            Ok(issues) => Err(BridgeError::InvalidPayload(issues)),
+            Err(BridgeError::ServiceNotFound(_) | BridgeError::MethodNotFound { .. }) => {
+                warn!(service = %params.service, method = %params.method, "method is not indexed, payload not validated");
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Runs a prepared call, records it in history and applies its extraction rules.
+    pub async fn perform_call(&self, call: &PreparedCall) -> CompletedCall {
+        let redactor = &call.redactor;
+        let params = &call.params;
+        let created_at = storage::now_unix_ms();
+        info!(target = %redactor.text(&call.target.to_string()), tls = call.tls, "parsed target for grpcurl");
+        let result = self.engine.unary(&call.args, &call.payload, redactor).await;
+        let mut entry = HistoryEntry {
+            id: 0,
+            created_at,
+            target: redactor.text(&params.target),
+            service: params.service.clone(),
+            method: params.method.clone(),
+            headers: redactor.headers(&call.headers),
+            payload: redactor.json_str(&call.payload),
+            response: None,
+            status: String::new(),
+            error: None,
+            metadata: serde_json::json!({}),
+            took_ms: 0,
+            root_id: params.root_id.clone(),
+            proto_files: params.proto_files.clone(),
+            tls: params.tls,
+            json_options: params.json_options,
+        };
+        let mut done = CompletedCall {
+            result,
+            raw: String::new(),
+            parsed: None,
+            headers: Vec::new(),
+            trailers: Vec::new(),
+            took_ms: 0,
+            history_id: None,
+            extracted: Vec::new(),
+            extraction_errors: Vec::new(),
+            rendered: None,
+            render_error: None,
+            redactor: call.redactor.clone(),
+        };
+        match &done.result {
+            Ok(outcome) => {
+                // extracted secrets must be hidden before the response is shown or recorded
+                let extracted = outcome.is_ok().then(|| extract::apply(&params.extractions, outcome));
+                if let Some(extracted) = &extracted {
+                    let secrets = self.workspace.extracted_secrets(call.environment_id.as_deref(), &params.extractions, &extracted.values);
+                    done.redactor = done.redactor.with_secrets(secrets);
+                }
+                let redactor = &done.redactor;
+                let mut parsed = outcome.parsed();
+                done.raw = match parsed.as_mut() {
+                    Some(v) => {
+                        let before = v.clone();
+                        redactor.json(v);
+                        if !params.json_options.is_canonical() {
+                            if let Err(e) = self.shape_response(params, outcome.messages.len(), v) { done.render_error = Some(e.to_string()); }
+                        }
+                        if *v != before { serde_json::to_string_pretty(v).unwrap_or_default() } else { outcome.raw() }
+                    }
+                    None => redactor.text(&outcome.raw()),
+                };
+                done.parsed = parsed;
+                done.headers = redactor.metadata(&outcome.headers);
+                done.trailers = redactor.metadata(&outcome.trailers);
+                done.took_ms = outcome.took_ms;
+                entry.response = (!outcome.messages.is_empty()).then(|| done.raw.clone());
+                entry.status = outcome.status.clone();
+                entry.error = outcome.error().map(|e| e.to_string());
+                entry.metadata = serde_json::json!({ "headers": done.headers, "trailers": done.trailers });
+                entry.took_ms = outcome.took_ms as u64;
+                done.history_id = self.workspace.record_history(&entry);
+                if let Some(extracted) = extracted {
+                    done.extracted = self.workspace.store_extracted(call.environment_id.as_deref(), &params.extractions, extracted.values);
+                    done.extraction_errors = extracted.errors;
+                }
+                if params.response_format != PayloadFormat::Json && !outcome.messages.is_empty() {
+                    match self.render_response(params, outcome, redactor) {
+                        Ok(rendered) => done.rendered = Some(rendered),
+                        Err(e) => done.render_error = Some(e.to_string()),
+                    }
+                }
+            }
+            Err(err) => {
+                entry.status = err.code().to_string();
+                entry.error = Some(err.to_string());
+                entry.took_ms = storage::now_unix_ms().saturating_sub(created_at);
+                done.took_ms = u128::from(entry.took_ms);
+                done.history_id = self.workspace.record_history(&entry);
+            }
+        }
+        done
+    }
+
+    /// Rewrites the parsed response (an array when the call returned several messages) with
+    /// `params.json_options`.
+    fn shape_response(&self, params: &RunParams, messages: usize, parsed: &mut serde_json::Value) -> BridgeResult<()> {
+        let (schema, message) = self.index.method_output(params.root_id.as_deref(), &params.service, &params.method)?;
+        let shape = |v: &serde_json::Value| json_output::apply(&schema, &message, v, &params.json_options);
+        *parsed = match &*parsed {
+            serde_json::Value::Array(items) if messages > 1 => serde_json::Value::Array(items.iter().map(shape).collect()),
+            v => shape(v),
+        };
+        Ok(())
+    }
+
+    /// The response messages, redacted, in `params.response_format`.
+    fn render_response(&self, params: &RunParams, outcome: &CallOutcome, redactor: &Redactor) -> BridgeResult<String> {
+        let (schema, message) = self.index.method_output(params.root_id.as_deref(), &params.service, &params.method)?;
+        let mut out = Vec::with_capacity(outcome.messages.len());
+        for raw in &outcome.messages {
+            let mut value: serde_json::Value = serde_json::from_str(raw)
+                .map_err(|e| BridgeError::PayloadFormat { format: "json".into(), reason: e.to_string() })?;
+            redactor.json(&mut value);
+            out.push(format::from_json(&schema, &message, params.response_format, &value)
+                .map_err(|reason| BridgeError::PayloadFormat { format: params.response_format.to_string(), reason })?);
+        }
+        Ok(out.join("\n"))
+    }
+
+    /// Prepares and performs a call while holding the engine's busy flag.
+    pub async fn call(&self, params: RunParams) -> BridgeResult<CompletedCall> {
+        let call = self.prepare_call(params).await?;
+        let _busy = self.engine.acquire()?;
+        Ok(self.perform_call(&call).await)
+    }
+
+    /// Substitutes `{{var}}` placeholders and template functions in target, headers and payload,
+    /// using the requested (or active) environment. `file("...")` reads below `root_dir`. Every
+    /// problem is reported at once as [`BridgeError::Template`].
+    fn render_params(&self, params: &RunParams, root_dir: Option<&Path>) -> BridgeResult<Rendered> {
+        let env = self.workspace.resolve_environment(params.environment_id.clone())?;
+        let ctx = template::Context { vars: &env.vars, base_dir: root_dir };
+        let mut issues = Vec::new();
+        let mut render = |field: &str, input: &str, mode| match template::render(field, input, &ctx, mode) {
+            Ok(s) => s,
+            Err(mut found) => { issues.append(&mut found); String::new() }
+        };
+        let target = render("target", &params.target, template::Mode::Text);
+        let headers: Vec<String> = params.headers.iter().flatten().enumerate()
+            .map(|(i, h)| render(&format!("headers[{}]", i), h, template::Mode::Text))
+            .collect();
+        let mode = if params.payload_format == PayloadFormat::Json { template::Mode::Json } else { template::Mode::Text };
+        let payload = render("payload", &params.payload, mode);
+        if !issues.is_empty() { return Err(BridgeError::Template(issues)); }
+        Ok(Rendered { environment_id: env.id, target, headers, payload, secrets: env.secrets })
+    }
+
+    /// Parameters to send a history entry again. Header values that were redacted when the
+    /// entry was stored are dropped; pass them in `headers` (`"Key: Value"`, replacing entries
+    /// with the same key). Redacted payload fields must be given in `payload_values`, keyed by
+    /// their dotted path (`user.password`); [`BridgeError::RedactedPayload`] lists missing ones.
+    pub fn replay_params(&self, id: i64, headers: Vec<String>, payload_values: serde_json::Map<String, serde_json::Value>) -> BridgeResult<RunParams> {
+        let entry = self.storage().get_history(id)?;
+        let key = |h: &str| h.split_once(':').map(|(k, _)| k.trim().to_ascii_lowercase()).unwrap_or_default();
+        let mut merged: Vec<String> = entry.headers.iter()
+            .filter(|h| !h.ends_with(redact::REDACTED))
+            .filter(|h| !headers.iter().any(|o| key(o) == key(h)))
+            .cloned()
+            .collect();
+        merged.extend(headers);
+        // history keeps the JSON that was sent, so only an empty payload fails to parse
+        let payload = match serde_json::from_str::<serde_json::Value>(&entry.payload) {
+            Ok(mut value) if !payload_values.is_empty() || entry.payload.contains(redact::REDACTED) => {
+                let missing: Vec<String> = redact::redacted_fields(&value).into_iter().filter(|p| !payload_values.contains_key(p)).collect();
+                if !missing.is_empty() { return Err(BridgeError::RedactedPayload(missing)); }
+                for (path, v) in payload_values {
+                    if !redact::set_field(&mut value, &path, v) { return Err(BridgeError::InvalidArgument(format!("no payload field {}", path))); }
+                }
+                value.to_string()
+            }
+            _ => entry.payload,
+        };
+        Ok(RunParams {
+            target: entry.target,
+            service: entry.service,
+            method: entry.method,
+            payload,
+            proto_files: entry.proto_files,
+            root_id: entry.root_id,
+            headers: Some(merged),
+            tls: entry.tls,
+            environment_id: None,
+            extractions: Vec::new(),
+            validate: false,
+            payload_format: PayloadFormat::Json,
+            response_format: PayloadFormat::Json,
+            json_options: entry.json_options,
+        })
+    }
+
+    /// Runs the requests of a collection (or one folder of it) in order, calling `on_step`
+    /// after each. Values extracted by one step are visible to the next. Steps keep running
+    /// after a failure so the summary covers the whole folder.
+    pub async fn run_collection(&self, collection_id: &str, folder_id: Option<&str>, environment_id: Option<String>, mut on_step: impl FnMut(&StepResult)) -> BridgeResult<RunSummary> {
+        let (collection, requests) = self.storage().run_order(collection_id, folder_id)?;
+        let busy = self.engine.acquire()?;
+        let started_at = storage::now_unix_ms();
+        let mut steps = Vec::with_capacity(requests.len());
+        for (folder, req) in requests {
+            let step = self.run_step(folder, req, environment_id.clone()).await;
+            on_step(&step);
+            steps.push(step);
+        }
+        drop(busy);
+        let took_ms = storage::now_unix_ms().saturating_sub(started_at);
+        Ok(RunSummary::new(collection.id, collection.name, started_at, took_ms, steps))
+    }
+
+    async fn run_step(&self, folder: String, req: SavedRequest, environment_id: Option<String>) -> StepResult {
+        let (id, name, assertions) = (req.id.clone(), req.name.clone(), req.assertions.clone());
+        let params = RunParams { environment_id, ..RunParams::from(req) };
+        let call = match self.prepare_call(params).await {
+            Ok(call) => call,
+            Err(e) => return StepResult::errored(id, name, folder, e.code(), e.to_string()),
+        };
+        let done = self.perform_call(&call).await;
+        let mut step = match &done.result {
+            Ok(outcome) => StepResult::judge(id, name, folder, &assertions, outcome),
+            Err(e) => StepResult::errored(id, name, folder, e.code(), e.to_string()),
+        };
+        step.took_ms = done.took_ms as u64;
+        step.history_id = done.history_id;
+        step.extracted = done.extracted;
+        // messages may quote response values, which can contain substituted secrets
+        step.error = step.error.map(|e| done.redactor.text(&e));
+        for a in &mut step.assertions { a.message = a.message.as_deref().map(|m| done.redactor.text(m)); }
+        step
+    }
+
+    /// Sends mutated payloads built from the method's input message (see [`mutate::mutations`])
+    /// and calls `on_case` after each. Responses that look like server bugs (see
+    /// [`fuzz::finding`]) are saved as requests in `collection_id`, or in a new collection. Cases
+    /// are not recorded in history. The run stops when the target stops accepting connections.
+    pub async fn fuzz(&self, params: FuzzParams, mut on_case: impl FnMut(&FuzzCase)) -> BridgeResult<FuzzReport> {
+        let (schema, message) = self.index.method_input(params.root_id.as_deref(), &params.service, &params.method)?;
+        let seed = params.seed.unwrap_or_else(rand::random);
+        let mut mutations = mutate::mutations(&schema, &message, seed);
+        if let Some(max) = params.max_cases { mutations.truncate(max); }
+        // payloads go to grpcurl's stdin, so one set of arguments serves every case
+        let call = self.prepare_call(RunParams {
+            target: params.target.clone(),
+            service: params.service.clone(),
+            method: params.method.clone(),
+            payload: String::new(),
+            proto_files: params.proto_files.clone(),
+            root_id: params.root_id.clone(),
+            headers: params.headers.clone(),
+            tls: params.tls,
+            environment_id: params.environment_id.clone(),
+            extractions: Vec::new(),
+            validate: false,
+            payload_format: PayloadFormat::Json,
+            response_format: PayloadFormat::Json,
+            json_options: JsonOptions::default(),
+        }).await?;
+        let busy = self.engine.acquire()?;
+        let started_at = storage::now_unix_ms();
+        let mut report = FuzzReport {
+            service: params.service.clone(),
+            method: params.method.clone(),
+            seed,
+            cases: 0,
+            statuses: Default::default(),
+            findings: Vec::new(),
+            collection_id: None,
+            stopped_early: false,
+            took_ms: 0,
+        };
+        for (index, mutation) in mutations.into_iter().enumerate() {
+            let payload = serde_json::to_string_pretty(&mutation.payload).unwrap_or_default();
+            let case_started = storage::now_unix_ms();
+            let result = self.engine.unary(&call.args, &payload, &call.redactor).await;
+            let status = match &result { Ok(outcome) => outcome.status.clone(), Err(e) => e.code().to_string() };
+            let found = fuzz::finding(&result);
+            let case = FuzzCase { index, mutation: mutation.name, status, took_ms: storage::now_unix_ms().saturating_sub(case_started), finding: found.is_some() };
+            on_case(&case);
+            report.cases += 1;
+            *report.statuses.entry(case.status).or_default() += 1;
+            if let Some((status, message)) = found {
+                report.findings.push(FuzzFinding { mutation: case.mutation, status, message: call.redactor.text(&message), payload, saved_request_id: None });
+            }
+            // a dead server fails every later case the same way, and not because of this payload
+            if fuzz::is_unreachable(&result) { report.stopped_early = true; break; }
+        }
+        drop(busy);
+        report.took_ms = storage::now_unix_ms().saturating_sub(started_at);
+        if !report.findings.is_empty() { self.save_reproducers(&params, &mut report)?; }
+        Ok(report)
+    }
+
+    /// Saves each finding of `report` as a request named after its mutation and status.
+    fn save_reproducers(&self, params: &FuzzParams, report: &mut FuzzReport) -> BridgeResult<()> {
+        let collection_id = match &params.collection_id {
+            Some(id) => id.clone(),
+            None => self.storage().create_collection(&format!("Fuzz findings: {}/{}", params.service, params.method))?.id,
+        };
+        for finding in &mut report.findings {
+            let saved = self.storage().save_request(&SavedRequest {
+                id: String::new(),
+                collection_id: collection_id.clone(),
+                folder_id: None,
+                name: format!("{} ({})", finding.mutation, finding.status),
+                root_id: params.root_id.clone(),
+                fq_service: params.service.clone(),
+                method: params.method.clone(),
+                target: params.target.clone(),
+                headers: params.headers.clone().unwrap_or_default(),
+                payload: finding.payload.clone(),
+                proto_files: params.proto_files.clone(),
+                tls: params.tls,
+                extractions: Vec::new(),
+                assertions: Vec::new(),
+                payload_format: PayloadFormat::Json,
+                response_format: PayloadFormat::Json,
+                json_options: JsonOptions::default(),
+            })?;
+            finding.saved_request_id = Some(saved.id);
+        }
+        report.collection_id = Some(collection_id);
+        Ok(())
+    }
+
+    /// Asks the server itself through gRPC reflection: the service list without `symbol`,
+    /// the description of a service, method or message with one. Placeholders in `target` and
+    /// `headers` are filled as for a call.
+    pub async fn reflect(&self, target: &str, tls: Option<bool>, headers: &[String], environment_id: Option<String>, symbol: Option<&str>) -> BridgeResult<String> {
+        let params = RunParams {
+            target: target.to_string(),
+            service: String::new(),
+            method: String::new(),
+            payload: String::new(),
+            proto_files: Vec::new(),
+            root_id: None,
+            headers: Some(headers.to_vec()),
+            tls,
+            environment_id,
+            extractions: Vec::new(),
+            validate: false,
+            payload_format: PayloadFormat::Json,
+            response_format: PayloadFormat::Json,
+            json_options: JsonOptions::default(),
+        };
+        let rendered = self.render_params(&params, None)?;
+        let parsed = Target::parse(&rendered.target)
+            .map_err(|e| BridgeError::InvalidTarget { target: rendered.target.clone(), reason: e.to_string() })?;
+        let args = grpcurl::build_reflect_args(&parsed, tls.unwrap_or(parsed.tls), &rendered.headers, symbol)?;
+        self.engine.reflect(&args, &self.engine.redactor(rendered.secrets)).await
+    }
+}
+
+struct Rendered {
+    /// Environment the variables came from; extracted values are written back to it.
+    environment_id: Option<String>,
+    target: String,
+    headers: Vec<String>,
+    payload: String,
+    /// Values of the secret variables that were available, to be redacted from history and events.
+    secrets: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secrets::MemoryStore;
+    use crate::storage::Variable;
+    use crate::template::TemplateIssue;
+
+    fn bridge() -> Bridge {
+        Bridge::load(Storage::open_in_memory().unwrap(), Box::<MemoryStore>::default(), None).unwrap()
+    }
+
+    fn environment(bridge: &Bridge, name: &str, vars: &[(&str, &str)]) -> String {
+        let env = bridge.storage().create_environment(name).unwrap();
+        let vars: Vec<Variable> = vars.iter().map(|(k, v)| Variable { key: k.to_string(), value: v.to_string(), secret: false }).collect();
+        bridge.workspace().set_environment_variables(&env.id, &vars).unwrap();
+        env.id
+    }
+
+    fn params(target: &str, headers: &[&str], environment_id: Option<&str>) -> RunParams {
+        serde_json::from_value(serde_json::json!({
+            "target": target, "service": "shop.v1.Orders", "method": "Get", "payload": "{}", "proto_files": [],
+            "headers": headers, "environment_id": environment_id,
+        })).unwrap()
+    }
+
+    #[test]
+    fn target_and_headers_are_rendered_with_the_chosen_environment() {
+        let bridge = bridge();
+        let dev = environment(&bridge, "dev", &[("host", "dev.local"), ("token", "d")]);
+        let prod = environment(&bridge, "prod", &[("host", "prod.example"), ("token", "p")]);
+        bridge.storage().set_active_environment(Some(&dev)).unwrap();
+
+        let p = params("{{ host }}:443", &["authorization: Bearer {{token}}", "x-n: {{token}}{{token}}"], None);
+        let rendered = bridge.render_params(&p, None).unwrap();
+        assert_eq!(rendered.environment_id.as_deref(), Some(dev.as_str()));
+        assert_eq!(rendered.target, "dev.local:443");
+        assert_eq!(rendered.headers, ["authorization: Bearer d", "x-n: dd"]);
+
+        let rendered = bridge.render_params(&params("{{host}}", &[], Some(&prod)), None).unwrap();
+        assert_eq!(rendered.target, "prod.example");
+    }
+
+    #[test]
+    fn variables_do_not_shadow_functions() {
+        let bridge = bridge();
+        let dev = environment(&bridge, "dev", &[("uuid", "fixed")]);
+        let rendered = bridge.render_params(&params("{{uuid}}", &["x-id: {{uuid()}}"], Some(&dev)), None).unwrap();
+        assert_eq!(rendered.target, "fixed");
+        assert_eq!(rendered.headers[0].len(), "x-id: ".len() + 36);
+    }
+
+    #[test]
+    fn every_undefined_variable_is_reported_with_its_location() {
+        let bridge = bridge();
+        let dev = environment(&bridge, "dev", &[("host", "dev.local")]);
+        let p = params("{{host}}:{{port}}", &["a: ok", "x-user: {{user}}"], Some(&dev));
+        let Err(BridgeError::Template(issues)) = bridge.render_params(&p, None) else { panic!("expected a template error") };
+        let at: Vec<(&str, usize, usize)> = issues.iter().map(|i: &TemplateIssue| (i.field.as_str(), i.line, i.column)).collect();
+        assert_eq!(at, [("target", 1, 10), ("headers[1]", 1, 9)]);
+        assert!(issues.iter().all(|i| i.message.starts_with("undefined variable")));
+
+        // without any environment nothing is defined
+        assert!(matches!(bridge.render_params(&params("{{host}}", &[], None), None), Err(BridgeError::Template(_))));
+    }
+}
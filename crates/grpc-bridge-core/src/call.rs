@@ -0,0 +1,222 @@
+use crate::error::{BridgeError, BridgeResult, CallFailureKind};
+use crate::grpcurl::{self, GrpcurlConfig, GrpcurlInfo, Metadata};
+use crate::redact::{RedactionConfig, Redactor};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::{io::AsyncWriteExt, process::Command};
+use tracing::info;
+
+/// Result of one grpcurl invocation that got far enough to report a status.
+#[derive(Debug, Clone, Serialize)]
+pub struct CallOutcome {
+    /// gRPC status code name, `OK` on success.
+    pub status: String,
+    /// Response messages as JSON text.
+    pub messages: Vec<String>,
+    pub headers: Metadata,
+    pub trailers: Metadata,
+    /// grpcurl's stderr, already redacted by the caller's redactor if any.
+    pub stderr: String,
+    pub exit_code: Option<i32>,
+    pub took_ms: u128,
+}
+
+impl CallOutcome {
+    pub fn is_ok(&self) -> bool { self.exit_code == Some(0) }
+
+    /// Messages joined the way grpcurl prints them without `-v`.
+    pub fn raw(&self) -> String { self.messages.join("\n") }
+
+    /// A single message as JSON, several as a JSON array.
+    pub fn parsed(&self) -> Option<serde_json::Value> {
+        let values: Option<Vec<serde_json::Value>> = self.messages.iter().map(|m| serde_json::from_str(m).ok()).collect();
+        match values? {
+            mut v if v.len() == 1 => v.pop(),
+            v if v.is_empty() => None,
+            v => Some(serde_json::Value::Array(v)),
+        }
+    }
+
+    pub fn error(&self) -> Option<BridgeError> {
+        if self.is_ok() { return None; }
+        Some(BridgeError::CallFailed {
+            kind: CallFailureKind::from_stderr(&self.stderr),
+            message: self.stderr.trim().to_string(),
+            exit_code: self.exit_code,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct EnvironmentReport { pub grpcurl: GrpcurlInfo, pub min_grpcurl_version: String }
+
+/// Held while a request runs, see [`CallEngine::acquire`]. Owns its flag so it can move into
+/// a spawned task.
+#[must_use = "the engine is free again as soon as the guard is dropped"]
+pub struct Busy(Arc<Mutex<bool>>);
+
+impl Drop for Busy {
+    fn drop(&mut self) {
+        if let Ok(mut busy) = self.0.lock() { *busy = false; }
+    }
+}
+
+/// Runs grpcurl: finds and verifies the binary, allows one request at a time and owns the
+/// redaction settings applied to everything a call logs or reports.
+pub struct CallEngine {
+    busy: Arc<Mutex<bool>>, // single unary guard, see `Busy`
+    redactor: Mutex<Redactor>,
+    grpcurl_config: Mutex<GrpcurlConfig>,
+    grpcurl: Mutex<Option<GrpcurlInfo>>, // verified binary, reset when the config changes
+    /// Where a bundled grpcurl is looked for before `PATH`.
+    sidecar_dir: Option<PathBuf>,
+}
+
+impl CallEngine {
+    pub fn new(sidecar_dir: Option<PathBuf>) -> Self {
+        Self {
+            busy: Default::default(),
+            redactor: Default::default(),
+            grpcurl_config: Default::default(),
+            grpcurl: Default::default(),
+            sidecar_dir,
+        }
+    }
+
+    /// Marks the engine busy until the returned guard is dropped; only one request (or
+    /// collection run) executes at a time.
+    pub fn acquire(&self) -> BridgeResult<Busy> {
+        let mut busy = self.busy.lock().unwrap();
+        if *busy { return Err(BridgeError::RequestAlreadyRunning); }
+        *busy = true;
+        Ok(Busy(self.busy.clone()))
+    }
+
+    /// Returns the verified grpcurl binary, inspecting it on first use or after a config change.
+    pub async fn resolve_grpcurl(&self) -> BridgeResult<GrpcurlInfo> {
+        if let Some(info) = self.grpcurl.lock().unwrap().clone() { return Ok(info); }
+        let config = self.grpcurl_config.lock().unwrap().clone();
+        let info = grpcurl::inspect(&config, self.sidecar_dir.as_deref()).await?;
+        *self.grpcurl.lock().unwrap() = Some(info.clone());
+        Ok(info)
+    }
+
+    /// Locates and verifies grpcurl again, ignoring the cached result.
+    pub async fn check_environment(&self) -> BridgeResult<EnvironmentReport> {
+        *self.grpcurl.lock().unwrap() = None;
+        let info = self.resolve_grpcurl().await?;
+        let (major, minor, patch) = grpcurl::MIN_VERSION;
+        Ok(EnvironmentReport { grpcurl: info, min_grpcurl_version: format!("{}.{}.{}", major, minor, patch) })
+    }
+
+    pub fn grpcurl_config(&self) -> GrpcurlConfig {
+        self.grpcurl_config.lock().unwrap().clone()
+    }
+
+    pub fn set_grpcurl_config(&self, config: GrpcurlConfig) {
+        *self.grpcurl_config.lock().unwrap() = config;
+        *self.grpcurl.lock().unwrap() = None;
+    }
+
+    pub fn redaction_config(&self) -> RedactionConfig {
+        self.redactor.lock().unwrap().config().clone()
+    }
+
+    pub fn set_redaction_config(&self, config: RedactionConfig) {
+        *self.redactor.lock().unwrap() = Redactor::new(config);
+    }
+
+    /// The configured redactor, additionally scrubbing `secrets`.
+    pub fn redactor(&self, secrets: Vec<String>) -> Redactor {
+        self.redactor.lock().unwrap().clone().with_secrets(secrets)
+    }
+
+    /// Runs a unary call built by [`grpcurl::build_args`]; see [`execute`].
+    pub async fn unary(&self, args: &[String], payload: &str, redactor: &Redactor) -> BridgeResult<CallOutcome> {
+        let binary = self.resolve_grpcurl().await?;
+        // never log the Command itself: its Debug output includes header values verbatim
+        info!(args = ?redactor.args(args), "spawning grpcurl");
+        let result = execute(&binary.path, args, payload, |s| redactor.text(s)).await;
+        self.forget_missing(&result);
+        result
+    }
+
+    /// Runs a reflection query built by [`grpcurl::build_reflect_args`]; see [`reflect`].
+    pub async fn reflect(&self, args: &[String], redactor: &Redactor) -> BridgeResult<String> {
+        let binary = self.resolve_grpcurl().await?;
+        info!(args = ?redactor.args(args), "spawning grpcurl");
+        let result = reflect(&binary.path, args, |s| redactor.text(s)).await;
+        self.forget_missing(&result);
+        result
+    }
+
+    fn forget_missing<T>(&self, result: &BridgeResult<T>) {
+        if let Err(BridgeError::GrpcurlMissing { .. }) = result {
+            // binary vanished since it was verified; look it up again next time
+            *self.grpcurl.lock().unwrap() = None;
+        }
+    }
+}
+
+/// Runs grpcurl with `args`, streams `payload` to its stdin and collects the outcome.
+/// `scrub` is applied to stderr before it is stored (credential redaction).
+pub async fn execute(binary: &Path, args: &[String], payload: &str, scrub: impl Fn(&str) -> String) -> BridgeResult<CallOutcome> {
+    let started = Instant::now();
+    let mut cmd = Command::new(binary);
+    cmd.args(args)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true);
+    let out = run_with_stdin(cmd, payload.as_bytes()).await.map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            BridgeError::GrpcurlMissing { searched: vec![binary.to_string_lossy().to_string()] }
+        } else { BridgeError::Spawn(e.to_string()) }
+    })?;
+    let took_ms = started.elapsed().as_millis();
+    let verbose = grpcurl::parse_output(&String::from_utf8_lossy(&out.stdout));
+    let stderr = scrub(&String::from_utf8_lossy(&out.stderr));
+    let status = if out.status.success() { "OK".to_string() }
+        else { grpcurl::parse_status(&stderr).map(|(code, _)| code).unwrap_or_else(|| "Unknown".into()) };
+    Ok(CallOutcome {
+        status,
+        messages: verbose.messages,
+        headers: verbose.headers,
+        trailers: verbose.trailers,
+        stderr,
+        exit_code: out.status.code(),
+        took_ms,
+    })
+}
+
+/// Runs a grpcurl reflection query (see [`grpcurl::build_reflect_args`]) and returns its output.
+pub async fn reflect(binary: &Path, args: &[String], scrub: impl Fn(&str) -> String) -> BridgeResult<String> {
+    let mut cmd = Command::new(binary);
+    cmd.args(args)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true);
+    let out = cmd.output().await.map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            BridgeError::GrpcurlMissing { searched: vec![binary.to_string_lossy().to_string()] }
+        } else { BridgeError::Spawn(e.to_string()) }
+    })?;
+    if !out.status.success() {
+        let stderr = scrub(&String::from_utf8_lossy(&out.stderr));
+        return Err(BridgeError::CallFailed { kind: CallFailureKind::from_stderr(&stderr), message: stderr.trim().to_string(), exit_code: out.status.code() });
+    }
+    Ok(scrub(&String::from_utf8_lossy(&out.stdout)))
+}
+
+/// Spawns `cmd`, streams `input` to its stdin and waits for it to exit.
+async fn run_with_stdin(mut cmd: Command, input: &[u8]) -> std::io::Result<std::process::Output> {
+    let mut child = cmd.spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // grpcurl may exit before reading everything (e.g. on a bad flag); its stderr explains why
+        if let Err(e) = stdin.write_all(input).await { if e.kind() != std::io::ErrorKind::BrokenPipe { return Err(e); } }
+    }
+    child.wait_with_output().await
+}
@@ -0,0 +1,196 @@
+use crate::payload::validate::PayloadIssue;
+use crate::template::TemplateIssue;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde_json::Value;
+
+/// Error returned by the core API, every Tauri command and carried in `grpc://error` events.
+///
+/// Serialized as `{ code, message, details, retryable }`. `code` is stable and meant to be
+/// used as an i18n key by the frontend; `message` is an English fallback.
+#[derive(Debug, thiserror::Error)]
+pub enum BridgeError {
+    #[error("proto root not found: {0}")]
+    RootNotFound(String),
+    #[error("service not found: {0}")]
+    ServiceNotFound(String),
+    #[error("method {method} not found in {service}")]
+    MethodNotFound { service: String, method: String },
+    #[error("message type not found: {0}")]
+    MessageNotFound(String),
+    #[error("invalid target {target:?}: {reason}")]
+    InvalidTarget { target: String, reason: String },
+    #[error("invalid method {service}/{method}")]
+    InvalidMethod { service: String, method: String },
+    #[error("invalid header {name:?}: {reason}")]
+    InvalidHeader { name: String, reason: String },
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+    #[error("a request is already running")]
+    RequestAlreadyRunning,
+    #[error("grpcurl failed: {message}")]
+    CallFailed { kind: CallFailureKind, message: String, exit_code: Option<i32> },
+    #[error("grpcurl not found (searched {} locations)", searched.len())]
+    GrpcurlMissing { searched: Vec<String> },
+    #[error("grpcurl at {path} is not usable: {reason}")]
+    GrpcurlIncompatible { path: String, reason: String },
+    #[error("failed to spawn grpcurl: {0}")]
+    Spawn(String),
+    #[error("history entry {0} not found")]
+    HistoryNotFound(i64),
+    #[error("redacted payload fields need values: {}", .0.join(", "))]
+    RedactedPayload(Vec<String>),
+    #[error("collection not found: {0}")]
+    CollectionNotFound(String),
+    #[error("folder not found: {0}")]
+    FolderNotFound(String),
+    #[error("saved request not found: {0}")]
+    SavedRequestNotFound(String),
+    #[error("environment not found: {0}")]
+    EnvironmentNotFound(String),
+    #[error("{}", template_message(.0))]
+    Template(Vec<TemplateIssue>),
+    #[error("{}", payload_message(.0))]
+    InvalidPayload(Vec<PayloadIssue>),
+    #[error("payload is not valid {format}: {reason}")]
+    PayloadFormat { format: String, reason: String },
+    #[error("secret store error: {0}")]
+    Secret(String),
+    #[error("storage error: {0}")]
+    Storage(String),
+    #[error("failed to emit event: {0}")]
+    Emit(String),
+}
+
+/// Classification of a failed grpcurl invocation, derived from its stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallFailureKind {
+    UnknownService,
+    UnknownMethod,
+    DialFailure,
+    Timeout,
+    PermissionDenied,
+    Unauthenticated,
+    Unavailable,
+    Other,
+}
+
+impl CallFailureKind {
+    pub fn from_stderr(stderr: &str) -> Self {
+        let lowered = stderr.to_lowercase();
+        if lowered.contains("unknown service") { Self::UnknownService }
+        else if lowered.contains("unknown method") { Self::UnknownMethod }
+        else if lowered.contains("failed to dial") { Self::DialFailure }
+        else if lowered.contains("deadline exceeded") { Self::Timeout }
+        else if lowered.contains("permission denied") { Self::PermissionDenied }
+        else if lowered.contains("unauthenticated") { Self::Unauthenticated }
+        else if lowered.contains("unavailable") { Self::Unavailable }
+        else { Self::Other }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::UnknownService => "unknown_service",
+            Self::UnknownMethod => "unknown_method",
+            Self::DialFailure => "dial_failure",
+            Self::Timeout => "timeout",
+            Self::PermissionDenied => "permission_denied",
+            Self::Unauthenticated => "unauthenticated",
+            Self::Unavailable => "unavailable",
+            Self::Other => "error",
+        }
+    }
+}
+
+impl BridgeError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::RootNotFound(_) => "root_not_found",
+            Self::ServiceNotFound(_) => "service_not_found",
+            Self::MethodNotFound { .. } => "method_not_found",
+            Self::MessageNotFound(_) => "message_not_found",
+            Self::InvalidTarget { .. } => "invalid_target",
+            Self::InvalidMethod { .. } => "invalid_method",
+            Self::InvalidHeader { .. } => "invalid_header",
+            Self::InvalidArgument(_) => "invalid_argument",
+            Self::RequestAlreadyRunning => "request_already_running",
+            Self::CallFailed { kind, .. } => kind.as_str(),
+            Self::GrpcurlMissing { .. } => "grpcurl_missing",
+            Self::GrpcurlIncompatible { .. } => "grpcurl_incompatible",
+            Self::Spawn(_) => "spawn_failed",
+            Self::HistoryNotFound(_) => "history_not_found",
+            Self::RedactedPayload(_) => "redacted_payload",
+            Self::CollectionNotFound(_) => "collection_not_found",
+            Self::FolderNotFound(_) => "folder_not_found",
+            Self::SavedRequestNotFound(_) => "saved_request_not_found",
+            Self::EnvironmentNotFound(_) => "environment_not_found",
+            Self::Template(_) => "template_error",
+            Self::InvalidPayload(_) => "invalid_payload",
+            Self::PayloadFormat { .. } => "invalid_payload_format",
+            Self::Secret(_) => "secret_store_error",
+            Self::Storage(_) => "storage_error",
+            Self::Emit(_) => "emit_failed",
+        }
+    }
+
+    /// Whether repeating the same request unchanged may succeed.
+    pub fn retryable(&self) -> bool {
+        match self {
+            Self::RequestAlreadyRunning => true,
+            Self::CallFailed { kind, .. } => matches!(kind, CallFailureKind::DialFailure | CallFailureKind::Timeout | CallFailureKind::Unavailable),
+            _ => false,
+        }
+    }
+
+    pub fn details(&self) -> Option<Value> {
+        match self {
+            Self::RootNotFound(root_id) => Some(serde_json::json!({ "root_id": root_id })),
+            Self::HistoryNotFound(id) => Some(serde_json::json!({ "id": id })),
+            Self::RedactedPayload(fields) => Some(serde_json::json!({ "fields": fields })),
+            Self::CollectionNotFound(id) | Self::FolderNotFound(id) | Self::SavedRequestNotFound(id) | Self::EnvironmentNotFound(id) => Some(serde_json::json!({ "id": id })),
+            Self::Template(issues) => Some(serde_json::json!({ "issues": issues })),
+            Self::InvalidPayload(issues) => Some(serde_json::json!({ "issues": issues })),
+            Self::PayloadFormat { format, reason } => Some(serde_json::json!({ "format": format, "reason": reason })),
+            Self::ServiceNotFound(service) => Some(serde_json::json!({ "service": service })),
+            Self::MessageNotFound(message) => Some(serde_json::json!({ "message": message })),
+            Self::MethodNotFound { service, method } => Some(serde_json::json!({ "service": service, "method": method })),
+            Self::InvalidTarget { target, reason } => Some(serde_json::json!({ "target": target, "reason": reason })),
+            Self::InvalidMethod { service, method } => Some(serde_json::json!({ "service": service, "method": method })),
+            Self::InvalidHeader { name, reason } => Some(serde_json::json!({ "name": name, "reason": reason })),
+            Self::InvalidArgument(reason) => Some(serde_json::json!({ "reason": reason })),
+            Self::GrpcurlMissing { searched } => Some(serde_json::json!({ "searched": searched })),
+            Self::GrpcurlIncompatible { path, reason } => Some(serde_json::json!({ "path": path, "reason": reason })),
+            Self::CallFailed { exit_code, .. } => Some(serde_json::json!({ "exit_code": exit_code })),
+            _ => None,
+        }
+    }
+}
+
+fn template_message(issues: &[TemplateIssue]) -> String {
+    match issues {
+        [one] => format!("{} at {}:{}:{}", one.message, one.field, one.line, one.column),
+        [first, ..] => format!("{} at {}:{}:{} (and {} more)", first.message, first.field, first.line, first.column, issues.len() - 1),
+        [] => "template error".into(),
+    }
+}
+
+fn payload_message(issues: &[PayloadIssue]) -> String {
+    let at = |i: &PayloadIssue| if i.path.is_empty() { i.message.clone() } else { format!("{} at {}", i.message, i.path) };
+    match issues {
+        [one] => at(one),
+        [first, ..] => format!("{} (and {} more)", at(first), issues.len() - 1),
+        [] => "invalid payload".into(),
+    }
+}
+
+impl Serialize for BridgeError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("BridgeError", 4)?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("message", &self.to_string())?;
+        s.serialize_field("details", &self.details())?;
+        s.serialize_field("retryable", &self.retryable())?;
+        s.end()
+    }
+}
+
+pub type BridgeResult<T> = Result<T, BridgeError>;
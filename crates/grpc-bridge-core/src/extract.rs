@@ -0,0 +1,59 @@
+use crate::call::CallOutcome;
+use crate::jsonpath::{self, JsonPath};
+use serde::{Deserialize, Serialize};
+
+/// Where an extracted value is read from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "from", rename_all = "snake_case")]
+pub enum ExtractSource {
+    /// JSONPath over the response body; with several response messages the body is an array.
+    Body { path: String },
+    Header { key: String },
+    Trailer { key: String },
+}
+
+/// Copies a value from a successful response into an environment variable, e.g. the token
+/// returned by `Login` into `{{token}}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractionRule {
+    pub variable: String,
+    #[serde(flatten)]
+    pub source: ExtractSource,
+    /// Keep the value in the secret store and redact it like other secrets.
+    #[serde(default)]
+    pub secret: bool,
+}
+
+/// Result of applying extraction rules to one response.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Extracted {
+    /// `(variable, value)` for every rule that matched.
+    pub values: Vec<(String, String)>,
+    /// One message per rule that did not.
+    pub errors: Vec<String>,
+}
+
+pub fn apply(rules: &[ExtractionRule], outcome: &CallOutcome) -> Extracted {
+    let mut out = Extracted::default();
+    let body = outcome.parsed();
+    for rule in rules {
+        let found = match &rule.source {
+            ExtractSource::Body { path } => JsonPath::parse(path).and_then(|p| {
+                let body = body.as_ref().ok_or("response body is not JSON")?;
+                p.first(body).map(jsonpath::to_text).ok_or_else(|| format!("{} matched nothing", path))
+            }),
+            ExtractSource::Header { key } => metadata_value(&outcome.headers, key).ok_or_else(|| format!("no response header {:?}", key)),
+            ExtractSource::Trailer { key } => metadata_value(&outcome.trailers, key).ok_or_else(|| format!("no response trailer {:?}", key)),
+        };
+        match found {
+            Ok(value) => out.values.push((rule.variable.clone(), value)),
+            Err(e) => out.errors.push(format!("{}: {}", rule.variable, e)),
+        }
+    }
+    out
+}
+
+/// Metadata keys are case-insensitive.
+pub fn metadata_value(entries: &[(String, String)], key: &str) -> Option<String> {
+    entries.iter().find(|(k, _)| k.eq_ignore_ascii_case(key.trim())).map(|(_, v)| v.clone())
+}
@@ -0,0 +1,105 @@
+use crate::call::CallOutcome;
+use crate::error::{BridgeError, BridgeResult, CallFailureKind};
+use crate::grpcurl;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Stderr fragments of a connection that broke mid-call, as when the server process dies.
+const TRANSPORT_FAILURES: &[&str] = &[
+    "transport is closing", "connection reset", "rst_stream", "unexpected eof", "error reading from server: eof",
+    "broken pipe", "goaway",
+];
+
+/// A fuzz run against one method, as sent by the UI or the CLI. `target` and `headers` may
+/// contain template placeholders.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FuzzParams {
+    pub target: String,
+    pub service: String,
+    pub method: String,
+    #[serde(default)] pub proto_files: Vec<String>,
+    pub root_id: Option<String>,
+    pub headers: Option<Vec<String>>, // "Key: Value"
+    pub tls: Option<bool>,
+    pub environment_id: Option<String>,
+    pub seed: Option<u64>, // random when unset; reported so the run can be repeated
+    pub max_cases: Option<usize>,
+    pub collection_id: Option<String>, // where reproducers go; a new collection when unset
+}
+
+/// One mutated payload sent by `fuzz`.
+#[derive(Debug, Clone, Serialize)]
+pub struct FuzzCase {
+    pub index: usize,
+    pub mutation: String,
+    /// gRPC status name, or the error code when grpcurl could not make the call.
+    pub status: String,
+    pub took_ms: u64,
+    /// Whether the response counts as a finding (see [`finding`]).
+    pub finding: bool,
+}
+
+/// A case whose response looks like a server bug, with the payload that caused it.
+#[derive(Debug, Clone, Serialize)]
+pub struct FuzzFinding {
+    pub mutation: String,
+    pub status: String,
+    pub message: String,
+    pub payload: String,
+    /// The reproducer saved in the report's collection.
+    pub saved_request_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FuzzReport {
+    pub service: String,
+    pub method: String,
+    pub seed: u64,
+    pub cases: usize,
+    /// How many cases ended with each status.
+    pub statuses: BTreeMap<String, usize>,
+    pub findings: Vec<FuzzFinding>,
+    /// Collection holding the reproducers; unset when nothing was found.
+    pub collection_id: Option<String>,
+    /// The target stopped accepting connections, so the remaining cases were not sent.
+    pub stopped_early: bool,
+    pub took_ms: u64,
+}
+
+/// Status and message of a response that counts as a finding: `INTERNAL` or `UNKNOWN` from the
+/// server, or a connection that broke. Statuses grpcurl reports for payloads it refused to
+/// send (e.g. unknown fields) are not findings.
+pub fn finding(result: &BridgeResult<CallOutcome>) -> Option<(String, String)> {
+    match result {
+        Ok(outcome) if outcome.is_ok() => None,
+        Ok(outcome) => match grpcurl::parse_status(&outcome.stderr) {
+            Some((code, message)) if is_server_fault(&code) || is_transport_failure(&message) => Some((code, message)),
+            Some(_) => None,
+            None if is_transport_failure(&outcome.stderr) => Some(("transport".into(), outcome.stderr.trim().to_string())),
+            None => None,
+        },
+        Err(e @ BridgeError::CallFailed { message, .. }) if is_transport_failure(message) => Some((e.code().to_string(), message.clone())),
+        Err(_) => None,
+    }
+}
+
+/// Whether the target can no longer be reached at all, so later cases would fail the same way.
+/// Only grpcurl's own dial failures count: a status from the server means it answered, even
+/// when its message mentions a refused connection of its own.
+pub fn is_unreachable(result: &BridgeResult<CallOutcome>) -> bool {
+    match result {
+        Ok(outcome) => grpcurl::parse_status(&outcome.stderr).is_none() && CallFailureKind::from_stderr(&outcome.stderr) == CallFailureKind::DialFailure,
+        Err(BridgeError::CallFailed { kind, .. }) => *kind == CallFailureKind::DialFailure,
+        Err(_) => false,
+    }
+}
+
+fn is_server_fault(code: &str) -> bool {
+    let code: String = code.chars().filter(|c| *c != '_').collect::<String>().to_lowercase();
+    code == "internal" || code == "unknown" || code == "13" || code == "2"
+}
+
+fn is_transport_failure(message: &str) -> bool {
+    let lowered = message.to_lowercase();
+    TRANSPORT_FAILURES.iter().any(|f| lowered.contains(f))
+}
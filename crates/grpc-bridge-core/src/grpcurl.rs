@@ -0,0 +1,341 @@
+use crate::error::{BridgeError, BridgeResult};
+use crate::target::Target;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+lazy_static::lazy_static! {
+    static ref RE_FQ_SERVICE: regex::Regex = regex::Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*(\.[A-Za-z_][A-Za-z0-9_]*)*$").unwrap();
+    static ref RE_METHOD: regex::Regex = regex::Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").unwrap();
+    static ref RE_HEADER_NAME: regex::Regex = regex::Regex::new(r"^[A-Za-z0-9_][A-Za-z0-9_.\-]*$").unwrap();
+}
+
+/// Everything needed to build a grpcurl argument list for a unary call.
+/// The request body is not part of the arguments; it is written to stdin (`-d @`).
+pub struct CallSpec<'a> {
+    pub target: &'a Target,
+    pub tls: bool,
+    pub import_path: Option<&'a str>,
+    pub proto_files: &'a [String],
+    pub headers: &'a [String],
+    pub service: &'a str,
+    pub method: &'a str,
+}
+
+/// Builds the grpcurl argument list after validating every user supplied value.
+///
+/// Flag values are always passed as separate arguments and the positional arguments follow a
+/// `--` terminator, so nothing the user types can be parsed as a grpcurl flag.
+pub fn build_args(spec: &CallSpec) -> BridgeResult<Vec<String>> {
+    validate_method(spec.service, spec.method)?;
+    let mut args: Vec<String> = Vec::new();
+    if !spec.tls { args.push("-plaintext".into()); }
+    if spec.target.is_unix() { args.push("-unix".into()); }
+    if let Some(ip) = spec.import_path { args.push("-import-path".into()); args.push(ip.into()); }
+    for f in spec.proto_files {
+        if f.trim().is_empty() { return Err(BridgeError::InvalidArgument("empty proto file path".into())); }
+        args.push("-proto".into());
+        args.push(f.clone());
+    }
+    for h in spec.headers {
+        validate_header(h)?;
+        args.push("-H".into());
+        args.push(h.clone());
+    }
+    // verbose output carries response headers and trailers, see `parse_output`
+    args.push("-v".into());
+    args.push("-d".into());
+    args.push("@".into());
+    args.push("--".into());
+    args.push(spec.target.dial_address());
+    args.push(format!("{}/{}", spec.service, spec.method));
+    Ok(args)
+}
+
+/// Builds the argument list for a reflection query: `list` without `symbol`, `describe symbol`
+/// with one. Validated the same way as [`build_args`].
+pub fn build_reflect_args(target: &Target, tls: bool, headers: &[String], symbol: Option<&str>) -> BridgeResult<Vec<String>> {
+    let mut args: Vec<String> = Vec::new();
+    if !tls { args.push("-plaintext".into()); }
+    if target.is_unix() { args.push("-unix".into()); }
+    for h in headers {
+        validate_header(h)?;
+        args.push("-H".into());
+        args.push(h.clone());
+    }
+    args.push("--".into());
+    args.push(target.dial_address());
+    match symbol {
+        Some(s) if !RE_FQ_SERVICE.is_match(s) => return Err(BridgeError::InvalidArgument(format!("invalid symbol {:?}", s))),
+        Some(s) => { args.push("describe".into()); args.push(s.into()); }
+        None => args.push("list".into()),
+    }
+    Ok(args)
+}
+
+pub fn validate_method(service: &str, method: &str) -> BridgeResult<()> {
+    if !RE_FQ_SERVICE.is_match(service) || !RE_METHOD.is_match(method) {
+        return Err(BridgeError::InvalidMethod { service: service.into(), method: method.into() });
+    }
+    Ok(())
+}
+
+/// Validates a `Key: Value` metadata entry. Only the key is echoed back in errors.
+pub fn validate_header(header: &str) -> BridgeResult<()> {
+    let (name, value) = header.split_once(':')
+        .ok_or_else(|| BridgeError::InvalidHeader { name: String::new(), reason: "expected \"Key: Value\"".into() })?;
+    let name = name.trim();
+    if !RE_HEADER_NAME.is_match(name) {
+        return Err(BridgeError::InvalidHeader { name: name.into(), reason: "invalid metadata key".into() });
+    }
+    if value.chars().any(|c| c.is_control() && c != '\t') {
+        return Err(BridgeError::InvalidHeader { name: name.into(), reason: "value contains control characters".into() });
+    }
+    Ok(())
+}
+
+/// Response metadata entries in the order grpcurl printed them.
+pub type Metadata = Vec<(String, String)>;
+
+/// What grpcurl's verbose (`-v`) stdout contains besides the request echo.
+#[derive(Debug, Clone, Default)]
+pub struct VerboseOutput {
+    /// Response messages, each as the JSON text grpcurl printed.
+    pub messages: Vec<String>,
+    pub headers: Metadata,
+    pub trailers: Metadata,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Section { Skip, Headers, Contents, Trailers }
+
+/// Splits verbose stdout into sections. The "Request metadata to send" echo is dropped on
+/// purpose: it repeats header values, credentials included.
+pub fn parse_output(stdout: &str) -> VerboseOutput {
+    let mut out = VerboseOutput::default();
+    let mut section = Section::Skip;
+    let mut current = String::new();
+    let flush = |out: &mut VerboseOutput, current: &mut String| {
+        let msg = current.trim();
+        if !msg.is_empty() { out.messages.push(msg.to_string()); }
+        current.clear();
+    };
+    for line in stdout.lines() {
+        let next = match line.trim_end() {
+            "Response headers received:" => Some(Section::Headers),
+            "Response contents:" => Some(Section::Contents),
+            "Response trailers received:" => Some(Section::Trailers),
+            "Resolved method descriptor:" | "Request metadata to send:" => Some(Section::Skip),
+            l if l.starts_with("Sent ") && l.contains(" request") => Some(Section::Skip),
+            _ => None,
+        };
+        if let Some(next) = next {
+            if section == Section::Contents { flush(&mut out, &mut current); }
+            section = next;
+            continue;
+        }
+        match section {
+            Section::Contents => { current.push_str(line); current.push('\n'); }
+            Section::Headers | Section::Trailers => {
+                if let Some((k, v)) = line.split_once(':') {
+                    let entry = (k.trim().to_string(), v.trim().to_string());
+                    if section == Section::Headers { out.headers.push(entry) } else { out.trailers.push(entry) }
+                }
+            }
+            Section::Skip => {}
+        }
+    }
+    if section == Section::Contents { flush(&mut out, &mut current); }
+    out
+}
+
+/// gRPC status code name and message from grpcurl's `ERROR:` block on stderr.
+pub fn parse_status(stderr: &str) -> Option<(String, String)> {
+    let code = stderr.lines().find_map(|l| l.trim().strip_prefix("Code: "))?.trim().to_string();
+    let message = stderr.lines().find_map(|l| l.trim().strip_prefix("Message: ")).unwrap_or("").trim().to_string();
+    Some((code, message))
+}
+
+/// Empty payloads are sent as an empty message.
+pub fn effective_payload(payload: &str) -> &str {
+    if payload.trim().is_empty() { "{}" } else { payload }
+}
+
+/// Oldest grpcurl release the bridge is known to work with.
+pub const MIN_VERSION: (u32, u32, u32) = (1, 8, 0);
+
+const BINARY_NAME: &str = "grpcurl";
+
+/// User configuration for the grpcurl binary.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GrpcurlConfig {
+    /// Explicit binary path; when set, no other location is tried.
+    pub path: Option<String>,
+    /// Hex encoded SHA-256 the binary must match.
+    pub expected_sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BinarySource { Configured, Sidecar, Path }
+
+/// A located and verified grpcurl binary.
+#[derive(Debug, Clone, Serialize)]
+pub struct GrpcurlInfo {
+    pub path: PathBuf,
+    pub source: BinarySource,
+    /// `None` for development builds that report no version.
+    pub version: Option<String>,
+    pub sha256: String,
+    pub sha256_verified: bool,
+}
+
+/// Finds the grpcurl binary: the configured path, then a Tauri sidecar next to the
+/// executable (`externalBin` in tauri.conf.json), then `PATH`.
+pub fn locate(config: &GrpcurlConfig, sidecar_dir: Option<&Path>) -> BridgeResult<(PathBuf, BinarySource)> {
+    let file_name = format!("{}{}", BINARY_NAME, std::env::consts::EXE_SUFFIX);
+    if let Some(p) = config.path.as_deref().filter(|p| !p.trim().is_empty()) {
+        let p = PathBuf::from(p);
+        return if p.is_file() { Ok((p, BinarySource::Configured)) }
+            else { Err(BridgeError::GrpcurlMissing { searched: vec![p.to_string_lossy().to_string()] }) };
+    }
+    let mut searched = Vec::new();
+    if let Some(dir) = sidecar_dir {
+        let candidate = dir.join(&file_name);
+        if candidate.is_file() { return Ok((candidate, BinarySource::Sidecar)); }
+        searched.push(candidate.to_string_lossy().to_string());
+    }
+    for dir in std::env::var_os("PATH").map(|p| std::env::split_paths(&p).collect::<Vec<_>>()).unwrap_or_default() {
+        let candidate = dir.join(&file_name);
+        if candidate.is_file() { return Ok((candidate, BinarySource::Path)); }
+        searched.push(candidate.to_string_lossy().to_string());
+    }
+    Err(BridgeError::GrpcurlMissing { searched })
+}
+
+/// Locates grpcurl, verifies its checksum against the configured pin and checks its version.
+pub async fn inspect(config: &GrpcurlConfig, sidecar_dir: Option<&Path>) -> BridgeResult<GrpcurlInfo> {
+    let (path, source) = locate(config, sidecar_dir)?;
+    let incompatible = |reason: String| BridgeError::GrpcurlIncompatible { path: path.to_string_lossy().to_string(), reason };
+
+    let bytes = tokio::fs::read(&path).await.map_err(|e| incompatible(format!("cannot read binary: {}", e)))?;
+    let sha256 = hex_digest(&bytes);
+    let sha256_verified = match config.expected_sha256.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        Some(expected) if !expected.eq_ignore_ascii_case(&sha256) => {
+            return Err(incompatible(format!("sha256 mismatch: expected {}, found {}", expected, sha256)));
+        }
+        Some(_) => true,
+        None => false,
+    };
+
+    let out = tokio::process::Command::new(&path).arg("-version").output().await
+        .map_err(|e| incompatible(format!("cannot execute: {}", e)))?;
+    // grpcurl prints its version to stdout or stderr depending on the release
+    let text = format!("{}{}", String::from_utf8_lossy(&out.stdout), String::from_utf8_lossy(&out.stderr));
+    let version = parse_version(&text);
+    if let Some(v) = version {
+        if v < MIN_VERSION {
+            return Err(incompatible(format!("version {}.{}.{} is older than the required {}.{}.{}", v.0, v.1, v.2, MIN_VERSION.0, MIN_VERSION.1, MIN_VERSION.2)));
+        }
+    } else if !text.contains(BINARY_NAME) {
+        return Err(incompatible("binary does not identify itself as grpcurl".into()));
+    }
+    Ok(GrpcurlInfo {
+        path,
+        source,
+        version: version.map(|(a, b, c)| format!("{}.{}.{}", a, b, c)),
+        sha256,
+        sha256_verified,
+    })
+}
+
+/// Extracts `1.9.1` from output such as `grpcurl v1.9.1`.
+fn parse_version(text: &str) -> Option<(u32, u32, u32)> {
+    text.split_whitespace().find_map(|word| {
+        let mut parts = word.strip_prefix('v')?.splitn(3, '.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next()?.parse().ok()?;
+        let patch_str: String = parts.next()?.chars().take_while(|c| c.is_ascii_digit()).collect();
+        Some((major, minor, patch_str.parse().ok()?))
+    })
+}
+
+fn hex_digest(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// A fake grpcurl in `dir` that prints `version_output` for `-version`.
+    fn fake_binary(dir: &Path, version_output: &str) -> PathBuf {
+        let path = dir.join(BINARY_NAME);
+        std::fs::write(&path, format!("#!/bin/sh\necho '{}'\n", version_output)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    fn configured(path: &Path, expected_sha256: Option<&str>) -> GrpcurlConfig {
+        GrpcurlConfig { path: Some(path.to_string_lossy().to_string()), expected_sha256: expected_sha256.map(str::to_string) }
+    }
+
+    #[test]
+    fn configured_path_wins_over_the_sidecar() {
+        let (configured_dir, sidecar_dir) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let own = fake_binary(configured_dir.path(), "grpcurl v1.9.1");
+        let sidecar = fake_binary(sidecar_dir.path(), "grpcurl v1.9.1");
+
+        assert_eq!(locate(&configured(&own, None), Some(sidecar_dir.path())).unwrap(), (own, BinarySource::Configured));
+        let blank = GrpcurlConfig { path: Some("  ".into()), expected_sha256: None };
+        assert_eq!(locate(&blank, Some(sidecar_dir.path())).unwrap(), (sidecar, BinarySource::Sidecar));
+    }
+
+    #[test]
+    fn a_missing_configured_path_is_not_replaced_by_another_binary() {
+        let sidecar_dir = tempfile::tempdir().unwrap();
+        fake_binary(sidecar_dir.path(), "grpcurl v1.9.1");
+        let missing = sidecar_dir.path().join("nope/grpcurl");
+        match locate(&configured(&missing, None), Some(sidecar_dir.path())) {
+            Err(BridgeError::GrpcurlMissing { searched }) => assert_eq!(searched, [missing.to_string_lossy().to_string()]),
+            other => panic!("expected GrpcurlMissing, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn versions_are_read_from_grpcurl_output() {
+        assert_eq!(parse_version("grpcurl v1.9.1"), Some((1, 9, 1)));
+        assert_eq!(parse_version("grpcurl v1.10.0-rc1\n"), Some((1, 10, 0)));
+        assert_eq!(parse_version("grpcurl dev build <no version set>"), None);
+    }
+
+    #[tokio::test]
+    async fn inspect_checks_version_and_pin() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = fake_binary(dir.path(), "grpcurl v1.9.1");
+        let sha256 = hex_digest(&std::fs::read(&path).unwrap());
+
+        let info = inspect(&configured(&path, None), None).await.unwrap();
+        assert_eq!((info.version.as_deref(), info.sha256_verified), (Some("1.9.1"), false));
+        let info = inspect(&configured(&path, Some(&sha256.to_uppercase())), None).await.unwrap();
+        assert!(info.sha256_verified);
+        let err = inspect(&configured(&path, Some(&"0".repeat(64))), None).await.unwrap_err();
+        assert!(matches!(err, BridgeError::GrpcurlIncompatible { ref reason, .. } if reason.starts_with("sha256 mismatch")), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn inspect_rejects_old_and_foreign_binaries() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = fake_binary(dir.path(), "grpcurl v1.7.9");
+        let err = inspect(&configured(&old, None), None).await.unwrap_err();
+        assert!(matches!(err, BridgeError::GrpcurlIncompatible { ref reason, .. } if reason.contains("older than the required 1.8.0")), "{:?}", err);
+
+        let dev = fake_binary(dir.path(), "grpcurl dev build <no version set>");
+        assert_eq!(inspect(&configured(&dev, None), None).await.unwrap().version, None);
+
+        let foreign = fake_binary(dir.path(), "curl 8.5.0");
+        let err = inspect(&configured(&foreign, None), None).await.unwrap_err();
+        assert!(matches!(err, BridgeError::GrpcurlIncompatible { ref reason, .. } if reason.contains("does not identify")), "{:?}", err);
+    }
+}
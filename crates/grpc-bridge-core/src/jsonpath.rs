@@ -0,0 +1,160 @@
+use serde_json::Value;
+
+/// One step of a JSONPath expression.
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Field(String),
+    Index(i64),
+    Wildcard,
+    /// `..name`: `name` at any depth.
+    Descendant(String),
+}
+
+/// The JSONPath subset used by extraction rules and assertions: `$`, `.field`, `['field']`,
+/// `[0]`, `[-1]`, `[*]`, `.*` and `..field`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+impl JsonPath {
+    pub fn parse(path: &str) -> Result<Self, String> {
+        let err = |msg: &str| format!("invalid JSONPath {:?}: {}", path, msg);
+        let rest = path.trim();
+        let mut rest = rest.strip_prefix('$').ok_or_else(|| err("must start with \"$\""))?;
+        let mut segments = Vec::new();
+        while !rest.is_empty() {
+            if let Some(r) = rest.strip_prefix("..") {
+                let (name, r) = split_name(r);
+                if name.is_empty() { return Err(err("expected a field name after \"..\"")); }
+                segments.push(Segment::Descendant(name.into()));
+                rest = r;
+            } else if let Some(r) = rest.strip_prefix('.') {
+                if let Some(r) = r.strip_prefix('*') { segments.push(Segment::Wildcard); rest = r; continue; }
+                let (name, r) = split_name(r);
+                if name.is_empty() { return Err(err("expected a field name after \".\"")); }
+                segments.push(Segment::Field(name.into()));
+                rest = r;
+            } else if let Some(r) = rest.strip_prefix('[') {
+                let end = r.find(']').ok_or_else(|| err("unclosed \"[\""))?;
+                let inner = r[..end].trim();
+                segments.push(if inner == "*" {
+                    Segment::Wildcard
+                } else if let Some(q) = inner.strip_prefix('\'').and_then(|s| s.strip_suffix('\''))
+                    .or_else(|| inner.strip_prefix('"').and_then(|s| s.strip_suffix('"'))) {
+                    Segment::Field(q.into())
+                } else {
+                    Segment::Index(inner.parse().map_err(|_| err("expected an index, '*' or a quoted name in brackets"))?)
+                });
+                rest = &r[end + 1..];
+            } else {
+                return Err(err(&format!("unexpected {:?}", rest)));
+            }
+        }
+        Ok(Self { segments })
+    }
+
+    /// All values the path matches, in document order.
+    pub fn select<'v>(&self, root: &'v Value) -> Vec<&'v Value> {
+        let mut current = vec![root];
+        for seg in &self.segments {
+            let mut next = Vec::new();
+            for v in current {
+                match seg {
+                    Segment::Field(name) => next.extend(v.get(name.as_str())),
+                    Segment::Index(i) => {
+                        if let Value::Array(items) = v {
+                            let idx = if *i < 0 { items.len() as i64 + i } else { *i };
+                            next.extend(usize::try_from(idx).ok().and_then(|i| items.get(i)));
+                        }
+                    }
+                    Segment::Wildcard => match v {
+                        Value::Object(map) => next.extend(map.values()),
+                        Value::Array(items) => next.extend(items.iter()),
+                        _ => {}
+                    },
+                    Segment::Descendant(name) => descendants(v, name, &mut next),
+                }
+            }
+            current = next;
+        }
+        current
+    }
+
+    /// The first match.
+    pub fn first<'v>(&self, root: &'v Value) -> Option<&'v Value> {
+        self.select(root).into_iter().next()
+    }
+}
+
+fn split_name(s: &str) -> (&str, &str) {
+    let end = s.find(['.', '[']).unwrap_or(s.len());
+    s.split_at(end)
+}
+
+fn descendants<'v>(v: &'v Value, name: &str, out: &mut Vec<&'v Value>) {
+    match v {
+        Value::Object(map) => {
+            if let Some(found) = map.get(name) { out.push(found); }
+            for child in map.values() { descendants(child, name, out); }
+        }
+        Value::Array(items) => for child in items { descendants(child, name, out); },
+        _ => {}
+    }
+}
+
+/// Text form of a matched value: strings without quotes, everything else as JSON.
+pub fn to_text(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn segments(path: &str) -> Vec<Segment> {
+        JsonPath::parse(path).unwrap().segments
+    }
+
+    #[test]
+    fn every_supported_form_parses() {
+        use Segment::*;
+        assert_eq!(segments("$"), []);
+        assert_eq!(segments(" $.a.b_c "), [Field("a".into()), Field("b_c".into())]);
+        assert_eq!(segments("$['a b'][\"c\"]"), [Field("a b".into()), Field("c".into())]);
+        assert_eq!(segments("$.items[0][-1][ * ].*"), [Field("items".into()), Index(0), Index(-1), Wildcard, Wildcard]);
+        assert_eq!(segments("$..token.id"), [Descendant("token".into()), Field("id".into())]);
+    }
+
+    #[test]
+    fn malformed_paths_are_rejected() {
+        for (path, reason) in [
+            ("a.b", "must start with"),
+            ("$.", "after \".\""),
+            ("$..", "after \"..\""),
+            ("$.a[0", "unclosed"),
+            ("$[x]", "expected an index"),
+            ("$a", "unexpected"),
+        ] {
+            let err = JsonPath::parse(path).unwrap_err();
+            assert!(err.contains(reason), "{:?}: {}", path, err);
+        }
+    }
+
+    #[test]
+    fn selection_follows_document_order() {
+        let doc = json!({ "items": [{ "id": 1, "tags": ["x"] }, { "id": 2 }], "meta": { "id": 3 } });
+        let select = |path: &str| JsonPath::parse(path).unwrap().select(&doc).into_iter().cloned().collect::<Vec<_>>();
+        assert_eq!(select("$.items[*].id"), [json!(1), json!(2)]);
+        assert_eq!(select("$.items[-1].id"), [json!(2)]);
+        assert_eq!(select("$..id"), [json!(1), json!(2), json!(3)]);
+        assert_eq!(select("$.meta.*"), [json!(3)]);
+        assert!(select("$.items[5]").is_empty() && select("$.items[-3]").is_empty() && select("$.meta.id.x").is_empty());
+        assert_eq!(to_text(&json!("s")), "s");
+        assert_eq!(to_text(&json!({ "a": 1 })), r#"{"a":1}"#);
+    }
+}
@@ -0,0 +1,27 @@
+//! Everything gRPC Bridge does without a UI, used by the Tauri app and by `grpc-bridge-cli`:
+//! [`ProtoIndex`] finds services in `.proto` files, [`CallEngine`] runs grpcurl and
+//! [`Workspace`] keeps history, collections and environments. [`Bridge`] wires them together.
+
+pub mod assertions;
+pub mod bridge;
+pub mod call;
+pub mod error;
+pub mod extract;
+pub mod fuzz;
+pub mod grpcurl;
+pub mod jsonpath;
+pub mod payload;
+pub mod proto_index;
+pub mod redact;
+pub mod runner;
+pub mod secrets;
+pub mod storage;
+pub mod target;
+pub mod template;
+pub mod workspace;
+
+pub use bridge::{Bridge, CompletedCall, PreparedCall, RunParams};
+pub use call::{CallEngine, EnvironmentReport};
+pub use proto_index::{MethodMeta, ProtoIndex, ServiceMeta};
+pub use error::{BridgeError, BridgeResult};
+pub use workspace::{ResolvedEnvironment, Workspace};
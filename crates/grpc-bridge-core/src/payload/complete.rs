@@ -0,0 +1,229 @@
+use crate::proto_index::schema::{FieldDef, FieldType, Label, Scalar, Schema};
+use serde::Serialize;
+
+/// Suggestions for the cursor position in a partially typed payload. `from`..`to` is the text the
+/// chosen item's `insert_text` replaces; offsets are UTF-16 code units, as the editor counts them.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Completions {
+    pub from: usize,
+    pub to: usize,
+    pub items: Vec<CompletionItem>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CompletionItem {
+    pub label: String,
+    pub kind: CompletionKind,
+    /// Field type, or the enum a value belongs to.
+    pub detail: Option<String>,
+    /// The field's doc comment.
+    pub documentation: Option<String>,
+    pub insert_text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompletionKind { Field, EnumValue, Keyword }
+
+/// What the JSON at the cursor belongs to.
+enum Container {
+    /// An object whose keys are the fields of a message.
+    Message(String),
+    /// An array or map object whose elements or values have this type.
+    Elements(FieldType),
+    /// JSON the schema says nothing about (`Struct`, unresolved types, ...).
+    Opaque,
+}
+
+/// An object or array open at the cursor, outermost first.
+enum Frame {
+    Object { keys: Vec<String>, key: Option<String>, after_colon: bool, has_value: bool },
+    Array { has_value: bool },
+}
+
+/// The token being typed at the cursor.
+struct Partial {
+    text: String,
+    /// Inside a string literal, so suggestions go in without quotes.
+    quoted: bool,
+}
+
+/// Suggestions at `cursor` (UTF-16 offset) in `payload`, read as the message `message`.
+pub fn complete(schema: &Schema, message: &str, payload: &str, cursor: usize) -> Completions {
+    let before = &payload[..byte_offset(payload, cursor)];
+    let to = before.encode_utf16().count();
+    let Some((frames, partial)) = scan(before) else { return Completions { from: to, to, items: Vec::new() }; };
+    let mut out = Completions { from: to - partial.text.encode_utf16().count(), to, items: Vec::new() };
+    let mut container = Container::Message(message.to_string());
+    for pair in frames.windows(2) {
+        container = child(schema, &container, &pair[0]);
+    }
+    let prefix = partial.text.to_lowercase();
+    match (frames.last(), &container) {
+        (Some(Frame::Object { keys, key: None, .. }), Container::Message(name)) => {
+            let Some(def) = schema.message(name) else { return out; };
+            let set = |f: &FieldDef| keys.iter().any(|k| *k == f.json_name || *k == f.name);
+            let taken_oneofs: Vec<usize> = def.fields.iter().filter(|f| set(f)).filter_map(|f| f.oneof).collect();
+            for field in &def.fields {
+                if set(field) || field.oneof.is_some_and(|o| taken_oneofs.contains(&o)) { continue; }
+                if !field.json_name.to_lowercase().starts_with(&prefix) && !field.name.to_lowercase().starts_with(&prefix) { continue; }
+                out.items.push(CompletionItem {
+                    label: field.json_name.clone(),
+                    kind: CompletionKind::Field,
+                    detail: Some(type_label(field)),
+                    documentation: field.doc.clone(),
+                    insert_text: if partial.quoted { field.json_name.clone() } else { format!("\"{}\": ", field.json_name) },
+                });
+            }
+        }
+        (Some(Frame::Object { key: Some(key), after_colon: true, has_value: false, .. }), Container::Message(name)) => {
+            let Some(field) = schema.message(name).and_then(|m| m.field(key)) else { return out; };
+            if field.label != Label::Repeated { out.items = values(schema, &field.ty, &partial); }
+        }
+        (Some(Frame::Object { after_colon: true, has_value: false, .. } | Frame::Array { has_value: false }), Container::Elements(ty)) => {
+            out.items = values(schema, ty, &partial);
+        }
+        _ => {}
+    }
+    out.items.retain(|i| i.label.to_lowercase().starts_with(&prefix) || i.kind == CompletionKind::Field);
+    out
+}
+
+/// The container a nested frame stands for, given the frame enclosing it.
+fn child(schema: &Schema, parent: &Container, frame: &Frame) -> Container {
+    match (parent, frame) {
+        (Container::Message(name), Frame::Object { key: Some(key), .. }) => {
+            let Some(field) = schema.message(name).and_then(|m| m.field(key)) else { return Container::Opaque; };
+            match (&field.ty, field.label) {
+                (FieldType::Map(_, ty), _) => Container::Elements((**ty).clone()),
+                (ty, Label::Repeated) => Container::Elements(ty.clone()),
+                (ty, _) => single(ty),
+            }
+        }
+        (Container::Elements(ty), _) => single(ty),
+        _ => Container::Opaque,
+    }
+}
+
+fn single(ty: &FieldType) -> Container {
+    match ty {
+        FieldType::Message(name) if super::well_known_default(name).is_none() => Container::Message(name.clone()),
+        _ => Container::Opaque,
+    }
+}
+
+/// Values that fit a field of type `ty`: enum names, `true`/`false` and `null`.
+fn values(schema: &Schema, ty: &FieldType, partial: &Partial) -> Vec<CompletionItem> {
+    let keyword = |word: &str| CompletionItem { label: word.into(), kind: CompletionKind::Keyword, detail: None, documentation: None, insert_text: word.into() };
+    if partial.quoted {
+        if let FieldType::Enum(name) = ty { return enum_values(schema, name, true); }
+        return Vec::new();
+    }
+    match ty {
+        FieldType::Enum(name) if name == "google.protobuf.NullValue" => vec![keyword("null")],
+        FieldType::Enum(name) => enum_values(schema, name, false),
+        FieldType::Scalar(Scalar::Bool) => vec![keyword("true"), keyword("false")],
+        FieldType::Message(name) if name == "google.protobuf.BoolValue" || name == "google.protobuf.Value" => vec![keyword("true"), keyword("false"), keyword("null")],
+        FieldType::Message(_) => vec![keyword("null")],
+        _ => Vec::new(),
+    }
+}
+
+fn enum_values(schema: &Schema, name: &str, quoted: bool) -> Vec<CompletionItem> {
+    let Some(def) = schema.enum_def(name) else { return Vec::new(); };
+    def.values.iter().map(|(value, _)| CompletionItem {
+        label: value.clone(),
+        kind: CompletionKind::EnumValue,
+        detail: Some(def.full_name.clone()),
+        documentation: None,
+        insert_text: if quoted { value.clone() } else { format!("\"{}\"", value) },
+    }).collect()
+}
+
+fn type_label(field: &FieldDef) -> String {
+    let name = |ty: &FieldType| match ty {
+        FieldType::Scalar(s) => s.name().to_string(),
+        FieldType::Message(n) | FieldType::Enum(n) | FieldType::Unresolved(n) => n.clone(),
+        FieldType::Map(..) => "map".into(),
+    };
+    match (&field.ty, field.label) {
+        (FieldType::Map(key, value), _) => format!("map<{}, {}>", key.name(), name(value)),
+        (ty, Label::Repeated) => format!("repeated {}", name(ty)),
+        (ty, _) => name(ty),
+    }
+}
+
+/// Reads `text` (the payload up to the cursor) leniently and returns the open containers and
+/// the token being typed, or `None` where nothing can be suggested (e.g. inside a placeholder).
+fn scan(text: &str) -> Option<(Vec<Frame>, Partial)> {
+    let bytes = text.as_bytes();
+    let mut frames: Vec<Frame> = Vec::new();
+    let mut i = 0;
+    let value_done = |frames: &mut Vec<Frame>| match frames.last_mut() {
+        Some(Frame::Object { has_value, .. } | Frame::Array { has_value }) => *has_value = true,
+        None => {}
+    };
+    while i < bytes.len() {
+        match bytes[i] {
+            b'{' if bytes.get(i + 1) == Some(&b'{') => {
+                i += text[i..].find("}}")? + 2;
+                value_done(&mut frames);
+                continue;
+            }
+            b'{' => frames.push(Frame::Object { keys: Vec::new(), key: None, after_colon: false, has_value: false }),
+            b'[' => frames.push(Frame::Array { has_value: false }),
+            b'}' | b']' => { frames.pop(); value_done(&mut frames); }
+            b':' => if let Some(Frame::Object { after_colon, .. }) = frames.last_mut() { *after_colon = true; },
+            b',' => match frames.last_mut() {
+                Some(Frame::Object { key, after_colon, has_value, .. }) => { *key = None; *after_colon = false; *has_value = false; }
+                Some(Frame::Array { has_value }) => *has_value = false,
+                None => {}
+            },
+            b'"' => {
+                let (content, len) = read_string(&text[i + 1..]);
+                let Some(len) = len else { return Some((frames, Partial { text: content, quoted: true })); };
+                match frames.last_mut() {
+                    Some(Frame::Object { keys, key, after_colon: false, .. }) => { keys.push(content.clone()); *key = Some(content); }
+                    _ => value_done(&mut frames),
+                }
+                i += len + 2;
+                continue;
+            }
+            b if b.is_ascii_whitespace() => {}
+            _ => {
+                let word_len = text[i..].find(|c: char| !(c.is_alphanumeric() || matches!(c, '-' | '+' | '.' | '_'))).unwrap_or(text.len() - i);
+                if i + word_len == text.len() { return Some((frames, Partial { text: text[i..].to_string(), quoted: false })); }
+                value_done(&mut frames);
+                i += word_len.max(text[i..].chars().next().map_or(1, char::len_utf8));
+                continue;
+            }
+        }
+        i += 1;
+    }
+    Some((frames, Partial { text: String::new(), quoted: false }))
+}
+
+/// The contents of a string literal starting after its opening quote, and its byte length
+/// without the quotes, or `None` when it is not closed.
+fn read_string(rest: &str) -> (String, Option<usize>) {
+    let mut out = String::new();
+    let mut chars = rest.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return (out, Some(i)),
+            '\\' => if let Some((_, e)) = chars.next() { out.push(match e { 'n' => '\n', 't' => '\t', e => e }); },
+            c => out.push(c),
+        }
+    }
+    (out, None)
+}
+
+/// Byte index of a UTF-16 offset, clamped to the text.
+fn byte_offset(text: &str, utf16: usize) -> usize {
+    let mut units = 0;
+    for (i, c) in text.char_indices() {
+        if units >= utf16 { return i; }
+        units += c.len_utf16();
+    }
+    text.len()
+}
@@ -0,0 +1,97 @@
+use super::{text, wire};
+use crate::proto_index::schema::Schema;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// How a payload is written: canonical JSON, protobuf text format, or the binary wire format
+/// as base64 or hex.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadFormat {
+    #[default]
+    Json,
+    Text,
+    BinaryBase64,
+    Hex,
+}
+
+impl PayloadFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Text => "text",
+            Self::BinaryBase64 => "binary_base64",
+            Self::Hex => "hex",
+        }
+    }
+}
+
+impl std::str::FromStr for PayloadFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "text" => Ok(Self::Text),
+            "binary_base64" | "base64" => Ok(Self::BinaryBase64),
+            "hex" => Ok(Self::Hex),
+            other => Err(format!("unknown payload format {:?} (expected json, text, binary_base64 or hex)", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for PayloadFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { f.write_str(self.as_str()) }
+}
+
+/// `payload`, written in `format`, as a JSON document of `message`. JSON is returned unchanged.
+pub fn to_json(schema: &Schema, message: &str, format: PayloadFormat, payload: &str) -> Result<String, String> {
+    let bytes = match format {
+        PayloadFormat::Json => return Ok(payload.to_string()),
+        PayloadFormat::Text => text::to_wire(schema, message, payload)?,
+        PayloadFormat::BinaryBase64 => decode_base64(payload)?,
+        PayloadFormat::Hex => decode_hex(payload)?,
+    };
+    let value = wire::to_json(schema, message, &bytes)?;
+    Ok(serde_json::to_string(&value).unwrap_or_default())
+}
+
+/// A JSON document of `message` rendered in `format`.
+pub fn from_json(schema: &Schema, message: &str, format: PayloadFormat, value: &Value) -> Result<String, String> {
+    if format == PayloadFormat::Json { return Ok(serde_json::to_string_pretty(value).unwrap_or_default()); }
+    let bytes = wire::from_json(schema, message, value)?;
+    Ok(match format {
+        PayloadFormat::Text => text::print(schema, message, &bytes)?,
+        PayloadFormat::BinaryBase64 => base64::engine::general_purpose::STANDARD.encode(&bytes),
+        _ => bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+    })
+}
+
+/// Raw protobuf bytes written as `binary_base64` or `hex`; other formats are rejected.
+pub fn decode_binary(format: PayloadFormat, text: &str) -> Result<Vec<u8>, String> {
+    match format {
+        PayloadFormat::BinaryBase64 => decode_base64(text),
+        PayloadFormat::Hex => decode_hex(text),
+        other => Err(format!("raw bytes are written as binary_base64 or hex, not {}", other)),
+    }
+}
+
+/// Standard or URL-safe base64, padded or not; whitespace is ignored.
+pub fn decode_base64(text: &str) -> Result<Vec<u8>, String> {
+    use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD};
+    let compact: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    [STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD].iter().find_map(|e| e.decode(&compact).ok())
+        .ok_or_else(|| "not valid base64".to_string())
+}
+
+/// Hex digits, optionally prefixed with `0x`; whitespace and `:` separators are ignored.
+pub fn decode_hex(text: &str) -> Result<Vec<u8>, String> {
+    let trimmed = text.trim();
+    let digits: Vec<u32> = trimmed.strip_prefix("0x").unwrap_or(trimmed).chars()
+        .filter(|c| !c.is_whitespace() && *c != ':')
+        .map(|c| c.to_digit(16).ok_or_else(|| format!("{:?} is not a hex digit", c)))
+        .collect::<Result<_, _>>()?;
+    if !digits.len().is_multiple_of(2) { return Err("odd number of hex digits".into()); }
+    Ok(digits.chunks(2).map(|pair| (pair[0] * 16 + pair[1]) as u8).collect())
+}
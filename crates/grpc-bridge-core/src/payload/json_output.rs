@@ -0,0 +1,157 @@
+//! Options for how response JSON is written, applied to canonical JSON with the field types of a
+//! [`Schema`].
+
+use super::skeleton::scalar_default;
+use crate::proto_index::schema::{FieldDef, FieldType, Label, Schema};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+/// Largest integer a JavaScript number holds exactly (2^53 - 1).
+const MAX_SAFE_INTEGER: i128 = (1 << 53) - 1;
+
+/// How 64-bit integers are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Int64Format {
+    /// Decimal strings, as in canonical JSON.
+    #[default]
+    String,
+    /// Numbers; JavaScript rounds those beyond 2^53.
+    Number,
+    /// Numbers where JavaScript holds them exactly, strings beyond.
+    Lossless,
+}
+
+impl std::str::FromStr for Int64Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "string" => Ok(Self::String),
+            "number" => Ok(Self::Number),
+            "lossless" => Ok(Self::Lossless),
+            other => Err(format!("unknown int64 format {:?} (expected string, number or lossless)", other)),
+        }
+    }
+}
+
+/// How response JSON is written. The default is canonical JSON, which is what grpcurl prints.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JsonOptions {
+    /// Add unset fields at their default value, except singular messages, `optional` and oneof fields.
+    pub emit_defaults: bool,
+    /// Name fields as in the .proto file instead of lowerCamelCase.
+    pub proto_names: bool,
+    /// Write enum values as numbers instead of names.
+    pub enums_as_numbers: bool,
+    pub int64: Int64Format,
+}
+
+impl JsonOptions {
+    pub fn is_canonical(&self) -> bool { *self == Self::default() }
+}
+
+/// `value`, a canonical JSON document of `message`, rewritten with `options`. Fields the schema
+/// does not declare and values that do not match their field's type are kept as they are.
+pub fn apply(schema: &Schema, message: &str, value: &Value, options: &JsonOptions) -> Value {
+    Shaper { schema, options }.message(message.trim_start_matches('.'), value)
+}
+
+struct Shaper<'a> {
+    schema: &'a Schema,
+    options: &'a JsonOptions,
+}
+
+impl Shaper<'_> {
+    fn message(&self, name: &str, value: &Value) -> Value {
+        match (name, value) {
+            ("google.protobuf.Int64Value" | "google.protobuf.UInt64Value", _) => return self.int64(value),
+            ("google.protobuf.Any", Value::Object(obj)) => return self.any(obj),
+            (_, Value::Object(_)) if !name.starts_with("google.protobuf.") => {}
+            _ => return value.clone(),
+        }
+        let Value::Object(obj) = value else { return value.clone(); };
+        let Some(def) = self.schema.message(name) else { return value.clone(); };
+        let mut out = Map::new();
+        for field in &def.fields {
+            let key = if self.options.proto_names { &field.name } else { &field.json_name };
+            match obj.get(&field.json_name).or_else(|| obj.get(&field.name)) {
+                Some(v) => { out.insert(key.clone(), self.field(field, v)); }
+                None if self.options.emit_defaults && !field.has_presence => { out.insert(key.clone(), self.field(field, &self.default(field))); }
+                None => {}
+            }
+        }
+        for (key, v) in obj {
+            if def.field(key).is_none() { out.insert(key.clone(), v.clone()); }
+        }
+        Value::Object(out)
+    }
+
+    /// An `Any` with a message type of the schema; other types are kept as they are.
+    fn any(&self, obj: &Map<String, Value>) -> Value {
+        let type_name = obj.get("@type").and_then(Value::as_str).and_then(|url| url.rsplit('/').next()).unwrap_or_default();
+        if type_name.starts_with("google.protobuf.") || self.schema.message(type_name).is_none() { return Value::Object(obj.clone()); }
+        let fields: Map<String, Value> = obj.iter().filter(|(k, _)| *k != "@type").map(|(k, v)| (k.clone(), v.clone())).collect();
+        let mut out: Map<String, Value> = obj.get("@type").map(|url| ("@type".to_string(), url.clone())).into_iter().collect();
+        if let Value::Object(shaped) = self.message(type_name, &Value::Object(fields)) { out.extend(shaped); }
+        Value::Object(out)
+    }
+
+    fn field(&self, field: &FieldDef, v: &Value) -> Value {
+        match (&field.ty, field.label, v) {
+            (FieldType::Map(_, ty), _, Value::Object(entries)) => {
+                Value::Object(entries.iter().map(|(k, v)| (k.clone(), self.value(ty, v))).collect())
+            }
+            (ty, Label::Repeated, Value::Array(items)) => Value::Array(items.iter().map(|v| self.value(ty, v)).collect()),
+            (ty, _, v) => self.value(ty, v),
+        }
+    }
+
+    fn value(&self, ty: &FieldType, v: &Value) -> Value {
+        match ty {
+            FieldType::Scalar(s) if s.is_64bit() => self.int64(v),
+            FieldType::Enum(e) if e != "google.protobuf.NullValue" => {
+                let Some(def) = self.schema.enum_def(e) else { return v.clone(); };
+                match v {
+                    Value::String(name) if self.options.enums_as_numbers => def.values.iter().find(|(n, _)| n == name).map_or_else(|| v.clone(), |(_, number)| json!(number)),
+                    Value::Number(n) if !self.options.enums_as_numbers => n.as_i64()
+                        .and_then(|n| def.values.iter().find(|(_, number)| i64::from(*number) == n))
+                        .map_or_else(|| v.clone(), |(name, _)| json!(name)),
+                    _ => v.clone(),
+                }
+            }
+            FieldType::Message(m) => self.message(m, v),
+            _ => v.clone(),
+        }
+    }
+
+    fn int64(&self, v: &Value) -> Value {
+        let n: Option<i128> = match v {
+            Value::String(s) => s.parse().ok(),
+            Value::Number(n) => n.as_i64().map(i128::from).or_else(|| n.as_u64().map(i128::from)),
+            _ => None,
+        };
+        let Some(n) = n else { return v.clone(); };
+        let number = || if n < 0 { json!(n as i64) } else { json!(n as u64) };
+        match self.options.int64 {
+            Int64Format::String => json!(n.to_string()),
+            Int64Format::Number => number(),
+            Int64Format::Lossless if n.abs() <= MAX_SAFE_INTEGER => number(),
+            Int64Format::Lossless => json!(n.to_string()),
+        }
+    }
+
+    /// The canonical JSON of an unset field without presence.
+    fn default(&self, field: &FieldDef) -> Value {
+        match (&field.ty, field.label) {
+            (FieldType::Map(..), _) => json!({}),
+            (_, Label::Repeated) => json!([]),
+            (FieldType::Scalar(s), _) => scalar_default(*s),
+            (FieldType::Enum(e), _) => self.schema.enum_def(e)
+                .and_then(|d| d.values.iter().find(|(_, n)| *n == 0).or(d.values.first()))
+                .map_or(json!(0), |(name, _)| json!(name)),
+            _ => Value::Null,
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use crate::proto_index::schema::{FieldDef, FieldType, Label, Scalar, Schema};
+use serde_json::{json, Map, Value};
+
+pub const DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// A draft 2020-12 JSON Schema for `message` in the proto3 JSON mapping. Messages and enums are
+/// defined once under `$defs` and referenced, so recursive messages terminate; well-known types
+/// are inlined in their canonical JSON form.
+pub fn json_schema(schema: &Schema, message: &str) -> Value {
+    let mut defs = Map::new();
+    let root = message_ref(schema, message.trim_start_matches('.'), &mut defs);
+    let mut out = json!({ "$schema": DRAFT, "title": message.trim_start_matches('.') });
+    let obj = out.as_object_mut().unwrap();
+    if let Value::Object(root) = root { obj.extend(root); }
+    if !defs.is_empty() { obj.insert("$defs".into(), Value::Object(defs)); }
+    out
+}
+
+fn message_ref(schema: &Schema, name: &str, defs: &mut Map<String, Value>) -> Value {
+    if let Some(wkt) = well_known(name) { return wkt; }
+    let Some(def) = schema.message(name) else { return unresolved(name); };
+    if !defs.contains_key(name) {
+        // placeholder first: a field referring back to this message only needs the `$ref`
+        defs.insert(name.to_string(), Value::Null);
+        let mut properties = Map::new();
+        for field in &def.fields {
+            let mut property = nullable(field_schema(schema, field, defs));
+            if let (Some(doc), Value::Object(p)) = (&field.doc, &mut property) { p.insert("description".into(), json!(doc)); }
+            for name in names(field) { properties.insert(name.to_string(), property.clone()); }
+        }
+        let mut out = json!({ "type": "object", "title": name, "properties": properties, "additionalProperties": false });
+        // a field may be named either way, but only once
+        let mut exclusive: Vec<Value> = def.fields.iter().filter(|f| f.name != f.json_name)
+            .map(|f| json!({ "not": { "required": [f.json_name, f.name] } }))
+            .collect();
+        exclusive.extend((0..def.oneofs.len()).filter_map(|i| {
+            let members: Vec<&FieldDef> = def.fields.iter().filter(|f| f.oneof == Some(i)).collect();
+            let pairs: Vec<Value> = members.iter().enumerate()
+                .flat_map(|(n, a)| members[n + 1..].iter().map(move |b| (*a, *b)))
+                .flat_map(|(a, b)| names(a).into_iter().flat_map(move |a| names(b).into_iter().map(move |b| json!({ "required": [a, b] }))))
+                .collect();
+            (!pairs.is_empty()).then(|| json!({ "not": { "anyOf": pairs } }))
+        }));
+        if !exclusive.is_empty() { out["allOf"] = json!(exclusive); }
+        defs.insert(name.to_string(), out);
+    }
+    json!({ "$ref": format!("#/$defs/{}", name) })
+}
+
+/// The lowerCamelCase name and, when it differs, the .proto name; parsers accept both.
+fn names(field: &FieldDef) -> Vec<&str> {
+    if field.name == field.json_name { vec![&field.json_name] } else { vec![&field.json_name, &field.name] }
+}
+
+/// `property` also accepting `null`, which parsers read as the field's default.
+fn nullable(property: Value) -> Value {
+    match property {
+        Value::Object(mut p) if !p.contains_key("$ref") && !p.contains_key("anyOf") && p.contains_key("type") => {
+            let mut types = match p.remove("type") { Some(Value::Array(types)) => types, Some(t) => vec![t], None => Vec::new() };
+            if !types.contains(&json!("null")) { types.push(json!("null")); }
+            p.insert("type".into(), if types.len() == 1 { types.remove(0) } else { Value::Array(types) });
+            Value::Object(p)
+        }
+        // no `type`, so anything goes already, `null` included
+        Value::Object(p) if !p.contains_key("$ref") && !p.contains_key("anyOf") => Value::Object(p),
+        p => json!({ "anyOf": [p, { "type": "null" }] }),
+    }
+}
+
+fn field_schema(schema: &Schema, field: &FieldDef, defs: &mut Map<String, Value>) -> Value {
+    match (&field.ty, field.label) {
+        (FieldType::Map(key, value), _) => {
+            let mut out = json!({ "type": "object", "additionalProperties": type_schema(schema, value, defs) });
+            let keys = match key {
+                Scalar::String => None,
+                Scalar::Bool => Some("^(true|false)$"),
+                s if is_unsigned(*s) => Some("^[0-9]+$"),
+                _ => Some("^-?[0-9]+$"),
+            };
+            if let Some(pattern) = keys { out["propertyNames"] = json!({ "pattern": pattern }); }
+            out
+        }
+        (ty, Label::Repeated) => json!({ "type": "array", "items": type_schema(schema, ty, defs) }),
+        (ty, _) => type_schema(schema, ty, defs),
+    }
+}
+
+fn type_schema(schema: &Schema, ty: &FieldType, defs: &mut Map<String, Value>) -> Value {
+    match ty {
+        FieldType::Scalar(s) => scalar_schema(*s),
+        FieldType::Message(name) => message_ref(schema, name, defs),
+        FieldType::Enum(name) if name == "google.protobuf.NullValue" => json!({ "type": "null" }),
+        FieldType::Enum(name) => {
+            let Some(def) = schema.enum_def(name) else { return unresolved(name); };
+            if !defs.contains_key(name) {
+                let names: Vec<&str> = def.values.iter().map(|(n, _)| n.as_str()).collect();
+                defs.insert(name.clone(), json!({
+                    "title": name,
+                    "anyOf": [
+                        { "type": "string", "enum": names },
+                        { "type": "integer", "minimum": i32::MIN, "maximum": i32::MAX }
+                    ]
+                }));
+            }
+            json!({ "$ref": format!("#/$defs/{}", name) })
+        }
+        FieldType::Map(..) => json!({ "type": "object" }),
+        FieldType::Unresolved(name) => unresolved(name),
+    }
+}
+
+/// 64-bit integers are strings in canonical JSON; parsers accept numbers and decimal strings for
+/// every integer and float type, and `NaN`/`Infinity`/`-Infinity` for floats. The patterns only
+/// apply to the string form.
+fn scalar_schema(s: Scalar) -> Value {
+    let integer = |pattern: &str, min: Value, max: Value| json!({ "type": ["string", "integer"], "pattern": pattern, "minimum": min, "maximum": max });
+    match s {
+        Scalar::Bool => json!({ "type": "boolean" }),
+        Scalar::String => json!({ "type": "string" }),
+        Scalar::Bytes => json!({ "type": "string", "contentEncoding": "base64" }),
+        Scalar::Double | Scalar::Float => json!({ "type": ["number", "string"], "pattern": "^(NaN|-?Infinity|[+-]?([0-9]+\\.?[0-9]*|\\.[0-9]+)([eE][+-]?[0-9]+)?)$" }),
+        Scalar::Int32 | Scalar::Sint32 | Scalar::Sfixed32 => integer("^-?[0-9]+$", json!(i32::MIN), json!(i32::MAX)),
+        Scalar::Uint32 | Scalar::Fixed32 => integer("^[0-9]+$", json!(0), json!(u32::MAX)),
+        Scalar::Uint64 | Scalar::Fixed64 => integer("^[0-9]+$", json!(0), json!(u64::MAX)),
+        _ => integer("^-?[0-9]+$", json!(i64::MIN), json!(i64::MAX)),
+    }
+}
+
+fn is_unsigned(s: Scalar) -> bool { matches!(s, Scalar::Uint32 | Scalar::Uint64 | Scalar::Fixed32 | Scalar::Fixed64) }
+
+fn unresolved(name: &str) -> Value {
+    json!({ "description": format!("{} is not defined in the indexed protos", name) })
+}
+
+fn well_known(name: &str) -> Option<Value> {
+    let wrapper = |s: Scalar| Some(scalar_schema(s));
+    Some(match name {
+        "google.protobuf.Timestamp" => json!({ "type": "string", "format": "date-time" }),
+        "google.protobuf.Duration" => json!({ "type": "string", "pattern": "^-?[0-9]+(\\.[0-9]{1,9})?s$" }),
+        "google.protobuf.FieldMask" => json!({ "type": "string" }),
+        "google.protobuf.Empty" => json!({ "type": "object", "additionalProperties": false }),
+        "google.protobuf.Any" => json!({ "type": "object", "properties": { "@type": { "type": "string" } }, "required": ["@type"] }),
+        "google.protobuf.Struct" => json!({ "type": "object" }),
+        "google.protobuf.Value" => json!({}),
+        "google.protobuf.ListValue" => json!({ "type": "array" }),
+        "google.protobuf.DoubleValue" => return wrapper(Scalar::Double),
+        "google.protobuf.FloatValue" => return wrapper(Scalar::Float),
+        "google.protobuf.Int64Value" => return wrapper(Scalar::Int64),
+        "google.protobuf.UInt64Value" => return wrapper(Scalar::Uint64),
+        "google.protobuf.Int32Value" => return wrapper(Scalar::Int32),
+        "google.protobuf.UInt32Value" => return wrapper(Scalar::Uint32),
+        "google.protobuf.BoolValue" => return wrapper(Scalar::Bool),
+        "google.protobuf.StringValue" => return wrapper(Scalar::String),
+        "google.protobuf.BytesValue" => return wrapper(Scalar::Bytes),
+        _ => return None,
+    })
+}
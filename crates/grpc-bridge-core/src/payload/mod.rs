@@ -0,0 +1,36 @@
+//! Request payloads in canonical protobuf JSON, built from the message definitions of a
+//! [`Schema`](crate::proto_index::schema::Schema).
+
+pub mod complete;
+pub mod format;
+pub mod json_output;
+pub mod json_schema;
+pub mod mutate;
+pub mod raw;
+pub mod rules;
+pub mod sample;
+pub mod skeleton;
+pub mod text;
+pub mod validate;
+pub mod wire;
+
+use serde_json::{json, Value};
+
+/// Canonical JSON of a well-known type at its default value, `None` for ordinary messages.
+pub fn well_known_default(message: &str) -> Option<Value> {
+    Some(match message.trim_start_matches('.') {
+        "google.protobuf.Timestamp" => json!("1970-01-01T00:00:00Z"),
+        "google.protobuf.Duration" => json!("0s"),
+        "google.protobuf.FieldMask" => json!(""),
+        "google.protobuf.Empty" | "google.protobuf.Struct" => json!({}),
+        "google.protobuf.Any" => json!({ "@type": "type.googleapis.com/google.protobuf.Empty" }),
+        "google.protobuf.Value" => Value::Null,
+        "google.protobuf.ListValue" => json!([]),
+        "google.protobuf.DoubleValue" | "google.protobuf.FloatValue" => json!(0.0),
+        "google.protobuf.Int32Value" | "google.protobuf.UInt32Value" => json!(0),
+        "google.protobuf.Int64Value" | "google.protobuf.UInt64Value" => json!("0"),
+        "google.protobuf.BoolValue" => json!(false),
+        "google.protobuf.StringValue" | "google.protobuf.BytesValue" => json!(""),
+        _ => return None,
+    })
+}
@@ -0,0 +1,185 @@
+use super::rules::{self, Lit};
+use super::validate::{int_range, pointer};
+use super::{sample, skeleton, well_known_default};
+use crate::proto_index::schema::{FieldDef, FieldType, Label, MessageDef, Scalar, Schema};
+use base64::Engine;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+/// Nesting depth of the deep-nesting mutation: the default recursion limit of most protobuf runtimes.
+pub const NESTING: usize = 100;
+
+const HUGE: usize = 1 << 20;
+const REPEATED_ITEMS: usize = 10_000;
+/// Overlong and truncated sequences, a lone continuation byte and bytes that never occur in UTF-8.
+const INVALID_UTF8: &[u8] = &[0xc3, 0x28, 0xa0, 0xa1, 0xe2, 0x28, 0xa1, 0xf0, 0x28, 0x8c, 0xbc, 0xc0, 0xaf, 0xfe, 0xff];
+const UNKNOWN_FIELD: &str = "grpcBridgeFuzzUnknownField";
+
+/// A payload derived from a valid sample by one change.
+#[derive(Debug, Clone, Serialize)]
+pub struct Mutation {
+    /// What was changed, e.g. `max int32 at /quantity`.
+    pub name: String,
+    pub payload: Value,
+}
+
+/// Hostile payloads for `message`: the seeded [`sample::sample`] with one field at a time set to
+/// a boundary integer, a 1 MiB string, invalid UTF-8 bytes, an undeclared enum number or
+/// [`REPEATED_ITEMS`] items; required fields missing or empty; the message nested [`NESTING`]
+/// levels deep where it can refer to itself; and an unknown field. Unknown fields are rejected
+/// or dropped by grpcurl's JSON parser, so that case mostly exercises the client side.
+pub fn mutations(schema: &Schema, message: &str, seed: u64) -> Vec<Mutation> {
+    let message = message.trim_start_matches('.');
+    let base = sample::sample(schema, message, seed, skeleton::DEFAULT_DEPTH);
+    let mut mutator = Mutator { schema, base: &base, out: vec![Mutation { name: "empty message".into(), payload: json!({}) }] };
+    mutator.message(message, "", skeleton::DEFAULT_DEPTH);
+    let mut out = mutator.out;
+    if let Some(payload) = nested(schema, message) {
+        out.push(Mutation { name: format!("{} levels of nesting", NESTING), payload });
+    }
+    let mut unknown = base.clone();
+    if let Value::Object(obj) = &mut unknown { obj.insert(UNKNOWN_FIELD.into(), json!("x")); }
+    out.push(Mutation { name: format!("unknown field {}", UNKNOWN_FIELD), payload: unknown });
+    out
+}
+
+struct Mutator<'a> {
+    schema: &'a Schema,
+    base: &'a Value,
+    out: Vec<Mutation>,
+}
+
+impl Mutator<'_> {
+    fn message(&mut self, name: &str, path: &str, depth_left: usize) {
+        let schema = self.schema;
+        let Some(def) = schema.message(name) else { return; };
+        for field in &def.fields {
+            let at = pointer(path, &field.json_name);
+            let required = field.label == Label::Required || rules::field_rules(field).iter()
+                .any(|r| (r.name == "required" || r.name.ends_with(".required")) && r.value == Lit::Bool(true));
+            if required {
+                self.push(format!("missing required field {}", at), path, def, field, None);
+                self.push(format!("empty required field {}", at), path, def, field, Some(empty(field)));
+            }
+            match (&field.ty, field.label) {
+                (FieldType::Map(..), _) => {}
+                (ty, Label::Repeated) => {
+                    let item = self.base.pointer(&at).and_then(|v| v.get(0)).cloned().unwrap_or_else(|| element(ty));
+                    self.push(format!("{} items at {}", REPEATED_ITEMS, at), path, def, field, Some(Value::Array(vec![item; REPEATED_ITEMS])));
+                }
+                (FieldType::Scalar(s), _) => {
+                    for (what, value) in scalar(*s) { self.push(format!("{} at {}", what, at), path, def, field, Some(value)); }
+                }
+                (FieldType::Enum(e), _) if e != "google.protobuf.NullValue" => {
+                    self.push(format!("undeclared enum number at {}", at), path, def, field, Some(json!(i32::MAX)));
+                }
+                (FieldType::Message(m), _) if depth_left > 0 && well_known_default(m).is_none() && self.base.pointer(&at).is_some_and(Value::is_object) => {
+                    self.message(m, &at, depth_left - 1);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Adds the base payload with `field` of the object at `parent` set to `value`, or removed.
+    /// Other members of the field's oneof are cleared so the payload stays parseable.
+    fn push(&mut self, name: String, parent: &str, def: &MessageDef, field: &FieldDef, value: Option<Value>) {
+        let mut payload = self.base.clone();
+        let Some(Value::Object(obj)) = payload.pointer_mut(parent) else { return; };
+        if let Some(oneof) = field.oneof {
+            for other in def.fields.iter().filter(|f| f.oneof == Some(oneof)) { obj.remove(&other.json_name); }
+        }
+        match value {
+            Some(value) => { obj.insert(field.json_name.clone(), value); }
+            None => { obj.remove(&field.json_name); }
+        }
+        self.out.push(Mutation { name, payload });
+    }
+}
+
+/// Boundary and oversized values of a scalar type, named.
+fn scalar(s: Scalar) -> Vec<(String, Value)> {
+    let b64 = |bytes: &[u8]| json!(base64::engine::general_purpose::STANDARD.encode(bytes));
+    match s {
+        Scalar::Bool => Vec::new(),
+        Scalar::String => vec![("1 MiB string".into(), json!("x".repeat(HUGE)))],
+        Scalar::Bytes => vec![("invalid UTF-8 bytes".into(), b64(INVALID_UTF8)), ("1 MiB bytes".into(), b64(&vec![0xff; HUGE]))],
+        Scalar::Double | Scalar::Float => {
+            let max = if s == Scalar::Float { f64::from(f32::MAX) } else { f64::MAX };
+            vec![
+                (format!("max {}", s.name()), json!(max)),
+                (format!("min {}", s.name()), json!(-max)),
+                ("NaN".into(), json!("NaN")),
+                ("Infinity".into(), json!("Infinity")),
+                ("-Infinity".into(), json!("-Infinity")),
+            ]
+        }
+        _ => {
+            let (lo, hi) = int_range(s);
+            // 64-bit integers are strings in canonical JSON
+            let wide = matches!(s, Scalar::Int64 | Scalar::Uint64 | Scalar::Sint64 | Scalar::Fixed64 | Scalar::Sfixed64);
+            let value = |n: i128| if wide { json!(n.to_string()) } else { json!(n as i64) };
+            vec![(format!("min {}", s.name()), value(lo)), (format!("max {}", s.name()), value(hi))]
+        }
+    }
+}
+
+/// The value that reads as "not set" for a field.
+fn empty(field: &FieldDef) -> Value {
+    match (&field.ty, field.label) {
+        (FieldType::Map(..), _) => json!({}),
+        (_, Label::Repeated) => json!([]),
+        (FieldType::Scalar(s), _) => skeleton::scalar_default(*s),
+        (FieldType::Enum(_), _) => json!(0),
+        (FieldType::Message(m), _) => well_known_default(m).unwrap_or_else(|| json!({})),
+        (FieldType::Unresolved(_), _) => Value::Null,
+    }
+}
+
+fn element(ty: &FieldType) -> Value {
+    match ty {
+        FieldType::Scalar(s) => skeleton::scalar_default(*s),
+        FieldType::Enum(_) => json!(0),
+        FieldType::Message(m) => well_known_default(m).unwrap_or_else(|| json!({})),
+        _ => Value::Null,
+    }
+}
+
+/// `message` nested [`NESTING`] JSON levels deep through the shortest chain of message fields
+/// leading back to it, or through a `google.protobuf.Struct`/`Value` field.
+fn nested(schema: &Schema, message: &str) -> Option<Value> {
+    let Some(chain) = cycle(schema, message) else {
+        let field = schema.message(message)?.fields.iter()
+            .find(|f| f.label != Label::Repeated && matches!(&f.ty, FieldType::Message(m) if m == "google.protobuf.Struct" || m == "google.protobuf.Value"))?;
+        let mut inner = json!({});
+        for _ in 0..NESTING { inner = json!({ "a": inner }); }
+        return Some(json!({ field.json_name.clone(): inner }));
+    };
+    let (mut out, mut depth) = (json!({}), 0);
+    while depth < NESTING {
+        for (name, repeated) in chain.iter().rev() {
+            let mut obj = Map::new();
+            obj.insert(name.clone(), if *repeated { depth += 1; json!([out]) } else { out });
+            out = Value::Object(obj);
+            depth += 1;
+        }
+    }
+    Some(out)
+}
+
+/// JSON names (and whether each is repeated) of the shortest chain of message fields from
+/// `message` back to itself.
+fn cycle(schema: &Schema, message: &str) -> Option<Vec<(String, bool)>> {
+    let mut queue = std::collections::VecDeque::from([(message.to_string(), Vec::new())]);
+    let mut seen = std::collections::HashSet::new();
+    while let Some((name, chain)) = queue.pop_front() {
+        for field in schema.message(&name).map(|m| m.fields.as_slice()).unwrap_or_default() {
+            let FieldType::Message(next) = &field.ty else { continue; };
+            let mut chain: Vec<(String, bool)> = chain.clone();
+            chain.push((field.json_name.clone(), field.label == Label::Repeated));
+            if next == message { return Some(chain); }
+            if seen.insert(next.clone()) { queue.push_back((next.clone(), chain)); }
+        }
+    }
+    None
+}
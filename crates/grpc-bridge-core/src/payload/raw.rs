@@ -0,0 +1,148 @@
+//! Protobuf bytes decoded without a schema, like `protoc --decode_raw`.
+
+use super::text::quote;
+use super::wire::{self, scalar_json, WireValue};
+use crate::proto_index::schema::{Scalar, Schema};
+use base64::Engine;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::fmt::Write;
+
+/// How deep length-delimited values are tried as nested messages.
+const NESTING_LIMIT: usize = 32;
+/// How many indexed message types `decode_raw` suggests at most.
+pub const MAX_CANDIDATES: usize = 20;
+
+/// One field of a message decoded without its type.
+#[derive(Debug, Clone, Serialize)]
+pub struct RawField {
+    pub number: u32,
+    /// `varint`, `fixed64`, `length_delimited` or `fixed32`.
+    pub wire_type: &'static str,
+    /// The most likely reading: `int`, `bool`, `fixed64`, `fixed32`, `string`, `message` or `bytes`.
+    pub guess: &'static str,
+    /// The value read as `guess`, in canonical JSON; unset for messages.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+    /// Other readings of the same bits by protobuf type, e.g. `sint64`, `double` or `bytes`.
+    pub alternatives: Map<String, Value>,
+    /// Fields of the nested message, when `guess` is `message`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<RawField>,
+}
+
+/// The result of `decode_raw`: the field tree, indexed types the bytes also decode as and, when
+/// one was asked for, the bytes decoded as that type.
+#[derive(Debug, Clone, Serialize)]
+pub struct RawDecoding {
+    pub fields: Vec<RawField>,
+    pub candidates: Vec<String>,
+    pub message: Option<String>,
+    pub decoded: Option<Value>,
+}
+
+/// The field tree of `bytes`. Length-delimited values are read as text when they are printable
+/// UTF-8, else as a nested message when they parse as one, else as bytes.
+pub fn decode_raw(bytes: &[u8]) -> Result<Vec<RawField>, String> {
+    decode(bytes, 0)
+}
+
+fn decode(bytes: &[u8], depth: usize) -> Result<Vec<RawField>, String> {
+    wire::fields(bytes)?.into_iter().map(|(number, v)| Ok(field(number, &v, depth))).collect()
+}
+
+fn field(number: u32, v: &WireValue, depth: usize) -> RawField {
+    let raw = |s: Scalar, bits: u64| scalar_json(s, bits);
+    let mut alternatives = Map::new();
+    let mut fields = Vec::new();
+    let (wire_type, guess, value) = match *v {
+        WireValue::Varint(n) => {
+            alternatives.insert("int64".into(), raw(Scalar::Int64, n));
+            alternatives.insert("sint64".into(), raw(Scalar::Sint64, n));
+            if n <= 1 { ("varint", "bool", Some(json!(n == 1))) } else { ("varint", "int", Some(raw(Scalar::Uint64, n))) }
+        }
+        WireValue::Fixed64(n) => {
+            alternatives.insert("sfixed64".into(), raw(Scalar::Sfixed64, n));
+            alternatives.insert("double".into(), raw(Scalar::Double, n));
+            ("fixed64", "fixed64", Some(raw(Scalar::Fixed64, n)))
+        }
+        WireValue::Fixed32(n) => {
+            alternatives.insert("sfixed32".into(), raw(Scalar::Sfixed32, u64::from(n)));
+            alternatives.insert("float".into(), raw(Scalar::Float, u64::from(n)));
+            ("fixed32", "fixed32", Some(raw(Scalar::Fixed32, u64::from(n))))
+        }
+        WireValue::Len(bytes) => {
+            let encoded = json!(base64::engine::general_purpose::STANDARD.encode(bytes));
+            let text = std::str::from_utf8(bytes).ok();
+            let nested = if depth < NESTING_LIMIT && !bytes.is_empty() { decode(bytes, depth + 1).ok() } else { None };
+            if let Some(text) = text.filter(|t| is_printable(t)) {
+                alternatives.insert("bytes".into(), encoded);
+                ("length_delimited", "string", Some(json!(text)))
+            } else if let Some(nested) = nested {
+                fields = nested;
+                alternatives.insert("bytes".into(), encoded);
+                if let Some(text) = text { alternatives.insert("string".into(), json!(text)); }
+                ("length_delimited", "message", None)
+            } else {
+                if let Some(text) = text { alternatives.insert("string".into(), json!(text)); }
+                ("length_delimited", "bytes", Some(encoded))
+            }
+        }
+    };
+    RawField { number, wire_type, guess, value, alternatives, fields }
+}
+
+fn is_printable(text: &str) -> bool {
+    text.chars().all(|c| !c.is_control() || matches!(c, '\n' | '\r' | '\t'))
+}
+
+/// The field tree in the layout of `protoc --decode_raw`: varints as unsigned numbers, fixed
+/// values in hex, nested messages in braces and everything else as a quoted string.
+pub fn print(fields: &[RawField]) -> String {
+    let mut out = String::new();
+    print_fields(fields, 0, &mut out);
+    out
+}
+
+fn print_fields(fields: &[RawField], indent: usize, out: &mut String) {
+    for f in fields {
+        let pad = "  ".repeat(indent);
+        if f.guess == "message" {
+            let _ = writeln!(out, "{}{} {{", pad, f.number);
+            print_fields(&f.fields, indent + 1, out);
+            let _ = writeln!(out, "{}}}", pad);
+            continue;
+        }
+        let value = match (f.wire_type, &f.value) {
+            ("varint", Some(Value::Bool(b))) => u8::from(*b).to_string(),
+            ("fixed64" | "fixed32", Some(v)) => {
+                let n: u64 = v.as_u64().or_else(|| v.as_str().and_then(|s| s.parse().ok())).unwrap_or_default();
+                if f.wire_type == "fixed64" { format!("0x{:016x}", n) } else { format!("0x{:08x}", n) }
+            }
+            ("length_delimited", Some(Value::String(s))) if f.guess == "string" => quote(s.as_bytes()),
+            ("length_delimited", Some(Value::String(s))) => quote(&super::format::decode_base64(s).unwrap_or_default()),
+            (_, Some(Value::String(s))) => s.clone(),
+            (_, Some(v)) => v.to_string(),
+            (_, None) => String::new(),
+        };
+        let _ = writeln!(out, "{}{}: {}", pad, f.number, value);
+    }
+}
+
+/// Message types of `schema` that `bytes` decode as with every top-level field declared, the
+/// closest fits (fewest declared fields left unset) first. Well-known types are left out.
+pub fn candidates(schema: &Schema, bytes: &[u8]) -> Vec<(usize, String)> {
+    let Ok(parsed) = wire::fields(bytes) else { return Vec::new(); };
+    if parsed.is_empty() { return Vec::new(); }
+    let mut numbers: Vec<u32> = parsed.iter().map(|(n, _)| *n).collect();
+    numbers.sort_unstable();
+    numbers.dedup();
+    let mut out: Vec<(usize, String)> = schema.messages()
+        .filter(|m| !m.full_name.starts_with("google.protobuf."))
+        .filter(|m| numbers.iter().all(|n| m.fields.iter().any(|f| i64::from(f.number) == i64::from(*n))))
+        .filter(|m| wire::to_json(schema, &m.full_name, bytes).is_ok())
+        .map(|m| (m.fields.len().saturating_sub(numbers.len()), m.full_name.clone()))
+        .collect();
+    out.sort();
+    out
+}
@@ -0,0 +1,451 @@
+//! Field constraints from protovalidate (`(buf.validate.field)`) and protoc-gen-validate
+//! (`(validate.rules)`) options, checked against a JSON payload.
+//!
+//! Supported: `required`, `ignore`/`ignore_empty`, string length, byte length, `pattern`, `prefix`,
+//! `suffix`, `contains`, `not_contains`, the `email`/`hostname`/`ip`/`ipv4`/`ipv6`/`uri`/`uuid`/
+//! `address` formats, `const`/`in`/`not_in`, numeric `gt`/`gte`/`lt`/`lte`, `enum.defined_only`,
+//! `repeated` item counts, `unique` and `items`, and `map` pair counts, `keys` and `values`.
+//! Other rules (CEL expressions, duration and timestamp bounds) are left to the server.
+
+use super::validate::{IssueKind, PayloadIssue};
+use crate::proto_index::schema::{FieldDef, FieldType, Label, Scalar, Schema};
+use base64::Engine;
+use serde_json::{json, Value};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+lazy_static::lazy_static! {
+    static ref RE_UUID: regex::Regex = regex::Regex::new(r"^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$").unwrap();
+    static ref RE_URI: regex::Regex = regex::Regex::new(r"^[A-Za-z][A-Za-z0-9+.\-]*:[^\s]*$").unwrap();
+    static ref PATTERNS: Mutex<HashMap<String, Result<regex::Regex, String>>> = Mutex::new(HashMap::new());
+}
+
+/// One constraint, flattened from the option it was written in: `string = { min_len: 1 }` and
+/// `string.min_len = 1` both give `("string.min_len", 1)`. List values give one rule per item.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub name: String,
+    pub value: Lit,
+}
+
+/// A scalar in protobuf text format.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lit {
+    Str(String),
+    Num(String),
+    Bool(bool),
+    /// An enum value name such as `IGNORE_IF_UNPOPULATED`.
+    Ident(String),
+}
+
+impl std::fmt::Display for Lit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Lit::Str(s) => write!(f, "{:?}", s),
+            Lit::Num(n) | Lit::Ident(n) => f.write_str(n),
+            Lit::Bool(b) => write!(f, "{}", b),
+        }
+    }
+}
+
+/// The constraints declared on `field`, in declaration order.
+pub fn field_rules(field: &FieldDef) -> Vec<Rule> {
+    let mut rules = Vec::new();
+    for (key, value) in &field.options {
+        let key: String = key.chars().filter(|c| !c.is_whitespace()).collect();
+        let Some(rest) = key.strip_prefix("(buf.validate.field)").or_else(|| key.strip_prefix("(validate.rules)")) else { continue; };
+        let mut parser = TextParser { chars: value.chars().peekable() };
+        if let Some(text) = parser.value() { flatten(rest.trim_start_matches('.'), text, &mut rules); }
+    }
+    rules
+}
+
+/// Checks one field of a message against its constraints. `value` is `None` when the payload
+/// leaves the field unset; fields without presence are then checked at their default value.
+pub(super) fn check_field(schema: &Schema, field: &FieldDef, value: Option<&Value>, path: &str) -> Vec<PayloadIssue> {
+    let rules = field_rules(field);
+    let mut checker = Checker { schema, issues: Vec::new() };
+    if rules.is_empty() || rules.iter().any(|r| r.name == "ignore" && r.value == Lit::Ident("IGNORE_ALWAYS".into())) { return checker.issues; }
+    // `required` (protovalidate) or `message.required`, `duration.required`, ... (protoc-gen-validate)
+    let required = rules.iter().find(|r| (r.name == "required" || (r.name.matches('.').count() == 1 && r.name.ends_with(".required"))) && r.value == Lit::Bool(true));
+    let default;
+    let value = match value {
+        Some(v) => v,
+        None if field.has_presence => {
+            if let Some(rule) = required { checker.violation(path, rule, "is required".into()); }
+            return checker.issues;
+        }
+        None => { default = default_value(field); &default }
+    };
+    if is_empty(field, value) {
+        // without presence, the default value is indistinguishable from unset
+        if let Some(rule) = required.filter(|_| !field.has_presence) { checker.violation(path, rule, "is required".into()); return checker.issues; }
+        let ignore = rules.iter().any(|r| (r.name == "ignore" && matches!(&r.value, Lit::Ident(i) if i != "IGNORE_UNSPECIFIED"))
+            || (r.name.ends_with(".ignore_empty") && r.value == Lit::Bool(true)));
+        if ignore { return checker.issues; }
+    }
+    match (&field.ty, field.label) {
+        (FieldType::Map(key, ty), _) => {
+            let Value::Object(entries) = value else { return checker.issues; };
+            checker.counts(path, &rules, "map.min_pairs", "map.max_pairs", entries.len(), "entries");
+            let keys = under(&rules, "map.keys.");
+            let values = under(&rules, "map.values.");
+            for (k, v) in entries {
+                let at = super::validate::pointer(path, k);
+                checker.value(&FieldType::Scalar(*key), &keys, &json!(k), &at);
+                checker.value(ty, &values, v, &at);
+            }
+        }
+        (ty, Label::Repeated) => {
+            let Value::Array(items) = value else { return checker.issues; };
+            checker.counts(path, &rules, "repeated.min_items", "repeated.max_items", items.len(), "items");
+            if let Some(rule) = rules.iter().find(|r| r.name == "repeated.unique" && r.value == Lit::Bool(true)) {
+                if items.iter().enumerate().any(|(i, a)| items[..i].contains(a)) { checker.violation(path, rule, "items must be unique".into()); }
+            }
+            let each = under(&rules, "repeated.items.");
+            for (i, item) in items.iter().enumerate() { checker.value(ty, &each, item, &super::validate::pointer(path, &i.to_string())); }
+        }
+        (ty, _) => checker.value(ty, &under(&rules, ""), value, path),
+    }
+    checker.issues
+}
+
+/// Rules whose name starts with `prefix`, paired with the rest of their name.
+pub(super) fn under<'r>(rules: &'r [Rule], prefix: &str) -> Vec<(&'r str, &'r Rule)> {
+    rules.iter().filter_map(|r| r.name.strip_prefix(prefix).map(|rest| (rest, r))).collect()
+}
+
+struct Checker<'a> {
+    schema: &'a Schema,
+    issues: Vec<PayloadIssue>,
+}
+
+impl Checker<'_> {
+    fn violation(&mut self, path: &str, rule: &Rule, message: String) {
+        self.issues.push(PayloadIssue { path: path.to_string(), kind: IssueKind::Constraint, message, rule: Some(rule.name.clone()) });
+    }
+
+    fn counts(&mut self, path: &str, rules: &[Rule], min: &str, max: &str, len: usize, noun: &str) {
+        for rule in rules.iter().filter(|r| r.name == min || r.name == max) {
+            let Some(limit) = as_usize(&rule.value) else { continue; };
+            if rule.name == min && len < limit { self.violation(path, rule, format!("must have at least {} {}", limit, noun)); }
+            if rule.name == max && len > limit { self.violation(path, rule, format!("must have at most {} {}", limit, noun)); }
+        }
+    }
+
+    /// Applies type rules (`string.min_len`, `int32.gt`, ...) to a single value. Values of the
+    /// wrong JSON type were already reported by the schema check and are skipped.
+    fn value(&mut self, ty: &FieldType, rules: &[(&str, &Rule)], value: &Value, path: &str) {
+        let mut in_list: Vec<&Rule> = Vec::new();
+        let mut not_in_list: Vec<&Rule> = Vec::new();
+        let mut bounds: Vec<(&str, &Rule)> = Vec::new();
+        for &(name, rule) in rules {
+            let Some((kind, op)) = name.split_once('.') else { continue; };
+            match op {
+                "in" => in_list.push(rule),
+                "not_in" => not_in_list.push(rule),
+                "gt" | "gte" | "lt" | "lte" if is_numeric_kind(kind) => bounds.push((op, rule)),
+                _ => if let Some(message) = self.single(kind, op, &rule.value, ty, value) { self.violation(path, rule, message); }
+            }
+        }
+        let Some(actual) = comparable(ty, value, self.schema) else { return; };
+        if let Some(first) = in_list.first() {
+            if !in_list.iter().any(|r| same(&actual, &r.value)) {
+                self.violation(path, first, format!("must be one of {}", in_list.iter().map(|r| r.value.to_string()).collect::<Vec<_>>().join(", ")));
+            }
+        }
+        if let Some(r) = not_in_list.iter().find(|r| same(&actual, &r.value)) { self.violation(path, r, format!("must not be {}", r.value)); }
+        self.bounds(path, &actual, &bounds);
+    }
+
+    fn bounds(&mut self, path: &str, actual: &Lit, bounds: &[(&str, &Rule)]) {
+        let Lit::Num(n) = actual else { return; };
+        let lower = bounds.iter().find(|(op, _)| op.starts_with("gt"));
+        let upper = bounds.iter().find(|(op, _)| op.starts_with("lt"));
+        let ok = |(op, rule): &(&str, &Rule)| {
+            let Lit::Num(limit) = &rule.value else { return true; };
+            match compare(n, limit) {
+                Some(ord) => match *op { "gt" => ord.is_gt(), "gte" => ord.is_ge(), "lt" => ord.is_lt(), _ => ord.is_le() },
+                None => true,
+            }
+        };
+        let describe = |(op, rule): &(&str, &Rule)| {
+            let relation = match *op { "gt" => "greater than", "gte" => "greater than or equal to", "lt" => "less than", _ => "less than or equal to" };
+            format!("{} {}", relation, rule.value)
+        };
+        let failed = match (lower, upper) {
+            (Some(lo), Some(hi)) if inverted(lo.1, hi.1) => (!ok(lo) && !ok(hi)).then(|| (lo.1, format!("must be {} or {}", describe(lo), describe(hi)))),
+            (Some(lo), Some(hi)) => (!ok(lo) || !ok(hi)).then(|| (lo.1, format!("must be {} and {}", describe(lo), describe(hi)))),
+            (Some(one), None) | (None, Some(one)) => (!ok(one)).then(|| (one.1, format!("must be {}", describe(one)))),
+            (None, None) => None,
+        };
+        if let Some((rule, message)) = failed { self.violation(path, rule, message); }
+    }
+
+    /// The message for a violated rule that needs no other rule to be checked, if violated.
+    fn single(&self, kind: &str, op: &str, limit: &Lit, ty: &FieldType, value: &Value) -> Option<String> {
+        match (kind, value) {
+            ("string", Value::String(s)) => string_rule(op, limit, s),
+            ("bytes", Value::String(b)) => {
+                let decoded = decode_base64(b)?;
+                let n = as_usize(limit)?;
+                match op {
+                    "len" if decoded.len() != n => Some(format!("must be exactly {} bytes", n)),
+                    "min_len" if decoded.len() < n => Some(format!("must be at least {} bytes", n)),
+                    "max_len" if decoded.len() > n => Some(format!("must be at most {} bytes", n)),
+                    _ => None,
+                }
+            }
+            ("enum", _) if op == "defined_only" && *limit == Lit::Bool(true) => {
+                let FieldType::Enum(name) = ty else { return None; };
+                let def = self.schema.enum_def(name)?;
+                let Some(Lit::Num(n)) = comparable(ty, value, self.schema) else { return None; };
+                (!def.values.iter().any(|(_, v)| v.to_string() == n)).then(|| format!("must be a defined value of {}", def.full_name))
+            }
+            (kind, _) if op == "const" && (kind == "bool" || kind == "enum" || is_numeric_kind(kind)) => {
+                let actual = comparable(ty, value, self.schema)?;
+                (!same(&actual, limit)).then(|| format!("must be {}", limit))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn string_rule(op: &str, limit: &Lit, s: &str) -> Option<String> {
+    let text = || match limit { Lit::Str(t) => Some(t.as_str()), _ => None };
+    let chars = s.chars().count();
+    let format_rule = |valid: bool, what: &str| (*limit == Lit::Bool(true) && !valid).then(|| format!("must be {}", what));
+    match op {
+        "const" => (Some(s) != text()).then(|| format!("must be {}", limit)),
+        "len" => as_usize(limit).filter(|n| chars != *n).map(|n| format!("must be exactly {} characters", n)),
+        "min_len" => as_usize(limit).filter(|n| chars < *n).map(|n| format!("must be at least {} characters", n)),
+        "max_len" => as_usize(limit).filter(|n| chars > *n).map(|n| format!("must be at most {} characters", n)),
+        "len_bytes" => as_usize(limit).filter(|n| s.len() != *n).map(|n| format!("must be exactly {} bytes", n)),
+        "min_bytes" => as_usize(limit).filter(|n| s.len() < *n).map(|n| format!("must be at least {} bytes", n)),
+        "max_bytes" => as_usize(limit).filter(|n| s.len() > *n).map(|n| format!("must be at most {} bytes", n)),
+        "pattern" => match pattern(text()?) {
+            Ok(re) => (!re.is_match(s)).then(|| format!("must match {}", limit)),
+            Err(e) => Some(format!("pattern {} is not supported: {}", limit, e)),
+        },
+        "prefix" => (!s.starts_with(text()?)).then(|| format!("must start with {}", limit)),
+        "suffix" => (!s.ends_with(text()?)).then(|| format!("must end with {}", limit)),
+        "contains" => (!s.contains(text()?)).then(|| format!("must contain {}", limit)),
+        "not_contains" => s.contains(text()?).then(|| format!("must not contain {}", limit)),
+        "email" => format_rule(is_email(s), "an email address"),
+        "hostname" => format_rule(is_hostname(s), "a hostname"),
+        "ip" => format_rule(s.parse::<std::net::IpAddr>().is_ok(), "an IP address"),
+        "ipv4" => format_rule(s.parse::<std::net::Ipv4Addr>().is_ok(), "an IPv4 address"),
+        "ipv6" => format_rule(s.parse::<std::net::Ipv6Addr>().is_ok(), "an IPv6 address"),
+        "address" => format_rule(is_hostname(s) || s.parse::<std::net::IpAddr>().is_ok(), "a hostname or IP address"),
+        "uri" => format_rule(RE_URI.is_match(s), "an absolute URI"),
+        "uuid" => format_rule(RE_UUID.is_match(s), "a UUID"),
+        _ => None,
+    }
+}
+
+/// The compiled `string.pattern` (or response assertion pattern), or why it does not compile.
+/// Each pattern is compiled once and shared by every check and every item of a repeated field.
+pub(crate) fn pattern(text: &str) -> Result<regex::Regex, String> {
+    let mut cache = PATTERNS.lock().unwrap_or_else(|e| e.into_inner());
+    cache.entry(text.to_string()).or_insert_with(|| regex::Regex::new(text).map_err(|e| {
+        // syntax errors quote the pattern over several lines; the last one names the problem
+        let e = e.to_string();
+        e.lines().last().unwrap_or_default().trim().trim_start_matches("error: ").to_string()
+    })).clone()
+}
+
+/// The value as a rule literal: numbers (enum names turned into their numbers), strings and bools.
+fn comparable(ty: &FieldType, value: &Value, schema: &Schema) -> Option<Lit> {
+    match (ty, value) {
+        (FieldType::Enum(name), Value::String(s)) => schema.enum_def(name)?.values.iter().find(|(n, _)| n == s).map(|(_, v)| Lit::Num(v.to_string())),
+        (FieldType::Scalar(Scalar::String | Scalar::Bytes), Value::String(s)) => Some(Lit::Str(s.clone())),
+        (_, Value::String(s)) => Some(Lit::Num(s.clone())),
+        (_, Value::Number(n)) => Some(Lit::Num(n.to_string())),
+        (_, Value::Bool(b)) => Some(Lit::Bool(*b)),
+        _ => None,
+    }
+}
+
+/// A lower bound above the upper one means "outside the range".
+fn inverted(lower: &Rule, upper: &Rule) -> bool {
+    matches!((&lower.value, &upper.value), (Lit::Num(a), Lit::Num(b)) if compare(a, b).is_some_and(Ordering::is_gt))
+}
+
+fn same(actual: &Lit, expected: &Lit) -> bool {
+    match (actual, expected) {
+        (Lit::Num(a), Lit::Num(b)) => compare(a, b) == Some(Ordering::Equal),
+        _ => actual == expected,
+    }
+}
+
+/// Compares as integers when both sides are, so 64-bit limits stay exact.
+fn compare(a: &str, b: &str) -> Option<Ordering> {
+    match (a.parse::<i128>(), b.parse::<i128>()) {
+        (Ok(x), Ok(y)) => Some(x.cmp(&y)),
+        _ => a.parse::<f64>().ok()?.partial_cmp(&b.parse::<f64>().ok()?),
+    }
+}
+
+fn is_numeric_kind(kind: &str) -> bool {
+    Scalar::parse(kind).is_some_and(|s| !matches!(s, Scalar::Bool | Scalar::String | Scalar::Bytes))
+}
+
+fn as_usize(lit: &Lit) -> Option<usize> {
+    match lit { Lit::Num(n) => n.parse().ok(), _ => None }
+}
+
+fn default_value(field: &FieldDef) -> Value {
+    match (&field.ty, field.label) {
+        (FieldType::Map(..), _) => json!({}),
+        (_, Label::Repeated) => json!([]),
+        (FieldType::Scalar(Scalar::Bool), _) => json!(false),
+        (FieldType::Scalar(Scalar::String | Scalar::Bytes), _) => json!(""),
+        _ => json!(0),
+    }
+}
+
+/// Whether `value` is the default of `field`; 64-bit integers are also written as strings.
+fn is_empty(field: &FieldDef, value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Bool(b) => !b,
+        Value::Number(n) => n.as_f64() == Some(0.0),
+        Value::String(s) if s == "0" => field.label != Label::Repeated && matches!(field.ty, FieldType::Scalar(s) if s.is_64bit()),
+        Value::String(s) => s.is_empty(),
+        Value::Array(a) => a.is_empty(),
+        Value::Object(o) => o.is_empty(),
+    }
+}
+
+fn decode_base64(s: &str) -> Option<Vec<u8>> {
+    let s = s.trim_end_matches('=');
+    base64::engine::general_purpose::STANDARD_NO_PAD.decode(s).or_else(|_| base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(s)).ok()
+}
+
+fn is_email(s: &str) -> bool {
+    s.split_once('@').is_some_and(|(local, domain)| !local.is_empty() && !local.contains(char::is_whitespace) && !domain.contains('@') && is_hostname(domain))
+}
+
+fn is_hostname(s: &str) -> bool {
+    let s = s.strip_suffix('.').unwrap_or(s);
+    !s.is_empty() && s.len() <= 253 && s.split('.').all(|label| {
+        (1..=63).contains(&label.len()) && !label.starts_with('-') && !label.ends_with('-')
+            && label.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+    })
+}
+
+/// Parsed option value: `1`, `"a"`, `[1, 2]` or `{ min_len: 1 }`.
+enum Text {
+    Lit(Lit),
+    List(Vec<Text>),
+    Message(Vec<(String, Text)>),
+}
+
+fn flatten(prefix: &str, text: Text, out: &mut Vec<Rule>) {
+    match text {
+        Text::Lit(value) => out.push(Rule { name: prefix.to_string(), value }),
+        Text::List(items) => for item in items { flatten(prefix, item, out) },
+        Text::Message(entries) => for (key, value) in entries {
+            let name = if prefix.is_empty() { key } else { format!("{}.{}", prefix, key) };
+            flatten(&name, value, out);
+        },
+    }
+}
+
+/// Reads protobuf text format, as option values are kept by the schema parser.
+struct TextParser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl TextParser<'_> {
+    fn skip_space(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn value(&mut self) -> Option<Text> {
+        self.skip_space();
+        match *self.chars.peek()? {
+            '{' | '<' => {
+                self.chars.next();
+                let mut entries = Vec::new();
+                loop {
+                    self.skip_space();
+                    match self.chars.peek() {
+                        None => break,
+                        Some('}' | '>') => { self.chars.next(); break; }
+                        Some(',' | ';') => { self.chars.next(); continue; }
+                        _ => {}
+                    }
+                    let key = self.word();
+                    if key.is_empty() { break; }
+                    self.skip_space();
+                    self.chars.next_if_eq(&':');
+                    entries.push((key, self.value()?));
+                }
+                Some(Text::Message(entries))
+            }
+            '[' => {
+                self.chars.next();
+                let mut items = Vec::new();
+                loop {
+                    self.skip_space();
+                    match self.chars.peek() {
+                        None => break,
+                        Some(']') => { self.chars.next(); break; }
+                        Some(',') => { self.chars.next(); }
+                        _ => items.push(self.value()?),
+                    }
+                }
+                Some(Text::List(items))
+            }
+            q @ ('"' | '\'') => {
+                self.chars.next();
+                Some(Text::Lit(Lit::Str(self.string(q))))
+            }
+            _ => {
+                let negative = self.chars.next_if_eq(&'-').is_some();
+                if negative { self.skip_space(); }
+                let word = self.word();
+                if word.is_empty() { return None; }
+                Some(Text::Lit(match word.as_str() {
+                    "true" if !negative => Lit::Bool(true),
+                    "false" if !negative => Lit::Bool(false),
+                    w if w.starts_with(|c: char| c.is_ascii_digit() || c == '.') || w == "inf" || w == "nan" => Lit::Num(if negative { format!("-{}", w) } else { w.to_string() }),
+                    w => Lit::Ident(w.to_string()),
+                }))
+            }
+        }
+    }
+
+    fn word(&mut self) -> String {
+        let mut w = String::new();
+        while let Some(c) = self.chars.next_if(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '+')) { w.push(c); }
+        w
+    }
+
+    /// The rest of a quoted string, unescaping what the schema parser's `{:?}` produced.
+    fn string(&mut self, quote: char) -> String {
+        let mut s = String::new();
+        while let Some(c) = self.chars.next() {
+            match c {
+                '\\' => match self.chars.next() {
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    Some('r') => s.push('\r'),
+                    Some('0') => s.push('\0'),
+                    Some('u') if self.chars.next_if_eq(&'{').is_some() => {
+                        let hex: String = std::iter::from_fn(|| self.chars.next_if(|c| *c != '}')).collect();
+                        self.chars.next();
+                        if let Some(c) = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) { s.push(c); }
+                    }
+                    Some(e) => s.push(e),
+                    None => break,
+                },
+                c if c == quote => break,
+                c => s.push(c),
+            }
+        }
+        s
+    }
+}
@@ -0,0 +1,316 @@
+use super::rules::{self, Lit, Rule};
+use super::validate::int_range;
+use crate::proto_index::schema::{FieldDef, FieldType, Label, Scalar, Schema};
+use base64::Engine;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use regex_syntax::hir::{Class, Hir, HirKind};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+const WORDS: &[&str] = &["alpha", "bravo", "charlie", "delta", "echo", "foxtrot", "golf", "hotel", "india", "juliet", "kilo", "lima"];
+
+/// A generated payload and the seed that reproduces it.
+#[derive(Debug, Clone, Serialize)]
+pub struct SamplePayload {
+    pub seed: u64,
+    pub payload: String,
+}
+
+/// A payload for `message` with random values of the right types: one member of each oneof,
+/// a few items per repeated field and map, and values that satisfy the field constraints
+/// (see [`super::rules`]) where they can be generated. The same seed gives the same payload.
+pub fn sample(schema: &Schema, message: &str, seed: u64, max_depth: usize) -> Value {
+    let mut generator = Generator { schema, rng: ChaCha8Rng::seed_from_u64(seed) };
+    generator.message(message, max_depth)
+}
+
+/// Constraints that apply to one value, e.g. `string.min_len`, looked up by the part after the type.
+struct Constraints<'r>(Vec<(&'r str, &'r Rule)>);
+
+impl<'r> Constraints<'r> {
+    fn all(&self, op: &str) -> Vec<&'r Lit> {
+        self.0.iter().filter(|(name, _)| name.split_once('.').is_some_and(|(_, o)| o == op)).map(|(_, r)| &r.value).collect()
+    }
+
+    fn get(&self, op: &str) -> Option<&'r Lit> { self.all(op).into_iter().next() }
+
+    fn num(&self, op: &str) -> Option<f64> {
+        match self.get(op)? { Lit::Num(n) => n.parse().ok(), _ => None }
+    }
+
+    fn text(&self, op: &str) -> Option<&'r str> {
+        match self.get(op)? { Lit::Str(s) => Some(s.as_str()), _ => None }
+    }
+
+    fn flag(&self, op: &str) -> bool { self.get(op) == Some(&Lit::Bool(true)) }
+}
+
+struct Generator<'a> {
+    schema: &'a Schema,
+    rng: ChaCha8Rng,
+}
+
+impl Generator<'_> {
+    fn message(&mut self, name: &str, depth_left: usize) -> Value {
+        if let Some(v) = self.well_known(name) { return v; }
+        let schema = self.schema;
+        let Some(def) = schema.message(name) else { return json!({}); };
+        let mut out = Map::new();
+        if depth_left == 0 { return Value::Object(out); }
+        let chosen: Vec<usize> = (0..def.oneofs.len()).filter_map(|i| {
+            let members: Vec<usize> = def.fields.iter().enumerate().filter(|(_, f)| f.oneof == Some(i)).map(|(n, _)| n).collect();
+            (!members.is_empty()).then(|| members[self.rng.gen_range(0..members.len())])
+        }).collect();
+        for (n, field) in def.fields.iter().enumerate() {
+            if field.oneof.is_some() && !chosen.contains(&n) { continue; }
+            let value = self.field(field, depth_left);
+            out.insert(field.json_name.clone(), value);
+        }
+        Value::Object(out)
+    }
+
+    fn field(&mut self, field: &FieldDef, depth_left: usize) -> Value {
+        let all = rules::field_rules(field);
+        match (&field.ty, field.label) {
+            (FieldType::Map(key, ty), _) => {
+                let count = self.count(&Constraints(rules::under(&all, "map.")), "min_pairs", "max_pairs", 1, 2);
+                let keys = Constraints(rules::under(&all, "map.keys."));
+                let values = Constraints(rules::under(&all, "map.values."));
+                let mut out = Map::new();
+                for _ in 0..count * 4 {
+                    if out.len() == count { break; }
+                    let k = match self.single(field, &FieldType::Scalar(*key), &keys, depth_left) {
+                        Value::String(s) => s,
+                        other => other.to_string(),
+                    };
+                    let v = self.single(field, ty, &values, depth_left);
+                    out.insert(k, v);
+                }
+                Value::Object(out)
+            }
+            (ty, Label::Repeated) => {
+                let repeated = Constraints(rules::under(&all, "repeated."));
+                let count = self.count(&repeated, "min_items", "max_items", 1, 3);
+                let each = Constraints(rules::under(&all, "repeated.items."));
+                let unique = all.iter().any(|r| r.name == "repeated.unique" && r.value == Lit::Bool(true));
+                let mut items: Vec<Value> = Vec::new();
+                for _ in 0..count * 4 {
+                    if items.len() == count { break; }
+                    let item = self.single(field, ty, &each, depth_left);
+                    if !unique || !items.contains(&item) { items.push(item); }
+                }
+                Value::Array(items)
+            }
+            (ty, _) => self.single(field, ty, &Constraints(rules::under(&all, "")), depth_left),
+        }
+    }
+
+    /// How many items to generate, within `min`/`max` constraints (`repeated.min_items`, ...).
+    fn count(&mut self, c: &Constraints, min: &str, max: &str, lo: usize, hi: usize) -> usize {
+        let min = c.0.iter().find(|(n, _)| *n == min).and_then(|(_, r)| match &r.value { Lit::Num(n) => n.parse().ok(), _ => None });
+        let max = c.0.iter().find(|(n, _)| *n == max).and_then(|(_, r)| match &r.value { Lit::Num(n) => n.parse().ok(), _ => None });
+        let lo = min.unwrap_or(lo).min(max.unwrap_or(usize::MAX));
+        let hi = max.unwrap_or(hi).max(lo).min(lo + 3);
+        self.rng.gen_range(lo..=hi)
+    }
+
+    fn single(&mut self, field: &FieldDef, ty: &FieldType, c: &Constraints, depth_left: usize) -> Value {
+        match ty {
+            FieldType::Scalar(Scalar::Bool) => match c.get("const") { Some(Lit::Bool(b)) => json!(b), _ => json!(self.rng.gen_bool(0.5)) },
+            FieldType::Scalar(Scalar::String) => json!(self.string(&field.name, c)),
+            FieldType::Scalar(Scalar::Bytes) => {
+                let len = self.length(c, 4, 12);
+                let bytes: Vec<u8> = (0..len).map(|_| self.rng.gen()).collect();
+                json!(base64::engine::general_purpose::STANDARD.encode(bytes))
+            }
+            FieldType::Scalar(s @ (Scalar::Double | Scalar::Float)) => json!(self.float(*s, c)),
+            FieldType::Scalar(s) => {
+                let v = self.integer(*s, c);
+                if s.is_64bit() { json!(v.to_string()) } else { json!(v as i64) }
+            }
+            FieldType::Enum(name) if name == "google.protobuf.NullValue" => Value::Null,
+            FieldType::Enum(name) => self.enumeration(name, c),
+            FieldType::Message(name) => self.message(name, depth_left - 1),
+            FieldType::Map(..) | FieldType::Unresolved(_) => json!({}),
+        }
+    }
+
+    fn string(&mut self, field_name: &str, c: &Constraints) -> String {
+        if let Some(s) = c.text("const") { return s.to_string(); }
+        let choices = c.all("in");
+        if !choices.is_empty() {
+            let Lit::Str(s) = choices[self.rng.gen_range(0..choices.len())] else { return String::new(); };
+            return s.clone();
+        }
+        let excluded = c.all("not_in");
+        // a pattern that does not compile is reported by validation; the candidate stays unchecked
+        let pattern = c.text("pattern").and_then(|p| rules::pattern(p).ok());
+        let mut candidate = String::new();
+        for _ in 0..20 {
+            candidate = self.string_candidate(field_name, c);
+            let allowed = !excluded.iter().any(|e| matches!(e, Lit::Str(s) if *s == candidate));
+            if allowed && pattern.as_ref().is_none_or(|re| re.is_match(&candidate)) { break; }
+        }
+        candidate
+    }
+
+    fn string_candidate(&mut self, field_name: &str, c: &Constraints) -> String {
+        let word = WORDS[self.rng.gen_range(0..WORDS.len())];
+        let n: u16 = self.rng.gen_range(1..1000);
+        let name = field_name.to_lowercase();
+        let mut s = if let Some(pattern) = c.text("pattern") {
+            match regex_syntax::Parser::new().parse(pattern) {
+                Ok(hir) => { let mut out = String::new(); self.matching(&hir, &mut out); out }
+                Err(_) => word.to_string(),
+            }
+        } else if c.flag("uuid") || (c.0.is_empty() && name.ends_with("uuid")) {
+            uuid::Builder::from_random_bytes(self.rng.gen()).into_uuid().to_string()
+        } else if c.flag("email") || (c.0.is_empty() && name.contains("email")) {
+            format!("{}{}@example.com", word, n)
+        } else if c.flag("hostname") || c.flag("address") {
+            format!("{}.example.com", word)
+        } else if c.flag("ip") || c.flag("ipv4") {
+            format!("10.{}.{}.{}", self.rng.gen::<u8>(), self.rng.gen::<u8>(), self.rng.gen_range(1..255))
+        } else if c.flag("ipv6") {
+            format!("fd00::{:x}", self.rng.gen::<u16>())
+        } else if c.flag("uri") || (c.0.is_empty() && (name.ends_with("url") || name.ends_with("uri"))) {
+            format!("https://example.com/{}/{}", word, n)
+        } else if name == "id" || name.ends_with("_id") {
+            format!("{}-{}", &word[..3], n)
+        } else {
+            format!("{} {}", word, n)
+        };
+        if let Some(p) = c.text("prefix").filter(|p| !s.starts_with(*p)) { s = format!("{}{}", p, s); }
+        if let Some(x) = c.text("contains").filter(|x| !s.contains(*x)) { s.push_str(x); }
+        if let Some(x) = c.text("suffix").filter(|x| !s.ends_with(*x)) { s.push_str(x); }
+        let (min, max) = (c.num("len").or(c.num("min_len")), c.num("len").or(c.num("max_len")));
+        let min = min.or(c.num("min_bytes")).map(|n| n as usize);
+        let max = max.or(c.num("max_bytes")).map(|n| n as usize);
+        while min.is_some_and(|m| s.chars().count() < m) { s.push(self.rng.gen_range(b'a'..=b'z') as char); }
+        if let Some(max) = max { s = s.chars().take(max).collect(); }
+        s
+    }
+
+    /// A random string matching a parsed regular expression, with repetitions kept short and
+    /// characters drawn from printable ASCII where the class allows.
+    fn matching(&mut self, hir: &Hir, out: &mut String) {
+        match hir.kind() {
+            HirKind::Empty | HirKind::Look(_) => {}
+            HirKind::Literal(lit) => out.push_str(&String::from_utf8_lossy(&lit.0)),
+            HirKind::Class(Class::Unicode(class)) => {
+                let ranges: Vec<(u32, u32)> = class.ranges().iter().map(|r| (r.start() as u32, r.end() as u32)).collect();
+                if let Some(c) = self.pick_char(&ranges) { out.push(c); }
+            }
+            HirKind::Class(Class::Bytes(class)) => {
+                let ranges: Vec<(u32, u32)> = class.ranges().iter().map(|r| (r.start() as u32, r.end() as u32)).collect();
+                if let Some(c) = self.pick_char(&ranges) { out.push(c); }
+            }
+            HirKind::Repetition(rep) => {
+                let max = rep.max.unwrap_or(rep.min + 3).min(rep.min + 3);
+                for _ in 0..self.rng.gen_range(rep.min..=max) { self.matching(&rep.sub, out); }
+            }
+            HirKind::Capture(cap) => self.matching(&cap.sub, out),
+            HirKind::Concat(parts) => for part in parts { self.matching(part, out) },
+            HirKind::Alternation(branches) => {
+                let branch = &branches[self.rng.gen_range(0..branches.len())];
+                self.matching(branch, out);
+            }
+        }
+    }
+
+    fn pick_char(&mut self, ranges: &[(u32, u32)]) -> Option<char> {
+        let printable: Vec<(u32, u32)> = ranges.iter().filter_map(|&(lo, hi)| {
+            let (lo, hi) = (lo.max(0x21), hi.min(0x7e));
+            (lo <= hi).then_some((lo, hi))
+        }).collect();
+        let pool = if printable.is_empty() { ranges } else { &printable };
+        let &(lo, hi) = pool.get(self.rng.gen_range(0..pool.len().max(1)))?;
+        char::from_u32(self.rng.gen_range(lo..=hi))
+    }
+
+    fn length(&mut self, c: &Constraints, lo: usize, hi: usize) -> usize {
+        if let Some(n) = c.num("len") { return n as usize; }
+        let min = c.num("min_len").map_or(lo, |n| n as usize);
+        let max = c.num("max_len").map_or(hi.max(min), |n| n as usize).max(min);
+        self.rng.gen_range(min..=max.min(min + hi))
+    }
+
+    fn integer(&mut self, s: Scalar, c: &Constraints) -> i128 {
+        let listed: Vec<i128> = c.all("in").iter().filter_map(|l| match l { Lit::Num(n) => n.parse().ok(), _ => None }).collect();
+        if let Some(Lit::Num(n)) = c.get("const") { return n.parse().unwrap_or_default(); }
+        if !listed.is_empty() { return listed[self.rng.gen_range(0..listed.len())]; }
+        let bound = |op: &str| c.get(op).and_then(|l| match l { Lit::Num(n) => n.parse::<i128>().ok(), _ => None });
+        let (type_min, type_max) = int_range(s);
+        let lower = bound("gte").or(bound("gt").map(|v| v + 1));
+        let upper = bound("lte").or(bound("lt").map(|v| v - 1));
+        let (lo, hi) = match (lower, upper) {
+            (Some(lo), Some(hi)) if lo > hi => (lo, lo + 1000), // outside an inverted range
+            (Some(lo), Some(hi)) => (lo, hi),
+            (Some(lo), None) => (lo, lo + 1000),
+            (None, Some(hi)) => (hi.min(0).max(hi - 1000), hi),
+            (None, None) => (0, 1000),
+        };
+        let (lo, hi) = (lo.clamp(type_min, type_max), hi.clamp(type_min, type_max));
+        let excluded: Vec<i128> = c.all("not_in").iter().filter_map(|l| match l { Lit::Num(n) => n.parse().ok(), _ => None }).collect();
+        let mut v = self.rng.gen_range(lo..=hi);
+        for _ in 0..20 {
+            if !excluded.contains(&v) { break; }
+            v = self.rng.gen_range(lo..=hi);
+        }
+        v
+    }
+
+    fn float(&mut self, s: Scalar, c: &Constraints) -> f64 {
+        if let Some(v) = c.num("const") { return v; }
+        let listed: Vec<f64> = c.all("in").iter().filter_map(|l| match l { Lit::Num(n) => n.parse().ok(), _ => None }).collect();
+        if !listed.is_empty() { return listed[self.rng.gen_range(0..listed.len())]; }
+        let lower = c.num("gte").or(c.num("gt").map(|v| v + 0.01));
+        let upper = c.num("lte").or(c.num("lt").map(|v| v - 0.01));
+        let (lo, hi) = match (lower, upper) {
+            (Some(lo), Some(hi)) if lo > hi => (lo, lo + 100.0),
+            (Some(lo), Some(hi)) => (lo, hi),
+            (Some(lo), None) => (lo, lo + 100.0),
+            (None, Some(hi)) => (hi - 100.0, hi),
+            (None, None) => (0.0, 100.0),
+        };
+        let v = (self.rng.gen_range(lo..=hi) * 100.0).round() / 100.0;
+        let v = v.clamp(lo, hi);
+        if s == Scalar::Float { v as f32 as f64 } else { v }
+    }
+
+    /// A random value name, avoiding the zero value when there are others to choose from.
+    fn enumeration(&mut self, name: &str, c: &Constraints) -> Value {
+        let Some(def) = self.schema.enum_def(name) else { return json!(0); };
+        let number = |l: &Lit| match l { Lit::Num(n) => n.parse::<i32>().ok(), _ => None };
+        let listed: Vec<i32> = c.all("in").into_iter().filter_map(number).collect();
+        let excluded: Vec<i32> = c.all("not_in").into_iter().filter_map(number).collect();
+        let konst = c.get("const").and_then(number);
+        let allowed: Vec<&(String, i32)> = def.values.iter()
+            .filter(|(_, v)| (konst.is_none() || konst == Some(*v)) && (listed.is_empty() || listed.contains(v)) && !excluded.contains(v))
+            .collect();
+        let preferred: Vec<&&(String, i32)> = allowed.iter().filter(|(_, v)| *v != 0).collect();
+        let pick = if preferred.is_empty() { allowed.first().copied() } else { Some(*preferred[self.rng.gen_range(0..preferred.len())]) };
+        pick.map(|(n, _)| json!(n)).unwrap_or(json!(0))
+    }
+
+    fn well_known(&mut self, name: &str) -> Option<Value> {
+        let word = WORDS[self.rng.gen_range(0..WORDS.len())];
+        Some(match name {
+            "google.protobuf.Timestamp" => {
+                let (month, day) = (self.rng.gen_range(1..=12), self.rng.gen_range(1..=28));
+                let (hour, minute, second) = (self.rng.gen_range(0..24), self.rng.gen_range(0..60), self.rng.gen_range(0..60));
+                json!(format!("{}-{:02}-{:02}T{:02}:{:02}:{:02}Z", self.rng.gen_range(2020..=2030), month, day, hour, minute, second))
+            }
+            "google.protobuf.Duration" => json!(format!("{}s", self.rng.gen_range(1..=3600))),
+            "google.protobuf.Value" | "google.protobuf.StringValue" => json!(word),
+            "google.protobuf.Struct" => json!({ word: self.rng.gen_range(0..100) }),
+            "google.protobuf.ListValue" => json!([word]),
+            "google.protobuf.Int64Value" | "google.protobuf.UInt64Value" => json!(self.rng.gen_range(0..1000).to_string()),
+            "google.protobuf.Int32Value" | "google.protobuf.UInt32Value" => json!(self.rng.gen_range(0..1000)),
+            "google.protobuf.DoubleValue" | "google.protobuf.FloatValue" => json!((self.rng.gen_range(0.0..100.0_f64) * 100.0).round() / 100.0),
+            "google.protobuf.BoolValue" => json!(self.rng.gen_bool(0.5)),
+            other => return super::well_known_default(other),
+        })
+    }
+}
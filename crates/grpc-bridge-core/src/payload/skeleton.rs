@@ -0,0 +1,63 @@
+use super::well_known_default;
+use crate::proto_index::schema::{FieldType, Label, Scalar, Schema};
+use serde_json::{json, Map, Value};
+
+/// Nested message levels expanded when no depth is requested.
+pub const DEFAULT_DEPTH: usize = 3;
+
+/// A payload for `message` with every field at its type's default value: nested messages down
+/// to `max_depth` levels (deeper ones as `{}`), repeated fields as one-element arrays, maps with
+/// one example entry, enums at their first value and only the first field of each oneof.
+pub fn skeleton(schema: &Schema, message: &str, max_depth: usize) -> Value {
+    message_value(schema, message, max_depth)
+}
+
+fn message_value(schema: &Schema, name: &str, depth_left: usize) -> Value {
+    if let Some(v) = well_known_default(name) { return v; }
+    let Some(message) = schema.message(name) else { return json!({}); };
+    let mut out = Map::new();
+    if depth_left == 0 { return Value::Object(out); }
+    let mut oneofs_seen = Vec::new();
+    for field in &message.fields {
+        if let Some(i) = field.oneof {
+            if oneofs_seen.contains(&i) { continue; }
+            oneofs_seen.push(i);
+        }
+        let value = match (&field.ty, field.label) {
+            (FieldType::Map(key, v), _) => json!({ map_key(*key): single(schema, v, depth_left) }),
+            (ty, Label::Repeated) => json!([single(schema, ty, depth_left)]),
+            (ty, _) => single(schema, ty, depth_left),
+        };
+        out.insert(field.json_name.clone(), value);
+    }
+    Value::Object(out)
+}
+
+fn single(schema: &Schema, ty: &FieldType, depth_left: usize) -> Value {
+    match ty {
+        FieldType::Scalar(s) => scalar_default(*s),
+        FieldType::Enum(name) if name == "google.protobuf.NullValue" => Value::Null,
+        FieldType::Enum(name) => schema.enum_def(name).and_then(|e| e.values.first()).map(|(n, _)| json!(n)).unwrap_or(json!(0)),
+        FieldType::Message(name) => message_value(schema, name, depth_left - 1),
+        FieldType::Map(..) | FieldType::Unresolved(_) => json!({}),
+    }
+}
+
+pub fn scalar_default(s: Scalar) -> Value {
+    match s {
+        Scalar::Bool => json!(false),
+        Scalar::String | Scalar::Bytes => json!(""),
+        Scalar::Double | Scalar::Float => json!(0.0),
+        s if s.is_64bit() => json!("0"),
+        _ => json!(0),
+    }
+}
+
+/// Map keys are strings in JSON, whatever the key type.
+fn map_key(s: Scalar) -> &'static str {
+    match s {
+        Scalar::String => "key",
+        Scalar::Bool => "true",
+        _ => "0",
+    }
+}
@@ -0,0 +1,398 @@
+//! The protobuf text format, read into and printed from the binary wire format with the field
+//! types of a [`Schema`].
+
+use super::wire::{self, WireValue};
+use crate::proto_index::schema::{FieldDef, FieldType, Label, Scalar, Schema};
+use serde_json::{json, Value};
+use std::fmt::Write;
+
+const RECURSION_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Ident(String),
+    /// A number as written, with its sign; also `-inf` and `-nan`.
+    Num(String),
+    Str(Vec<u8>),
+    Sym(char),
+}
+
+/// Encodes text format `text` as `message`. Fields may be separated by `,` or `;`, messages
+/// may use `{}` or `<>`, repeated fields may be given one by one or as a `[...]` list, and an
+/// `Any` may be written expanded as `[type.googleapis.com/pkg.Message] { ... }`.
+pub fn to_wire(schema: &Schema, message: &str, text: &str) -> Result<Vec<u8>, String> {
+    let mut parser = Parser { schema, toks: tokenize(text)?, pos: 0 };
+    let mut out = Vec::new();
+    parser.message(message.trim_start_matches('.'), None, &mut out, 0)?;
+    Ok(out)
+}
+
+/// Prints encoded `message` in text format, two spaces per level. Fields missing from the
+/// schema are printed by number.
+pub fn print(schema: &Schema, message: &str, bytes: &[u8]) -> Result<String, String> {
+    let mut out = String::new();
+    print_message(schema, message.trim_start_matches('.'), bytes, 0, &mut out, 0)?;
+    Ok(out)
+}
+
+/// Tokens with their line numbers. `#` starts a comment.
+fn tokenize(text: &str) -> Result<Vec<(Tok, usize)>, String> {
+    let mut out = Vec::new();
+    let mut line = 1;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => {}
+            '#' => { for c in chars.by_ref() { if c == '\n' { line += 1; break; } } }
+            '"' | '\'' => {
+                let mut bytes = Vec::new();
+                loop {
+                    let next = chars.next().filter(|n| *n != '\n').ok_or_else(|| format!("line {}: unterminated string", line))?;
+                    if next == c { break; }
+                    if next != '\\' {
+                        bytes.extend_from_slice(next.encode_utf8(&mut [0; 4]).as_bytes());
+                        continue;
+                    }
+                    let escape = chars.next().ok_or_else(|| format!("line {}: unterminated string", line))?;
+                    match escape {
+                        'n' => bytes.push(b'\n'),
+                        'r' => bytes.push(b'\r'),
+                        't' => bytes.push(b'\t'),
+                        'a' => bytes.push(0x07),
+                        'b' => bytes.push(0x08),
+                        'f' => bytes.push(0x0c),
+                        'v' => bytes.push(0x0b),
+                        '0'..='7' => {
+                            let mut n = escape.to_digit(8).unwrap();
+                            for _ in 0..2 {
+                                match chars.peek().and_then(|d| d.to_digit(8)) { Some(d) => { n = n * 8 + d; chars.next(); } None => break }
+                            }
+                            bytes.push(u8::try_from(n).map_err(|_| format!("line {}: octal escape out of range", line))?);
+                        }
+                        'x' | 'X' => {
+                            let mut n = 0;
+                            for i in 0..2 {
+                                match chars.peek().and_then(|d| d.to_digit(16)) {
+                                    Some(d) => { n = n * 16 + d; chars.next(); }
+                                    None if i == 0 => return Err(format!("line {}: \\x needs hex digits", line)),
+                                    None => break,
+                                }
+                            }
+                            bytes.push(n as u8);
+                        }
+                        'u' | 'U' => {
+                            let digits: String = (0..if escape == 'u' { 4 } else { 8 }).filter_map(|_| chars.next()).collect();
+                            let c = u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32)
+                                .ok_or_else(|| format!("line {}: invalid \\{}{} escape", line, escape, digits))?;
+                            bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                        }
+                        other => bytes.extend_from_slice(other.encode_utf8(&mut [0; 4]).as_bytes()),
+                    }
+                }
+                out.push((Tok::Str(bytes), line));
+            }
+            c if c.is_ascii_digit() || (c == '-' && chars.peek().is_some_and(|n| n.is_ascii_alphanumeric() || *n == '.')) || (c == '.' && chars.peek().is_some_and(char::is_ascii_digit)) => {
+                let mut n = String::from(c);
+                while let Some(&next) = chars.peek() {
+                    let exponent_sign = (next == '+' || next == '-') && n.ends_with(['e', 'E']) && !n.trim_start_matches('-').starts_with("0x");
+                    if !(next.is_ascii_alphanumeric() || next == '.' || next == '_' || exponent_sign) { break; }
+                    n.push(next);
+                    chars.next();
+                }
+                out.push((Tok::Num(n), line));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut ident = String::from(c);
+                while let Some(&next) = chars.peek() {
+                    if !(next.is_alphanumeric() || next == '_' || next == '.') { break; }
+                    ident.push(next);
+                    chars.next();
+                }
+                out.push((Tok::Ident(ident), line));
+            }
+            c => out.push((Tok::Sym(c), line)),
+        }
+    }
+    Ok(out)
+}
+
+struct Parser<'a> {
+    schema: &'a Schema,
+    toks: Vec<(Tok, usize)>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Tok> { self.toks.get(self.pos).map(|(t, _)| t) }
+
+    fn next(&mut self) -> Option<Tok> {
+        let tok = self.toks.get(self.pos).map(|(t, _)| t.clone());
+        self.pos += 1;
+        tok
+    }
+
+    fn eat(&mut self, sym: char) -> bool {
+        let found = self.peek() == Some(&Tok::Sym(sym));
+        if found { self.pos += 1; }
+        found
+    }
+
+    fn error(&self, message: impl std::fmt::Display) -> String {
+        let line = self.toks.get(self.pos.min(self.toks.len().saturating_sub(1))).map_or(1, |(_, l)| *l);
+        format!("line {}: {}", line, message)
+    }
+
+    /// `{` or `<`, returning the matching closing symbol.
+    fn open(&mut self) -> Result<char, String> {
+        if self.eat('{') { Ok('}') } else if self.eat('<') { Ok('>') } else { Err(self.error("expected '{' or '<'")) }
+    }
+
+    /// Fields of `name` up to `end`, or to the end of the input.
+    fn message(&mut self, name: &str, end: Option<char>, out: &mut Vec<u8>, depth: usize) -> Result<(), String> {
+        if depth > RECURSION_LIMIT { return Err(self.error(format!("messages nest deeper than {} levels", RECURSION_LIMIT))); }
+        let def = self.schema.message(name).ok_or_else(|| format!("{} is not defined in the indexed protos", name))?;
+        loop {
+            match self.peek() {
+                None if end.is_none() => return Ok(()),
+                None => return Err(self.error(format!("expected '{}'", end.unwrap_or('}')))),
+                Some(Tok::Sym(c)) if Some(*c) == end => { self.pos += 1; return Ok(()); }
+                Some(Tok::Sym('[')) if name == "google.protobuf.Any" => self.expanded_any(out, depth)?,
+                Some(Tok::Ident(ident)) => {
+                    let ident = ident.clone();
+                    let field = def.fields.iter().find(|f| f.name == ident || f.json_name == ident)
+                        .ok_or_else(|| self.error(format!("{} has no field {:?}", name, ident)))?;
+                    self.pos += 1;
+                    let colon = self.eat(':');
+                    if field.label == Label::Repeated && self.eat('[') {
+                        while !self.eat(']') {
+                            self.field_value(field, colon, out, depth)?;
+                            if !self.eat(',') && self.peek() != Some(&Tok::Sym(']')) { return Err(self.error("expected ',' or ']'")); }
+                        }
+                    } else {
+                        self.field_value(field, colon, out, depth)?;
+                    }
+                    if !self.eat(',') { self.eat(';'); }
+                }
+                Some(tok) => return Err(self.error(format!("expected a field name, found {:?}", tok))),
+            }
+        }
+    }
+
+    fn field_value(&mut self, field: &FieldDef, colon: bool, out: &mut Vec<u8>, depth: usize) -> Result<(), String> {
+        match &field.ty {
+            FieldType::Map(key, value) => {
+                let close = self.open()?;
+                let mut entry = Vec::new();
+                while !self.eat(close) {
+                    match self.next() {
+                        Some(Tok::Ident(k)) if k == "key" => { self.eat(':'); self.value(&FieldType::Scalar(*key), 1, &mut entry, depth)?; }
+                        Some(Tok::Ident(v)) if v == "value" => { self.eat(':'); self.value(value, 2, &mut entry, depth)?; }
+                        _ => { self.pos -= 1; return Err(self.error("expected 'key' or 'value'")); }
+                    }
+                    if !self.eat(',') { self.eat(';'); }
+                }
+                wire::put_bytes(out, field.number, &entry);
+                Ok(())
+            }
+            FieldType::Message(_) => self.value(&field.ty, field.number, out, depth),
+            _ if !colon => Err(self.error(format!("expected ':' after {}", field.name))),
+            ty => self.value(ty, field.number, out, depth),
+        }
+    }
+
+    /// One value of type `ty`, written as field `number`.
+    fn value(&mut self, ty: &FieldType, number: i32, out: &mut Vec<u8>, depth: usize) -> Result<(), String> {
+        let schema = self.schema;
+        match ty {
+            FieldType::Message(m) => {
+                let close = self.open()?;
+                let mut body = Vec::new();
+                self.message(m, Some(close), &mut body, depth + 1)?;
+                wire::put_bytes(out, number, &body);
+                Ok(())
+            }
+            FieldType::Scalar(Scalar::String | Scalar::Bytes) => {
+                let mut bytes = match self.next() { Some(Tok::Str(b)) => b, _ => { self.pos -= 1; return Err(self.error("expected a string")); } };
+                // adjacent literals are concatenated
+                while let Some(Tok::Str(more)) = self.peek() { bytes.extend_from_slice(more); self.pos += 1; }
+                if *ty == FieldType::Scalar(Scalar::String) && std::str::from_utf8(&bytes).is_err() { return Err(self.error("string field holds invalid UTF-8")); }
+                wire::put_bytes(out, number, &bytes);
+                Ok(())
+            }
+            ty => {
+                let tok = self.next().ok_or_else(|| self.error("expected a value"))?;
+                let value = scalar_value(ty, &tok).ok_or_else(|| { self.pos -= 1; self.error(format!("{:?} is not a valid value here", tok)) })?;
+                wire::put_field(schema, out, ty, number, &value).map_err(|e| self.error(e))
+            }
+        }
+    }
+
+    /// `[type.googleapis.com/pkg.Message] { ... }` inside an `Any`.
+    fn expanded_any(&mut self, out: &mut Vec<u8>, depth: usize) -> Result<(), String> {
+        self.pos += 1;
+        let mut url = String::new();
+        loop {
+            match self.next() {
+                Some(Tok::Sym(']')) => break,
+                Some(Tok::Ident(part)) => url.push_str(&part),
+                Some(Tok::Sym('/')) => url.push('/'),
+                _ => { self.pos -= 1; return Err(self.error("expected a type URL")); }
+            }
+        }
+        self.eat(':');
+        let close = self.open()?;
+        let mut body = Vec::new();
+        self.message(url.rsplit('/').next().unwrap_or_default(), Some(close), &mut body, depth + 1)?;
+        wire::put_bytes(out, 1, url.as_bytes());
+        wire::put_bytes(out, 2, &body);
+        Ok(())
+    }
+}
+
+/// A scalar or enum token as the JSON value [`wire`] encodes.
+fn scalar_value(ty: &FieldType, tok: &Tok) -> Option<Value> {
+    match (ty, tok) {
+        (FieldType::Enum(_), Tok::Ident(name)) => Some(json!(name)),
+        (FieldType::Enum(_), Tok::Num(n)) => parse_int(n).and_then(|n| i64::try_from(n).ok()).map(|n| json!(n)),
+        (FieldType::Scalar(Scalar::Bool), Tok::Ident(b) | Tok::Num(b)) => match b.as_str() {
+            "true" | "True" | "t" | "1" => Some(json!(true)),
+            "false" | "False" | "f" | "0" => Some(json!(false)),
+            _ => None,
+        },
+        (FieldType::Scalar(Scalar::Double | Scalar::Float), Tok::Ident(n) | Tok::Num(n)) => {
+            let lowered = n.to_lowercase();
+            let (negative, body) = match lowered.strip_prefix('-') { Some(b) => (true, b), None => (false, lowered.as_str()) };
+            match body {
+                "inf" | "infinity" => Some(json!(if negative { "-Infinity" } else { "Infinity" })),
+                "nan" => Some(json!("NaN")),
+                _ if body.starts_with("0x") => parse_int(n).map(|i| json!(i as f64)),
+                _ => lowered.trim_end_matches('f').parse::<f64>().ok().map(|f| json!(f)),
+            }
+        }
+        (FieldType::Scalar(_), Tok::Num(n)) => parse_int(n).map(|n| json!(n.to_string())),
+        _ => None,
+    }
+}
+
+/// Decimal, `0x` hexadecimal or `0` octal.
+fn parse_int(text: &str) -> Option<i128> {
+    let (negative, body) = match text.strip_prefix('-') { Some(b) => (true, b), None => (false, text) };
+    let n = if let Some(hex) = body.strip_prefix("0x").or_else(|| body.strip_prefix("0X")) { i128::from_str_radix(hex, 16).ok()? }
+        else if body.len() > 1 && body.starts_with('0') { i128::from_str_radix(&body[1..], 8).ok()? }
+        else { body.parse().ok()? };
+    Some(if negative { -n } else { n })
+}
+
+fn print_message(schema: &Schema, name: &str, bytes: &[u8], indent: usize, out: &mut String, depth: usize) -> Result<(), String> {
+    if depth > RECURSION_LIMIT { return Err(format!("messages nest deeper than {} levels", RECURSION_LIMIT)); }
+    let pad = "  ".repeat(indent);
+    let parsed = wire::fields(bytes)?;
+    if name == "google.protobuf.Any" {
+        let url = parsed.iter().find_map(|(n, v)| match (n, v) { (1, WireValue::Len(s)) => std::str::from_utf8(s).ok(), _ => None });
+        let value = parsed.iter().rev().find_map(|(n, v)| match (n, v) { (2, WireValue::Len(b)) => Some(*b), _ => None }).unwrap_or_default();
+        if let Some(url) = url.filter(|u| schema.message(u.rsplit('/').next().unwrap_or_default()).is_some()) {
+            let _ = writeln!(out, "{}[{}] {{", pad, url);
+            print_message(schema, url.rsplit('/').next().unwrap_or_default(), value, indent + 1, out, depth + 1)?;
+            let _ = writeln!(out, "{}}}", pad);
+            return Ok(());
+        }
+    }
+    let def = schema.message(name);
+    for (number, v) in parsed {
+        let Some(field) = def.and_then(|d| d.fields.iter().find(|f| i64::from(f.number) == i64::from(number))) else {
+            let _ = writeln!(out, "{}{}: {}", pad, number, unknown_text(&v));
+            continue;
+        };
+        match (&field.ty, field.label) {
+            (FieldType::Map(key, value), _) => {
+                let WireValue::Len(entry) = v else { return Err(format!("field {} is not a map entry", field.name)); };
+                let _ = writeln!(out, "{}{} {{", pad, field.name);
+                let inner = "  ".repeat(indent + 1);
+                for (n, item) in wire::fields(entry)? {
+                    match n {
+                        1 => { let _ = writeln!(out, "{}key: {}", inner, value_text(schema, field, &FieldType::Scalar(*key), &item)?); }
+                        2 => print_field(schema, field, "value", value, &item, indent + 1, out, depth)?,
+                        _ => {}
+                    }
+                }
+                let _ = writeln!(out, "{}}}", pad);
+            }
+            (ty, Label::Repeated) if wire::wire_type(ty) != 2 && v.wire_type() == 2 => {
+                let WireValue::Len(packed) = v else { unreachable!() };
+                for item in wire::unpack(ty, packed)? { let _ = writeln!(out, "{}{}: {}", pad, field.name, value_text(schema, field, ty, &item)?); }
+            }
+            (ty, _) => print_field(schema, field, &field.name, ty, &v, indent, out, depth)?,
+        }
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn print_field(schema: &Schema, field: &FieldDef, label: &str, ty: &FieldType, v: &WireValue, indent: usize, out: &mut String, depth: usize) -> Result<(), String> {
+    let pad = "  ".repeat(indent);
+    match (ty, v) {
+        (FieldType::Message(m), WireValue::Len(body)) => {
+            let _ = writeln!(out, "{}{} {{", pad, label);
+            print_message(schema, m, body, indent + 1, out, depth + 1)?;
+            let _ = writeln!(out, "{}}}", pad);
+        }
+        _ => { let _ = writeln!(out, "{}{}: {}", pad, label, value_text(schema, field, ty, v)?); }
+    }
+    Ok(())
+}
+
+fn value_text(schema: &Schema, field: &FieldDef, ty: &FieldType, v: &WireValue) -> Result<String, String> {
+    let mismatch = || format!("field {} ({}) has wire type {}", field.name, field.number, v.wire_type());
+    match (ty, v) {
+        (FieldType::Scalar(Scalar::String | Scalar::Bytes), WireValue::Len(bytes)) => Ok(quote(bytes)),
+        (FieldType::Enum(e), WireValue::Varint(n)) => {
+            let n = *n as i32;
+            Ok(schema.enum_def(e).and_then(|d| d.values.iter().find(|(_, number)| *number == n)).map_or(n.to_string(), |(name, _)| name.clone()))
+        }
+        (FieldType::Scalar(s), v) if v.wire_type() == wire::wire_type(ty) => Ok(match wire::scalar_json(*s, raw(v)) {
+            Value::String(text) => match text.as_str() { "NaN" => "nan".into(), "Infinity" => "inf".into(), "-Infinity" => "-inf".into(), _ => text },
+            other => other.to_string(),
+        }),
+        _ => Err(mismatch()),
+    }
+}
+
+fn raw(v: &WireValue) -> u64 {
+    match *v { WireValue::Varint(n) | WireValue::Fixed64(n) => n, WireValue::Fixed32(n) => u64::from(n), WireValue::Len(_) => 0 }
+}
+
+fn unknown_text(v: &WireValue) -> String {
+    match v {
+        WireValue::Varint(n) => n.to_string(),
+        WireValue::Fixed32(n) => format!("0x{:08x}", n),
+        WireValue::Fixed64(n) => format!("0x{:016x}", n),
+        WireValue::Len(bytes) => quote(bytes),
+    }
+}
+
+/// A double-quoted literal; valid UTF-8 is kept, other bytes and control characters are escaped.
+pub(super) fn quote(bytes: &[u8]) -> String {
+    let mut out = String::from('"');
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.chars().for_each(|c| escape(&mut out, c)),
+        Err(_) => for &b in bytes {
+            if b.is_ascii() { escape(&mut out, b as char) } else { let _ = write!(out, "\\{:03o}", b); }
+        },
+    }
+    out.push('"');
+    out
+}
+
+fn escape(out: &mut String, c: char) {
+    match c {
+        '\n' => out.push_str("\\n"),
+        '\r' => out.push_str("\\r"),
+        '\t' => out.push_str("\\t"),
+        '"' => out.push_str("\\\""),
+        '\'' => out.push_str("\\'"),
+        '\\' => out.push_str("\\\\"),
+        c if (c as u32) < 0x20 || c as u32 == 0x7f => { let _ = write!(out, "\\{:03o}", c as u32); }
+        c => out.push(c),
+    }
+}
@@ -0,0 +1,273 @@
+use super::rules;
+use crate::grpcurl;
+use crate::proto_index::schema::{FieldDef, FieldType, Label, Scalar, Schema};
+use serde::Serialize;
+use serde_json::Value;
+
+lazy_static::lazy_static! {
+    static ref RE_TIMESTAMP: regex::Regex = regex::Regex::new(r"^(\d{4})-(\d{2})-(\d{2})T(\d{2}):(\d{2}):(\d{2})(\.\d{1,9})?(Z|[+-](\d{2}):(\d{2}))$").unwrap();
+    static ref RE_DURATION: regex::Regex = regex::Regex::new(r"^-?(\d+)(\.\d{1,9})?s$").unwrap();
+}
+
+/// A problem found in a request payload, located by the JSON pointer of the offending value.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PayloadIssue {
+    /// RFC 6901 pointer such as `/items/0/sku`; empty for the payload as a whole.
+    pub path: String,
+    pub kind: IssueKind,
+    pub message: String,
+    /// The violated constraint for `constraint` issues, e.g. `string.min_len`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind { InvalidJson, UnknownField, WrongType, InvalidEnum, OutOfRange, InvalidFormat, OneofConflict, Constraint }
+
+/// Parses `payload` (empty means `{}`) and checks it against `message`.
+pub fn validate_str(schema: &Schema, message: &str, payload: &str) -> Vec<PayloadIssue> {
+    match serde_json::from_str::<Value>(grpcurl::effective_payload(payload)) {
+        Ok(value) => validate(schema, message, &value),
+        Err(e) => vec![PayloadIssue { path: String::new(), kind: IssueKind::InvalidJson, message: e.to_string(), rule: None }],
+    }
+}
+
+/// Checks `payload` the way grpcurl's JSON parser would read it as `message`, then against the
+/// field constraints declared in the protos (see [`super::rules`]). All issues are collected.
+/// Types the schema cannot resolve are accepted as they are.
+pub fn validate(schema: &Schema, message: &str, payload: &Value) -> Vec<PayloadIssue> {
+    let mut validator = Validator { schema, issues: Vec::new() };
+    validator.message(message, payload, "");
+    validator.issues
+}
+
+/// Appends `segment` to a JSON pointer, escaping `~` and `/`.
+pub fn pointer(parent: &str, segment: &str) -> String {
+    format!("{}/{}", parent, segment.replace('~', "~0").replace('/', "~1"))
+}
+
+type Check = Result<(), (IssueKind, String)>;
+
+struct Validator<'a> {
+    schema: &'a Schema,
+    issues: Vec<PayloadIssue>,
+}
+
+impl Validator<'_> {
+    fn report(&mut self, path: &str, check: Check) {
+        if let Err((kind, message)) = check { self.issues.push(PayloadIssue { path: path.to_string(), kind, message, rule: None }); }
+    }
+
+    fn message(&mut self, name: &str, value: &Value, path: &str) {
+        if let Some(check) = well_known(name, value) { return self.report(path, check); }
+        let schema = self.schema;
+        let Some(def) = schema.message(name) else { return; };
+        let Value::Object(fields) = value else { return self.report(path, Err(wrong_type("an object", value))); };
+        let mut oneofs_set: Vec<(usize, &str)> = Vec::new();
+        let mut mistyped = Vec::new();
+        for (key, v) in fields {
+            let at = pointer(path, key);
+            let Some(field) = def.field(key) else {
+                self.report(&at, Err((IssueKind::UnknownField, format!("unknown field {:?} in {}", key, def.full_name))));
+                continue;
+            };
+            // null leaves a field unset
+            if v.is_null() { continue; }
+            if let Some(i) = field.oneof {
+                match oneofs_set.iter().find(|(o, _)| *o == i) {
+                    Some((_, other)) => self.report(&at, Err((IssueKind::OneofConflict, format!("oneof {:?} is already set by {:?}", def.oneofs[i], other)))),
+                    None => oneofs_set.push((i, key)),
+                }
+            }
+            let before = self.issues.len();
+            self.field(field, v, &at);
+            if self.issues.len() > before { mistyped.push(field.number); }
+        }
+        for field in def.fields.iter().filter(|f| !mistyped.contains(&f.number)) {
+            let (key, value) = match fields.get_key_value(&field.json_name).or_else(|| fields.get_key_value(&field.name)) {
+                Some((key, v)) if !v.is_null() => (key.as_str(), Some(v)),
+                _ => (field.json_name.as_str(), None),
+            };
+            let issues = rules::check_field(schema, field, value, &pointer(path, key));
+            self.issues.extend(issues);
+        }
+    }
+
+    fn field(&mut self, field: &FieldDef, value: &Value, path: &str) {
+        match (&field.ty, field.label) {
+            (FieldType::Map(key, ty), _) => {
+                let Value::Object(entries) = value else { return self.report(path, Err(wrong_type("an object", value))); };
+                for (k, v) in entries {
+                    let at = pointer(path, k);
+                    self.report(&at, map_key(*key, k));
+                    self.single(ty, v, &at);
+                }
+            }
+            (ty, Label::Repeated) => {
+                let Value::Array(items) = value else { return self.report(path, Err(wrong_type("an array", value))); };
+                for (i, v) in items.iter().enumerate() { self.single(ty, v, &pointer(path, &i.to_string())); }
+            }
+            (ty, _) => self.single(ty, value, path),
+        }
+    }
+
+    fn single(&mut self, ty: &FieldType, value: &Value, path: &str) {
+        match ty {
+            FieldType::Scalar(s) => self.report(path, scalar(*s, value)),
+            FieldType::Enum(name) => { let check = self.enumeration(name, value); self.report(path, check) }
+            FieldType::Message(name) => self.message(name, value, path),
+            FieldType::Map(..) | FieldType::Unresolved(_) => {}
+        }
+    }
+
+    fn enumeration(&self, name: &str, value: &Value) -> Check {
+        if name == "google.protobuf.NullValue" && value.is_null() { return Ok(()); }
+        let Some(def) = self.schema.enum_def(name) else { return Ok(()); };
+        match value {
+            Value::String(s) if def.values.iter().any(|(n, _)| n == s) => Ok(()),
+            Value::String(s) => {
+                let names: Vec<&str> = def.values.iter().map(|(n, _)| n.as_str()).collect();
+                Err((IssueKind::InvalidEnum, format!("{:?} is not a value of {} (expected one of {})", s, def.full_name, names.join(", "))))
+            }
+            // proto3 enums are open: any int32 is accepted
+            Value::Number(n) if n.as_i64().is_some_and(|i| i32::try_from(i).is_ok()) => Ok(()),
+            Value::Number(n) => Err((IssueKind::OutOfRange, format!("{} is out of range for enum {}", n, def.full_name))),
+            other => Err(wrong_type("an enum value name", other)),
+        }
+    }
+}
+
+/// The check for a well-known type's canonical JSON form, `None` for other messages.
+fn well_known(name: &str, value: &Value) -> Option<Check> {
+    Some(match name {
+        "google.protobuf.Timestamp" => formatted(value, is_timestamp, "an RFC 3339 timestamp such as \"1970-01-01T00:00:00Z\""),
+        "google.protobuf.Duration" => formatted(value, is_duration, "a duration in seconds such as \"1.5s\""),
+        "google.protobuf.FieldMask" => formatted(value, |_| true, "comma-separated field paths"),
+        "google.protobuf.Any" => match value {
+            Value::Object(o) if o.get("@type").is_some_and(Value::is_string) => Ok(()),
+            Value::Object(_) => Err((IssueKind::InvalidFormat, "Any requires an \"@type\" string".into())),
+            other => Err(wrong_type("an object", other)),
+        },
+        "google.protobuf.Struct" => if value.is_object() { Ok(()) } else { Err(wrong_type("an object", value)) },
+        "google.protobuf.ListValue" => if value.is_array() { Ok(()) } else { Err(wrong_type("an array", value)) },
+        "google.protobuf.Value" => Ok(()),
+        "google.protobuf.DoubleValue" => scalar(Scalar::Double, value),
+        "google.protobuf.FloatValue" => scalar(Scalar::Float, value),
+        "google.protobuf.Int64Value" => scalar(Scalar::Int64, value),
+        "google.protobuf.UInt64Value" => scalar(Scalar::Uint64, value),
+        "google.protobuf.Int32Value" => scalar(Scalar::Int32, value),
+        "google.protobuf.UInt32Value" => scalar(Scalar::Uint32, value),
+        "google.protobuf.BoolValue" => scalar(Scalar::Bool, value),
+        "google.protobuf.StringValue" => scalar(Scalar::String, value),
+        "google.protobuf.BytesValue" => scalar(Scalar::Bytes, value),
+        _ => return None,
+    })
+}
+
+fn formatted(value: &Value, valid: impl Fn(&str) -> bool, expected: &str) -> Check {
+    match value {
+        Value::String(s) if valid(s) => Ok(()),
+        Value::String(s) => Err((IssueKind::InvalidFormat, format!("{:?} is not {}", s, expected))),
+        other => Err(wrong_type(expected, other)),
+    }
+}
+
+fn scalar(s: Scalar, value: &Value) -> Check {
+    let wrong = |expected: &str| Err(wrong_type(&format!("{} ({})", expected, s.name()), value));
+    match s {
+        Scalar::Bool => if value.is_boolean() { Ok(()) } else { wrong("a boolean") },
+        Scalar::String => if value.is_string() { Ok(()) } else { wrong("a string") },
+        Scalar::Bytes => match value {
+            Value::String(b) if is_base64(b) => Ok(()),
+            Value::String(_) => Err((IssueKind::InvalidFormat, "bytes must be base64-encoded".into())),
+            _ => wrong("a base64 string"),
+        },
+        Scalar::Double | Scalar::Float => {
+            let v = match value {
+                Value::Number(n) => n.as_f64().unwrap_or_default(),
+                Value::String(t) if matches!(t.as_str(), "NaN" | "Infinity" | "-Infinity") => return Ok(()),
+                Value::String(t) => match t.parse::<f64>() {
+                    Ok(v) if v.is_finite() => v,
+                    _ => return Err((IssueKind::InvalidFormat, format!("{:?} is not a number", t))),
+                },
+                _ => return wrong("a number"),
+            };
+            if s == Scalar::Float && v.abs() > f32::MAX as f64 {
+                return Err((IssueKind::OutOfRange, format!("{} is out of range for float", v)));
+            }
+            Ok(())
+        }
+        _ => {
+            let v = match value {
+                Value::Number(n) => n.as_i64().map(i128::from).or_else(|| n.as_u64().map(i128::from))
+                    .or_else(|| n.as_f64().filter(|f| f.fract() == 0.0).map(|f| f as i128)),
+                Value::String(t) => t.parse::<i128>().ok(),
+                _ => return wrong("an integer"),
+            };
+            let Some(v) = v else { return Err((IssueKind::InvalidFormat, format!("{} is not an integer", value))); };
+            let (min, max) = int_range(s);
+            if v < min || v > max {
+                return Err((IssueKind::OutOfRange, format!("{} is out of range for {} ({}..={})", v, s.name(), min, max)));
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Map keys are always JSON strings; they must still parse as the key type.
+fn map_key(key: Scalar, k: &str) -> Check {
+    match key {
+        Scalar::String => Ok(()),
+        Scalar::Bool if k == "true" || k == "false" => Ok(()),
+        Scalar::Bool => Err((IssueKind::InvalidFormat, format!("map key {:?} is not \"true\" or \"false\"", k))),
+        s => scalar(s, &Value::String(k.to_string())),
+    }
+}
+
+pub(super) fn int_range(s: Scalar) -> (i128, i128) {
+    match s {
+        Scalar::Int32 | Scalar::Sint32 | Scalar::Sfixed32 => (i32::MIN.into(), i32::MAX.into()),
+        Scalar::Uint32 | Scalar::Fixed32 => (0, u32::MAX.into()),
+        Scalar::Uint64 | Scalar::Fixed64 => (0, u64::MAX.into()),
+        _ => (i64::MIN.into(), i64::MAX.into()),
+    }
+}
+
+fn wrong_type(expected: &str, value: &Value) -> (IssueKind, String) {
+    let got = match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    };
+    (IssueKind::WrongType, format!("expected {}, got {}", expected, got))
+}
+
+/// Standard or URL-safe alphabet, padding optional, as grpcurl accepts.
+fn is_base64(s: &str) -> bool {
+    let body = s.trim_end_matches('=');
+    s.len() - body.len() <= 2 && body.len() % 4 != 1 && body.bytes().all(|b| b.is_ascii_alphanumeric() || b"+/-_".contains(&b))
+}
+
+fn is_timestamp(s: &str) -> bool {
+    let Some(c) = RE_TIMESTAMP.captures(s) else { return false; };
+    let n = |i: usize| c.get(i).map_or(0, |m| m.as_str().parse::<u32>().unwrap_or(u32::MAX));
+    let (year, month, day) = (n(1), n(2), n(3));
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    year >= 1 && (1..=days).contains(&day) && n(4) < 24 && n(5) < 60 && n(6) < 60 && n(9) < 24 && n(10) < 60
+}
+
+/// At most ±10 000 years, as `google.protobuf.Duration` allows.
+fn is_duration(s: &str) -> bool {
+    RE_DURATION.captures(s).is_some_and(|c| c[1].parse::<u64>().is_ok_and(|secs| secs <= 315_576_000_000))
+}
@@ -0,0 +1,639 @@
+//! The protobuf binary wire format, converted to and from the proto3 JSON mapping with the field
+//! types of a [`Schema`].
+
+use super::format::decode_base64;
+use super::skeleton::scalar_default;
+use super::validate::int_range;
+use crate::proto_index::schema::{json_name, FieldDef, FieldType, Label, Scalar, Schema};
+use base64::Engine;
+use serde_json::{json, Map, Value};
+
+/// How deep messages may nest, as in most protobuf runtimes.
+const RECURSION_LIMIT: usize = 100;
+
+/// One field value as it appears on the wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireValue<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Len(&'a [u8]),
+    Fixed32(u32),
+}
+
+impl WireValue<'_> {
+    /// The wire type number (0, 1, 2 or 5).
+    pub fn wire_type(&self) -> u8 {
+        match self { Self::Varint(_) => 0, Self::Fixed64(_) => 1, Self::Len(_) => 2, Self::Fixed32(_) => 5 }
+    }
+
+    /// The value as an unsigned integer; `None` for length-delimited values.
+    fn raw(&self) -> Option<u64> {
+        match *self { Self::Varint(v) | Self::Fixed64(v) => Some(v), Self::Fixed32(v) => Some(u64::from(v)), Self::Len(_) => None }
+    }
+}
+
+/// Field numbers and values of an encoded message, in wire order. Groups are not supported.
+pub fn fields(bytes: &[u8]) -> Result<Vec<(u32, WireValue<'_>)>, String> {
+    let mut out = Vec::new();
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        let tag = varint(&mut rest)?;
+        let number = u32::try_from(tag >> 3).map_err(|_| format!("field number {} is too large", tag >> 3))?;
+        if number == 0 { return Err("field number 0 is not allowed".into()); }
+        let value = match tag & 7 {
+            0 => WireValue::Varint(varint(&mut rest)?),
+            1 => WireValue::Fixed64(u64::from_le_bytes(take(&mut rest, 8)?.try_into().unwrap())),
+            2 => {
+                let len = usize::try_from(varint(&mut rest)?).map_err(|_| "length does not fit in memory".to_string())?;
+                WireValue::Len(take(&mut rest, len)?)
+            }
+            5 => WireValue::Fixed32(u32::from_le_bytes(take(&mut rest, 4)?.try_into().unwrap())),
+            3 | 4 => return Err(format!("field {} is a group, which is not supported", number)),
+            t => return Err(format!("field {} has invalid wire type {}", number, t)),
+        };
+        out.push((number, value));
+    }
+    Ok(out)
+}
+
+fn varint(rest: &mut &[u8]) -> Result<u64, String> {
+    let mut value = 0u64;
+    for i in 0..10 {
+        let (&byte, tail) = rest.split_first().ok_or("truncated varint")?;
+        *rest = tail;
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 { return Ok(value); }
+    }
+    Err("varint is longer than 10 bytes".into())
+}
+
+fn take<'a>(rest: &mut &'a [u8], len: usize) -> Result<&'a [u8], String> {
+    if rest.len() < len { return Err(format!("truncated message: {} bytes needed, {} left", len, rest.len())); }
+    let (head, tail) = rest.split_at(len);
+    *rest = tail;
+    Ok(head)
+}
+
+/// Wire type a value of `ty` is written with, unpacked.
+pub(super) fn wire_type(ty: &FieldType) -> u8 {
+    match ty {
+        FieldType::Scalar(Scalar::Double | Scalar::Fixed64 | Scalar::Sfixed64) => 1,
+        FieldType::Scalar(Scalar::Float | Scalar::Fixed32 | Scalar::Sfixed32) => 5,
+        FieldType::Scalar(Scalar::String | Scalar::Bytes) | FieldType::Message(_) | FieldType::Map(..) | FieldType::Unresolved(_) => 2,
+        _ => 0,
+    }
+}
+
+/// The values of a packed repeated field of type `ty`.
+pub(super) fn unpack<'a>(ty: &FieldType, bytes: &'a [u8]) -> Result<Vec<WireValue<'a>>, String> {
+    let mut rest = bytes;
+    let mut out = Vec::new();
+    while !rest.is_empty() {
+        out.push(match wire_type(ty) {
+            0 => WireValue::Varint(varint(&mut rest)?),
+            1 => WireValue::Fixed64(u64::from_le_bytes(take(&mut rest, 8)?.try_into().unwrap())),
+            5 => WireValue::Fixed32(u32::from_le_bytes(take(&mut rest, 4)?.try_into().unwrap())),
+            _ => return Err("only numeric fields can be packed".into()),
+        });
+    }
+    Ok(out)
+}
+
+/// Decodes `bytes` as `message` into canonical JSON.
+pub fn to_json(schema: &Schema, message: &str, bytes: &[u8]) -> Result<Value, String> {
+    message_json(schema, message.trim_start_matches('.'), bytes, 0)
+}
+
+fn message_json(schema: &Schema, name: &str, bytes: &[u8], depth: usize) -> Result<Value, String> {
+    if depth > RECURSION_LIMIT { return Err(format!("messages nest deeper than {} levels", RECURSION_LIMIT)); }
+    if name == "google.protobuf.Any" { return any_json(schema, bytes, depth); }
+    let def = schema.message(name).ok_or_else(|| format!("{} is not defined in the indexed protos", name))?;
+    let parsed = fields(bytes)?;
+    let mut out = Map::new();
+    for field in &def.fields {
+        let values: Vec<WireValue> = parsed.iter().filter(|(n, _)| i64::from(*n) == i64::from(field.number)).map(|(_, v)| *v).collect();
+        let Some(last) = values.last() else { continue; };
+        let value = match (&field.ty, field.label) {
+            (FieldType::Map(key, value), _) => {
+                let mut entries = Map::new();
+                for entry in &values {
+                    let (k, v) = map_entry(schema, field, *key, value, entry, depth)?;
+                    entries.insert(k, v);
+                }
+                Value::Object(entries)
+            }
+            (ty, Label::Repeated) => {
+                let mut items = Vec::new();
+                for v in &values {
+                    match v {
+                        WireValue::Len(packed) if wire_type(ty) != 2 => for item in unpack(ty, packed)? { items.push(value_json(schema, field, ty, &item, depth)?); },
+                        v => items.push(value_json(schema, field, ty, v, depth)?),
+                    }
+                }
+                Value::Array(items)
+            }
+            // repeated occurrences of a message field are merged, which is what decoding their concatenation does
+            (FieldType::Message(m), _) => {
+                let mut joined = Vec::new();
+                for v in &values { joined.extend_from_slice(len(field, v)?); }
+                message_json(schema, m, &joined, depth + 1)?
+            }
+            (ty, _) => value_json(schema, field, ty, last, depth)?,
+        };
+        out.insert(field.json_name.clone(), value);
+    }
+    Ok(well_known_json(name, Value::Object(out)))
+}
+
+fn map_entry(schema: &Schema, field: &FieldDef, key: Scalar, value: &FieldType, entry: &WireValue, depth: usize) -> Result<(String, Value), String> {
+    let (mut k, mut v) = (None, None);
+    for (number, item) in fields(len(field, entry)?)? {
+        match number {
+            1 => k = Some(value_json(schema, field, &FieldType::Scalar(key), &item, depth)?),
+            2 => v = Some(value_json(schema, field, value, &item, depth)?),
+            _ => {}
+        }
+    }
+    let k = match k.unwrap_or_else(|| scalar_default(key)) { Value::String(s) => s, other => other.to_string() };
+    let v = match v {
+        Some(v) => v,
+        None => match value {
+            FieldType::Message(m) => message_json(schema, m, &[], depth + 1)?,
+            other => default_json(other),
+        },
+    };
+    Ok((k, v))
+}
+
+fn value_json(schema: &Schema, field: &FieldDef, ty: &FieldType, v: &WireValue, depth: usize) -> Result<Value, String> {
+    match ty {
+        FieldType::Scalar(Scalar::String) => String::from_utf8(len(field, v)?.to_vec()).map(Value::String)
+            .map_err(|_| format!("field {} holds invalid UTF-8", field.name)),
+        FieldType::Scalar(Scalar::Bytes) => Ok(json!(base64::engine::general_purpose::STANDARD.encode(len(field, v)?))),
+        FieldType::Scalar(s) => {
+            if v.wire_type() != wire_type(ty) { return Err(wrong_wire_type(field, v)); }
+            Ok(scalar_json(*s, v.raw().unwrap_or_default()))
+        }
+        FieldType::Enum(e) => {
+            let WireValue::Varint(n) = v else { return Err(wrong_wire_type(field, v)); };
+            if e == "google.protobuf.NullValue" { return Ok(Value::Null); }
+            let n = *n as i32;
+            Ok(schema.enum_def(e).and_then(|d| d.values.iter().find(|(_, number)| *number == n)).map_or(json!(n), |(name, _)| json!(name)))
+        }
+        FieldType::Message(m) => message_json(schema, m, len(field, v)?, depth + 1),
+        FieldType::Map(..) | FieldType::Unresolved(_) => Err(format!("field {} has a type that is not defined in the indexed protos", field.name)),
+    }
+}
+
+/// A numeric or bool scalar from its raw wire bits.
+pub(super) fn scalar_json(s: Scalar, raw: u64) -> Value {
+    match s {
+        Scalar::Double => float_json(f64::from_bits(raw)),
+        // through the shortest f32 text, so 0.1f stays 0.1
+        Scalar::Float => float_json(f32::from_bits(raw as u32).to_string().parse().unwrap_or_default()),
+        Scalar::Int32 => json!(raw as i32),
+        Scalar::Int64 => json!((raw as i64).to_string()),
+        Scalar::Uint32 | Scalar::Fixed32 => json!(raw as u32),
+        Scalar::Uint64 | Scalar::Fixed64 => json!(raw.to_string()),
+        Scalar::Sint32 => json!(unzigzag(raw) as i32),
+        Scalar::Sint64 => json!(unzigzag(raw).to_string()),
+        Scalar::Sfixed32 => json!(raw as u32 as i32),
+        Scalar::Sfixed64 => json!((raw as i64).to_string()),
+        Scalar::Bool => json!(raw != 0),
+        Scalar::String | Scalar::Bytes => Value::Null,
+    }
+}
+
+fn float_json(f: f64) -> Value {
+    if f.is_nan() { json!("NaN") } else if f.is_infinite() { json!(if f > 0.0 { "Infinity" } else { "-Infinity" }) } else { json!(f) }
+}
+
+fn unzigzag(raw: u64) -> i64 { (raw >> 1) as i64 ^ -((raw & 1) as i64) }
+
+fn default_json(ty: &FieldType) -> Value {
+    match ty {
+        FieldType::Scalar(s) => scalar_default(*s),
+        FieldType::Enum(e) if e == "google.protobuf.NullValue" => Value::Null,
+        FieldType::Enum(_) => json!(0),
+        _ => Value::Null,
+    }
+}
+
+fn len<'a>(field: &FieldDef, v: &WireValue<'a>) -> Result<&'a [u8], String> {
+    match v { WireValue::Len(bytes) => Ok(bytes), v => Err(wrong_wire_type(field, v)) }
+}
+
+fn wrong_wire_type(field: &FieldDef, v: &WireValue) -> String {
+    format!("field {} ({}) has wire type {}", field.name, field.number, v.wire_type())
+}
+
+/// `google.protobuf.Any` as `{"@type", ...fields}`, or `{"@type", "value"}` for well-known types
+/// with a JSON form of their own.
+fn any_json(schema: &Schema, bytes: &[u8], depth: usize) -> Result<Value, String> {
+    let (mut type_url, mut value) = (String::new(), &[][..]);
+    for (number, v) in fields(bytes)? {
+        match (number, v) {
+            (1, WireValue::Len(s)) => type_url = String::from_utf8(s.to_vec()).map_err(|_| "Any.type_url holds invalid UTF-8".to_string())?,
+            (2, WireValue::Len(b)) => value = b,
+            _ => {}
+        }
+    }
+    if type_url.is_empty() { return Ok(json!({})); }
+    let name = type_url.rsplit('/').next().unwrap_or_default();
+    let inner = message_json(schema, name, value, depth + 1)?;
+    let mut out = Map::new();
+    out.insert("@type".into(), json!(type_url));
+    match inner {
+        Value::Object(fields) if !has_json_form(name) => out.extend(fields),
+        inner => { out.insert("value".into(), inner); }
+    }
+    Ok(Value::Object(out))
+}
+
+/// Well-known types embedded in an `Any` under `"value"`.
+fn has_json_form(name: &str) -> bool {
+    matches!(name.strip_prefix("google.protobuf."), Some(
+        "Any" | "Timestamp" | "Duration" | "FieldMask" | "Empty" | "Struct" | "Value" | "ListValue" | "DoubleValue" | "FloatValue"
+        | "Int64Value" | "UInt64Value" | "Int32Value" | "UInt32Value" | "BoolValue" | "StringValue" | "BytesValue"
+    ))
+}
+
+/// The JSON form of a well-known type, given its fields decoded as for any other message.
+fn well_known_json(name: &str, generic: Value) -> Value {
+    let Some(short) = name.strip_prefix("google.protobuf.") else { return generic; };
+    let field = |key: &str| generic.get(key).cloned();
+    let int = |key: &str| generic.get(key).and_then(|v| v.as_str().map(str::to_string).or_else(|| v.as_i64().map(|n| n.to_string())))
+        .and_then(|s| s.parse::<i64>().ok()).unwrap_or(0);
+    match short {
+        "Timestamp" => json!(format_timestamp(int("seconds"), int("nanos") as i32)),
+        "Duration" => json!(format_duration(int("seconds"), int("nanos") as i32)),
+        "FieldMask" => json!(field("paths").and_then(|p| p.as_array().cloned()).unwrap_or_default().iter()
+            .filter_map(Value::as_str).map(json_name).collect::<Vec<_>>().join(",")),
+        "Struct" => field("fields").unwrap_or_else(|| json!({})),
+        "ListValue" => field("values").unwrap_or_else(|| json!([])),
+        "Value" => generic.as_object().and_then(|o| o.values().next().cloned()).unwrap_or(Value::Null),
+        "DoubleValue" | "FloatValue" | "Int64Value" | "UInt64Value" | "Int32Value" | "UInt32Value" | "BoolValue" | "StringValue" | "BytesValue" => {
+            field("value").unwrap_or_else(|| wrapper_default(short))
+        }
+        _ => generic,
+    }
+}
+
+fn wrapper_default(short: &str) -> Value {
+    match short {
+        "Int64Value" | "UInt64Value" => json!("0"),
+        "BoolValue" => json!(false),
+        "StringValue" | "BytesValue" => json!(""),
+        _ => json!(0),
+    }
+}
+
+/// RFC 3339 in UTC with 0, 3, 6 or 9 fractional digits, as protobuf prints timestamps.
+fn format_timestamp(seconds: i64, nanos: i32) -> String {
+    let (days, secs) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+    let (y, m, d) = civil_from_days(days);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}Z", y, m, d, secs / 3600, secs / 60 % 60, secs % 60, fraction(nanos.unsigned_abs()))
+}
+
+fn format_duration(seconds: i64, nanos: i32) -> String {
+    let sign = if seconds < 0 || nanos < 0 { "-" } else { "" };
+    format!("{}{}{}s", sign, seconds.unsigned_abs(), fraction(nanos.unsigned_abs()))
+}
+
+fn fraction(nanos: u32) -> String {
+    if nanos == 0 { String::new() }
+    else if nanos.is_multiple_of(1_000_000) { format!(".{:03}", nanos / 1_000_000) }
+    else if nanos.is_multiple_of(1_000) { format!(".{:06}", nanos / 1_000) }
+    else { format!(".{:09}", nanos) }
+}
+
+/// Year, month and day of a day count since 1970-01-01 (proleptic Gregorian).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    (yoe + era * 400 + i64::from(m <= 2), m, d)
+}
+
+fn days_from_civil(y: i64, m: u32, d: u32) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = i64::from((m + 9) % 12);
+    let doy = (153 * mp + 2) / 5 + i64::from(d) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Encodes a canonical JSON `value` as `message`. Fields are written in field-number order and
+/// fields without presence are left out when they hold their default, as protobuf encoders do.
+pub fn from_json(schema: &Schema, message: &str, value: &Value) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    encode_message(schema, message.trim_start_matches('.'), value, &mut out, 0)?;
+    Ok(out)
+}
+
+fn encode_message(schema: &Schema, name: &str, value: &Value, out: &mut Vec<u8>, depth: usize) -> Result<(), String> {
+    if depth > RECURSION_LIMIT { return Err(format!("messages nest deeper than {} levels", RECURSION_LIMIT)); }
+    if name == "google.protobuf.Any" { return encode_any(schema, value, out, depth); }
+    let generic = well_known_fields(name, value)?;
+    let value = generic.as_ref().unwrap_or(value);
+    let def = schema.message(name).ok_or_else(|| format!("{} is not defined in the indexed protos", name))?;
+    let Value::Object(obj) = value else { return Err(format!("{} must be a JSON object", name)); };
+    if let Some(key) = obj.keys().find(|k| def.field(k).is_none()) { return Err(format!("{} has no field {:?}", name, key)); }
+    let mut fields: Vec<&FieldDef> = def.fields.iter().collect();
+    fields.sort_by_key(|f| f.number);
+    for field in fields {
+        let Some(v) = obj.get(&field.json_name).or_else(|| obj.get(&field.name)) else { continue; };
+        // null leaves a field unset, except in google.protobuf.Value where it is NULL_VALUE
+        if v.is_null() && !matches!(&field.ty, FieldType::Message(m) if m == "google.protobuf.Value") && !matches!(&field.ty, FieldType::Enum(e) if e == "google.protobuf.NullValue") { continue; }
+        encode_field(schema, field, v, def.syntax == "proto3", out, depth)?;
+    }
+    Ok(())
+}
+
+fn encode_field(schema: &Schema, field: &FieldDef, v: &Value, proto3: bool, out: &mut Vec<u8>, depth: usize) -> Result<(), String> {
+    let at = |e: String| format!("{}: {}", field.name, e);
+    match (&field.ty, field.label) {
+        (FieldType::Map(key, value), _) => {
+            let Value::Object(entries) = v else { return Err(at("expected an object".into())); };
+            for (k, item) in entries {
+                let mut entry = Vec::new();
+                encode_value(schema, &FieldType::Scalar(*key), 1, &map_key(*key, k).map_err(at)?, &mut entry, depth).map_err(at)?;
+                encode_value(schema, value, 2, item, &mut entry, depth).map_err(at)?;
+                put_len(out, field.number, &entry);
+            }
+        }
+        (ty, Label::Repeated) => {
+            let Value::Array(items) = v else { return Err(at("expected an array".into())); };
+            let packed = field.options.iter().find(|(k, _)| k == "packed").map_or(proto3, |(_, v)| v == "true");
+            if packed && wire_type(ty) != 2 {
+                if items.is_empty() { return Ok(()); }
+                let mut body = Vec::new();
+                for item in items { put_raw(&mut body, &primitive(schema, ty, item).map_err(at)?); }
+                put_len(out, field.number, &body);
+            } else {
+                for item in items { encode_value(schema, ty, field.number, item, out, depth).map_err(at)?; }
+            }
+        }
+        (ty, _) => {
+            if !field.has_presence && field.oneof.is_none() && is_default(schema, ty, v) { return Ok(()); }
+            encode_value(schema, ty, field.number, v, out, depth).map_err(at)?;
+        }
+    }
+    Ok(())
+}
+
+/// A map key from its JSON object key.
+fn map_key(key: Scalar, text: &str) -> Result<Value, String> {
+    match key {
+        Scalar::String => Ok(json!(text)),
+        Scalar::Bool => text.parse::<bool>().map(Value::Bool).map_err(|_| format!("map key {:?} is not a bool", text)),
+        _ => Ok(json!(text)),
+    }
+}
+
+fn is_default(schema: &Schema, ty: &FieldType, v: &Value) -> bool {
+    match (ty, v) {
+        (FieldType::Scalar(Scalar::String | Scalar::Bytes), Value::String(s)) => s.is_empty(),
+        (FieldType::Scalar(Scalar::Bool), Value::Bool(b)) => !b,
+        (FieldType::Scalar(_), Value::Number(n)) => n.as_f64() == Some(0.0) && !n.to_string().starts_with('-'),
+        (FieldType::Scalar(_), Value::String(s)) => s.parse::<i128>() == Ok(0),
+        (FieldType::Enum(_), v) => primitive(schema, ty, v).is_ok_and(|p| p == Primitive::Varint(0)),
+        _ => false,
+    }
+}
+
+fn encode_value(schema: &Schema, ty: &FieldType, number: i32, v: &Value, out: &mut Vec<u8>, depth: usize) -> Result<(), String> {
+    match ty {
+        FieldType::Message(m) => {
+            let mut body = Vec::new();
+            encode_message(schema, m, v, &mut body, depth + 1)?;
+            put_len(out, number, &body);
+        }
+        ty => {
+            let p = primitive(schema, ty, v)?;
+            put_tag(out, number, p.wire_type());
+            put_raw(out, &p);
+        }
+    }
+    Ok(())
+}
+
+/// A non-message value, ready to be written.
+#[derive(Debug, PartialEq)]
+enum Primitive {
+    Varint(u64),
+    Fixed64(u64),
+    Len(Vec<u8>),
+    Fixed32(u32),
+}
+
+impl Primitive {
+    fn wire_type(&self) -> u8 {
+        match self { Self::Varint(_) => 0, Self::Fixed64(_) => 1, Self::Len(_) => 2, Self::Fixed32(_) => 5 }
+    }
+}
+
+fn primitive(schema: &Schema, ty: &FieldType, v: &Value) -> Result<Primitive, String> {
+    match ty {
+        FieldType::Enum(e) if e == "google.protobuf.NullValue" => Ok(Primitive::Varint(0)),
+        FieldType::Enum(e) => {
+            let n = match v {
+                Value::String(name) => schema.enum_def(e).and_then(|d| d.values.iter().find(|(n, _)| n == name)).map(|(_, n)| *n)
+                    .ok_or_else(|| format!("{:?} is not a value of {}", name, e))?,
+                v => i32::try_from(v.as_i64().ok_or_else(|| format!("expected a value of {}, got {}", e, v))?).map_err(|_| format!("{} does not fit an enum", v))?,
+            };
+            Ok(Primitive::Varint(i64::from(n) as u64))
+        }
+        FieldType::Scalar(s) => scalar_primitive(*s, v),
+        _ => Err("type is not defined in the indexed protos".into()),
+    }
+}
+
+fn scalar_primitive(s: Scalar, v: &Value) -> Result<Primitive, String> {
+    match s {
+        Scalar::String => v.as_str().map(|t| Primitive::Len(t.as_bytes().to_vec())).ok_or_else(|| format!("expected a string, got {}", v)),
+        Scalar::Bytes => {
+            let text = v.as_str().ok_or_else(|| format!("expected base64, got {}", v))?;
+            decode_base64(text).map(Primitive::Len).map_err(|_| format!("{:?} is not base64", text))
+        }
+        Scalar::Bool => v.as_bool().map(|b| Primitive::Varint(u64::from(b))).ok_or_else(|| format!("expected true or false, got {}", v)),
+        Scalar::Double | Scalar::Float => {
+            let f = match v {
+                Value::Number(n) => n.as_f64().unwrap_or_default(),
+                Value::String(t) => match t.as_str() {
+                    "NaN" => f64::NAN,
+                    "Infinity" => f64::INFINITY,
+                    "-Infinity" => f64::NEG_INFINITY,
+                    t => t.parse().map_err(|_| format!("{:?} is not a number", t))?,
+                },
+                v => return Err(format!("expected a number, got {}", v)),
+            };
+            Ok(if s == Scalar::Float { Primitive::Fixed32((f as f32).to_bits()) } else { Primitive::Fixed64(f.to_bits()) })
+        }
+        _ => {
+            let n: i128 = match v {
+                Value::Number(n) => match (n.as_i64(), n.as_u64(), n.as_f64()) {
+                    (Some(i), _, _) => i128::from(i),
+                    (_, Some(u), _) => i128::from(u),
+                    (_, _, Some(f)) if f.fract() == 0.0 && f.abs() < 1e38 => f as i128,
+                    _ => return Err(format!("{} is not an integer", n)),
+                },
+                Value::String(t) => t.parse().map_err(|_| format!("{:?} is not an integer", t))?,
+                v => return Err(format!("expected an integer, got {}", v)),
+            };
+            let (lo, hi) = int_range(s);
+            if n < lo || n > hi { return Err(format!("{} is out of range for {}", n, s.name())); }
+            Ok(match s {
+                Scalar::Sint32 | Scalar::Sint64 => Primitive::Varint(((n << 1) ^ (n >> 63)) as u64),
+                Scalar::Fixed32 | Scalar::Sfixed32 => Primitive::Fixed32(n as u32),
+                Scalar::Fixed64 | Scalar::Sfixed64 => Primitive::Fixed64(n as u64),
+                // negative int32 and int64 are sign-extended to ten bytes
+                _ => Primitive::Varint(n as i64 as u64),
+            })
+        }
+    }
+}
+
+fn put_varint(out: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        out.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+fn put_tag(out: &mut Vec<u8>, number: i32, wire_type: u8) {
+    put_varint(out, (u64::from(number as u32) << 3) | u64::from(wire_type));
+}
+
+fn put_len(out: &mut Vec<u8>, number: i32, body: &[u8]) {
+    put_tag(out, number, 2);
+    put_varint(out, body.len() as u64);
+    out.extend_from_slice(body);
+}
+
+fn put_raw(out: &mut Vec<u8>, p: &Primitive) {
+    match p {
+        Primitive::Varint(v) => put_varint(out, *v),
+        Primitive::Fixed64(v) => out.extend_from_slice(&v.to_le_bytes()),
+        Primitive::Fixed32(v) => out.extend_from_slice(&v.to_le_bytes()),
+        Primitive::Len(bytes) => {
+            put_varint(out, bytes.len() as u64);
+            out.extend_from_slice(bytes);
+        }
+    }
+}
+
+/// Encodes a field of a wire message being built by hand, e.g. by the text format parser.
+pub(super) fn put_field(schema: &Schema, out: &mut Vec<u8>, ty: &FieldType, number: i32, v: &Value) -> Result<(), String> {
+    let p = primitive(schema, ty, v)?;
+    put_tag(out, number, p.wire_type());
+    put_raw(out, &p);
+    Ok(())
+}
+
+pub(super) fn put_bytes(out: &mut Vec<u8>, number: i32, bytes: &[u8]) { put_len(out, number, bytes); }
+
+fn encode_any(schema: &Schema, value: &Value, out: &mut Vec<u8>, depth: usize) -> Result<(), String> {
+    let Value::Object(obj) = value else { return Err("google.protobuf.Any must be a JSON object".into()); };
+    let Some(type_url) = obj.get("@type").and_then(Value::as_str) else {
+        return if obj.is_empty() { Ok(()) } else { Err("google.protobuf.Any needs \"@type\"".into()) };
+    };
+    let name = type_url.rsplit('/').next().unwrap_or_default();
+    let inner = if has_json_form(name) {
+        obj.get("value").cloned().unwrap_or(Value::Null)
+    } else {
+        Value::Object(obj.iter().filter(|(k, _)| *k != "@type").map(|(k, v)| (k.clone(), v.clone())).collect())
+    };
+    let mut body = Vec::new();
+    encode_message(schema, name, &inner, &mut body, depth + 1)?;
+    put_len(out, 1, type_url.as_bytes());
+    if !body.is_empty() { put_len(out, 2, &body); }
+    Ok(())
+}
+
+/// The fields of a well-known type given in its own JSON form, e.g. a Timestamp string as
+/// `{"seconds", "nanos"}`. `None` for other messages.
+fn well_known_fields(name: &str, value: &Value) -> Result<Option<Value>, String> {
+    let Some(short) = name.strip_prefix("google.protobuf.") else { return Ok(None); };
+    let text = || value.as_str().ok_or_else(|| format!("{} must be a string, got {}", name, value));
+    Ok(Some(match short {
+        "Timestamp" => {
+            let (seconds, nanos) = parse_timestamp(text()?).ok_or_else(|| format!("{:?} is not an RFC 3339 timestamp", value))?;
+            json!({ "seconds": seconds.to_string(), "nanos": nanos })
+        }
+        "Duration" => {
+            let (seconds, nanos) = parse_duration(text()?).ok_or_else(|| format!("{:?} is not a duration such as \"1.5s\"", value))?;
+            json!({ "seconds": seconds.to_string(), "nanos": nanos })
+        }
+        "FieldMask" => {
+            let paths: Vec<String> = text()?.split(',').filter(|p| !p.is_empty()).map(snake_case).collect();
+            json!({ "paths": paths })
+        }
+        "Struct" => json!({ "fields": value }),
+        "ListValue" => json!({ "values": value }),
+        "Value" => match value {
+            Value::Null => json!({ "nullValue": null }),
+            Value::Number(_) => json!({ "numberValue": value }),
+            Value::String(_) => json!({ "stringValue": value }),
+            Value::Bool(_) => json!({ "boolValue": value }),
+            Value::Object(_) => json!({ "structValue": value }),
+            Value::Array(_) => json!({ "listValue": value }),
+        },
+        "DoubleValue" | "FloatValue" | "Int64Value" | "UInt64Value" | "Int32Value" | "UInt32Value" | "BoolValue" | "StringValue" | "BytesValue" => json!({ "value": value }),
+        _ => return Ok(None),
+    }))
+}
+
+fn snake_case(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for c in path.chars() {
+        if c.is_ascii_uppercase() { out.push('_'); out.push(c.to_ascii_lowercase()); } else { out.push(c); }
+    }
+    out
+}
+
+/// Seconds and nanos of an RFC 3339 timestamp with any UTC offset.
+fn parse_timestamp(text: &str) -> Option<(i64, i32)> {
+    let (date, rest) = text.split_once(['T', 't'])?;
+    let mut ymd = date.splitn(3, '-');
+    let (y, m, d): (i64, u32, u32) = (ymd.next()?.parse().ok()?, ymd.next()?.parse().ok()?, ymd.next()?.parse().ok()?);
+    let zone_at = rest.rfind(['Z', 'z', '+', '-'])?;
+    let (time, zone) = rest.split_at(zone_at);
+    let offset = match zone {
+        "Z" | "z" => 0,
+        z => {
+            let (h, min) = z[1..].split_once(':')?;
+            let secs = h.parse::<i64>().ok()? * 3600 + min.parse::<i64>().ok()? * 60;
+            if z.starts_with('-') { -secs } else { secs }
+        }
+    };
+    let (clock, frac) = time.split_once('.').unwrap_or((time, ""));
+    let mut hms = clock.splitn(3, ':');
+    let (h, min, s): (i64, i64, i64) = (hms.next()?.parse().ok()?, hms.next()?.parse().ok()?, hms.next()?.parse().ok()?);
+    if !(1..=12).contains(&m) || !(1..=31).contains(&d) || h > 23 || min > 59 || s > 60 { return None; }
+    let nanos = parse_fraction(frac)?;
+    Some((days_from_civil(y, m, d) * 86_400 + h * 3600 + min * 60 + s - offset, nanos))
+}
+
+fn parse_duration(text: &str) -> Option<(i64, i32)> {
+    let body = text.strip_suffix('s')?;
+    let (negative, body) = match body.strip_prefix('-') { Some(b) => (true, b), None => (false, body) };
+    let (whole, frac) = body.split_once('.').unwrap_or((body, ""));
+    let seconds: i64 = whole.parse().ok()?;
+    let nanos = parse_fraction(frac)?;
+    Some(if negative { (-seconds, -nanos) } else { (seconds, nanos) })
+}
+
+fn parse_fraction(frac: &str) -> Option<i32> {
+    if frac.is_empty() { return Some(0); }
+    if frac.len() > 9 || !frac.bytes().all(|b| b.is_ascii_digit()) { return None; }
+    format!("{:0<9}", frac).parse().ok()
+}
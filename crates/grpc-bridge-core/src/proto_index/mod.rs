@@ -0,0 +1,277 @@
+pub mod scanner;
+pub mod parser;
+pub mod schema;
+
+use crate::error::{BridgeError, BridgeResult};
+use crate::payload::complete::{self, Completions};
+use crate::payload::sample::{self, SamplePayload};
+use crate::payload::raw::{self, RawDecoding};
+use crate::payload::{json_schema, skeleton, validate::{self, PayloadIssue}, wire};
+use crate::storage::{self, ScanResult, Storage};
+use schema::Schema;
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tracing::info;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProtoRoot {
+    pub id: String,
+    pub path: String,
+    /// Unix seconds of the last completed scan.
+    pub last_scan: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParsedService {
+    pub fq_service: String,
+    pub file: String,
+    pub methods: Vec<ParsedMethod>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParsedMethod {
+    pub name: String,
+    pub input_type: String,
+    pub output_type: String,
+    pub streaming: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceMeta { pub fq_service: String, pub file: String, pub methods: Vec<MethodMeta> }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MethodMeta { pub name: String, pub input_type: String, pub output_type: String, pub streaming: bool }
+
+/// Registered proto roots and what their last scan found. Roots and scans are persisted in
+/// [`Storage`]; lookups are served from memory.
+pub struct ProtoIndex {
+    storage: Arc<Storage>,
+    roots: Mutex<HashMap<String, ProtoRoot>>,
+    services_by_root: Mutex<HashMap<String, Vec<ParsedService>>>,
+    files_by_root: Mutex<HashMap<String, Vec<String>>>,
+    schemas: Mutex<HashMap<String, Arc<Schema>>>, // parsed on first use, dropped by a rescan
+}
+
+impl ProtoIndex {
+    /// Restores the roots and scans persisted in earlier sessions.
+    pub fn load(storage: Arc<Storage>) -> BridgeResult<Self> {
+        let mut roots = HashMap::new();
+        let mut services_by_root = HashMap::new();
+        let mut files_by_root = HashMap::new();
+        for root in storage.load_roots()? {
+            if let Some(scan) = storage.load_scan(&root.id)? {
+                services_by_root.insert(root.id.clone(), scan.services);
+                files_by_root.insert(root.id.clone(), scan.files);
+            }
+            roots.insert(root.id.clone(), root);
+        }
+        info!(roots = roots.len(), "restored proto roots from storage");
+        Ok(Self {
+            storage,
+            roots: Mutex::new(roots),
+            services_by_root: Mutex::new(services_by_root),
+            files_by_root: Mutex::new(files_by_root),
+            schemas: Default::default(),
+        })
+    }
+
+    pub fn register_root(&self, path: &str) -> BridgeResult<String> {
+        let id = uuid::Uuid::new_v4().to_string();
+        let root = ProtoRoot { id: id.clone(), path: path.to_string(), last_scan: None };
+        self.storage.upsert_root(&root)?;
+        self.roots.lock().unwrap().insert(id.clone(), root);
+        Ok(id)
+    }
+
+    pub fn roots(&self) -> Vec<ProtoRoot> {
+        self.roots.lock().unwrap().values().cloned().collect()
+    }
+
+    pub fn root_path(&self, root_id: &str) -> Option<String> {
+        self.roots.lock().unwrap().get(root_id).map(|r| r.path.clone())
+    }
+
+    pub fn remove_root(&self, root_id: &str) -> BridgeResult<()> {
+        // Remove from all related data structures
+        self.storage.delete_root(root_id)?;
+        self.roots.lock().unwrap().remove(root_id);
+        self.services_by_root.lock().unwrap().remove(root_id);
+        self.files_by_root.lock().unwrap().remove(root_id);
+        self.schemas.lock().unwrap().remove(root_id);
+        info!(root_id = %root_id, "removed proto root and associated data");
+        Ok(())
+    }
+
+    /// Finds and parses every `.proto` file below a root and persists the result. File paths
+    /// are relative to the root.
+    pub async fn scan_root(&self, root_id: &str) -> BridgeResult<ScanResult> {
+        let root_path = self.root_path(root_id).ok_or_else(|| BridgeError::RootNotFound(root_id.to_string()))?;
+        let scan = tokio::task::spawn_blocking(move || scan_dir(Path::new(&root_path))).await
+            .map_err(|e| BridgeError::InvalidArgument(format!("scan aborted: {}", e)))?;
+        let scanned_at = storage::now_unix();
+        self.storage.save_scan(root_id, &scan, scanned_at)?;
+        if let Some(root) = self.roots.lock().unwrap().get_mut(root_id) { root.last_scan = Some(scanned_at); }
+        self.services_by_root.lock().unwrap().insert(root_id.to_string(), scan.services.clone());
+        self.files_by_root.lock().unwrap().insert(root_id.to_string(), scan.files.clone());
+        self.schemas.lock().unwrap().remove(root_id);
+        Ok(scan)
+    }
+
+    pub fn files(&self, root_id: &str) -> Vec<String> {
+        self.files_by_root.lock().unwrap().get(root_id).cloned().unwrap_or_default()
+    }
+
+    /// Services of one root, or of every registered root.
+    pub fn services(&self, root_id: Option<&str>) -> Vec<ServiceMeta> {
+        let map = self.services_by_root.lock().unwrap();
+        let roots = self.roots.lock().unwrap();
+        map.iter()
+            .filter(|(rid, _)| (root_id.is_none() || root_id == Some(rid.as_str())) && roots.contains_key(*rid))
+            .flat_map(|(_, services)| services)
+            .map(|ps| ServiceMeta {
+                fq_service: ps.fq_service.clone(),
+                file: ps.file.clone(),
+                methods: ps.methods.iter().map(|m| MethodMeta { name: m.name.clone(), input_type: m.input_type.clone(), output_type: m.output_type.clone(), streaming: m.streaming }).collect(),
+            })
+            .collect()
+    }
+
+    /// Message and enum definitions of a root's scanned files.
+    pub fn schema(&self, root_id: &str) -> BridgeResult<Arc<Schema>> {
+        if let Some(schema) = self.schemas.lock().unwrap().get(root_id) { return Ok(schema.clone()); }
+        let root_path = self.root_path(root_id).ok_or_else(|| BridgeError::RootNotFound(root_id.to_string()))?;
+        let schema = Arc::new(Schema::load(Path::new(&root_path), &self.files(root_id)));
+        self.schemas.lock().unwrap().insert(root_id.to_string(), schema.clone());
+        Ok(schema)
+    }
+
+    /// The schema of a scanned root that defines the message `fq_message`.
+    pub fn message_schema(&self, fq_message: &str) -> BridgeResult<Arc<Schema>> {
+        for schema in self.scanned_schemas()? {
+            if schema.message(fq_message).is_some() { return Ok(schema); }
+        }
+        Err(BridgeError::MessageNotFound(fq_message.to_string()))
+    }
+
+    /// Schemas of every root with scanned files, by root id.
+    fn scanned_schemas(&self) -> BridgeResult<Vec<Arc<Schema>>> {
+        let mut root_ids: Vec<String> = self.files_by_root.lock().unwrap().iter().filter(|(_, files)| !files.is_empty()).map(|(id, _)| id.clone()).collect();
+        root_ids.sort();
+        root_ids.iter().map(|id| self.schema(id)).collect()
+    }
+
+    /// The schema of the root defining `fq_service` and the full name of the method's input message.
+    /// `root_id` is looked at first; when several other roots define the method, the one with the
+    /// smallest id is used.
+    pub fn method_input(&self, root_id: Option<&str>, fq_service: &str, method: &str) -> BridgeResult<(Arc<Schema>, String)> {
+        self.method_message(root_id, fq_service, method, false)
+    }
+
+    /// Like [`Self::method_input`], for the method's output message.
+    pub fn method_output(&self, root_id: Option<&str>, fq_service: &str, method: &str) -> BridgeResult<(Arc<Schema>, String)> {
+        self.method_message(root_id, fq_service, method, true)
+    }
+
+    fn method_message(&self, preferred: Option<&str>, fq_service: &str, method: &str, output: bool) -> BridgeResult<(Arc<Schema>, String)> {
+        let (root_id, type_name) = {
+            let map = self.services_by_root.lock().unwrap();
+            let mut root_ids: Vec<&String> = map.keys().collect();
+            root_ids.sort_by_key(|id| (Some(id.as_str()) != preferred, id.as_str()));
+            let mut service_known = false;
+            let found = root_ids.into_iter().find_map(|root_id| {
+                map[root_id].iter().filter(|s| s.fq_service == fq_service).find_map(|svc| {
+                    service_known = true;
+                    svc.methods.iter().find(|m| m.name == method)
+                        .map(|m| (root_id.clone(), if output { m.output_type.clone() } else { m.input_type.clone() }))
+                })
+            });
+            match found {
+                Some(found) => found,
+                None if !service_known => return Err(BridgeError::ServiceNotFound(fq_service.to_string())),
+                None => return Err(BridgeError::MethodNotFound { service: fq_service.to_string(), method: method.to_string() }),
+            }
+        };
+        let schema = self.schema(&root_id)?;
+        let package = fq_service.rsplit_once('.').map(|(p, _)| p).unwrap_or("");
+        let message = schema.resolve(package, &type_name)
+            .filter(|name| schema.message(name).is_some())
+            .ok_or(BridgeError::MessageNotFound(type_name))?;
+        Ok((schema, message))
+    }
+
+    /// A request payload for the method with every field at its default value, nested messages
+    /// expanded `max_depth` levels deep (default [`skeleton::DEFAULT_DEPTH`]).
+    pub fn method_skeleton(&self, root_id: Option<&str>, fq_service: &str, method: &str, max_depth: Option<usize>) -> BridgeResult<String> {
+        let (schema, message) = self.method_input(root_id, fq_service, method)?;
+        let value = skeleton::skeleton(&schema, &message, max_depth.unwrap_or(skeleton::DEFAULT_DEPTH));
+        Ok(serde_json::to_string_pretty(&value).unwrap_or_default())
+    }
+
+    /// A random payload for the method's input message that satisfies its field constraints.
+    /// Without a seed a random one is picked; it is returned so the payload can be reproduced.
+    pub fn sample_payload(&self, root_id: Option<&str>, fq_service: &str, method: &str, seed: Option<u64>) -> BridgeResult<SamplePayload> {
+        let (schema, message) = self.method_input(root_id, fq_service, method)?;
+        let seed = seed.unwrap_or_else(rand::random);
+        let value = sample::sample(&schema, &message, seed, skeleton::DEFAULT_DEPTH);
+        Ok(SamplePayload { seed, payload: serde_json::to_string_pretty(&value).unwrap_or_default() })
+    }
+
+    /// Checks a JSON payload against the method's input message; an empty list means it is valid.
+    pub fn validate_payload(&self, root_id: Option<&str>, fq_service: &str, method: &str, payload: &str) -> BridgeResult<Vec<PayloadIssue>> {
+        let (schema, message) = self.method_input(root_id, fq_service, method)?;
+        Ok(validate::validate_str(&schema, &message, payload))
+    }
+
+    /// A draft 2020-12 JSON Schema for a message in the proto3 JSON mapping.
+    pub fn json_schema(&self, fq_message: &str) -> BridgeResult<serde_json::Value> {
+        let schema = self.message_schema(fq_message)?;
+        Ok(json_schema::json_schema(&schema, fq_message))
+    }
+
+    /// Decodes protobuf bytes without a schema, like `protoc --decode_raw`, and lists up to
+    /// [`raw::MAX_CANDIDATES`] indexed message types they also decode as. With `fq_message` the
+    /// bytes are decoded as that type too.
+    pub fn decode_raw(&self, bytes: &[u8], fq_message: Option<&str>) -> BridgeResult<RawDecoding> {
+        let invalid = |reason: String| BridgeError::PayloadFormat { format: "protobuf".into(), reason };
+        let fields = raw::decode_raw(bytes).map_err(invalid)?;
+        let mut scored = Vec::new();
+        for schema in self.scanned_schemas()? { scored.extend(raw::candidates(&schema, bytes)); }
+        scored.sort();
+        let mut candidates: Vec<String> = Vec::new();
+        for (_, name) in scored {
+            if !candidates.contains(&name) { candidates.push(name); }
+        }
+        candidates.truncate(raw::MAX_CANDIDATES);
+        let decoded = match fq_message {
+            Some(message) => Some(wire::to_json(&*self.message_schema(message)?, message, bytes)
+                .map_err(|e| invalid(format!("not a {}: {}", message, e)))?),
+            None => None,
+        };
+        Ok(RawDecoding { fields, candidates, message: fq_message.map(str::to_string), decoded })
+    }
+
+    /// Suggestions for a partially typed payload at `cursor_offset` (UTF-16 code units).
+    pub fn complete_payload(&self, root_id: Option<&str>, fq_service: &str, method: &str, payload: &str, cursor_offset: usize) -> BridgeResult<Completions> {
+        let (schema, message) = self.method_input(root_id, fq_service, method)?;
+        Ok(complete::complete(&schema, &message, payload, cursor_offset))
+    }
+}
+
+fn scan_dir(root: &Path) -> ScanResult {
+    let files_abs = scanner::find_proto_files(root);
+    info!(?root, count = files_abs.len(), "scan proto root found files");
+    // convert to relative (fallback to absolute if strip fails)
+    let files: Vec<String> = files_abs.iter().map(|p| {
+        p.strip_prefix(root).map(|rp| rp.to_string_lossy().to_string()).unwrap_or_else(|_| p.to_string_lossy().to_string())
+    }).collect();
+    let mut services = Vec::new();
+    for (abs, f_rel) in files_abs.iter().zip(&files) {
+        let mut parsed = parser::parse_file(abs);
+        info!(file = ?abs, "parsed proto file");
+        // service.file is relative for UI consistency
+        for s in &mut parsed { s.file = f_rel.clone(); }
+        services.extend(parsed);
+    }
+    ScanResult { files, services }
+}
@@ -31,7 +31,7 @@ fn parse_services(content: &str) -> Vec<(String, String)> {
         // skip whitespace to first '{'
         let mut k = name_end;
         while k < content.len() && content[k..].chars().next().unwrap().is_whitespace() { k += content[k..].chars().next().unwrap().len_utf8(); }
-        if k >= content.len() || content[k..].chars().next().unwrap() != '{' { i = name_end; continue; }
+        if k >= content.len() || !content[k..].starts_with('{') { i = name_end; continue; }
         k += 1; // move past '{'
         // brace balance
         let mut depth = 1;
@@ -68,7 +68,7 @@ fn parse_methods(body: &str) -> Vec<ParsedMethod> {
         let name = &body[name_start..cursor];
         // skip whitespace
         while cursor < body.len() && body[cursor..].chars().next().unwrap().is_whitespace() { cursor += body[cursor..].chars().next().unwrap().len_utf8(); }
-        if cursor >= body.len() || body[cursor..].chars().next().unwrap() != '(' { i = cursor; continue; }
+        if cursor >= body.len() || !body[cursor..].starts_with('(') { i = cursor; continue; }
         cursor += 1; // past '('
         // input type (optional 'stream ')
         let mut in_stream = false;
@@ -84,7 +84,7 @@ fn parse_methods(body: &str) -> Vec<ParsedMethod> {
         if !body[cursor..].starts_with("returns") { i = cursor; continue; }
         cursor += "returns".len();
         while cursor < body.len() && body[cursor..].chars().next().unwrap().is_whitespace() { cursor += body[cursor..].chars().next().unwrap().len_utf8(); }
-        if cursor >= body.len() || body[cursor..].chars().next().unwrap() != '(' { i = cursor; continue; }
+        if cursor >= body.len() || !body[cursor..].starts_with('(') { i = cursor; continue; }
         cursor += 1; // past '('
         let mut out_stream = false;
         if body[cursor..].starts_with("stream ") { out_stream = true; cursor += "stream ".len(); }
@@ -94,7 +94,7 @@ fn parse_methods(body: &str) -> Vec<ParsedMethod> {
         while cursor < body.len() { let ch = body[cursor..].chars().next().unwrap(); cursor += ch.len_utf8(); if ch == ')' { break; } }
         // after output ')' optional whitespace, then either ';' or '{' block
         while cursor < body.len() && body[cursor..].chars().next().unwrap().is_whitespace() { cursor += body[cursor..].chars().next().unwrap().len_utf8(); }
-        if cursor < body.len() && body[cursor..].chars().next().unwrap() == '{' {
+        if cursor < body.len() && body[cursor..].starts_with('{') {
             // skip block
             let mut depth = 0;
             while cursor < body.len() {
@@ -105,7 +105,7 @@ fn parse_methods(body: &str) -> Vec<ParsedMethod> {
             }
         } else {
             // consume until ';'
-            while cursor < body.len() { let ch = body[cursor..].chars().next().unwrap(); cursor += ch.len_utf8(); if ch == ';' { break; } if ch == '{' { break; } }
+            while cursor < body.len() { let ch = body[cursor..].chars().next().unwrap(); cursor += ch.len_utf8(); if ch == ';' || ch == '{' { break; } }
         }
         methods.push(ParsedMethod { name: name.to_string(), input_type: input_type.to_string(), output_type: output_type.to_string(), streaming: in_stream || out_stream });
         i = cursor;
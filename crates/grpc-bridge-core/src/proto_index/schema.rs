@@ -0,0 +1,520 @@
+//! Message and enum definitions of a proto root, enough to build and check request payloads.
+//! Services are handled by [`super::parser`]; this parser only cares about types.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Definitions of the well-known types, so they resolve without `google/protobuf` in the root.
+const WELL_KNOWN: &str = r#"
+syntax = "proto3";
+package google.protobuf;
+message Timestamp { int64 seconds = 1; int32 nanos = 2; }
+message Duration { int64 seconds = 1; int32 nanos = 2; }
+message Empty {}
+message FieldMask { repeated string paths = 1; }
+message Any { string type_url = 1; bytes value = 2; }
+message Struct { map<string, Value> fields = 1; }
+message Value {
+  oneof kind {
+    NullValue null_value = 1;
+    double number_value = 2;
+    string string_value = 3;
+    bool bool_value = 4;
+    Struct struct_value = 5;
+    ListValue list_value = 6;
+  }
+}
+enum NullValue { NULL_VALUE = 0; }
+message ListValue { repeated Value values = 1; }
+message DoubleValue { double value = 1; }
+message FloatValue { float value = 1; }
+message Int64Value { int64 value = 1; }
+message UInt64Value { uint64 value = 1; }
+message Int32Value { int32 value = 1; }
+message UInt32Value { uint32 value = 1; }
+message BoolValue { bool value = 1; }
+message StringValue { string value = 1; }
+message BytesValue { bytes value = 1; }
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scalar { Double, Float, Int32, Int64, Uint32, Uint64, Sint32, Sint64, Fixed32, Fixed64, Sfixed32, Sfixed64, Bool, String, Bytes }
+
+impl Scalar {
+    pub fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "double" => Self::Double,
+            "float" => Self::Float,
+            "int32" => Self::Int32,
+            "int64" => Self::Int64,
+            "uint32" => Self::Uint32,
+            "uint64" => Self::Uint64,
+            "sint32" => Self::Sint32,
+            "sint64" => Self::Sint64,
+            "fixed32" => Self::Fixed32,
+            "fixed64" => Self::Fixed64,
+            "sfixed32" => Self::Sfixed32,
+            "sfixed64" => Self::Sfixed64,
+            "bool" => Self::Bool,
+            "string" => Self::String,
+            "bytes" => Self::Bytes,
+            _ => return None,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Double => "double",
+            Self::Float => "float",
+            Self::Int32 => "int32",
+            Self::Int64 => "int64",
+            Self::Uint32 => "uint32",
+            Self::Uint64 => "uint64",
+            Self::Sint32 => "sint32",
+            Self::Sint64 => "sint64",
+            Self::Fixed32 => "fixed32",
+            Self::Fixed64 => "fixed64",
+            Self::Sfixed32 => "sfixed32",
+            Self::Sfixed64 => "sfixed64",
+            Self::Bool => "bool",
+            Self::String => "string",
+            Self::Bytes => "bytes",
+        }
+    }
+
+    /// 64-bit integers are strings in canonical JSON.
+    pub fn is_64bit(self) -> bool {
+        matches!(self, Self::Int64 | Self::Uint64 | Self::Sint64 | Self::Fixed64 | Self::Sfixed64)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldType {
+    Scalar(Scalar),
+    /// Fully qualified message name.
+    Message(String),
+    /// Fully qualified enum name.
+    Enum(String),
+    Map(Scalar, Box<FieldType>),
+    /// A type name that matches no definition in the root (usually an import from elsewhere).
+    Unresolved(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Label { Optional, Required, Repeated }
+
+#[derive(Debug, Clone)]
+pub struct FieldDef {
+    pub name: String,
+    /// Name in canonical JSON: lowerCamelCase unless set with `json_name`.
+    pub json_name: String,
+    pub number: i32,
+    pub label: Label,
+    pub ty: FieldType,
+    /// Index into [`MessageDef::oneofs`].
+    pub oneof: Option<usize>,
+    /// Whether an unset field differs from one set to its default: `optional`, proto2 and
+    /// oneof fields, and singular message fields once their type is resolved.
+    pub has_presence: bool,
+    /// Leading comment of the field, or its trailing comment when there is none.
+    pub doc: Option<String>,
+    /// Field options as written, e.g. `("json_name", "\"id\"")` or `("(validate.rules).string.min_len", "1")`.
+    pub options: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MessageDef {
+    pub full_name: String,
+    pub fields: Vec<FieldDef>,
+    pub oneofs: Vec<String>,
+    /// `proto2` or `proto3`.
+    pub syntax: String,
+}
+
+impl MessageDef {
+    /// Looks a field up by its proto or JSON name, as grpcurl accepts both.
+    pub fn field(&self, name: &str) -> Option<&FieldDef> {
+        self.fields.iter().find(|f| f.json_name == name || f.name == name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EnumDef {
+    pub full_name: String,
+    pub values: Vec<(String, i32)>,
+}
+
+/// Every message and enum defined in a set of files, with field types resolved.
+#[derive(Debug, Default)]
+pub struct Schema {
+    messages: HashMap<String, MessageDef>,
+    enums: HashMap<String, EnumDef>,
+}
+
+impl Schema {
+    /// Parses `files` (relative to `root`); unreadable files are skipped.
+    pub fn load(root: &Path, files: &[String]) -> Self {
+        let sources: Vec<String> = files.iter().filter_map(|f| fs::read_to_string(root.join(f)).ok()).collect();
+        Self::parse(sources.iter().map(String::as_str))
+    }
+
+    /// Builds a schema from proto sources. The well-known types are always included.
+    pub fn parse<'a>(sources: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut schema = Schema::default();
+        for source in std::iter::once(WELL_KNOWN).chain(sources) {
+            let (tokens, comments) = tokenize(source);
+            let mut parser = Parser { tokens: &tokens, comments: &comments, pos: 0, schema: &mut schema, syntax: "proto2".into() };
+            parser.file();
+        }
+        schema.resolve_fields();
+        schema
+    }
+
+    pub fn message(&self, full_name: &str) -> Option<&MessageDef> { self.messages.get(full_name.trim_start_matches('.')) }
+
+    /// Every message definition, in no particular order.
+    pub fn messages(&self) -> impl Iterator<Item = &MessageDef> { self.messages.values() }
+
+    pub fn enum_def(&self, full_name: &str) -> Option<&EnumDef> { self.enums.get(full_name.trim_start_matches('.')) }
+
+    /// Resolves a type reference the way protoc does: relative to `scope` (a package or message
+    /// name), then to each enclosing scope. A leading `.` makes the name absolute.
+    pub fn resolve(&self, scope: &str, name: &str) -> Option<String> {
+        if let Some(abs) = name.strip_prefix('.') {
+            return self.is_type(abs).then(|| abs.to_string());
+        }
+        let mut scope = scope;
+        loop {
+            let candidate = if scope.is_empty() { name.to_string() } else { format!("{}.{}", scope, name) };
+            if self.is_type(&candidate) { return Some(candidate); }
+            if scope.is_empty() { return None; }
+            scope = scope.rsplit_once('.').map(|(parent, _)| parent).unwrap_or("");
+        }
+    }
+
+    fn is_type(&self, name: &str) -> bool { self.messages.contains_key(name) || self.enums.contains_key(name) }
+
+    fn resolve_type(&self, scope: &str, ty: &FieldType) -> FieldType {
+        match ty {
+            FieldType::Unresolved(name) => match self.resolve(scope, name) {
+                Some(full) if self.enums.contains_key(&full) => FieldType::Enum(full),
+                Some(full) => FieldType::Message(full),
+                None => ty.clone(),
+            },
+            FieldType::Map(key, value) => FieldType::Map(*key, Box::new(self.resolve_type(scope, value))),
+            _ => ty.clone(),
+        }
+    }
+
+    fn resolve_fields(&mut self) {
+        let resolved: Vec<(String, Vec<FieldType>)> = self.messages.values()
+            .map(|m| (m.full_name.clone(), m.fields.iter().map(|f| self.resolve_type(&m.full_name, &f.ty)).collect()))
+            .collect();
+        for (name, types) in resolved {
+            let message = self.messages.get_mut(&name).unwrap();
+            for (field, ty) in message.fields.iter_mut().zip(types) {
+                // a singular message field is present when set, even to an empty message
+                if field.label != Label::Repeated && matches!(ty, FieldType::Message(_)) { field.has_presence = true; }
+                field.ty = ty;
+            }
+        }
+    }
+}
+
+/// `foo_bar_baz` → `fooBarBaz`, as protoc derives `json_name`.
+pub fn json_name(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut upper = false;
+    for c in name.chars() {
+        if c == '_' { upper = true; }
+        else if upper { out.extend(c.to_uppercase()); upper = false; }
+        else { out.push(c); }
+    }
+    out
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok { Word(String), Str(String), Sym(char) }
+
+/// Comments attached to tokens by index, the way protoc attaches them to declarations: a
+/// comment block directly above a token leads it, one on the same line after a token trails it.
+#[derive(Debug, Default)]
+struct Comments {
+    leading: HashMap<usize, String>,
+    trailing: HashMap<usize, String>,
+}
+
+/// Splits proto source into words (identifiers, numbers, dotted names), string literals and
+/// single-character symbols, collecting comments separately.
+fn tokenize(src: &str) -> (Vec<Tok>, Comments) {
+    let mut out = Vec::new();
+    let mut comments = Comments::default();
+    let mut pending: Vec<String> = Vec::new();
+    // newlines since the last token and since the last comment
+    let (mut lines_after_token, mut lines_after_comment) = (0, 0);
+    let mut chars = src.chars().peekable();
+    while let Some(c) = chars.next() {
+        let comment = match c {
+            '/' if chars.peek() == Some(&'/') => {
+                let text: String = chars.by_ref().take_while(|c| *c != '\n').collect();
+                Some((text.trim_start_matches('/').trim().to_string(), 1))
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let (mut text, mut prev) = (String::new(), ' ');
+                for c in chars.by_ref() { if prev == '*' && c == '/' { text.pop(); break; } text.push(c); prev = c; }
+                let lines: Vec<&str> = text.lines().map(|l| l.trim().trim_start_matches('*').trim()).filter(|l| !l.is_empty()).collect();
+                Some((lines.join("\n"), 0))
+            }
+            _ => None,
+        };
+        if let Some((text, newlines)) = comment {
+            if lines_after_token == 0 && pending.is_empty() && !out.is_empty() {
+                comments.trailing.insert(out.len() - 1, text);
+            } else if !text.is_empty() {
+                pending.push(text);
+            }
+            lines_after_token += newlines;
+            lines_after_comment = newlines;
+            continue;
+        }
+        if c == '\n' {
+            lines_after_token += 1;
+            lines_after_comment += 1;
+            // a blank line detaches the comments above it
+            if lines_after_comment >= 2 { pending.clear(); }
+        }
+        if !c.is_whitespace() {
+            if !pending.is_empty() { comments.leading.insert(out.len(), pending.join("\n")); pending.clear(); }
+            lines_after_token = 0;
+        }
+        match c {
+            c if c.is_whitespace() => {}
+            '"' | '\'' => {
+                let mut s = String::new();
+                while let Some(c2) = chars.next() {
+                    match c2 {
+                        '\\' => { if let Some(e) = chars.next() { s.push(match e { 'n' => '\n', 't' => '\t', 'r' => '\r', e => e }); } }
+                        c2 if c2 == c => break,
+                        c2 => s.push(c2),
+                    }
+                }
+                // adjacent literals concatenate
+                if let Some(Tok::Str(prev)) = out.last_mut() { prev.push_str(&s); } else { out.push(Tok::Str(s)); }
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '.' => {
+                let mut w = String::from(c);
+                while let Some(&n) = chars.peek() {
+                    if n.is_alphanumeric() || n == '_' || n == '.' { w.push(n); chars.next(); } else { break; }
+                }
+                out.push(Tok::Word(w));
+            }
+            c => out.push(Tok::Sym(c)),
+        }
+    }
+    (out, comments)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Tok],
+    comments: &'a Comments,
+    pos: usize,
+    schema: &'a mut Schema,
+    syntax: String,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Tok> { self.tokens.get(self.pos) }
+
+    fn next(&mut self) -> Option<Tok> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn word(&mut self) -> Option<String> {
+        match self.next()? { Tok::Word(w) => Some(w), _ => None }
+    }
+
+    fn eat(&mut self, sym: char) -> bool {
+        if self.peek() == Some(&Tok::Sym(sym)) { self.pos += 1; true } else { false }
+    }
+
+    /// Skips to the end of the current statement: a `;` or a balanced `{ ... }` block.
+    fn skip_statement(&mut self) {
+        let mut depth = 0;
+        while let Some(t) = self.next() {
+            match t {
+                Tok::Sym('{') => depth += 1,
+                Tok::Sym('}') => { depth -= 1; if depth <= 0 { return; } }
+                Tok::Sym(';') if depth == 0 => return,
+                _ => {}
+            }
+        }
+    }
+
+    fn file(&mut self) {
+        let mut package = String::new();
+        while let Some(t) = self.peek().cloned() {
+            match t {
+                Tok::Word(w) if w == "syntax" || w == "edition" => {
+                    self.pos += 1;
+                    self.eat('=');
+                    if let Some(Tok::Str(s)) = self.next() { self.syntax = s; }
+                    self.eat(';');
+                }
+                Tok::Word(w) if w == "package" => {
+                    self.pos += 1;
+                    package = self.word().unwrap_or_default();
+                    self.eat(';');
+                }
+                Tok::Word(w) if w == "message" => { self.pos += 1; self.message(&package); }
+                Tok::Word(w) if w == "enum" => { self.pos += 1; self.enumeration(&package); }
+                _ => self.skip_statement(),
+            }
+        }
+    }
+
+    fn message(&mut self, scope: &str) {
+        let Some(name) = self.word() else { return self.skip_statement(); };
+        let full_name = qualify(scope, &name);
+        if !self.eat('{') { return self.skip_statement(); }
+        let mut message = MessageDef { full_name: full_name.clone(), fields: Vec::new(), oneofs: Vec::new(), syntax: self.syntax.clone() };
+        self.message_body(&mut message, None);
+        self.schema.messages.insert(full_name, message);
+    }
+
+    /// Parses fields up to the closing `}`; `oneof` is set inside a `oneof` block.
+    fn message_body(&mut self, message: &mut MessageDef, oneof: Option<usize>) {
+        while let Some(t) = self.peek().cloned() {
+            match t {
+                Tok::Sym('}') => { self.pos += 1; return; }
+                Tok::Sym(';') => { self.pos += 1; }
+                Tok::Word(w) => match w.as_str() {
+                    "message" => { self.pos += 1; let scope = message.full_name.clone(); self.message(&scope); }
+                    "enum" => { self.pos += 1; let scope = message.full_name.clone(); self.enumeration(&scope); }
+                    "oneof" => {
+                        self.pos += 1;
+                        let name = self.word().unwrap_or_default();
+                        if !self.eat('{') { self.skip_statement(); continue; }
+                        message.oneofs.push(name);
+                        let index = message.oneofs.len() - 1;
+                        self.message_body(message, Some(index));
+                    }
+                    "option" | "reserved" | "extensions" | "extend" | "group" => self.skip_statement(),
+                    _ => match self.field(oneof) {
+                        Some(field) => message.fields.push(field),
+                        None => self.skip_statement(),
+                    },
+                },
+                _ => self.skip_statement(),
+            }
+        }
+    }
+
+    fn field(&mut self, oneof: Option<usize>) -> Option<FieldDef> {
+        let start = self.pos;
+        let mut label = Label::Optional;
+        let mut has_presence = oneof.is_some() || self.syntax != "proto3";
+        let mut type_name = self.word()?;
+        match type_name.as_str() {
+            "repeated" => { label = Label::Repeated; type_name = self.word()?; }
+            "required" => { label = Label::Required; type_name = self.word()?; }
+            "optional" => { has_presence = true; type_name = self.word()?; }
+            _ => {}
+        }
+        if type_name == "group" { return None; }
+        let ty = if type_name == "map" && self.eat('<') {
+            let key = Scalar::parse(&self.word()?)?;
+            self.eat(',');
+            let value = self.word()?;
+            if !self.eat('>') { return None; }
+            label = Label::Repeated;
+            FieldType::Map(key, Box::new(type_of(&value)))
+        } else {
+            type_of(&type_name)
+        };
+        let name = self.word()?;
+        if !self.eat('=') { return None; }
+        let number = self.word()?.parse().ok()?;
+        let options = if self.eat('[') { self.options() } else { Vec::new() };
+        self.eat(';');
+        let doc = self.comments.leading.get(&start).or_else(|| self.comments.trailing.get(&(self.pos - 1))).cloned();
+        let json = options.iter().find(|(k, _)| k == "json_name").map(|(_, v)| v.trim_matches('"').to_string());
+        Some(FieldDef { json_name: json.unwrap_or_else(|| json_name(&name)), name, number, label, ty, oneof, has_presence: has_presence && label != Label::Repeated, doc, options })
+    }
+
+    /// Reads `name = value, ...` up to the closing `]`. Values are kept as written; aggregate
+    /// values (`{ min_len: 1 }`) are kept with their braces.
+    fn options(&mut self) -> Vec<(String, String)> {
+        let mut out = Vec::new();
+        let (mut name, mut value) = (String::new(), String::new());
+        let (mut in_value, mut depth) = (false, 0);
+        while let Some(t) = self.next() {
+            match &t {
+                Tok::Sym(']') if depth == 0 => break,
+                Tok::Sym(',') if depth == 0 => {
+                    out.push((std::mem::take(&mut name), std::mem::take(&mut value)));
+                    in_value = false;
+                    continue;
+                }
+                Tok::Sym('=') if depth == 0 && !in_value => { in_value = true; continue; }
+                Tok::Sym('{') => depth += 1,
+                Tok::Sym('}') => depth -= 1,
+                _ => {}
+            }
+            let text = match t { Tok::Word(w) => w, Tok::Str(s) => format!("{:?}", s), Tok::Sym(c) => c.to_string() };
+            let target = if in_value { &mut value } else { &mut name };
+            if !target.is_empty() && needs_space(target, &text) { target.push(' '); }
+            target.push_str(&text);
+        }
+        if !name.is_empty() { out.push((name, value)); }
+        out
+    }
+
+    fn enumeration(&mut self, scope: &str) {
+        let Some(name) = self.word() else { return self.skip_statement(); };
+        let full_name = qualify(scope, &name);
+        if !self.eat('{') { return self.skip_statement(); }
+        let mut values = Vec::new();
+        while let Some(t) = self.peek().cloned() {
+            match t {
+                Tok::Sym('}') => { self.pos += 1; break; }
+                Tok::Word(w) if w == "option" || w == "reserved" => self.skip_statement(),
+                Tok::Word(w) => {
+                    self.pos += 1;
+                    if !self.eat('=') { self.skip_statement(); continue; }
+                    let negative = self.eat('-');
+                    let number: Option<i32> = self.word().and_then(|n| parse_int(&n));
+                    if self.eat('[') { self.options(); }
+                    self.eat(';');
+                    if let Some(n) = number { values.push((w, if negative { -n } else { n })); }
+                }
+                _ => { self.pos += 1; }
+            }
+        }
+        self.schema.enums.insert(full_name.clone(), EnumDef { full_name, values });
+    }
+}
+
+fn qualify(scope: &str, name: &str) -> String {
+    if scope.is_empty() { name.to_string() } else { format!("{}.{}", scope, name) }
+}
+
+fn type_of(name: &str) -> FieldType {
+    Scalar::parse(name).map(FieldType::Scalar).unwrap_or_else(|| FieldType::Unresolved(name.to_string()))
+}
+
+fn parse_int(s: &str) -> Option<i32> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => i32::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Words and literals keep a space between them when re-joined; symbols do not.
+fn needs_space(prev: &str, next: &str) -> bool {
+    let word = |s: &str| s.chars().next().is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '"');
+    prev.chars().last().is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '"') && word(next)
+}
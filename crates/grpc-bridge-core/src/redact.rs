@@ -0,0 +1,301 @@
+use crate::proto_index::schema::json_name;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const REDACTED: &str = "<redacted>";
+
+/// Which metadata keys and JSON fields are treated as credentials.
+///
+/// Header patterns are matched case-insensitively against the metadata key and may use `*`
+/// as a wildcard (`*-token`). JSON paths are dot separated field names where `*` matches any
+/// single field or array element and `**` matches any number of levels (`**.password`). A field
+/// name matches both its .proto and its lowerCamelCase JSON spelling (`client_secret`, `clientSecret`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionConfig {
+    pub header_patterns: Vec<String>,
+    pub json_paths: Vec<String>,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            header_patterns: ["authorization", "proxy-authorization", "cookie", "set-cookie", "x-api-key", "*-token"]
+                .iter().map(|s| s.to_string()).collect(),
+            json_paths: ["**.password", "**.client_secret"].iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+/// Compiled form of a [`RedactionConfig`], applied to log lines, emitted events and history.
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    config: RedactionConfig,
+    /// Segments of each path, field names in their JSON spelling.
+    json_paths: Vec<Vec<String>>,
+    /// Literal values (resolved secrets) replaced wherever they appear.
+    secrets: Vec<String>,
+}
+
+impl Default for Redactor {
+    fn default() -> Self { Self::new(RedactionConfig::default()) }
+}
+
+impl Redactor {
+    pub fn new(config: RedactionConfig) -> Self {
+        let json_paths = config.json_paths.iter()
+            .map(|p| p.trim_start_matches("$.").split('.').filter(|s| !s.is_empty()).map(json_name).collect())
+            .collect();
+        Self { config, json_paths, secrets: Vec::new() }
+    }
+
+    /// Also hides every occurrence of `values`, e.g. secrets substituted into a call.
+    pub fn with_secrets(mut self, values: Vec<String>) -> Self {
+        self.secrets.extend(values.into_iter().filter(|v| !v.is_empty()));
+        // longest first so a secret containing another is replaced whole
+        self.secrets.sort_by_key(|v| std::cmp::Reverse(v.len()));
+        self
+    }
+
+    fn scrub(&self, text: &str) -> String {
+        self.secrets.iter().fold(text.to_string(), |acc, s| acc.replace(s.as_str(), REDACTED))
+    }
+
+    pub fn config(&self) -> &RedactionConfig { &self.config }
+
+    pub fn is_sensitive_header(&self, name: &str) -> bool {
+        let name = name.trim().to_ascii_lowercase();
+        self.config.header_patterns.iter().any(|p| glob_match(&p.to_ascii_lowercase(), &name))
+    }
+
+    /// Redacts the value of a `Key: Value` metadata entry if its key is sensitive.
+    pub fn header(&self, header: &str) -> String {
+        match header.split_once(':') {
+            Some((name, _)) if self.is_sensitive_header(name) => format!("{}: {}", name, REDACTED),
+            _ => self.scrub(header),
+        }
+    }
+
+    pub fn headers(&self, headers: &[String]) -> Vec<String> {
+        headers.iter().map(|h| self.header(h)).collect()
+    }
+
+    /// Redacts sensitive entries of response metadata (headers or trailers).
+    pub fn metadata(&self, entries: &[(String, String)]) -> Vec<(String, String)> {
+        entries.iter()
+            .map(|(k, v)| (k.clone(), if self.is_sensitive_header(k) { REDACTED.to_string() } else { self.scrub(v) }))
+            .collect()
+    }
+
+    /// Redacts the values following `-H` in a grpcurl argument list.
+    pub fn args(&self, args: &[String]) -> Vec<String> {
+        let mut out = Vec::with_capacity(args.len());
+        let mut header_next = false;
+        for a in args {
+            out.push(if header_next { self.header(a) } else { self.scrub(a) });
+            header_next = a == "-H" || a == "-rpc-header" || a == "-reflect-header";
+        }
+        out
+    }
+
+    /// Replaces every sensitive `key: value` line in free text (e.g. grpcurl's verbose output).
+    pub fn text(&self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        for line in text.split_inclusive('\n') {
+            let (body, eol) = match line.strip_suffix('\n') { Some(b) => (b, "\n"), None => (line, "") };
+            out.push_str(&self.header(body));
+            out.push_str(eol);
+        }
+        out
+    }
+
+    pub fn json(&self, value: &mut Value) {
+        for path in &self.json_paths { redact_path(value, path); }
+        if !self.secrets.is_empty() { self.scrub_strings(value); }
+    }
+
+    fn scrub_strings(&self, value: &mut Value) {
+        match value {
+            Value::String(s) => *s = self.scrub(s),
+            _ => for child in children(value) { self.scrub_strings(child); },
+        }
+    }
+
+    /// Redacts a JSON document given as text; text that is not JSON is returned unchanged.
+    pub fn json_str(&self, text: &str) -> String {
+        match serde_json::from_str::<Value>(text) {
+            Ok(mut v) => { self.json(&mut v); serde_json::to_string(&v).unwrap_or_else(|_| text.to_string()) }
+            Err(_) => self.scrub(text),
+        }
+    }
+}
+
+/// Dotted paths (`user.password`, `items.0.token`) of the strings in `value` that were
+/// redacted, in document order.
+pub fn redacted_fields(value: &Value) -> Vec<String> {
+    fn walk(value: &Value, path: &mut Vec<String>, out: &mut Vec<String>) {
+        match value {
+            Value::String(s) if s.contains(REDACTED) => out.push(path.join(".")),
+            Value::Object(map) => for (k, v) in map { path.push(k.clone()); walk(v, path, out); path.pop(); },
+            Value::Array(items) => for (i, v) in items.iter().enumerate() { path.push(i.to_string()); walk(v, path, out); path.pop(); },
+            _ => {}
+        }
+    }
+    let mut out = Vec::new();
+    walk(value, &mut Vec::new(), &mut out);
+    out
+}
+
+/// Replaces the value at a dotted path as returned by [`redacted_fields`]; returns whether it exists.
+pub fn set_field(value: &mut Value, path: &str, new: Value) -> bool {
+    let mut at = value;
+    for key in path.split('.').filter(|k| !k.is_empty()) {
+        let next = match at {
+            Value::Object(map) => map.get_mut(key),
+            Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get_mut(i)),
+            _ => None,
+        };
+        let Some(next) = next else { return false; };
+        at = next;
+    }
+    *at = new;
+    true
+}
+
+fn redact_path(value: &mut Value, path: &[String]) {
+    let Some((head, rest)) = path.split_first() else {
+        *value = Value::String(REDACTED.into());
+        return;
+    };
+    if head == "**" {
+        // zero levels: match the rest here; one or more: descend and keep `**`
+        redact_path(value, rest);
+        for child in children(value) { redact_path(child, path); }
+        return;
+    }
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map.iter_mut() {
+                if head == "*" || json_name(k) == *head { redact_path(v, rest); }
+            }
+        }
+        Value::Array(items) => {
+            for (i, v) in items.iter_mut().enumerate() {
+                if head == "*" || *head == i.to_string() { redact_path(v, rest); }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn children(value: &mut Value) -> Vec<&mut Value> {
+    match value {
+        Value::Object(map) => map.values_mut().collect(),
+        Value::Array(items) => items.iter_mut().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Minimal glob: `*` matches any run of characters, everything else literally.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 { return pattern == text; }
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !text.starts_with(first) || text.len() < first.len() + last.len() || !text.ends_with(last) { return false; }
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for mid in &parts[1..parts.len() - 1] {
+        match rest.find(mid) { Some(i) => rest = &rest[i + mid.len()..], None => return false }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn redactor(headers: &[&str], paths: &[&str]) -> Redactor {
+        Redactor::new(RedactionConfig {
+            header_patterns: headers.iter().map(|s| s.to_string()).collect(),
+            json_paths: paths.iter().map(|s| s.to_string()).collect(),
+        })
+    }
+
+    #[test]
+    fn header_patterns_are_case_insensitive_globs() {
+        let r = Redactor::default();
+        assert!(r.is_sensitive_header("Authorization"));
+        assert!(r.is_sensitive_header(" x-refresh-token "));
+        assert!(!r.is_sensitive_header("x-token-id"));
+        assert!(!r.is_sensitive_header("content-type"));
+        assert_eq!(r.header("X-Auth-Token: abc"), "X-Auth-Token: <redacted>");
+        assert_eq!(r.header("accept: */*"), "accept: */*");
+
+        let r = redactor(&["x-*-key*"], &[]);
+        assert!(r.is_sensitive_header("x-api-key"));
+        assert!(r.is_sensitive_header("x-signing-key-v2"));
+        assert!(!r.is_sensitive_header("x-key"));
+    }
+
+    #[test]
+    fn json_paths_match_fields_wildcards_and_any_depth() {
+        let mut v = json!({
+            "password": "top",
+            "user": { "password": "nested", "name": "ada" },
+            "items": [{ "password": "in-array" }],
+            "client_secret": "s"
+        });
+        Redactor::default().json(&mut v);
+        assert_eq!(v["password"], REDACTED);
+        assert_eq!(v["user"]["password"], REDACTED);
+        assert_eq!(v["items"][0]["password"], REDACTED);
+        assert_eq!(v["client_secret"], REDACTED);
+        assert_eq!(v["user"]["name"], "ada");
+
+        // grpcurl prints lowerCamelCase names
+        let mut v = json!({ "clientSecret": "s", "auth": { "client_secret": "t", "clientId": "id" } });
+        Redactor::default().json(&mut v);
+        assert_eq!(v, json!({ "clientSecret": REDACTED, "auth": { "client_secret": REDACTED, "clientId": "id" } }));
+        let mut v = json!({ "apiKey": "k", "api_key": "l" });
+        redactor(&[], &["apiKey"]).json(&mut v);
+        assert_eq!(v, json!({ "apiKey": REDACTED, "api_key": REDACTED }));
+
+        let mut v = json!({ "a": { "x": 1, "y": 2 }, "list": ["p", "q"], "pin": 7 });
+        redactor(&[], &["$.a.*", "list.1", "other.pin"]).json(&mut v);
+        assert_eq!(v, json!({ "a": { "x": REDACTED, "y": REDACTED }, "list": ["p", REDACTED], "pin": 7 }));
+    }
+
+    #[test]
+    fn secrets_are_scrubbed_longest_first() {
+        let r = redactor(&[], &[]).with_secrets(vec!["abc".into(), "abcdef".into(), String::new()]);
+        assert_eq!(r.text("key=abcdef and abc\nnext: abc"), "key=<redacted> and <redacted>\nnext: <redacted>");
+        let mut v = json!({ "echo": ["token abcdef"], "n": 1 });
+        r.json(&mut v);
+        assert_eq!(v, json!({ "echo": ["token <redacted>"], "n": 1 }));
+        assert_eq!(r.metadata(&[("x-note".into(), "abc".into())]), [("x-note".to_string(), REDACTED.to_string())]);
+    }
+
+    #[test]
+    fn arguments_after_header_flags_are_redacted() {
+        let args: Vec<String> = ["-H", "authorization: Bearer t", "-rpc-header", "x-id-token: t", "-d", "authorization: t"]
+            .iter().map(|s| s.to_string()).collect();
+        assert_eq!(Redactor::default().args(&args), ["-H", "authorization: <redacted>", "-rpc-header", "x-id-token: <redacted>", "-d", "authorization: t"]);
+    }
+
+    #[test]
+    fn json_text_is_parsed_and_other_text_only_scrubbed() {
+        let r = Redactor::default().with_secrets(vec!["hunter2".into()]);
+        assert_eq!(r.json_str(r#"{"password":"x","note":"hunter2!"}"#), r#"{"password":"<redacted>","note":"<redacted>!"}"#);
+        assert_eq!(r.json_str("password: hunter2"), "password: <redacted>");
+    }
+
+    #[test]
+    fn redacted_fields_are_found_and_replaced_by_path() {
+        let mut v = json!({ "a": { "password": REDACTED }, "items": [{ "token": "Bearer <redacted>" }, { "token": "t" }], "n": 1 });
+        assert_eq!(redacted_fields(&v), ["a.password", "items.0.token"]);
+        assert!(set_field(&mut v, "items.0.token", json!("Bearer x")));
+        assert!(!set_field(&mut v, "items.5.token", json!("y")));
+        assert!(!set_field(&mut v, "n.deeper", json!(2)));
+        assert_eq!(redacted_fields(&v), ["a.password"]);
+        assert_eq!(v["items"][0]["token"], "Bearer x");
+    }
+}
@@ -0,0 +1,182 @@
+use crate::assertions::{self, Assertion, AssertionResult};
+use crate::call::CallOutcome;
+use serde::{Deserialize, Serialize};
+
+/// One saved request executed by `run_collection`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepResult {
+    pub request_id: String,
+    pub name: String,
+    /// Folder path inside the collection, `/` separated; empty at the top level.
+    pub folder: String,
+    /// gRPC status name, or the error code when the call could not be made.
+    pub status: String,
+    pub took_ms: u64,
+    pub passed: bool,
+    /// Why the call itself failed (invalid request, template error, non-OK status without a
+    /// status assertion, grpcurl failure).
+    pub error: Option<String>,
+    pub assertions: Vec<AssertionResult>,
+    pub history_id: Option<i64>,
+    /// Variables written by extraction rules, available to the following steps.
+    pub extracted: Vec<String>,
+}
+
+impl StepResult {
+    pub fn errored(request_id: String, name: String, folder: String, status: &str, error: String) -> Self {
+        Self { request_id, name, folder, status: status.into(), took_ms: 0, passed: false, error: Some(error), assertions: Vec::new(), history_id: None, extracted: Vec::new() }
+    }
+
+    /// Evaluates `assertions` against a completed call. A non-OK status fails the step unless
+    /// one of the assertions is about the status.
+    pub fn judge(request_id: String, name: String, folder: String, assertions: &[Assertion], outcome: &CallOutcome) -> Self {
+        let results = assertions::evaluate(assertions, outcome);
+        let error = match outcome.error() {
+            Some(e) if !assertions::has_status_assertion(assertions) => Some(e.to_string()),
+            _ => None,
+        };
+        Self {
+            request_id,
+            name,
+            folder,
+            status: outcome.status.clone(),
+            took_ms: outcome.took_ms as u64,
+            passed: error.is_none() && results.iter().all(|r| r.passed),
+            error,
+            assertions: results,
+            history_id: None,
+            extracted: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub collection_id: String,
+    pub name: String,
+    /// Unix milliseconds.
+    pub started_at: u64,
+    pub took_ms: u64,
+    pub total: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub steps: Vec<StepResult>,
+}
+
+impl RunSummary {
+    pub fn new(collection_id: String, name: String, started_at: u64, took_ms: u64, steps: Vec<StepResult>) -> Self {
+        let passed = steps.iter().filter(|s| s.passed).count();
+        Self { collection_id, name, started_at, took_ms, total: steps.len(), passed, failed: steps.len() - passed, steps }
+    }
+
+    /// JUnit XML as read by most CI systems: one `<testsuite>` per run, one `<testcase>` per
+    /// step, `<error>` when the call failed and `<failure>` when assertions did.
+    pub fn to_junit(&self) -> String {
+        let secs = |ms: u64| format!("{:.3}", ms as f64 / 1000.0);
+        let errors = self.steps.iter().filter(|s| s.error.is_some()).count();
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuites name=\"grpc-bridge\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{}\">\n",
+            self.total, self.failed - errors, errors, secs(self.took_ms)
+        ));
+        xml.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{}\" timestamp=\"{}\">\n",
+            escape(&self.name), self.total, self.failed - errors, errors, secs(self.took_ms),
+            crate::template::rfc3339(self.started_at)
+        ));
+        for step in &self.steps {
+            let classname = if step.folder.is_empty() { self.name.clone() } else { format!("{}.{}", self.name, step.folder.replace('/', ".")) };
+            xml.push_str(&format!("    <testcase name=\"{}\" classname=\"{}\" time=\"{}\"", escape(&step.name), escape(&classname), secs(step.took_ms)));
+            let failed: Vec<&AssertionResult> = step.assertions.iter().filter(|a| !a.passed).collect();
+            if let Some(error) = &step.error {
+                xml.push_str(&format!(">\n      <error message=\"{}\" type=\"{}\">{}</error>\n    </testcase>\n", escape(first_line(error)), escape(&step.status), escape(error)));
+            } else if !failed.is_empty() {
+                let lines: Vec<String> = failed.iter().map(|a| a.message.clone().unwrap_or_default()).collect();
+                xml.push_str(&format!(
+                    ">\n      <failure message=\"{} of {} assertions failed\">{}</failure>\n    </testcase>\n",
+                    failed.len(), step.assertions.len(), escape(&lines.join("\n"))
+                ));
+            } else {
+                xml.push_str(" />\n");
+            }
+        }
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+        xml
+    }
+}
+
+fn first_line(s: &str) -> &str {
+    s.lines().next().unwrap_or("")
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\n' | '\t' => out.push(c),
+            // not representable in XML 1.0
+            c if c.is_control() => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(name: &str, folder: &str, error: Option<&str>, assertions: &[(bool, &str)]) -> StepResult {
+        StepResult {
+            request_id: name.into(),
+            name: name.into(),
+            folder: folder.into(),
+            status: if error.is_some() { "dial_failure".into() } else { "OK".into() },
+            took_ms: 1234,
+            passed: error.is_none() && assertions.iter().all(|(passed, _)| *passed),
+            error: error.map(str::to_string),
+            assertions: assertions.iter().map(|(passed, message)| AssertionResult {
+                assertion: Assertion::LatencyBelow { ms: 10 },
+                passed: *passed,
+                message: (!message.is_empty()).then(|| message.to_string()),
+            }).collect(),
+            history_id: None,
+            extracted: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn junit_counts_failures_and_errors_apart() {
+        let summary = RunSummary::new("c1".into(), "smoke".into(), 0, 2500, vec![
+            step("ok", "", None, &[(true, "")]),
+            step("slow", "orders/get", None, &[(true, ""), (false, "took 1234 ms")]),
+            step("down", "", Some("failed to dial\nsecond line"), &[]),
+        ]);
+        assert_eq!((summary.total, summary.passed, summary.failed), (3, 1, 2));
+        let xml = summary.to_junit();
+        assert!(xml.contains(r#"<testsuites name="grpc-bridge" tests="3" failures="1" errors="1" time="2.500">"#), "{}", xml);
+        assert!(xml.contains(r#"<testsuite name="smoke" tests="3" failures="1" errors="1" time="2.500" timestamp="1970-01-01T00:00:00.000Z">"#));
+        assert!(xml.contains(r#"<testcase name="ok" classname="smoke" time="1.234" />"#));
+        assert!(xml.contains(r#"<testcase name="slow" classname="smoke.orders.get" time="1.234">"#));
+        assert!(xml.contains(r#"<failure message="1 of 2 assertions failed">took 1234 ms</failure>"#));
+        assert!(xml.contains("<error message=\"failed to dial\" type=\"dial_failure\">failed to dial\nsecond line</error>"));
+        assert!(xml.ends_with("  </testsuite>\n</testsuites>\n"));
+    }
+
+    #[test]
+    fn junit_escapes_markup_and_drops_control_characters() {
+        let summary = RunSummary::new("c1".into(), "a & b".into(), 0, 0, vec![
+            step("<get \"x\">", "", None, &[(false, "expected 'a' & got <b>\u{1}")]),
+        ]);
+        let xml = summary.to_junit();
+        assert!(xml.contains(r#"<testsuite name="a &amp; b""#));
+        assert!(xml.contains(r#"<testcase name="&lt;get &quot;x&quot;&gt;" classname="a &amp; b""#));
+        assert!(xml.contains("expected &apos;a&apos; &amp; got &lt;b&gt;</failure>"));
+        assert!(!xml.contains('\u{1}'));
+    }
+}
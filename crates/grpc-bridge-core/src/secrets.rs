@@ -0,0 +1,291 @@
+use crate::error::{BridgeError, BridgeResult};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const SERVICE: &str = "grpc-bridge";
+const NONCE_LEN: usize = 12;
+const DATA_FILE: &str = "secrets.bin";
+const KEY_FILE: &str = "secrets.key";
+
+/// Where secret values live. Nothing stored here is ever written to SQLite.
+pub trait SecretStore: Send + Sync {
+    /// Backend name for logs and diagnostics.
+    fn name(&self) -> &'static str;
+    fn get(&self, key: &str) -> BridgeResult<Option<String>>;
+    fn set(&self, key: &str, value: &str) -> BridgeResult<()>;
+    /// Removing a missing key is not an error.
+    fn delete(&self, key: &str) -> BridgeResult<()>;
+}
+
+/// Store key of a secret environment variable.
+pub fn env_key(environment_id: &str, key: &str) -> String {
+    format!("env/{}/{}", environment_id, key)
+}
+
+/// Where the key of the encrypted file store is kept: the platform config directory, apart from
+/// the data directory holding the ciphertext so a copy of one alone reveals nothing. On macOS
+/// both are the same directory.
+pub fn default_key_dir() -> Option<PathBuf> {
+    directories::ProjectDirs::from("com", "example", "grpcbridge").map(|d| d.config_dir().to_path_buf())
+}
+
+/// Picks the platform keyring when it is reachable, otherwise an encrypted file in `data_dir`
+/// with its key in `key_dir` (headless Linux without a Secret Service), otherwise memory.
+/// Without a `key_dir` the key sits next to the ciphertext.
+pub fn open_default(data_dir: Option<&Path>, key_dir: Option<&Path>) -> Box<dyn SecretStore> {
+    select(Box::new(KeyringStore), data_dir, key_dir)
+}
+
+fn select(keyring: Box<dyn SecretStore>, data_dir: Option<&Path>, key_dir: Option<&Path>) -> Box<dyn SecretStore> {
+    match keyring.get("probe") {
+        Ok(_) => return keyring,
+        Err(e) => tracing::warn!(%e, "platform keyring unavailable"),
+    }
+    if let Some(dir) = data_dir {
+        let store = FileStore::new(dir.join(DATA_FILE), key_dir.unwrap_or(dir).join(KEY_FILE));
+        match store.load() {
+            Ok(_) => return Box::new(store),
+            Err(e) => tracing::warn!(%e, "encrypted secret file unavailable"),
+        }
+    }
+    Box::<MemoryStore>::default()
+}
+
+/// The OS credential store: Keychain, Windows Credential Manager or Secret Service.
+pub struct KeyringStore;
+
+impl KeyringStore {
+    fn entry(&self, key: &str) -> BridgeResult<keyring::Entry> {
+        keyring::Entry::new(SERVICE, key).map_err(secret_error)
+    }
+}
+
+impl SecretStore for KeyringStore {
+    fn name(&self) -> &'static str { "keyring" }
+
+    fn get(&self, key: &str) -> BridgeResult<Option<String>> {
+        match self.entry(key)?.get_password() {
+            Ok(v) => Ok(Some(v)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(secret_error(e)),
+        }
+    }
+
+    fn set(&self, key: &str, value: &str) -> BridgeResult<()> {
+        self.entry(key)?.set_password(value).map_err(secret_error)
+    }
+
+    fn delete(&self, key: &str) -> BridgeResult<()> {
+        match self.entry(key)?.delete_password() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(secret_error(e)),
+        }
+    }
+}
+
+/// All secrets in one ChaCha20-Poly1305 encrypted file. The key is kept in a separate file
+/// readable only by the current user, so this protects against copies of the data directory
+/// and accidental disclosure, not against the user's own account.
+pub struct FileStore {
+    path: PathBuf,
+    key_path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileStore {
+    pub fn new(path: PathBuf, key_path: PathBuf) -> Self {
+        Self { path, key_path, lock: Mutex::new(()) }
+    }
+
+    fn cipher(&self) -> BridgeResult<ChaCha20Poly1305> {
+        let key = match std::fs::read(&self.key_path) {
+            Ok(bytes) if bytes.len() == 32 => *Key::from_slice(&bytes),
+            Ok(_) => return Err(BridgeError::Secret(format!("{} is not a valid key file", self.key_path.display()))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+                write_private(&self.key_path, &key)?;
+                key
+            }
+            Err(e) => return Err(io_error(&self.key_path, e)),
+        };
+        Ok(ChaCha20Poly1305::new(&key))
+    }
+
+    fn load(&self) -> BridgeResult<HashMap<String, String>> {
+        let cipher = self.cipher()?;
+        let data = match std::fs::read(&self.path) {
+            Ok(d) => d,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+            Err(e) => return Err(io_error(&self.path, e)),
+        };
+        if data.len() < NONCE_LEN { return Err(BridgeError::Secret("secret file is truncated".into())); }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let plain = cipher.decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| BridgeError::Secret("secret file cannot be decrypted".into()))?;
+        serde_json::from_slice(&plain).map_err(|e| BridgeError::Secret(format!("secret file is corrupt: {}", e)))
+    }
+
+    fn save(&self, map: &HashMap<String, String>) -> BridgeResult<()> {
+        let cipher = self.cipher()?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let plain = serde_json::to_vec(map).map_err(|e| BridgeError::Secret(e.to_string()))?;
+        let ciphertext = cipher.encrypt(&nonce, plain.as_slice())
+            .map_err(|_| BridgeError::Secret("encryption failed".into()))?;
+        let mut data = nonce.to_vec();
+        data.extend_from_slice(&ciphertext);
+        // write then rename so a crash never leaves a half written file
+        let tmp = self.path.with_extension("tmp");
+        write_private(&tmp, &data)?;
+        std::fs::rename(&tmp, &self.path).map_err(|e| io_error(&self.path, e))
+    }
+
+    fn update(&self, f: impl FnOnce(&mut HashMap<String, String>)) -> BridgeResult<()> {
+        let _guard = self.lock.lock().unwrap();
+        let mut map = self.load()?;
+        f(&mut map);
+        self.save(&map)
+    }
+}
+
+impl SecretStore for FileStore {
+    fn name(&self) -> &'static str { "encrypted_file" }
+
+    fn get(&self, key: &str) -> BridgeResult<Option<String>> {
+        let _guard = self.lock.lock().unwrap();
+        Ok(self.load()?.remove(key))
+    }
+
+    fn set(&self, key: &str, value: &str) -> BridgeResult<()> {
+        self.update(|m| { m.insert(key.to_string(), value.to_string()); })
+    }
+
+    fn delete(&self, key: &str) -> BridgeResult<()> {
+        self.update(|m| { m.remove(key); })
+    }
+}
+
+/// Process-local store: the last resort when nothing persistent works, and the mock backend
+/// for tests.
+#[derive(Default)]
+pub struct MemoryStore {
+    values: Mutex<HashMap<String, String>>,
+}
+
+impl SecretStore for MemoryStore {
+    fn name(&self) -> &'static str { "memory" }
+
+    fn get(&self, key: &str) -> BridgeResult<Option<String>> {
+        Ok(self.values.lock().unwrap().get(key).cloned())
+    }
+
+    fn set(&self, key: &str, value: &str) -> BridgeResult<()> {
+        self.values.lock().unwrap().insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> BridgeResult<()> {
+        self.values.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
+
+fn write_private(path: &Path, data: &[u8]) -> BridgeResult<()> {
+    use std::io::Write;
+    if let Some(dir) = path.parent() { std::fs::create_dir_all(dir).map_err(|e| io_error(dir, e))?; }
+    let mut opts = std::fs::OpenOptions::new();
+    opts.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    { use std::os::unix::fs::OpenOptionsExt; opts.mode(0o600); }
+    let mut file = opts.open(path).map_err(|e| io_error(path, e))?;
+    file.write_all(data).map_err(|e| io_error(path, e))
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> BridgeError {
+    BridgeError::Secret(format!("{}: {}", path.display(), e))
+}
+
+fn secret_error(e: keyring::Error) -> BridgeError {
+    BridgeError::Secret(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A keyring that cannot be reached, as on headless Linux.
+    struct Unreachable;
+
+    impl SecretStore for Unreachable {
+        fn name(&self) -> &'static str { "unreachable" }
+        fn get(&self, _: &str) -> BridgeResult<Option<String>> { Err(BridgeError::Secret("no secret service".into())) }
+        fn set(&self, _: &str, _: &str) -> BridgeResult<()> { Err(BridgeError::Secret("no secret service".into())) }
+        fn delete(&self, _: &str) -> BridgeResult<()> { Err(BridgeError::Secret("no secret service".into())) }
+    }
+
+    fn file_store(dir: &Path) -> FileStore {
+        FileStore::new(dir.join("data").join(DATA_FILE), dir.join("config").join(KEY_FILE))
+    }
+
+    #[test]
+    fn file_store_round_trips_through_the_encrypted_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = file_store(dir.path());
+        assert_eq!(store.get("env/1/token").unwrap(), None);
+        store.set("env/1/token", "s3cr3t").unwrap();
+        store.set("env/1/other", "x").unwrap();
+        store.delete("env/1/other").unwrap();
+        store.delete("missing").unwrap();
+
+        let reopened = file_store(dir.path());
+        assert_eq!(reopened.get("env/1/token").unwrap().as_deref(), Some("s3cr3t"));
+        assert_eq!(reopened.get("env/1/other").unwrap(), None);
+        let data = std::fs::read(dir.path().join("data").join(DATA_FILE)).unwrap();
+        assert!(!String::from_utf8_lossy(&data).contains("s3cr3t"));
+        let key = dir.path().join("config").join(KEY_FILE);
+        assert_eq!(std::fs::read(&key).unwrap().len(), 32);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&key).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn wrong_or_corrupt_keys_are_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        file_store(dir.path()).set("k", "v").unwrap();
+        let data = dir.path().join("data").join(DATA_FILE);
+
+        let other_key = FileStore::new(data.clone(), dir.path().join("other.key"));
+        assert!(matches!(other_key.get("k"), Err(BridgeError::Secret(m)) if m.contains("cannot be decrypted")));
+        std::fs::write(dir.path().join("short.key"), [0u8; 16]).unwrap();
+        let short_key = FileStore::new(data.clone(), dir.path().join("short.key"));
+        assert!(matches!(short_key.get("k"), Err(BridgeError::Secret(m)) if m.contains("not a valid key file")));
+
+        std::fs::write(&data, [1u8; 4]).unwrap();
+        assert!(matches!(file_store(dir.path()).get("k"), Err(BridgeError::Secret(m)) if m.contains("truncated")));
+    }
+
+    #[test]
+    fn the_keyring_is_preferred_then_the_file_then_memory() {
+        let dir = tempfile::tempdir().unwrap();
+        let (data_dir, key_dir) = (dir.path().join("data"), dir.path().join("config"));
+
+        let store = select(Box::<MemoryStore>::default(), Some(&data_dir), Some(&key_dir));
+        assert_eq!(store.name(), "memory");
+        assert!(!data_dir.exists(), "a reachable keyring leaves the data directory alone");
+
+        let store = select(Box::new(Unreachable), Some(&data_dir), Some(&key_dir));
+        assert_eq!(store.name(), "encrypted_file");
+        store.set("k", "v").unwrap();
+        assert!(data_dir.join(DATA_FILE).is_file() && key_dir.join(KEY_FILE).is_file());
+        assert!(!data_dir.join(KEY_FILE).exists());
+
+        std::fs::write(key_dir.join(KEY_FILE), b"not a key").unwrap();
+        assert_eq!(select(Box::new(Unreachable), Some(&data_dir), Some(&key_dir)).name(), "memory");
+        assert_eq!(select(Box::new(Unreachable), None, Some(&key_dir)).name(), "memory");
+    }
+}
@@ -0,0 +1,573 @@
+use super::{now_unix_ms, Storage};
+use crate::assertions::Assertion;
+use crate::error::{BridgeError, BridgeResult};
+use crate::extract::ExtractionRule;
+use crate::payload::format::PayloadFormat;
+use crate::payload::json_output::JsonOptions;
+use rusqlite::{params, OptionalExtension, Row, Transaction};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Collection {
+    pub id: String,
+    pub name: String,
+    pub created_at: u64,
+}
+
+/// A folder inside a collection; `parent_id` is `None` for top level folders.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Folder {
+    pub id: String,
+    pub collection_id: String,
+    pub parent_id: Option<String>,
+    pub name: String,
+}
+
+/// A request saved for reuse, linked to the proto root and method it was built from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedRequest {
+    #[serde(default)]
+    pub id: String,
+    pub collection_id: String,
+    pub folder_id: Option<String>,
+    pub name: String,
+    pub root_id: Option<String>,
+    pub fq_service: String,
+    pub method: String,
+    pub target: String,
+    #[serde(default)]
+    pub headers: Vec<String>,
+    #[serde(default)]
+    pub payload: String,
+    #[serde(default)]
+    pub proto_files: Vec<String>,
+    pub tls: Option<bool>,
+    /// Values written into the environment after a successful call.
+    #[serde(default)]
+    pub extractions: Vec<ExtractionRule>,
+    /// Checked when the request runs as part of `run_collection`.
+    #[serde(default)]
+    pub assertions: Vec<Assertion>,
+    #[serde(default)]
+    pub payload_format: PayloadFormat,
+    #[serde(default)]
+    pub response_format: PayloadFormat,
+    #[serde(default)]
+    pub json_options: JsonOptions,
+}
+
+/// A collection with all of its folders and requests, flat; the UI builds the tree.
+#[derive(Debug, Clone, Serialize)]
+pub struct CollectionTree {
+    pub collection: Collection,
+    pub folders: Vec<Folder>,
+    pub requests: Vec<SavedRequest>,
+}
+
+impl CollectionTree {
+    /// Names from the top of the collection down to `folder_id`, joined with `/`.
+    pub fn folder_path(&self, folder_id: &str) -> BridgeResult<String> {
+        let mut parts = Vec::new();
+        let mut next = Some(folder_id);
+        while let Some(id) = next {
+            let f = self.folders.iter().find(|f| f.id == id).ok_or_else(|| BridgeError::FolderNotFound(id.into()))?;
+            parts.push(f.name.as_str());
+            next = f.parent_id.as_deref();
+        }
+        parts.reverse();
+        Ok(parts.join("/"))
+    }
+}
+
+const REQUEST_COLUMNS: &str = "id, collection_id, folder_id, name, root_id, fq_service, method, target, headers_json, payload, proto_files_json, tls, extractions_json, assertions_json, payload_format, response_format, json_options_json";
+
+fn request_from_row(r: &Row) -> rusqlite::Result<SavedRequest> {
+    fn json<T: serde::de::DeserializeOwned>(r: &Row, i: usize) -> rusqlite::Result<T> {
+        let s: String = r.get(i)?;
+        serde_json::from_str(&s).map_err(|e| rusqlite::Error::FromSqlConversionFailure(i, rusqlite::types::Type::Text, Box::new(e)))
+    }
+    fn format(r: &Row, i: usize) -> rusqlite::Result<PayloadFormat> {
+        let s: String = r.get(i)?;
+        s.parse().map_err(|e: String| rusqlite::Error::FromSqlConversionFailure(i, rusqlite::types::Type::Text, e.into()))
+    }
+    Ok(SavedRequest {
+        id: r.get(0)?,
+        collection_id: r.get(1)?,
+        folder_id: r.get(2)?,
+        name: r.get(3)?,
+        root_id: r.get(4)?,
+        fq_service: r.get(5)?,
+        method: r.get(6)?,
+        target: r.get(7)?,
+        headers: json(r, 8)?,
+        payload: r.get(9)?,
+        proto_files: json(r, 10)?,
+        tls: r.get(11)?,
+        extractions: json(r, 12)?,
+        assertions: json(r, 13)?,
+        payload_format: format(r, 14)?,
+        response_format: format(r, 15)?,
+        json_options: json(r, 16)?,
+    })
+}
+
+fn folder_from_row(r: &Row) -> rusqlite::Result<Folder> {
+    Ok(Folder { id: r.get(0)?, collection_id: r.get(1)?, parent_id: r.get(2)?, name: r.get(3)? })
+}
+
+fn new_id() -> String { uuid::Uuid::new_v4().to_string() }
+
+fn check_name(name: &str) -> BridgeResult<String> {
+    let name = name.trim();
+    if name.is_empty() { return Err(BridgeError::InvalidArgument("name must not be empty".into())); }
+    Ok(name.to_string())
+}
+
+/// Errors unless `folder_id` exists in `collection_id`.
+fn check_folder(tx: &Transaction, collection_id: &str, folder_id: &str) -> BridgeResult<()> {
+    let owner: Option<String> = tx
+        .query_row("SELECT collection_id FROM folders WHERE id = ?1", params![folder_id], |r| r.get(0))
+        .optional()?;
+    match owner {
+        Some(c) if c == collection_id => Ok(()),
+        Some(_) => Err(BridgeError::InvalidArgument(format!("folder {} belongs to another collection", folder_id))),
+        None => Err(BridgeError::FolderNotFound(folder_id.into())),
+    }
+}
+
+fn check_collection(tx: &Transaction, collection_id: &str) -> BridgeResult<()> {
+    let found: Option<i64> = tx.query_row("SELECT 1 FROM collections WHERE id = ?1", params![collection_id], |r| r.get(0)).optional()?;
+    found.map(|_| ()).ok_or_else(|| BridgeError::CollectionNotFound(collection_id.into()))
+}
+
+/// Where folders and requests sit among their siblings: the rows of `table` in one collection
+/// under one parent folder (`parent` names the column).
+struct Siblings<'a> {
+    table: &'static str,
+    parent: &'static str,
+    collection_id: &'a str,
+    parent_id: Option<&'a str>,
+}
+
+impl Siblings<'_> {
+    fn of_folder<'a>(collection_id: &'a str, parent_id: Option<&'a str>) -> Siblings<'a> {
+        Siblings { table: "folders", parent: "parent_id", collection_id, parent_id }
+    }
+
+    fn of_request<'a>(collection_id: &'a str, folder_id: Option<&'a str>) -> Siblings<'a> {
+        Siblings { table: "saved_requests", parent: "folder_id", collection_id, parent_id: folder_id }
+    }
+
+    /// The position after the last sibling.
+    fn next(&self, tx: &Transaction) -> rusqlite::Result<i64> {
+        tx.query_row(
+            &format!("SELECT COALESCE(MAX(position) + 1, 0) FROM {} WHERE collection_id = ?1 AND {} IS ?2", self.table, self.parent),
+            params![self.collection_id, self.parent_id],
+            |r| r.get(0),
+        )
+    }
+
+    /// Puts `id`, one of the siblings, at `index` and numbers the others around it.
+    fn place(&self, tx: &Transaction, id: &str, index: usize) -> rusqlite::Result<()> {
+        let mut ids: Vec<String> = {
+            let mut stmt = tx.prepare(&format!(
+                "SELECT id FROM {} WHERE collection_id = ?1 AND {} IS ?2 AND id != ?3 ORDER BY position, name COLLATE NOCASE",
+                self.table, self.parent,
+            ))?;
+            let rows = stmt.query_map(params![self.collection_id, self.parent_id, id], |r| r.get(0))?;
+            rows.collect::<Result<_, _>>()?
+        };
+        ids.insert(index.min(ids.len()), id.to_string());
+        for (position, id) in ids.iter().enumerate() {
+            tx.execute(&format!("UPDATE {} SET position = ?2 WHERE id = ?1", self.table), params![id, position as i64])?;
+        }
+        Ok(())
+    }
+}
+
+fn walk(tree: &CollectionTree, folder_id: Option<&str>, path: &str, out: &mut Vec<(String, SavedRequest)>) {
+    for r in tree.requests.iter().filter(|r| r.folder_id.as_deref() == folder_id) {
+        out.push((path.to_string(), r.clone()));
+    }
+    for f in tree.folders.iter().filter(|f| f.parent_id.as_deref() == folder_id) {
+        let sub = if path.is_empty() { f.name.clone() } else { format!("{}/{}", path, f.name) };
+        walk(tree, Some(&f.id), &sub, out);
+    }
+}
+
+impl Storage {
+    pub fn create_collection(&self, name: &str) -> BridgeResult<Collection> {
+        let c = Collection { id: new_id(), name: check_name(name)?, created_at: now_unix_ms() };
+        self.conn().execute("INSERT INTO collections (id, name, created_at) VALUES (?1, ?2, ?3)", params![c.id, c.name, c.created_at])?;
+        Ok(c)
+    }
+
+    pub fn rename_collection(&self, id: &str, name: &str) -> BridgeResult<()> {
+        let n = self.conn().execute("UPDATE collections SET name = ?2 WHERE id = ?1", params![id, check_name(name)?])?;
+        if n == 0 { return Err(BridgeError::CollectionNotFound(id.into())); }
+        Ok(())
+    }
+
+    /// Deletes a collection with all of its folders and requests.
+    pub fn delete_collection(&self, id: &str) -> BridgeResult<()> {
+        let n = self.conn().execute("DELETE FROM collections WHERE id = ?1", params![id])?;
+        if n == 0 { return Err(BridgeError::CollectionNotFound(id.into())); }
+        Ok(())
+    }
+
+    pub fn list_collections(&self) -> BridgeResult<Vec<CollectionTree>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT id, name, created_at FROM collections ORDER BY name COLLATE NOCASE, created_at")?;
+        let collections = stmt
+            .query_map([], |r| Ok(Collection { id: r.get(0)?, name: r.get(1)?, created_at: r.get(2)? }))?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut out = Vec::with_capacity(collections.len());
+        for collection in collections {
+            let mut stmt = conn.prepare("SELECT id, collection_id, parent_id, name FROM folders WHERE collection_id = ?1 ORDER BY position, name COLLATE NOCASE")?;
+            let folders = stmt.query_map(params![collection.id], folder_from_row)?.collect::<Result<Vec<_>, _>>()?;
+            let mut stmt = conn.prepare(&format!("SELECT {} FROM saved_requests WHERE collection_id = ?1 ORDER BY position, name COLLATE NOCASE", REQUEST_COLUMNS))?;
+            let requests = stmt.query_map(params![collection.id], request_from_row)?.collect::<Result<Vec<_>, _>>()?;
+            out.push(CollectionTree { collection, folders, requests });
+        }
+        Ok(out)
+    }
+
+    pub fn collection_tree(&self, id: &str) -> BridgeResult<CollectionTree> {
+        self.list_collections()?.into_iter().find(|t| t.collection.id == id)
+            .ok_or_else(|| BridgeError::CollectionNotFound(id.into()))
+    }
+
+    /// Requests of a collection, or of one folder, in run order: a folder's own requests, then
+    /// its subfolders, each in the order set by `reorder_request` and `reorder_folder`, depth
+    /// first. Each comes with its folder path.
+    pub fn run_order(&self, collection_id: &str, folder_id: Option<&str>) -> BridgeResult<(Collection, Vec<(String, SavedRequest)>)> {
+        let tree = self.collection_tree(collection_id)?;
+        let path = match folder_id {
+            Some(id) => tree.folder_path(id)?,
+            None => String::new(),
+        };
+        let mut out = Vec::new();
+        walk(&tree, folder_id, &path, &mut out);
+        Ok((tree.collection, out))
+    }
+
+    pub fn create_folder(&self, collection_id: &str, parent_id: Option<&str>, name: &str) -> BridgeResult<Folder> {
+        let folder = Folder { id: new_id(), collection_id: collection_id.into(), parent_id: parent_id.map(str::to_string), name: check_name(name)? };
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        check_collection(&tx, collection_id)?;
+        if let Some(p) = parent_id { check_folder(&tx, collection_id, p)?; }
+        let position = Siblings::of_folder(collection_id, parent_id).next(&tx)?;
+        tx.execute(
+            "INSERT INTO folders (id, collection_id, parent_id, name, position) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![folder.id, folder.collection_id, folder.parent_id, folder.name, position],
+        )?;
+        tx.commit()?;
+        Ok(folder)
+    }
+
+    pub fn rename_folder(&self, id: &str, name: &str) -> BridgeResult<()> {
+        let n = self.conn().execute("UPDATE folders SET name = ?2 WHERE id = ?1", params![id, check_name(name)?])?;
+        if n == 0 { return Err(BridgeError::FolderNotFound(id.into())); }
+        Ok(())
+    }
+
+    /// Deletes a folder, its sub folders and every request inside them.
+    pub fn delete_folder(&self, id: &str) -> BridgeResult<()> {
+        let n = self.conn().execute("DELETE FROM folders WHERE id = ?1", params![id])?;
+        if n == 0 { return Err(BridgeError::FolderNotFound(id.into())); }
+        Ok(())
+    }
+
+    /// Moves a folder (with its contents) under `parent_id` in `collection_id`.
+    pub fn move_folder(&self, id: &str, collection_id: &str, parent_id: Option<&str>) -> BridgeResult<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        check_collection(&tx, collection_id)?;
+        let subtree: Vec<String> = {
+            let mut stmt = tx.prepare(
+                "WITH RECURSIVE sub(id) AS (SELECT id FROM folders WHERE id = ?1
+                 UNION ALL SELECT f.id FROM folders f JOIN sub ON f.parent_id = sub.id)
+                 SELECT id FROM sub",
+            )?;
+            let rows = stmt.query_map(params![id], |r| r.get(0))?;
+            rows.collect::<Result<_, _>>()?
+        };
+        if subtree.is_empty() { return Err(BridgeError::FolderNotFound(id.into())); }
+        if let Some(p) = parent_id {
+            if subtree.iter().any(|f| f == p) {
+                return Err(BridgeError::InvalidArgument("cannot move a folder into itself".into()));
+            }
+            check_folder(&tx, collection_id, p)?;
+        }
+        let position = Siblings::of_folder(collection_id, parent_id).next(&tx)?;
+        tx.execute("UPDATE folders SET parent_id = ?2, position = ?3 WHERE id = ?1", params![id, parent_id, position])?;
+        for f in &subtree {
+            tx.execute("UPDATE folders SET collection_id = ?2 WHERE id = ?1", params![f, collection_id])?;
+            tx.execute("UPDATE saved_requests SET collection_id = ?2 WHERE folder_id = ?1", params![f, collection_id])?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Moves a folder to `index` among the folders next to it (0 is first).
+    pub fn reorder_folder(&self, id: &str, index: usize) -> BridgeResult<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let (collection_id, parent_id): (String, Option<String>) = tx
+            .query_row("SELECT collection_id, parent_id FROM folders WHERE id = ?1", params![id], |r| Ok((r.get(0)?, r.get(1)?)))
+            .optional()?
+            .ok_or_else(|| BridgeError::FolderNotFound(id.into()))?;
+        Siblings::of_folder(&collection_id, parent_id.as_deref()).place(&tx, id, index)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn get_saved_request(&self, id: &str) -> BridgeResult<SavedRequest> {
+        self.conn()
+            .query_row(&format!("SELECT {} FROM saved_requests WHERE id = ?1", REQUEST_COLUMNS), params![id], request_from_row)
+            .optional()?
+            .ok_or_else(|| BridgeError::SavedRequestNotFound(id.into()))
+    }
+
+    /// Inserts `req`, or updates it when its id already exists. Returns the stored request.
+    pub fn save_request(&self, req: &SavedRequest) -> BridgeResult<SavedRequest> {
+        let mut req = req.clone();
+        req.name = check_name(&req.name)?;
+        if req.id.is_empty() { req.id = new_id(); }
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        check_collection(&tx, &req.collection_id)?;
+        if let Some(f) = &req.folder_id { check_folder(&tx, &req.collection_id, f)?; }
+        // a request keeps its place unless it changes folder, then it goes last
+        let current: Option<(String, Option<String>, i64)> = tx
+            .query_row("SELECT collection_id, folder_id, position FROM saved_requests WHERE id = ?1", params![req.id], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+            .optional()?;
+        let position = match current {
+            Some((c, f, position)) if c == req.collection_id && f == req.folder_id => position,
+            _ => Siblings::of_request(&req.collection_id, req.folder_id.as_deref()).next(&tx)?,
+        };
+        tx.execute(
+            &format!(
+                "INSERT INTO saved_requests ({}, position, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)
+                 ON CONFLICT(id) DO UPDATE SET collection_id = excluded.collection_id, folder_id = excluded.folder_id,
+                 name = excluded.name, root_id = excluded.root_id, fq_service = excluded.fq_service, method = excluded.method,
+                 target = excluded.target, headers_json = excluded.headers_json, payload = excluded.payload,
+                 proto_files_json = excluded.proto_files_json, tls = excluded.tls,
+                 extractions_json = excluded.extractions_json, assertions_json = excluded.assertions_json,
+                 payload_format = excluded.payload_format, response_format = excluded.response_format,
+                 json_options_json = excluded.json_options_json, position = excluded.position, updated_at = excluded.updated_at",
+                REQUEST_COLUMNS
+            ),
+            params![
+                req.id, req.collection_id, req.folder_id, req.name, req.root_id, req.fq_service, req.method, req.target,
+                serde_json::to_string(&req.headers).unwrap_or_default(), req.payload,
+                serde_json::to_string(&req.proto_files).unwrap_or_default(), req.tls,
+                serde_json::to_string(&req.extractions).unwrap_or_default(),
+                serde_json::to_string(&req.assertions).unwrap_or_default(), req.payload_format.as_str(), req.response_format.as_str(),
+                serde_json::to_string(&req.json_options).unwrap_or_default(), position, now_unix_ms(),
+            ],
+        )?;
+        tx.commit()?;
+        Ok(req)
+    }
+
+    pub fn rename_request(&self, id: &str, name: &str) -> BridgeResult<()> {
+        let n = self.conn().execute("UPDATE saved_requests SET name = ?2, updated_at = ?3 WHERE id = ?1", params![id, check_name(name)?, now_unix_ms()])?;
+        if n == 0 { return Err(BridgeError::SavedRequestNotFound(id.into())); }
+        Ok(())
+    }
+
+    pub fn move_request(&self, id: &str, collection_id: &str, folder_id: Option<&str>) -> BridgeResult<()> {
+        let mut req = self.get_saved_request(id)?;
+        req.collection_id = collection_id.into();
+        req.folder_id = folder_id.map(str::to_string);
+        self.save_request(&req).map(|_| ())
+    }
+
+    /// Moves a request to `index` among the requests in its folder (0 is first).
+    pub fn reorder_request(&self, id: &str, index: usize) -> BridgeResult<()> {
+        let req = self.get_saved_request(id)?;
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        Siblings::of_request(&req.collection_id, req.folder_id.as_deref()).place(&tx, id, index)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Copies a request right after the original under a new id and a "(copy)" name.
+    pub fn duplicate_request(&self, id: &str) -> BridgeResult<SavedRequest> {
+        let mut req = self.get_saved_request(id)?;
+        let index = self.collection_tree(&req.collection_id)?.requests.iter()
+            .filter(|r| r.folder_id == req.folder_id)
+            .position(|r| r.id == id)
+            .unwrap_or_default();
+        req.id = String::new();
+        req.name = format!("{} (copy)", req.name);
+        let copy = self.save_request(&req)?;
+        self.reorder_request(&copy.id, index + 1)?;
+        Ok(copy)
+    }
+
+    pub fn delete_request(&self, id: &str) -> BridgeResult<()> {
+        let n = self.conn().execute("DELETE FROM saved_requests WHERE id = ?1", params![id])?;
+        if n == 0 { return Err(BridgeError::SavedRequestNotFound(id.into())); }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(collection_id: &str, folder_id: Option<&str>, name: &str) -> SavedRequest {
+        SavedRequest {
+            id: String::new(),
+            collection_id: collection_id.into(),
+            folder_id: folder_id.map(str::to_string),
+            name: name.into(),
+            root_id: None,
+            fq_service: "shop.v1.Orders".into(),
+            method: "Get".into(),
+            target: "localhost:50051".into(),
+            headers: vec!["x-trace: 1".into()],
+            payload: r#"{"id":"1"}"#.into(),
+            proto_files: vec!["shop.proto".into()],
+            tls: Some(false),
+            extractions: Vec::new(),
+            assertions: Vec::new(),
+            payload_format: PayloadFormat::Json,
+            response_format: PayloadFormat::Hex,
+            json_options: JsonOptions { proto_names: true, ..Default::default() },
+        }
+    }
+
+    fn tree(s: &Storage, collection_id: &str) -> CollectionTree {
+        s.list_collections().unwrap().into_iter().find(|t| t.collection.id == collection_id).unwrap()
+    }
+
+    #[test]
+    fn collections_and_folders_are_created_renamed_and_deleted() {
+        let s = Storage::open_in_memory().unwrap();
+        let b = s.create_collection(" beta ").unwrap();
+        let a = s.create_collection("Alpha").unwrap();
+        assert_eq!(b.name, "beta");
+        assert!(matches!(s.create_collection("  "), Err(BridgeError::InvalidArgument(_))));
+        s.rename_collection(&b.id, "gamma").unwrap();
+        let names: Vec<String> = s.list_collections().unwrap().into_iter().map(|t| t.collection.name).collect();
+        assert_eq!(names, ["Alpha", "gamma"]);
+
+        let top = s.create_folder(&a.id, None, "top").unwrap();
+        let sub = s.create_folder(&a.id, Some(&top.id), "sub").unwrap();
+        assert!(matches!(s.create_folder(&b.id, Some(&top.id), "x"), Err(BridgeError::InvalidArgument(_))));
+        assert!(matches!(s.create_folder("nope", None, "x"), Err(BridgeError::CollectionNotFound(_))));
+        s.rename_folder(&sub.id, "inner").unwrap();
+        let saved = s.save_request(&request(&a.id, Some(&sub.id), "get")).unwrap();
+        assert_eq!(s.get_saved_request(&saved.id).unwrap().response_format, PayloadFormat::Hex);
+        assert!(s.get_saved_request(&saved.id).unwrap().json_options.proto_names);
+
+        // deleting a folder takes its subfolders and requests along
+        s.delete_folder(&top.id).unwrap();
+        let t = tree(&s, &a.id);
+        assert!(t.folders.is_empty() && t.requests.is_empty());
+        assert!(matches!(s.get_saved_request(&saved.id), Err(BridgeError::SavedRequestNotFound(_))));
+        assert!(matches!(s.rename_folder(&sub.id, "x"), Err(BridgeError::FolderNotFound(_))));
+
+        s.delete_collection(&a.id).unwrap();
+        assert!(matches!(s.delete_collection(&a.id), Err(BridgeError::CollectionNotFound(_))));
+        assert!(matches!(s.rename_collection(&a.id, "x"), Err(BridgeError::CollectionNotFound(_))));
+    }
+
+    #[test]
+    fn folders_cannot_move_into_themselves() {
+        let s = Storage::open_in_memory().unwrap();
+        let c = s.create_collection("c").unwrap();
+        let a = s.create_folder(&c.id, None, "a").unwrap();
+        let b = s.create_folder(&c.id, Some(&a.id), "b").unwrap();
+        let deep = s.create_folder(&c.id, Some(&b.id), "deep").unwrap();
+
+        for target in [&a.id, &b.id, &deep.id] {
+            assert!(matches!(s.move_folder(&a.id, &c.id, Some(target)), Err(BridgeError::InvalidArgument(_))), "into {}", target);
+        }
+        assert!(matches!(s.move_folder("nope", &c.id, None), Err(BridgeError::FolderNotFound(_))));
+        assert_eq!(tree(&s, &c.id).folders.iter().find(|f| f.id == a.id).unwrap().parent_id, None);
+
+        s.move_folder(&deep.id, &c.id, None).unwrap();
+        s.move_folder(&a.id, &c.id, Some(&deep.id)).unwrap();
+        let (_, order) = s.run_order(&c.id, Some(&b.id)).unwrap();
+        assert!(order.is_empty());
+        let t = tree(&s, &c.id);
+        assert_eq!(t.folders.iter().find(|f| f.id == a.id).unwrap().parent_id.as_deref(), Some(deep.id.as_str()));
+    }
+
+    #[test]
+    fn moving_a_folder_to_another_collection_takes_its_contents() {
+        let s = Storage::open_in_memory().unwrap();
+        let (from, to) = (s.create_collection("from").unwrap(), s.create_collection("to").unwrap());
+        let a = s.create_folder(&from.id, None, "a").unwrap();
+        let b = s.create_folder(&from.id, Some(&a.id), "b").unwrap();
+        s.save_request(&request(&from.id, Some(&a.id), "one")).unwrap();
+        s.save_request(&request(&from.id, Some(&b.id), "two")).unwrap();
+        s.save_request(&request(&from.id, None, "stays")).unwrap();
+
+        s.move_folder(&a.id, &to.id, None).unwrap();
+        let (_, order) = s.run_order(&to.id, None).unwrap();
+        let order: Vec<(String, String)> = order.into_iter().map(|(path, r)| (path, r.name)).collect();
+        assert_eq!(order, [("a".to_string(), "one".to_string()), ("a/b".to_string(), "two".to_string())]);
+        let left = tree(&s, &from.id);
+        assert!(left.folders.is_empty());
+        assert_eq!(left.requests.iter().map(|r| r.name.as_str()).collect::<Vec<_>>(), ["stays"]);
+    }
+
+    #[test]
+    fn collections_run_in_the_order_set_not_by_name() {
+        let s = Storage::open_in_memory().unwrap();
+        let c = s.create_collection("c").unwrap();
+        let account = s.save_request(&request(&c.id, None, "Get account")).unwrap();
+        let login = s.save_request(&request(&c.id, None, "Login")).unwrap();
+        let zeta = s.create_folder(&c.id, None, "zeta").unwrap();
+        let alpha = s.create_folder(&c.id, None, "alpha").unwrap();
+        s.save_request(&request(&c.id, Some(&zeta.id), "z")).unwrap();
+        s.save_request(&request(&c.id, Some(&alpha.id), "a")).unwrap();
+        let names = || s.run_order(&c.id, None).unwrap().1.into_iter().map(|(_, r)| r.name).collect::<Vec<_>>();
+        assert_eq!(names(), ["Get account", "Login", "z", "a"]);
+
+        s.reorder_request(&login.id, 0).unwrap();
+        s.reorder_folder(&alpha.id, 0).unwrap();
+        assert_eq!(names(), ["Login", "Get account", "a", "z"]);
+        assert_eq!(tree(&s, &c.id).folders.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(), ["alpha", "zeta"]);
+        // saving keeps the place, a copy goes right after the original
+        s.save_request(&SavedRequest { payload: "{}".into(), ..s.get_saved_request(&login.id).unwrap() }).unwrap();
+        s.duplicate_request(&login.id).unwrap();
+        assert_eq!(names(), ["Login", "Login (copy)", "Get account", "a", "z"]);
+        // a request moved into a folder goes last there
+        s.move_request(&account.id, &c.id, Some(&alpha.id)).unwrap();
+        s.reorder_request(&account.id, 99).unwrap();
+        assert_eq!(names(), ["Login", "Login (copy)", "a", "Get account", "z"]);
+        assert!(matches!(s.reorder_folder("nope", 0), Err(BridgeError::FolderNotFound(_))));
+        assert!(matches!(s.reorder_request("nope", 0), Err(BridgeError::SavedRequestNotFound(_))));
+    }
+
+    #[test]
+    fn requests_are_duplicated_and_moved() {
+        let s = Storage::open_in_memory().unwrap();
+        let (c, other) = (s.create_collection("c").unwrap(), s.create_collection("other").unwrap());
+        let f = s.create_folder(&c.id, None, "f").unwrap();
+        let original = s.save_request(&request(&c.id, Some(&f.id), "get")).unwrap();
+
+        let copy = s.duplicate_request(&original.id).unwrap();
+        assert_ne!(copy.id, original.id);
+        assert_eq!(copy.name, "get (copy)");
+        let stored = s.get_saved_request(&copy.id).unwrap();
+        assert_eq!((stored.folder_id, stored.payload, stored.headers), (original.folder_id.clone(), original.payload.clone(), original.headers.clone()));
+        assert!(matches!(s.duplicate_request("nope"), Err(BridgeError::SavedRequestNotFound(_))));
+
+        assert!(matches!(s.move_request(&copy.id, &other.id, Some(&f.id)), Err(BridgeError::InvalidArgument(_))));
+        s.move_request(&copy.id, &other.id, None).unwrap();
+        assert_eq!(tree(&s, &other.id).requests.len(), 1);
+        s.rename_request(&original.id, "renamed").unwrap();
+        s.delete_request(&original.id).unwrap();
+        assert!(tree(&s, &c.id).requests.is_empty());
+        assert!(matches!(s.delete_request(&original.id), Err(BridgeError::SavedRequestNotFound(_))));
+    }
+}
@@ -0,0 +1,200 @@
+use super::{now_unix_ms, Storage};
+use crate::error::{BridgeError, BridgeResult};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const ACTIVE_KEY: &str = "environments.active";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Variable {
+    pub key: String,
+    /// Always empty for secrets read back from storage; see `secrets::SecretStore`.
+    pub value: String,
+    #[serde(default)]
+    pub secret: bool,
+}
+
+/// A named set of variables for `{{key}}` placeholders, e.g. local, staging, prod.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Environment {
+    pub id: String,
+    pub name: String,
+    pub variables: Vec<Variable>,
+}
+
+impl Environment {
+    /// Values of the non-secret variables.
+    pub fn to_map(&self) -> HashMap<String, String> {
+        self.variables.iter().filter(|v| !v.secret).map(|v| (v.key.clone(), v.value.clone())).collect()
+    }
+}
+
+fn check_key(key: &str) -> BridgeResult<()> {
+    let mut chars = key.chars();
+    let ok = matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.');
+    if !ok { return Err(BridgeError::InvalidArgument(format!("invalid variable name {:?}", key))); }
+    Ok(())
+}
+
+impl Storage {
+    pub fn list_environments(&self) -> BridgeResult<Vec<Environment>> {
+        let ids: Vec<String> = {
+            let conn = self.conn();
+            let mut stmt = conn.prepare("SELECT id FROM environments ORDER BY name COLLATE NOCASE")?;
+            let rows = stmt.query_map([], |r| r.get(0))?;
+            rows.collect::<Result<_, _>>()?
+        };
+        ids.iter().map(|id| self.get_environment(id)).collect()
+    }
+
+    pub fn get_environment(&self, id: &str) -> BridgeResult<Environment> {
+        let conn = self.conn();
+        let name: String = conn
+            .query_row("SELECT name FROM environments WHERE id = ?1", params![id], |r| r.get(0))
+            .optional()?
+            .ok_or_else(|| BridgeError::EnvironmentNotFound(id.into()))?;
+        let mut stmt = conn.prepare("SELECT key, value, secret FROM environment_variables WHERE environment_id = ?1 ORDER BY key")?;
+        let variables = stmt
+            .query_map(params![id], |r| Ok(Variable { key: r.get(0)?, value: r.get(1)?, secret: r.get(2)? }))?
+            .collect::<Result<_, _>>()?;
+        Ok(Environment { id: id.into(), name, variables })
+    }
+
+    pub fn create_environment(&self, name: &str) -> BridgeResult<Environment> {
+        let name = name.trim();
+        if name.is_empty() { return Err(BridgeError::InvalidArgument("name must not be empty".into())); }
+        let env = Environment { id: uuid::Uuid::new_v4().to_string(), name: name.into(), variables: Vec::new() };
+        self.conn().execute("INSERT INTO environments (id, name, created_at) VALUES (?1, ?2, ?3)", params![env.id, env.name, now_unix_ms()])?;
+        Ok(env)
+    }
+
+    pub fn rename_environment(&self, id: &str, name: &str) -> BridgeResult<()> {
+        let name = name.trim();
+        if name.is_empty() { return Err(BridgeError::InvalidArgument("name must not be empty".into())); }
+        let n = self.conn().execute("UPDATE environments SET name = ?2 WHERE id = ?1", params![id, name])?;
+        if n == 0 { return Err(BridgeError::EnvironmentNotFound(id.into())); }
+        Ok(())
+    }
+
+    pub fn delete_environment(&self, id: &str) -> BridgeResult<()> {
+        let n = self.conn().execute("DELETE FROM environments WHERE id = ?1", params![id])?;
+        if n == 0 { return Err(BridgeError::EnvironmentNotFound(id.into())); }
+        if self.active_environment()?.as_deref() == Some(id) { self.set_active_environment(None)?; }
+        Ok(())
+    }
+
+    /// Replaces all variables of an environment. Values of secret variables are not stored.
+    pub fn set_environment_variables(&self, id: &str, variables: &[Variable]) -> BridgeResult<()> {
+        for v in variables { check_key(&v.key)?; }
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let exists: Option<i64> = tx.query_row("SELECT 1 FROM environments WHERE id = ?1", params![id], |r| r.get(0)).optional()?;
+        if exists.is_none() { return Err(BridgeError::EnvironmentNotFound(id.into())); }
+        tx.execute("DELETE FROM environment_variables WHERE environment_id = ?1", params![id])?;
+        {
+            let mut ins = tx.prepare("INSERT OR REPLACE INTO environment_variables (environment_id, key, value, secret) VALUES (?1, ?2, ?3, ?4)")?;
+            for v in variables { ins.execute(params![id, v.key, if v.secret { "" } else { v.value.as_str() }, v.secret])?; }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Sets one non-secret variable, adding it if missing. Values of secret variables belong in
+    /// the secret store; for them only the (empty) row is kept.
+    pub fn set_environment_variable(&self, id: &str, key: &str, value: &str) -> BridgeResult<()> {
+        check_key(key)?;
+        self.get_environment(id)?;
+        self.conn().execute(
+            "INSERT INTO environment_variables (environment_id, key, value, secret) VALUES (?1, ?2, ?3, 0)
+             ON CONFLICT(environment_id, key) DO UPDATE SET value = CASE WHEN secret THEN '' ELSE excluded.value END",
+            params![id, key, value],
+        )?;
+        Ok(())
+    }
+
+    /// Marks a variable secret, adding it if missing, and drops any value stored here; the
+    /// value itself belongs in the secret store.
+    pub fn set_environment_secret(&self, id: &str, key: &str) -> BridgeResult<()> {
+        check_key(key)?;
+        self.get_environment(id)?;
+        self.conn().execute(
+            "INSERT INTO environment_variables (environment_id, key, value, secret) VALUES (?1, ?2, '', 1)
+             ON CONFLICT(environment_id, key) DO UPDATE SET value = '', secret = 1",
+            params![id, key],
+        )?;
+        Ok(())
+    }
+
+    /// Environment used by calls that do not name one.
+    pub fn active_environment(&self) -> BridgeResult<Option<String>> {
+        Ok(self.get_setting::<Option<String>>(ACTIVE_KEY)?.flatten())
+    }
+
+    pub fn set_active_environment(&self, id: Option<&str>) -> BridgeResult<()> {
+        if let Some(id) = id { self.get_environment(id)?; }
+        self.set_setting(ACTIVE_KEY, &id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var(key: &str, value: &str, secret: bool) -> Variable {
+        Variable { key: key.into(), value: value.into(), secret }
+    }
+
+    #[test]
+    fn environments_are_created_renamed_and_deleted() {
+        let s = Storage::open_in_memory().unwrap();
+        let prod = s.create_environment("prod").unwrap();
+        let local = s.create_environment(" Local ").unwrap();
+        assert!(matches!(s.create_environment(" "), Err(BridgeError::InvalidArgument(_))));
+        s.rename_environment(&prod.id, "staging").unwrap();
+        let names: Vec<String> = s.list_environments().unwrap().into_iter().map(|e| e.name).collect();
+        assert_eq!(names, ["Local", "staging"]);
+
+        s.delete_environment(&local.id).unwrap();
+        assert!(matches!(s.get_environment(&local.id), Err(BridgeError::EnvironmentNotFound(_))));
+        assert!(matches!(s.delete_environment(&local.id), Err(BridgeError::EnvironmentNotFound(_))));
+        assert!(matches!(s.rename_environment(&local.id, "x"), Err(BridgeError::EnvironmentNotFound(_))));
+    }
+
+    #[test]
+    fn variables_are_replaced_and_secret_values_never_stored() {
+        let s = Storage::open_in_memory().unwrap();
+        let env = s.create_environment("dev").unwrap();
+        s.set_environment_variables(&env.id, &[var("host", "localhost", false), var("token", "s3cr3t", true)]).unwrap();
+        let stored = s.get_environment(&env.id).unwrap();
+        assert_eq!(stored.variables.iter().map(|v| (v.key.as_str(), v.value.as_str(), v.secret)).collect::<Vec<_>>(),
+            [("host", "localhost", false), ("token", "", true)]);
+        assert_eq!(stored.to_map(), HashMap::from([("host".to_string(), "localhost".to_string())]));
+
+        s.set_environment_variable(&env.id, "port", "50051").unwrap();
+        s.set_environment_variable(&env.id, "token", "leaked").unwrap();
+        let stored = s.get_environment(&env.id).unwrap();
+        assert_eq!(stored.to_map().get("port").map(String::as_str), Some("50051"));
+        assert!(stored.variables.iter().all(|v| v.value != "leaked"));
+
+        s.set_environment_variables(&env.id, &[var("only", "1", false)]).unwrap();
+        assert_eq!(s.get_environment(&env.id).unwrap().variables.len(), 1);
+        for bad in ["", "1st", "has space", "a{b"] {
+            assert!(matches!(s.set_environment_variables(&env.id, &[var(bad, "", false)]), Err(BridgeError::InvalidArgument(_))), "{:?}", bad);
+        }
+        assert!(matches!(s.set_environment_variables("nope", &[]), Err(BridgeError::EnvironmentNotFound(_))));
+    }
+
+    #[test]
+    fn the_active_environment_is_cleared_when_deleted() {
+        let s = Storage::open_in_memory().unwrap();
+        assert_eq!(s.active_environment().unwrap(), None);
+        let env = s.create_environment("dev").unwrap();
+        assert!(matches!(s.set_active_environment(Some("nope")), Err(BridgeError::EnvironmentNotFound(_))));
+        s.set_active_environment(Some(&env.id)).unwrap();
+        assert_eq!(s.active_environment().unwrap().as_deref(), Some(env.id.as_str()));
+        s.delete_environment(&env.id).unwrap();
+        assert_eq!(s.active_environment().unwrap(), None);
+    }
+}
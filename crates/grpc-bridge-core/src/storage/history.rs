@@ -0,0 +1,186 @@
+use super::Storage;
+use crate::error::{BridgeError, BridgeResult};
+use crate::payload::json_output::JsonOptions;
+use rusqlite::{params, params_from_iter, types::Value as SqlValue, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+const RETENTION_KEY: &str = "history.retention";
+const DEFAULT_LIMIT: u32 = 100;
+
+/// One executed call. Headers, payload and response are stored already redacted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    #[serde(default)]
+    pub id: i64,
+    /// Unix milliseconds when the call was started.
+    pub created_at: u64,
+    pub target: String,
+    pub service: String,
+    pub method: String,
+    pub headers: Vec<String>,
+    pub payload: String,
+    pub response: Option<String>,
+    /// gRPC status code name (`OK`, `NotFound`, ...) or a bridge error code.
+    pub status: String,
+    pub error: Option<String>,
+    /// `{ "headers": [[k, v]], "trailers": [[k, v]] }`
+    pub metadata: serde_json::Value,
+    pub took_ms: u64,
+    pub root_id: Option<String>,
+    pub proto_files: Vec<String>,
+    pub tls: Option<bool>,
+    /// How the response was written, so a replay writes it the same way.
+    #[serde(default)]
+    pub json_options: JsonOptions,
+}
+
+/// Filters for [`Storage::list_history`]; every field is optional and they combine with AND.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HistoryQuery {
+    /// Full text search over payload and response.
+    pub text: Option<String>,
+    pub service: Option<String>,
+    pub method: Option<String>,
+    pub status: Option<String>,
+    /// Inclusive lower bound on `created_at` (unix ms).
+    pub from: Option<u64>,
+    /// Exclusive upper bound on `created_at` (unix ms).
+    pub to: Option<u64>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+/// How much history to keep; `None` disables a bound.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    pub max_entries: Option<u32>,
+    pub max_age_days: Option<u32>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self { Self { max_entries: Some(1000), max_age_days: Some(90) } }
+}
+
+const COLUMNS: &str = "id, created_at, target, service, method, headers_json, payload, response, status, error, metadata_json, took_ms, root_id, proto_files_json, tls, json_options_json";
+
+fn from_row(r: &Row) -> rusqlite::Result<HistoryEntry> {
+    let json = |i: usize| -> rusqlite::Result<serde_json::Value> {
+        let s: String = r.get(i)?;
+        serde_json::from_str(&s).map_err(|e| rusqlite::Error::FromSqlConversionFailure(i, rusqlite::types::Type::Text, Box::new(e)))
+    };
+    let strings = |v: serde_json::Value| serde_json::from_value(v).unwrap_or_default();
+    Ok(HistoryEntry {
+        id: r.get(0)?,
+        created_at: r.get(1)?,
+        target: r.get(2)?,
+        service: r.get(3)?,
+        method: r.get(4)?,
+        headers: strings(json(5)?),
+        payload: r.get(6)?,
+        response: r.get(7)?,
+        status: r.get(8)?,
+        error: r.get(9)?,
+        metadata: json(10)?,
+        took_ms: r.get(11)?,
+        root_id: r.get(12)?,
+        proto_files: strings(json(13)?),
+        tls: r.get(14)?,
+        json_options: serde_json::from_value(json(15)?).unwrap_or_default(),
+    })
+}
+
+/// Turns free text into an FTS5 query of quoted terms, so user input is never parsed as
+/// FTS syntax (`-`, `:`, `*`, quotes ...).
+fn fts_query(text: &str) -> String {
+    text.split_whitespace().map(|t| format!("\"{}\"", t.replace('"', "\"\""))).collect::<Vec<_>>().join(" ")
+}
+
+impl Storage {
+    pub fn insert_history(&self, e: &HistoryEntry) -> BridgeResult<i64> {
+        let conn = self.conn();
+        conn.execute(
+            "INSERT INTO history (created_at, target, service, method, headers_json, payload, response, status, error, metadata_json, took_ms, root_id, proto_files_json, tls, json_options_json)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            params![
+                e.created_at, e.target, e.service, e.method,
+                serde_json::to_string(&e.headers).unwrap_or_default(),
+                e.payload, e.response, e.status, e.error,
+                e.metadata.to_string(), e.took_ms, e.root_id,
+                serde_json::to_string(&e.proto_files).unwrap_or_default(),
+                e.tls, serde_json::to_string(&e.json_options).unwrap_or_default(),
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn get_history(&self, id: i64) -> BridgeResult<HistoryEntry> {
+        self.conn()
+            .query_row(&format!("SELECT {} FROM history WHERE id = ?1", COLUMNS), params![id], from_row)
+            .optional()?
+            .ok_or(BridgeError::HistoryNotFound(id))
+    }
+
+    /// Newest first.
+    pub fn list_history(&self, q: &HistoryQuery) -> BridgeResult<Vec<HistoryEntry>> {
+        let mut sql = format!("SELECT {} FROM history WHERE 1 = 1", COLUMNS);
+        let mut args: Vec<SqlValue> = Vec::new();
+        if let Some(text) = q.text.as_deref().map(fts_query).filter(|t| !t.is_empty()) {
+            sql.push_str(" AND id IN (SELECT rowid FROM history_fts WHERE history_fts MATCH ?)");
+            args.push(text.into());
+        }
+        for (col, val) in [("service", &q.service), ("method", &q.method), ("status", &q.status)] {
+            if let Some(v) = val {
+                sql.push_str(&format!(" AND {} = ?", col));
+                args.push(v.clone().into());
+            }
+        }
+        if let Some(from) = q.from { sql.push_str(" AND created_at >= ?"); args.push((from as i64).into()); }
+        if let Some(to) = q.to { sql.push_str(" AND created_at < ?"); args.push((to as i64).into()); }
+        sql.push_str(" ORDER BY created_at DESC, id DESC LIMIT ? OFFSET ?");
+        args.push(i64::from(q.limit.unwrap_or(DEFAULT_LIMIT)).into());
+        args.push(i64::from(q.offset.unwrap_or(0)).into());
+        let conn = self.conn();
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(args), from_row)?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    pub fn delete_history(&self, ids: &[i64]) -> BridgeResult<usize> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let mut n = 0;
+        for id in ids { n += tx.execute("DELETE FROM history WHERE id = ?1", params![id])?; }
+        tx.commit()?;
+        Ok(n)
+    }
+
+    pub fn clear_history(&self) -> BridgeResult<usize> {
+        Ok(self.conn().execute("DELETE FROM history", [])?)
+    }
+
+    pub fn retention_policy(&self) -> BridgeResult<RetentionPolicy> {
+        Ok(self.get_setting(RETENTION_KEY)?.unwrap_or_default())
+    }
+
+    pub fn set_retention_policy(&self, policy: &RetentionPolicy) -> BridgeResult<()> {
+        self.set_setting(RETENTION_KEY, policy)?;
+        self.apply_retention(policy, super::now_unix_ms()).map(|_| ())
+    }
+
+    /// Deletes entries older than the age limit and beyond the count limit. Returns how many went.
+    pub fn apply_retention(&self, policy: &RetentionPolicy, now_ms: u64) -> BridgeResult<usize> {
+        let conn = self.conn();
+        let mut removed = 0;
+        if let Some(days) = policy.max_age_days {
+            let cutoff = now_ms.saturating_sub(u64::from(days) * 86_400_000);
+            removed += conn.execute("DELETE FROM history WHERE created_at < ?1", params![cutoff])?;
+        }
+        if let Some(max) = policy.max_entries {
+            removed += conn.execute(
+                "DELETE FROM history WHERE id NOT IN (SELECT id FROM history ORDER BY created_at DESC, id DESC LIMIT ?1)",
+                params![max],
+            )?;
+        }
+        Ok(removed)
+    }
+}
@@ -0,0 +1,131 @@
+use rusqlite::Connection;
+
+/// Schema migrations, applied in order. The index + 1 of the last applied entry is kept in
+/// `PRAGMA user_version`; never edit an entry once released, append a new one instead.
+const MIGRATIONS: &[&str] = &[
+    // 1: proto roots and their last scan
+    "CREATE TABLE proto_roots (
+        id TEXT PRIMARY KEY,
+        path TEXT NOT NULL,
+        last_scan INTEGER,
+        created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+    );
+    CREATE TABLE root_files (
+        root_id TEXT NOT NULL REFERENCES proto_roots(id) ON DELETE CASCADE,
+        path TEXT NOT NULL,
+        PRIMARY KEY (root_id, path)
+    );
+    CREATE TABLE root_services (
+        root_id TEXT NOT NULL REFERENCES proto_roots(id) ON DELETE CASCADE,
+        fq_service TEXT NOT NULL,
+        file TEXT NOT NULL,
+        methods_json TEXT NOT NULL
+    );
+    CREATE INDEX root_services_root ON root_services(root_id);",
+    // 2: settings and request history with full text search over payload and response
+    "CREATE TABLE settings (
+        key TEXT PRIMARY KEY,
+        value_json TEXT NOT NULL
+    );
+    CREATE TABLE history (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        created_at INTEGER NOT NULL,
+        target TEXT NOT NULL,
+        service TEXT NOT NULL,
+        method TEXT NOT NULL,
+        headers_json TEXT NOT NULL,
+        payload TEXT NOT NULL,
+        response TEXT,
+        status TEXT NOT NULL,
+        error TEXT,
+        metadata_json TEXT NOT NULL,
+        took_ms INTEGER NOT NULL,
+        root_id TEXT,
+        proto_files_json TEXT NOT NULL,
+        tls INTEGER
+    );
+    CREATE INDEX history_created ON history(created_at);
+    CREATE INDEX history_method ON history(service, method);
+    CREATE VIRTUAL TABLE history_fts USING fts5(payload, response, content='history', content_rowid='id');
+    CREATE TRIGGER history_ai AFTER INSERT ON history BEGIN
+        INSERT INTO history_fts(rowid, payload, response) VALUES (new.id, new.payload, new.response);
+    END;
+    CREATE TRIGGER history_ad AFTER DELETE ON history BEGIN
+        INSERT INTO history_fts(history_fts, rowid, payload, response) VALUES ('delete', old.id, old.payload, old.response);
+    END;",
+    // 3: saved request collections
+    "CREATE TABLE collections (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+    CREATE TABLE folders (
+        id TEXT PRIMARY KEY,
+        collection_id TEXT NOT NULL REFERENCES collections(id) ON DELETE CASCADE,
+        parent_id TEXT REFERENCES folders(id) ON DELETE CASCADE,
+        name TEXT NOT NULL
+    );
+    CREATE TABLE saved_requests (
+        id TEXT PRIMARY KEY,
+        collection_id TEXT NOT NULL REFERENCES collections(id) ON DELETE CASCADE,
+        folder_id TEXT REFERENCES folders(id) ON DELETE CASCADE,
+        name TEXT NOT NULL,
+        root_id TEXT REFERENCES proto_roots(id) ON DELETE SET NULL,
+        fq_service TEXT NOT NULL,
+        method TEXT NOT NULL,
+        target TEXT NOT NULL,
+        headers_json TEXT NOT NULL,
+        payload TEXT NOT NULL,
+        proto_files_json TEXT NOT NULL,
+        tls INTEGER,
+        created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now') * 1000),
+        updated_at INTEGER NOT NULL
+    );
+    CREATE INDEX folders_collection ON folders(collection_id);
+    CREATE INDEX saved_requests_collection ON saved_requests(collection_id);",
+    // 4: environments for {{variable}} substitution
+    "CREATE TABLE environments (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+    CREATE TABLE environment_variables (
+        environment_id TEXT NOT NULL REFERENCES environments(id) ON DELETE CASCADE,
+        key TEXT NOT NULL,
+        value TEXT NOT NULL,
+        PRIMARY KEY (environment_id, key)
+    );",
+    // 5: secret variables keep their value in the secret store, never here
+    "ALTER TABLE environment_variables ADD COLUMN secret INTEGER NOT NULL DEFAULT 0;",
+    // 6: response extraction rules on saved requests
+    "ALTER TABLE saved_requests ADD COLUMN extractions_json TEXT NOT NULL DEFAULT '[]';",
+    // 7: response assertions on saved requests
+    "ALTER TABLE saved_requests ADD COLUMN assertions_json TEXT NOT NULL DEFAULT '[]';",
+    // 8: payload and response formats of saved requests
+    "ALTER TABLE saved_requests ADD COLUMN payload_format TEXT NOT NULL DEFAULT 'json';
+    ALTER TABLE saved_requests ADD COLUMN response_format TEXT NOT NULL DEFAULT 'json';",
+    // 9: response JSON options of saved requests and of calls in history
+    "ALTER TABLE saved_requests ADD COLUMN json_options_json TEXT NOT NULL DEFAULT '{}';
+    ALTER TABLE history ADD COLUMN json_options_json TEXT NOT NULL DEFAULT '{}';",
+    // 10: run order of requests and folders among their siblings, starting from name order
+    "ALTER TABLE folders ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE saved_requests ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+    UPDATE folders SET position = (SELECT COUNT(*) FROM folders s
+        WHERE s.collection_id = folders.collection_id AND s.parent_id IS folders.parent_id
+        AND (s.name < folders.name COLLATE NOCASE OR (s.name = folders.name COLLATE NOCASE AND s.id < folders.id)));
+    UPDATE saved_requests SET position = (SELECT COUNT(*) FROM saved_requests s
+        WHERE s.collection_id = saved_requests.collection_id AND s.folder_id IS saved_requests.folder_id
+        AND (s.name < saved_requests.name COLLATE NOCASE OR (s.name = saved_requests.name COLLATE NOCASE AND s.id < saved_requests.id)));",
+];
+
+pub fn apply(conn: &mut Connection) -> rusqlite::Result<()> {
+    let current: usize = conn.pragma_query_value(None, "user_version", |r| r.get::<_, i64>(0))? as usize;
+    for (i, sql) in MIGRATIONS.iter().enumerate().skip(current) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(sql)?;
+        tx.pragma_update(None, "user_version", (i + 1) as i64)?;
+        tx.commit()?;
+        tracing::info!(version = i + 1, "applied storage migration");
+    }
+    Ok(())
+}
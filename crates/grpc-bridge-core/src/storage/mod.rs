@@ -0,0 +1,150 @@
+mod collections;
+mod environments;
+mod history;
+mod migrations;
+
+pub use collections::{Collection, CollectionTree, Folder, SavedRequest};
+pub use environments::{Environment, Variable};
+pub use history::{HistoryEntry, HistoryQuery, RetentionPolicy};
+
+use crate::error::{BridgeError, BridgeResult};
+use crate::proto_index::{ParsedService, ProtoRoot};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{de::DeserializeOwned, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const DB_FILE: &str = "grpc-bridge.sqlite3";
+
+/// SQLite backed persistence for everything that should survive a restart.
+pub struct Storage {
+    conn: Mutex<Connection>,
+}
+
+/// Files and services found by the last scan of a root.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScanResult {
+    pub files: Vec<String>,
+    pub services: Vec<ParsedService>,
+}
+
+impl Storage {
+    /// Location of the database inside the platform app data directory.
+    pub fn default_path() -> Option<PathBuf> {
+        directories::ProjectDirs::from("com", "example", "grpcbridge").map(|d| d.data_dir().join(DB_FILE))
+    }
+
+    pub fn open(path: &Path) -> BridgeResult<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| BridgeError::Storage(format!("create {}: {}", dir.display(), e)))?;
+        }
+        Self::init(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> BridgeResult<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(mut conn: Connection) -> BridgeResult<Self> {
+        conn.pragma_update(None, "foreign_keys", "ON")?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        migrations::apply(&mut conn)?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn upsert_root(&self, root: &ProtoRoot) -> BridgeResult<()> {
+        self.conn().execute(
+            "INSERT INTO proto_roots (id, path, last_scan) VALUES (?1, ?2, ?3)
+             ON CONFLICT(id) DO UPDATE SET path = excluded.path, last_scan = excluded.last_scan",
+            params![root.id, root.path, root.last_scan],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_root(&self, root_id: &str) -> BridgeResult<()> {
+        // files and services go with it (ON DELETE CASCADE)
+        self.conn().execute("DELETE FROM proto_roots WHERE id = ?1", params![root_id])?;
+        Ok(())
+    }
+
+    pub fn load_roots(&self) -> BridgeResult<Vec<ProtoRoot>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT id, path, last_scan FROM proto_roots ORDER BY created_at, id")?;
+        let rows = stmt.query_map([], |r| Ok(ProtoRoot { id: r.get(0)?, path: r.get(1)?, last_scan: r.get(2)? }))?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Replaces the stored scan result of a root and records when it was taken.
+    pub fn save_scan(&self, root_id: &str, scan: &ScanResult, scanned_at: u64) -> BridgeResult<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM root_files WHERE root_id = ?1", params![root_id])?;
+        tx.execute("DELETE FROM root_services WHERE root_id = ?1", params![root_id])?;
+        {
+            let mut ins_file = tx.prepare("INSERT INTO root_files (root_id, path) VALUES (?1, ?2)")?;
+            for f in &scan.files { ins_file.execute(params![root_id, f])?; }
+            let mut ins_svc = tx.prepare("INSERT INTO root_services (root_id, fq_service, file, methods_json) VALUES (?1, ?2, ?3, ?4)")?;
+            for s in &scan.services {
+                let methods = serde_json::to_string(&s.methods).map_err(|e| BridgeError::Storage(e.to_string()))?;
+                ins_svc.execute(params![root_id, s.fq_service, s.file, methods])?;
+            }
+        }
+        tx.execute("UPDATE proto_roots SET last_scan = ?2 WHERE id = ?1", params![root_id, scanned_at])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn get_setting<T: DeserializeOwned>(&self, key: &str) -> BridgeResult<Option<T>> {
+        let raw: Option<String> = self.conn()
+            .query_row("SELECT value_json FROM settings WHERE key = ?1", params![key], |r| r.get(0))
+            .optional()?;
+        raw.map(|s| serde_json::from_str(&s).map_err(|e| BridgeError::Storage(format!("setting {}: {}", key, e)))).transpose()
+    }
+
+    pub fn set_setting<T: Serialize>(&self, key: &str, value: &T) -> BridgeResult<()> {
+        let json = serde_json::to_string(value).map_err(|e| BridgeError::Storage(e.to_string()))?;
+        self.conn().execute(
+            "INSERT INTO settings (key, value_json) VALUES (?1, ?2) ON CONFLICT(key) DO UPDATE SET value_json = excluded.value_json",
+            params![key, json],
+        )?;
+        Ok(())
+    }
+
+    /// Last stored scan of a root, or `None` if it was never scanned.
+    pub fn load_scan(&self, root_id: &str) -> BridgeResult<Option<ScanResult>> {
+        let conn = self.conn();
+        let scanned: Option<Option<u64>> = conn
+            .query_row("SELECT last_scan FROM proto_roots WHERE id = ?1", params![root_id], |r| r.get(0))
+            .optional()?;
+        if !matches!(scanned, Some(Some(_))) { return Ok(None); }
+        let mut stmt = conn.prepare("SELECT path FROM root_files WHERE root_id = ?1 ORDER BY rowid")?;
+        let files = stmt.query_map(params![root_id], |r| r.get(0))?.collect::<Result<Vec<String>, _>>()?;
+        let mut stmt = conn.prepare("SELECT fq_service, file, methods_json FROM root_services WHERE root_id = ?1 ORDER BY rowid")?;
+        let rows = stmt.query_map(params![root_id], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?, r.get::<_, String>(2)?)))?;
+        let mut services = Vec::new();
+        for row in rows {
+            let (fq_service, file, methods_json) = row?;
+            let methods = serde_json::from_str(&methods_json).map_err(|e| BridgeError::Storage(e.to_string()))?;
+            services.push(ParsedService { fq_service, file, methods });
+        }
+        Ok(Some(ScanResult { files, services }))
+    }
+}
+
+impl From<rusqlite::Error> for BridgeError {
+    fn from(e: rusqlite::Error) -> Self { Self::Storage(e.to_string()) }
+}
+
+/// Seconds since the Unix epoch, as stored in `last_scan`.
+pub fn now_unix() -> u64 {
+    now_unix_ms() / 1000
+}
+
+/// Milliseconds since the Unix epoch, as stored in history `created_at`.
+pub fn now_unix_ms() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
@@ -0,0 +1,233 @@
+use std::fmt;
+
+/// Where a call is sent, parsed from the user supplied target string.
+///
+/// Accepted forms:
+/// - `host:port`, `host`, `[::1]:50051`, `[::1]`
+/// - `http://host[:port]`, `https://host[:port]`, `grpc://…`, `grpcs://…`
+/// - `dns:///host[:port]` (an authority between `//` and the path is accepted and ignored)
+/// - `unix:///abs/path`, `unix:/abs/path`, `unix:relative/path`
+/// - `unix-abstract:name`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub address: Address,
+    /// TLS default implied by the scheme; callers may still override it per call.
+    pub tls: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Address {
+    Tcp { host: Host, port: u16 },
+    Unix(String),
+    UnixAbstract(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Host {
+    Name(String),
+    Ipv4(std::net::Ipv4Addr),
+    Ipv6(std::net::Ipv6Addr),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TargetError {
+    #[error("target is empty")]
+    Empty,
+    #[error("target contains whitespace")]
+    Whitespace,
+    #[error("unsupported scheme {0:?}")]
+    UnsupportedScheme(String),
+    #[error("missing host")]
+    MissingHost,
+    #[error("invalid host {0:?}")]
+    InvalidHost(String),
+    #[error("IPv6 addresses must be enclosed in brackets, e.g. [{0}]:443")]
+    UnbracketedIpv6(String),
+    #[error("invalid IPv6 address {0:?}")]
+    InvalidIpv6(String),
+    #[error("invalid port {0:?}")]
+    InvalidPort(String),
+    #[error("unexpected path {0:?}; gRPC targets carry no path")]
+    UnexpectedPath(String),
+    #[error("missing unix socket path")]
+    MissingSocketPath,
+}
+
+const TLS_PORT: u16 = 443;
+const PLAINTEXT_PORT: u16 = 80;
+
+impl Target {
+    pub fn parse(input: &str) -> Result<Self, TargetError> {
+        let input = input.trim();
+        if input.is_empty() { return Err(TargetError::Empty); }
+        if input.chars().any(char::is_whitespace) { return Err(TargetError::Whitespace); }
+
+        if let Some(rest) = input.strip_prefix("unix-abstract:") {
+            let name = rest.trim_start_matches('/');
+            if name.is_empty() { return Err(TargetError::MissingSocketPath); }
+            return Ok(Target { address: Address::UnixAbstract(name.to_string()), tls: false });
+        }
+        if let Some(rest) = input.strip_prefix("unix:") {
+            // unix:///abs/path and unix:/abs/path are absolute, unix:rel/path is relative
+            let path = match rest.strip_prefix("//") { Some(p) => p, None => rest };
+            if path.is_empty() { return Err(TargetError::MissingSocketPath); }
+            return Ok(Target { address: Address::Unix(path.to_string()), tls: false });
+        }
+
+        let (tls, rest) = match input.split_once("://") {
+            Some((scheme, rest)) => match scheme.to_ascii_lowercase().as_str() {
+                "http" | "grpc" => (false, rest),
+                "https" | "grpcs" => (true, rest),
+                // dns:[//authority]/host:port — the authority names a DNS server, which grpcurl cannot use
+                "dns" => (true, rest.split_once('/').map(|(_, host)| host).unwrap_or(rest)),
+                other => return Err(TargetError::UnsupportedScheme(other.to_string())),
+            },
+            None => match input.strip_prefix("dns:") {
+                Some(rest) => (true, rest),
+                None => (true, input),
+            },
+        };
+
+        // Allow a single trailing slash (`https://host:443/`) but nothing after it
+        let (authority, path) = match rest.find('/') { Some(i) => (&rest[..i], &rest[i..]), None => (rest, "") };
+        if !path.is_empty() && path != "/" { return Err(TargetError::UnexpectedPath(path.to_string())); }
+        if authority.is_empty() { return Err(TargetError::MissingHost); }
+
+        let default_port = if tls { TLS_PORT } else { PLAINTEXT_PORT };
+        let (host, port) = split_host_port(authority)?;
+        let port = match port { Some(p) => parse_port(p)?, None => default_port };
+        Ok(Target { address: Address::Tcp { host, port }, tls })
+    }
+
+    pub fn is_unix(&self) -> bool { matches!(self.address, Address::Unix(_) | Address::UnixAbstract(_)) }
+
+    /// Address in the form grpcurl expects as its positional argument.
+    pub fn dial_address(&self) -> String {
+        match &self.address {
+            Address::Tcp { host, port } => format!("{}:{}", host, port),
+            Address::Unix(path) => path.clone(),
+            // Go's net package maps a leading '@' to the abstract namespace
+            Address::UnixAbstract(name) => format!("@{}", name),
+        }
+    }
+}
+
+fn split_host_port(authority: &str) -> Result<(Host, Option<&str>), TargetError> {
+    if let Some(rest) = authority.strip_prefix('[') {
+        let (addr, after) = rest.split_once(']').ok_or_else(|| TargetError::InvalidIpv6(authority.to_string()))?;
+        let ip = addr.parse().map_err(|_| TargetError::InvalidIpv6(addr.to_string()))?;
+        let port = match after {
+            "" => None,
+            p => Some(p.strip_prefix(':').ok_or_else(|| TargetError::InvalidPort(p.to_string()))?),
+        };
+        return Ok((Host::Ipv6(ip), port));
+    }
+    if authority.matches(':').count() > 1 {
+        return Err(TargetError::UnbracketedIpv6(authority.to_string()));
+    }
+    let (host, port) = match authority.split_once(':') { Some((h, p)) => (h, Some(p)), None => (authority, None) };
+    if host.is_empty() { return Err(TargetError::MissingHost); }
+    if let Ok(ip) = host.parse() { return Ok((Host::Ipv4(ip), port)); }
+    let valid = host.split('.').all(|label| {
+        !label.is_empty()
+            && !label.starts_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    });
+    if !valid { return Err(TargetError::InvalidHost(host.to_string())); }
+    Ok((Host::Name(host.to_ascii_lowercase()), port))
+}
+
+fn parse_port(p: &str) -> Result<u16, TargetError> {
+    match p.parse::<u16>() {
+        Ok(port) if port != 0 && p.chars().all(|c| c.is_ascii_digit()) => Ok(port),
+        _ => Err(TargetError::InvalidPort(p.to_string())),
+    }
+}
+
+impl fmt::Display for Host {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Host::Name(n) => write!(f, "{}", n),
+            Host::Ipv4(ip) => write!(f, "{}", ip),
+            Host::Ipv6(ip) => write!(f, "[{}]", ip),
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.address {
+            Address::Tcp { .. } => write!(f, "{}://{}", if self.tls { "https" } else { "http" }, self.dial_address()),
+            Address::Unix(path) => write!(f, "unix:{}", path),
+            Address::UnixAbstract(name) => write!(f, "unix-abstract:{}", name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tcp(input: &str) -> (String, u16, bool) {
+        let target = Target::parse(input).unwrap();
+        match target.address {
+            Address::Tcp { host, port } => (host.to_string(), port, target.tls),
+            other => panic!("{} parsed as {:?}", input, other),
+        }
+    }
+
+    #[test]
+    fn host_and_port_forms() {
+        assert_eq!(tcp("localhost:50051"), ("localhost".into(), 50051, true));
+        assert_eq!(tcp("  API.Example.com  "), ("api.example.com".into(), 443, true));
+        assert_eq!(tcp("10.0.0.1:8080"), ("10.0.0.1".into(), 8080, true));
+        assert_eq!(tcp("[::1]:50051"), ("[::1]".into(), 50051, true));
+        assert_eq!(tcp("[2001:db8::1]"), ("[2001:db8::1]".into(), 443, true));
+    }
+
+    #[test]
+    fn schemes_imply_tls_and_default_ports() {
+        assert_eq!(tcp("http://localhost"), ("localhost".into(), 80, false));
+        assert_eq!(tcp("grpc://localhost:9000"), ("localhost".into(), 9000, false));
+        assert_eq!(tcp("HTTPS://localhost"), ("localhost".into(), 443, true));
+        assert_eq!(tcp("grpcs://[::1]/"), ("[::1]".into(), 443, true));
+        assert_eq!(tcp("dns:///svc.internal:50051"), ("svc.internal".into(), 50051, true));
+        assert_eq!(tcp("dns://8.8.8.8/svc.internal"), ("svc.internal".into(), 443, true));
+        assert_eq!(tcp("dns:svc.internal:7"), ("svc.internal".into(), 7, true));
+        assert_eq!(Target::parse("http://[::1]:8080").unwrap().to_string(), "http://[::1]:8080");
+    }
+
+    #[test]
+    fn unix_sockets() {
+        for (input, path) in [("unix:///run/app.sock", "/run/app.sock"), ("unix:/run/app.sock", "/run/app.sock"), ("unix:run/app.sock", "run/app.sock")] {
+            let target = Target::parse(input).unwrap();
+            assert_eq!(target, Target { address: Address::Unix(path.into()), tls: false }, "{}", input);
+            assert!(target.is_unix());
+            assert_eq!(target.dial_address(), path);
+        }
+        let abstract_ = Target::parse("unix-abstract:grpc-bridge").unwrap();
+        assert_eq!(abstract_.address, Address::UnixAbstract("grpc-bridge".into()));
+        assert_eq!(abstract_.dial_address(), "@grpc-bridge");
+        assert_eq!(Target::parse("unix:"), Err(TargetError::MissingSocketPath));
+        assert_eq!(Target::parse("unix-abstract:"), Err(TargetError::MissingSocketPath));
+    }
+
+    #[test]
+    fn malformed_targets_are_rejected() {
+        assert_eq!(Target::parse("   "), Err(TargetError::Empty));
+        assert_eq!(Target::parse("local host:1"), Err(TargetError::Whitespace));
+        assert_eq!(Target::parse("ftp://host"), Err(TargetError::UnsupportedScheme("ftp".into())));
+        assert_eq!(Target::parse("http://"), Err(TargetError::MissingHost));
+        assert_eq!(Target::parse(":50051"), Err(TargetError::MissingHost));
+        assert_eq!(Target::parse("-bad.example"), Err(TargetError::InvalidHost("-bad.example".into())));
+        assert_eq!(Target::parse("::1"), Err(TargetError::UnbracketedIpv6("::1".into())));
+        assert_eq!(Target::parse("2001:db8::1:443"), Err(TargetError::UnbracketedIpv6("2001:db8::1:443".into())));
+        assert_eq!(Target::parse("[::1"), Err(TargetError::InvalidIpv6("[::1".into())));
+        assert_eq!(Target::parse("[::g]:1"), Err(TargetError::InvalidIpv6("::g".into())));
+        assert_eq!(Target::parse("[::1]50051"), Err(TargetError::InvalidPort("50051".into())));
+        assert_eq!(Target::parse("host:0"), Err(TargetError::InvalidPort("0".into())));
+        assert_eq!(Target::parse("host:65536"), Err(TargetError::InvalidPort("65536".into())));
+        assert_eq!(Target::parse("host:+1"), Err(TargetError::InvalidPort("+1".into())));
+        assert_eq!(Target::parse("https://host/pkg.Service"), Err(TargetError::UnexpectedPath("/pkg.Service".into())));
+    }
+}
@@ -0,0 +1,324 @@
+use base64::Engine;
+use rand::Rng;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Largest file `file("...")` will read.
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+const FUNCTIONS: &str = "uuid, now_rfc3339, unix_ms, random_int, base64, file";
+
+/// A problem found while rendering a template, located in the user's input.
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateIssue {
+    /// Which input the template came from: `target`, `headers[2]`, `payload`.
+    pub field: String,
+    /// 1-based line and column of the opening `{{`.
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+/// Values available to `{{name}}` placeholders.
+pub struct Context<'a> {
+    pub vars: &'a HashMap<String, String>,
+    /// Directory `file("...")` reads from; files outside it cannot be read. `None` disables `file`.
+    pub base_dir: Option<&'a Path>,
+}
+
+/// How substituted values are written into the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Verbatim.
+    Text,
+    /// JSON document: inside a string literal values are JSON-escaped, elsewhere they are
+    /// inserted verbatim so `{"n": {{count}}}` yields a number.
+    Json,
+}
+
+/// Replaces every `{{ name }}` or `{{ function(args) }}` in `input`; `\{{` is a literal `{{`.
+/// All problems are collected rather than stopping at the first one, so the UI can point at
+/// each of them.
+pub fn render(field: &str, input: &str, ctx: &Context, mode: Mode) -> Result<String, Vec<TemplateIssue>> {
+    let mut out = String::with_capacity(input.len());
+    let mut issues = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut i = 0;
+    while i < input.len() {
+        let rest = &input[i..];
+        // a backslash escaped in a JSON string is not an escape for the braces
+        if !escaped && rest.starts_with("\\{{") {
+            out.push_str("{{");
+            i += 3;
+            continue;
+        }
+        if let Some(body) = rest.strip_prefix("{{") {
+            let issue = |message: String| {
+                let (line, column) = line_col(input, i);
+                TemplateIssue { field: field.to_string(), line, column, message }
+            };
+            let Some(end) = body.find("}}") else {
+                issues.push(issue("unterminated \"{{\"".into()));
+                break;
+            };
+            let expr = body[..end].trim();
+            match eval(expr, ctx) {
+                Ok(value) if mode == Mode::Json && in_string => out.push_str(&json_escape(&value)),
+                Ok(value) => out.push_str(&value),
+                Err(message) => issues.push(issue(message)),
+            }
+            i += 2 + end + 2;
+            continue;
+        }
+        let ch = rest.chars().next().unwrap();
+        if mode == Mode::Json {
+            if in_string {
+                if escaped { escaped = false; } else if ch == '\\' { escaped = true; } else if ch == '"' { in_string = false; }
+            } else if ch == '"' {
+                in_string = true;
+            }
+        }
+        out.push(ch);
+        i += ch.len_utf8();
+    }
+    if issues.is_empty() { Ok(out) } else { Err(issues) }
+}
+
+fn eval(expr: &str, ctx: &Context) -> Result<String, String> {
+    if expr.is_empty() { return Err("empty placeholder".into()); }
+    let mut parser = Parser { src: expr, pos: 0, ctx };
+    let value = parser.expr()?;
+    parser.skip_ws();
+    if parser.pos < expr.len() { return Err(format!("unexpected {:?} in {:?}", &expr[parser.pos..], expr)); }
+    String::from_utf8(value).map_err(|_| "value is not valid UTF-8; wrap it in base64(...)".to_string())
+}
+
+/// Evaluates while parsing: `expr := string | integer | name | name "(" [expr ("," expr)*] ")"`.
+/// Values are bytes so that `base64(file("key.bin"))` works for binary files.
+struct Parser<'s, 'c> {
+    src: &'s str,
+    pos: usize,
+    ctx: &'c Context<'c>,
+}
+
+impl Parser<'_, '_> {
+    fn skip_ws(&mut self) {
+        let rest = &self.src[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_ws();
+        if self.src[self.pos..].starts_with(c) { self.pos += 1; true } else { false }
+    }
+
+    fn expr(&mut self) -> Result<Vec<u8>, String> {
+        self.skip_ws();
+        let rest = &self.src[self.pos..];
+        if rest.starts_with('"') { return self.string().map(String::into_bytes); }
+        let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')).unwrap_or(rest.len());
+        let word = &rest[..len];
+        if word.is_empty() { return Err(format!("unexpected {:?}", rest)); }
+        self.pos += len;
+        if word.parse::<i64>().is_ok() { return Ok(word.as_bytes().to_vec()); }
+        if !is_var_name(word) { return Err(format!("invalid placeholder {:?}", word)); }
+        if !self.eat('(') {
+            return self.ctx.vars.get(word).map(|v| v.clone().into_bytes()).ok_or_else(|| format!("undefined variable {:?}", word));
+        }
+        let mut args = Vec::new();
+        if !self.eat(')') {
+            loop {
+                args.push(self.expr()?);
+                if self.eat(')') { break; }
+                if !self.eat(',') { return Err(format!("expected \",\" or \")\" in call to {}", word)); }
+            }
+        }
+        call(word, args, self.ctx)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        let mut out = String::new();
+        let mut chars = self.src[self.pos..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => { self.pos += i + 1; return Ok(out); }
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => out.push('\n'),
+                    Some((_, 't')) => out.push('\t'),
+                    Some((_, c)) => out.push(c),
+                    None => break,
+                },
+                c => out.push(c),
+            }
+        }
+        Err("unterminated string literal".into())
+    }
+}
+
+fn call(name: &str, args: Vec<Vec<u8>>, ctx: &Context) -> Result<Vec<u8>, String> {
+    let arity = |n: usize| if args.len() == n { Ok(()) } else { Err(format!("{}() takes {} argument(s), got {}", name, n, args.len())) };
+    match name {
+        "uuid" => { arity(0)?; Ok(uuid::Uuid::new_v4().to_string().into_bytes()) }
+        "now_rfc3339" => { arity(0)?; Ok(rfc3339(crate::storage::now_unix_ms()).into_bytes()) }
+        "unix_ms" => { arity(0)?; Ok(crate::storage::now_unix_ms().to_string().into_bytes()) }
+        "random_int" => {
+            arity(2)?;
+            let int = |b: &[u8]| std::str::from_utf8(b).ok().and_then(|s| s.trim().parse::<i64>().ok())
+                .ok_or_else(|| format!("random_int() expects integers, got {:?}", String::from_utf8_lossy(b)));
+            let (lo, hi) = (int(&args[0])?, int(&args[1])?);
+            if lo > hi { return Err(format!("random_int({}, {}): lower bound is greater than upper bound", lo, hi)); }
+            Ok(rand::thread_rng().gen_range(lo..=hi).to_string().into_bytes())
+        }
+        "base64" => { arity(1)?; Ok(base64::engine::general_purpose::STANDARD.encode(&args[0]).into_bytes()) }
+        "file" => { arity(1)?; read_file(&String::from_utf8_lossy(&args[0]), ctx.base_dir) }
+        _ => Err(format!("unknown function {:?} (available: {})", name, FUNCTIONS)),
+    }
+}
+
+/// Reads a file relative to `base_dir`, refusing absolute paths and anything that resolves
+/// outside it (`..`, symlinks).
+fn read_file(path: &str, base_dir: Option<&Path>) -> Result<Vec<u8>, String> {
+    let base = base_dir.ok_or("file() needs a proto root to read from")?;
+    if Path::new(path).is_absolute() { return Err(format!("file({:?}): path must be relative to the proto root", path)); }
+    let base = base.canonicalize().map_err(|e| format!("file({:?}): {}", path, e))?;
+    let full = base.join(path).canonicalize().map_err(|e| format!("file({:?}): {}", path, e))?;
+    if !full.starts_with(&base) { return Err(format!("file({:?}): path is outside the proto root", path)); }
+    let size = std::fs::metadata(&full).map_err(|e| format!("file({:?}): {}", path, e))?.len();
+    if size > MAX_FILE_BYTES { return Err(format!("file({:?}): larger than {} bytes", path, MAX_FILE_BYTES)); }
+    std::fs::read(&full).map_err(|e| format!("file({:?}): {}", path, e))
+}
+
+/// `2024-05-01T12:30:00.123Z` for a unix timestamp in milliseconds.
+pub fn rfc3339(unix_ms: u64) -> String {
+    let (secs, ms) = (unix_ms / 1000, unix_ms % 1000);
+    let (days, rem) = ((secs / 86_400) as i64, secs % 86_400);
+    // civil-from-days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z", year, month, day, rem / 3600, rem % 3600 / 60, rem % 60, ms)
+}
+
+fn is_var_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+/// Contents of a JSON string literal for `value`, without the surrounding quotes.
+fn json_escape(value: &str) -> String {
+    let quoted = serde_json::to_string(value).unwrap_or_default();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+fn line_col(input: &str, offset: usize) -> (usize, usize) {
+    let before = &input[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map(|l| l.chars().count()).unwrap_or(0) + 1;
+    (line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render_with(input: &str, vars: &[(&str, &str)], base_dir: Option<&Path>, mode: Mode) -> Result<String, Vec<TemplateIssue>> {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        render("payload", input, &Context { vars: &vars, base_dir }, mode)
+    }
+
+    fn text(input: &str) -> Result<String, Vec<TemplateIssue>> {
+        render_with(input, &[], None, Mode::Text)
+    }
+
+    fn message(result: Result<String, Vec<TemplateIssue>>) -> String {
+        result.unwrap_err().remove(0).message
+    }
+
+    #[test]
+    fn uuids_are_fresh_v4_values() {
+        let (a, b) = (text("{{uuid()}}").unwrap(), text("{{ uuid( ) }}").unwrap());
+        assert_ne!(a, b);
+        assert_eq!(uuid::Uuid::parse_str(&a).unwrap().get_version_num(), 4);
+        assert_eq!(message(text("{{uuid(1)}}")), "uuid() takes 0 argument(s), got 1");
+    }
+
+    #[test]
+    fn random_ints_stay_within_their_bounds() {
+        for _ in 0..200 {
+            let n: i64 = text("{{random_int(-2, 3)}}").unwrap().parse().unwrap();
+            assert!((-2..=3).contains(&n), "{}", n);
+        }
+        assert_eq!(text("{{random_int(7, 7)}}").unwrap(), "7");
+        assert_eq!(render_with("{{random_int(lo, \"5\")}}", &[("lo", "5")], None, Mode::Text).unwrap(), "5");
+        assert!(message(text("{{random_int(3, 1)}}")).contains("lower bound is greater"));
+        assert!(message(text("{{random_int(1, \"x\")}}")).contains("expects integers"));
+    }
+
+    #[test]
+    fn base64_encodes_strings_variables_and_files() {
+        assert_eq!(text(r#"{{base64("hi")}}"#).unwrap(), "aGk=");
+        assert_eq!(render_with("{{base64(user)}}", &[("user", "ada:pw")], None, Mode::Text).unwrap(), "YWRhOnB3");
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("key.bin"), [0u8, 0xff, 0x10]).unwrap();
+        assert_eq!(render_with(r#"{{base64(file("key.bin"))}}"#, &[], Some(dir.path()), Mode::Text).unwrap(), "AP8Q");
+        assert!(message(render_with(r#"{{file("key.bin")}}"#, &[], Some(dir.path()), Mode::Text)).contains("not valid UTF-8"));
+    }
+
+    #[test]
+    fn files_are_read_only_below_the_base_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("root");
+        std::fs::create_dir_all(root.join("certs")).unwrap();
+        std::fs::write(root.join("certs/ca.pem"), "CA").unwrap();
+        std::fs::write(dir.path().join("outside.txt"), "nope").unwrap();
+        let read = |path: &str| render_with(&format!("{{{{file({:?})}}}}", path), &[], Some(&root), Mode::Text);
+
+        assert_eq!(read("certs/ca.pem").unwrap(), "CA");
+        assert_eq!(read("certs/../certs/ca.pem").unwrap(), "CA");
+        assert!(message(read("../outside.txt")).contains("outside the proto root"));
+        assert!(message(read(&dir.path().join("outside.txt").to_string_lossy())).contains("must be relative"));
+        assert!(message(read("missing.txt")).starts_with("file(\"missing.txt\")"));
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.path().join("outside.txt"), root.join("link.txt")).unwrap();
+            assert!(message(read("link.txt")).contains("outside the proto root"));
+        }
+        assert!(message(text(r#"{{file("certs/ca.pem")}}"#)).contains("needs a proto root"));
+    }
+
+    #[test]
+    fn json_mode_escapes_only_inside_strings() {
+        let vars = [("name", "Ada \"A\"\n"), ("count", "3")];
+        let out = render_with(r#"{"name": "{{name}}", "n": {{count}}, "note": "\"{{count}}\""}"#, &vars, None, Mode::Json).unwrap();
+        assert_eq!(out, r#"{"name": "Ada \"A\"\n", "n": 3, "note": "\"3\""}"#);
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&out).unwrap()["name"], "Ada \"A\"\n");
+        assert_eq!(render_with("{{name}}", &vars, None, Mode::Text).unwrap(), "Ada \"A\"\n");
+    }
+
+    #[test]
+    fn braces_can_be_escaped() {
+        assert_eq!(text(r"\{{name}} and {{ 1 }}").unwrap(), "{{name}} and 1");
+        assert_eq!(text(r#"{{"{{"}}name}}"#).unwrap(), "{{name}}");
+        let json = render_with(r#"{"t": "\{{x}}", "u": "\\{{x}}"}"#, &[("x", "v")], None, Mode::Json).unwrap();
+        assert_eq!(json, r#"{"t": "{{x}}", "u": "\\v"}"#);
+    }
+
+    #[test]
+    fn problems_are_located_and_all_reported() {
+        let issues = text("a\n  {{missing}} {{nope(}} {{").unwrap_err();
+        let found: Vec<(usize, usize, &str)> = issues.iter().map(|i| (i.line, i.column, i.message.as_str())).collect();
+        assert_eq!(found[0], (2, 3, "undefined variable \"missing\""));
+        assert_eq!((found[1].0, found[1].1), (2, 15));
+        assert_eq!(found[2], (2, 25, "unterminated \"{{\""));
+        assert!(message(text("{{ shout() }}")).starts_with("unknown function \"shout\""));
+    }
+}
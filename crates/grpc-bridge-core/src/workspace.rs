@@ -0,0 +1,226 @@
+use crate::error::BridgeResult;
+use crate::extract::ExtractionRule;
+use crate::secrets::{self, SecretStore};
+use crate::storage::{self, Environment, HistoryEntry, Storage, Variable};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{error, info};
+
+/// The user's saved data: history, collections and environments, with secret variable values
+/// kept in a [`SecretStore`] instead of the database.
+pub struct Workspace {
+    storage: Arc<Storage>,
+    secrets: Box<dyn SecretStore>,
+}
+
+/// Variables of an environment as a call sees them, secrets included.
+#[derive(Debug, Default)]
+pub struct ResolvedEnvironment {
+    /// Environment the variables came from; extracted values are written back to it.
+    pub id: Option<String>,
+    pub vars: HashMap<String, String>,
+    /// Values of the secret variables that were available, to be redacted from history and events.
+    pub secrets: Vec<String>,
+}
+
+impl Workspace {
+    pub fn new(storage: Arc<Storage>, secrets: Box<dyn SecretStore>) -> Self {
+        Self { storage, secrets }
+    }
+
+    pub fn storage(&self) -> &Storage { &self.storage }
+
+    /// Loads the requested environment, or the active one when `environment_id` is `None`.
+    /// Secret values are read from the secret store only here, at call time; a secret without
+    /// a stored value stays undefined.
+    pub fn resolve_environment(&self, environment_id: Option<String>) -> BridgeResult<ResolvedEnvironment> {
+        let id = match environment_id {
+            Some(id) => Some(id),
+            None => self.storage.active_environment()?,
+        };
+        let Some(id) = id else { return Ok(ResolvedEnvironment::default()); };
+        let env = self.storage.get_environment(&id)?;
+        let mut vars = env.to_map();
+        let mut secret_values = Vec::new();
+        for v in env.variables.iter().filter(|v| v.secret) {
+            if let Some(value) = self.secrets.get(&secrets::env_key(&id, &v.key))? {
+                secret_values.push(value.clone());
+                vars.insert(v.key.clone(), value);
+            }
+        }
+        Ok(ResolvedEnvironment { id: Some(id), vars, secrets: secret_values })
+    }
+
+    pub fn delete_environment(&self, id: &str) -> BridgeResult<()> {
+        let env = self.storage.get_environment(id)?;
+        self.storage.delete_environment(id)?;
+        for v in env.variables.iter().filter(|v| v.secret) {
+            self.secrets.delete(&secrets::env_key(id, &v.key))?;
+        }
+        Ok(())
+    }
+
+    /// Replaces all variables of an environment. Secret values go to the secret store; a secret
+    /// sent with an empty value keeps the value stored earlier. Secrets are never returned.
+    /// The variables are saved only once the secret store took every value, so a failing store
+    /// leaves the environment as it was.
+    pub fn set_environment_variables(&self, id: &str, variables: &[Variable]) -> BridgeResult<Environment> {
+        let previous = self.storage.get_environment(id)?;
+        for v in variables.iter().filter(|v| v.secret && !v.value.is_empty()) {
+            self.secrets.set(&secrets::env_key(id, &v.key), &v.value)?;
+        }
+        self.storage.set_environment_variables(id, variables)?;
+        for old in previous.variables.iter().filter(|o| o.secret) {
+            if !variables.iter().any(|v| v.secret && v.key == old.key) {
+                self.secrets.delete(&secrets::env_key(id, &old.key))?;
+            }
+        }
+        self.storage.get_environment(id)
+    }
+
+    /// Writes extracted values into the environment a call used. Values of secret variables,
+    /// and of rules marked `secret`, go to the secret store. Returns the names of the variables
+    /// written.
+    pub fn store_extracted(&self, environment_id: Option<&str>, rules: &[ExtractionRule], values: Vec<(String, String)>) -> Vec<String> {
+        if values.is_empty() { return Vec::new(); }
+        let Some(id) = environment_id else {
+            info!("no environment selected, extracted values are discarded");
+            return Vec::new();
+        };
+        let env = match self.storage.get_environment(id) {
+            Ok(env) => env,
+            Err(e) => { error!(%e, "cannot store extracted values"); return Vec::new(); }
+        };
+        let mut stored = Vec::new();
+        for (key, value) in values {
+            let declared = env.variables.iter().any(|v| v.key == key && v.secret);
+            let result = if declared || is_flagged(rules, &key) {
+                self.secrets.set(&secrets::env_key(id, &key), &value)
+                    .and_then(|()| if declared { Ok(()) } else { self.storage.set_environment_secret(id, &key) })
+            } else {
+                self.storage.set_environment_variable(id, &key, &value)
+            };
+            match result {
+                Ok(()) => stored.push(key),
+                Err(e) => error!(%e, variable = %key, "cannot store extracted value"),
+            }
+        }
+        stored
+    }
+
+    /// The extracted values [`Self::store_extracted`] keeps as secrets, to be redacted from the
+    /// response before it is shown or recorded. Rules marked `secret` count without an environment.
+    pub fn extracted_secrets(&self, environment_id: Option<&str>, rules: &[ExtractionRule], values: &[(String, String)]) -> Vec<String> {
+        let env = environment_id.and_then(|id| self.storage.get_environment(id).ok());
+        values.iter()
+            .filter(|(key, _)| is_flagged(rules, key) || env.as_ref().is_some_and(|e| e.variables.iter().any(|v| v.key == *key && v.secret)))
+            .map(|(_, value)| value.clone())
+            .collect()
+    }
+
+    /// Stores `entry` and enforces the retention policy. History is best effort: a storage
+    /// failure is logged and never fails the call itself.
+    pub fn record_history(&self, entry: &HistoryEntry) -> Option<i64> {
+        let stored = self.storage.insert_history(entry).and_then(|id| {
+            let policy = self.storage.retention_policy()?;
+            self.storage.apply_retention(&policy, storage::now_unix_ms())?;
+            Ok(id)
+        });
+        match stored {
+            Ok(id) => Some(id),
+            Err(e) => { error!(%e, "failed to record history"); None }
+        }
+    }
+}
+
+fn is_flagged(rules: &[ExtractionRule], key: &str) -> bool {
+    rules.iter().any(|r| r.variable == key && r.secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::BridgeError;
+    use crate::extract::ExtractSource;
+    use crate::secrets::MemoryStore;
+
+    fn workspace() -> Workspace {
+        Workspace::new(Arc::new(Storage::open_in_memory().unwrap()), Box::<MemoryStore>::default())
+    }
+
+    fn var(key: &str, value: &str, secret: bool) -> Variable {
+        Variable { key: key.into(), value: value.into(), secret }
+    }
+
+    #[test]
+    fn an_explicit_environment_wins_over_the_active_one() {
+        let ws = workspace();
+        assert!(ws.resolve_environment(None).unwrap().id.is_none());
+        let (dev, prod) = (ws.storage().create_environment("dev").unwrap(), ws.storage().create_environment("prod").unwrap());
+        ws.set_environment_variables(&dev.id, &[var("host", "dev.local", false)]).unwrap();
+        ws.set_environment_variables(&prod.id, &[var("host", "prod.example", false)]).unwrap();
+        ws.storage().set_active_environment(Some(&dev.id)).unwrap();
+
+        let active = ws.resolve_environment(None).unwrap();
+        assert_eq!((active.id.as_deref(), active.vars["host"].as_str()), (Some(dev.id.as_str()), "dev.local"));
+        let explicit = ws.resolve_environment(Some(prod.id.clone())).unwrap();
+        assert_eq!((explicit.id.as_deref(), explicit.vars["host"].as_str()), (Some(prod.id.as_str()), "prod.example"));
+        assert!(matches!(ws.resolve_environment(Some("nope".into())), Err(BridgeError::EnvironmentNotFound(_))));
+    }
+
+    #[test]
+    fn secrets_resolve_from_the_store_and_are_kept_when_sent_empty() {
+        let ws = workspace();
+        let env = ws.storage().create_environment("dev").unwrap();
+        ws.set_environment_variables(&env.id, &[var("token", "t1", true), var("pin", "", true)]).unwrap();
+        let resolved = ws.resolve_environment(Some(env.id.clone())).unwrap();
+        assert_eq!(resolved.vars.get("token").map(String::as_str), Some("t1"));
+        assert!(!resolved.vars.contains_key("pin"), "a secret without a value stays undefined");
+        assert_eq!(resolved.secrets, ["t1"]);
+
+        // an empty value keeps the stored secret; dropping the variable deletes it
+        ws.set_environment_variables(&env.id, &[var("token", "", true)]).unwrap();
+        assert_eq!(ws.resolve_environment(Some(env.id.clone())).unwrap().vars["token"], "t1");
+        ws.set_environment_variables(&env.id, &[var("token", "plain", false)]).unwrap();
+        ws.set_environment_variables(&env.id, &[var("token", "", true)]).unwrap();
+        assert!(!ws.resolve_environment(Some(env.id.clone())).unwrap().vars.contains_key("token"));
+    }
+
+    #[test]
+    fn a_failing_secret_store_leaves_the_environment_unchanged() {
+        struct Broken;
+        impl SecretStore for Broken {
+            fn name(&self) -> &'static str { "broken" }
+            fn get(&self, _: &str) -> BridgeResult<Option<String>> { Ok(None) }
+            fn set(&self, _: &str, _: &str) -> BridgeResult<()> { Err(BridgeError::Secret("locked".into())) }
+            fn delete(&self, _: &str) -> BridgeResult<()> { Ok(()) }
+        }
+        let ws = Workspace::new(Arc::new(Storage::open_in_memory().unwrap()), Box::new(Broken));
+        let env = ws.storage().create_environment("dev").unwrap();
+        ws.set_environment_variables(&env.id, &[var("token", "plain", false)]).unwrap();
+        assert!(matches!(ws.set_environment_variables(&env.id, &[var("token", "t1", true)]), Err(BridgeError::Secret(_))));
+        let stored = ws.storage().get_environment(&env.id).unwrap();
+        assert_eq!(stored.variables.iter().map(|v| (v.value.as_str(), v.secret)).collect::<Vec<_>>(), [("plain", false)]);
+    }
+
+    #[test]
+    fn extracted_values_of_secret_rules_stay_out_of_the_database() {
+        let ws = workspace();
+        let env = ws.storage().create_environment("dev").unwrap();
+        ws.set_environment_variables(&env.id, &[var("declared", "", true), var("session", "plain", false)]).unwrap();
+        let rule = |variable: &str, secret: bool| ExtractionRule { variable: variable.into(), source: ExtractSource::Header { key: "x".into() }, secret };
+        let rules = [rule("token", true), rule("session", true), rule("declared", false), rule("request_id", false)];
+        let values: Vec<(String, String)> = [("token", "t1"), ("session", "s1"), ("declared", "d1"), ("request_id", "r1")]
+            .iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+
+        assert!(ws.store_extracted(None, &rules, values.clone()).is_empty());
+        assert_eq!(ws.store_extracted(Some(&env.id), &rules, values), ["token", "session", "declared", "request_id"]);
+        let stored = ws.storage().get_environment(&env.id).unwrap();
+        let row = |key: &str| stored.variables.iter().find(|v| v.key == key).map(|v| (v.value.as_str(), v.secret)).unwrap();
+        assert_eq!([row("token"), row("session"), row("declared"), row("request_id")], [("", true), ("", true), ("", true), ("r1", false)]);
+
+        let resolved = ws.resolve_environment(Some(env.id.clone())).unwrap();
+        assert_eq!(["token", "session", "declared"].map(|k| resolved.vars[k].as_str()), ["t1", "s1", "d1"]);
+        assert_eq!(resolved.secrets.len(), 3);
+    }
+}
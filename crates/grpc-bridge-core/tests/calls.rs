@@ -0,0 +1,570 @@
+#![cfg(unix)]
+mod support;
+
+use grpc_bridge_core::error::CallFailureKind;
+use grpc_bridge_core::extract::{ExtractSource, ExtractionRule};
+use grpc_bridge_core::fuzz::FuzzParams;
+use grpc_bridge_core::grpcurl::GrpcurlConfig;
+use grpc_bridge_core::payload::format::PayloadFormat;
+use grpc_bridge_core::payload::json_output::{Int64Format, JsonOptions};
+use grpc_bridge_core::redact::RedactionConfig;
+use grpc_bridge_core::secrets::MemoryStore;
+use grpc_bridge_core::storage::{HistoryQuery, SavedRequest, Storage, Variable};
+use grpc_bridge_core::{Bridge, BridgeError, RunParams};
+use serde_json::json;
+use support::{Reply, StandIn};
+
+fn params(target: &str, payload: &str) -> RunParams {
+    RunParams {
+        target: target.to_string(),
+        service: "demo.v1.Greeter".into(),
+        method: "Hello".into(),
+        payload: payload.into(),
+        proto_files: Vec::new(),
+        root_id: None,
+        headers: None,
+        tls: None,
+        environment_id: None,
+        extractions: Vec::new(),
+        validate: false,
+        payload_format: PayloadFormat::Json,
+        response_format: PayloadFormat::Json,
+        json_options: JsonOptions::default(),
+    }
+}
+
+fn var(key: &str, value: &str, secret: bool) -> Variable {
+    Variable { key: key.into(), value: value.into(), secret }
+}
+
+#[tokio::test]
+async fn unary_call_reaches_the_server_and_is_recorded() {
+    let server = StandIn::start(|req| Reply::message(json!({ "message": format!("hello {}", req.json()["name"].as_str().unwrap()) }))).await;
+    let dir = tempfile::tempdir().unwrap();
+    let bridge = support::bridge(dir.path());
+    let mut p = params(&format!("http://{}", server.addr), r#"{"name":"Ada"}"#);
+    p.headers = Some(vec!["x-trace: 1".into()]);
+
+    let done = bridge.call(p).await.unwrap();
+    let outcome = done.result.as_ref().unwrap();
+    assert!(outcome.is_ok());
+    assert_eq!(outcome.status, "OK");
+    assert_eq!(done.parsed, Some(json!({ "message": "hello Ada" })));
+    assert_eq!(done.headers, [("x-request-id".to_string(), "req-1".to_string())]);
+    assert_eq!(done.trailers, [("x-served-by".to_string(), "stand-in".to_string())]);
+
+    let seen = server.requests();
+    assert_eq!(seen.len(), 1);
+    assert_eq!(seen[0].method(), "demo.v1.Greeter/Hello");
+    assert_eq!(seen[0].headers(), ["x-trace: 1"]);
+    assert!(seen[0].args.contains(&"-plaintext".to_string()));
+
+    let entry = bridge.storage().get_history(done.history_id.unwrap()).unwrap();
+    assert_eq!(entry.status, "OK");
+    assert_eq!(entry.method, "Hello");
+    assert!(entry.response.unwrap().contains("hello Ada"));
+}
+
+#[tokio::test]
+async fn unix_socket_targets_dial_the_socket_in_plaintext() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("grpc.sock");
+    let server = StandIn::start_unix(&socket, |req| Reply::message(json!({ "message": format!("hello {}", req.json()["name"].as_str().unwrap()) }))).await;
+    let bridge = support::bridge(dir.path());
+
+    let done = bridge.call(params(&format!("unix://{}", server.addr), r#"{"name":"Ada"}"#)).await.unwrap();
+    assert!(done.result.as_ref().unwrap().is_ok());
+    assert_eq!(done.parsed, Some(json!({ "message": "hello Ada" })));
+    let seen = &server.requests()[0];
+    assert!(seen.args.contains(&"-unix".to_string()));
+    assert!(seen.args.contains(&"-plaintext".to_string()));
+    assert_eq!(seen.args[seen.args.len() - 2], server.addr);
+}
+
+#[tokio::test]
+async fn environment_values_are_substituted_and_secrets_redacted() {
+    let server = StandIn::start(|req| Reply::message(json!({ "echo": req.headers().join(",") }))).await;
+    let dir = tempfile::tempdir().unwrap();
+    let bridge = support::bridge(dir.path());
+    let env = bridge.storage().create_environment("staging").unwrap();
+    bridge.workspace().set_environment_variables(&env.id, &[var("addr", &server.addr, false), var("name", "Ada \"A\"", false), var("token", "s3cr3t", true)]).unwrap();
+    bridge.storage().set_active_environment(Some(&env.id)).unwrap();
+    let mut p = params("{{addr}}", r#"{"name":"{{name}}"}"#);
+    p.headers = Some(vec!["authorization: Bearer {{token}}".into()]);
+
+    let done = bridge.call(p).await.unwrap();
+    assert!(done.result.as_ref().unwrap().is_ok());
+    let seen = &server.requests()[0];
+    assert_eq!(seen.json(), json!({ "name": "Ada \"A\"" }));
+    assert_eq!(seen.headers(), ["authorization: Bearer s3cr3t"]);
+
+    // the server echoed the secret back; neither the event data nor history may contain it
+    assert!(!done.raw.contains("s3cr3t"));
+    let entry = bridge.storage().get_history(done.history_id.unwrap()).unwrap();
+    assert!(!serde_json::to_string(&entry).unwrap().contains("s3cr3t"));
+    assert!(bridge.storage().get_environment(&env.id).unwrap().variables.iter().all(|v| v.value != "s3cr3t"));
+}
+
+#[tokio::test]
+async fn responses_that_are_not_json_are_redacted_too() {
+    let server = StandIn::start(|req| Reply::text(&format!(
+        "Response headers received:\n(empty)\n\nResponse contents:\necho: \"{}\"\nx-api-key: k-123\n\nResponse trailers received:\n(empty)\nSent 1 request and received 1 response\n",
+        req.headers().join(","),
+    ))).await;
+    let dir = tempfile::tempdir().unwrap();
+    let bridge = support::bridge(dir.path());
+    let env = bridge.storage().create_environment("staging").unwrap();
+    bridge.workspace().set_environment_variables(&env.id, &[var("token", "s3cr3t", true)]).unwrap();
+    let mut p = params(&server.addr, "{}");
+    p.headers = Some(vec!["authorization: Bearer {{token}}".into()]);
+    p.environment_id = Some(env.id);
+
+    let done = bridge.call(p).await.unwrap();
+    assert_eq!(done.parsed, None);
+    assert_eq!(done.raw, "echo: \"authorization: Bearer <redacted>\"\nx-api-key: <redacted>");
+    let entry = bridge.storage().get_history(done.history_id.unwrap()).unwrap();
+    assert_eq!(entry.response.as_deref(), Some(done.raw.as_str()));
+}
+
+#[tokio::test]
+async fn secret_extractions_are_redacted_before_the_response_is_recorded() {
+    let server = StandIn::start(|_| Reply::message(json!({ "session": { "token": "t-42" }, "user": "ada" }))).await;
+    let dir = tempfile::tempdir().unwrap();
+    let bridge = support::bridge(dir.path());
+    let env = bridge.storage().create_environment("ci").unwrap();
+    let mut p = params(&server.addr, "{}");
+    p.environment_id = Some(env.id.clone());
+    p.extractions = vec![ExtractionRule { variable: "token".into(), source: ExtractSource::Body { path: "$.session.token".into() }, secret: true }];
+
+    let done = bridge.call(p).await.unwrap();
+    assert_eq!(done.extracted, ["token"]);
+    assert!(!done.raw.contains("t-42") && done.raw.contains("ada"));
+    assert_eq!(done.parsed.unwrap()["session"]["token"], "<redacted>");
+    let entry = bridge.storage().get_history(done.history_id.unwrap()).unwrap();
+    assert!(!entry.response.unwrap().contains("t-42"));
+    assert!(bridge.storage().list_history(&HistoryQuery { text: Some("t-42".into()), ..Default::default() }).unwrap().is_empty());
+    assert_eq!(bridge.workspace().resolve_environment(Some(env.id)).unwrap().vars["token"], "t-42");
+}
+
+#[tokio::test]
+async fn redaction_settings_survive_a_restart() {
+    let server = StandIn::start(|_| Reply::message(json!({ "pin": "4321", "note": "ok" }))).await;
+    let dir = tempfile::tempdir().unwrap();
+    let config = RedactionConfig { header_patterns: vec!["x-session".into()], json_paths: vec!["pin".into()] };
+    support::bridge(dir.path()).set_redaction_config(config).unwrap();
+
+    let bridge = support::bridge(dir.path());
+    assert_eq!(bridge.engine().redaction_config().json_paths, ["pin"]);
+    let mut p = params(&server.addr, "{}");
+    p.headers = Some(vec!["x-session: abc".into()]);
+    let done = bridge.call(p).await.unwrap();
+    let entry = serde_json::to_string(&bridge.storage().get_history(done.history_id.unwrap()).unwrap()).unwrap();
+    assert!(!entry.contains("4321") && !entry.contains("abc"));
+    assert!(entry.contains("ok"));
+}
+
+#[test]
+fn grpcurl_settings_survive_a_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = GrpcurlConfig { path: Some("/opt/grpcurl".into()), expected_sha256: Some("ab".repeat(32)) };
+    support::bridge(dir.path()).set_grpcurl_config(config).unwrap();
+
+    let storage = Storage::open(&dir.path().join("bridge.sqlite3")).unwrap();
+    let bridge = Bridge::load(storage, Box::<MemoryStore>::default(), None).unwrap();
+    let loaded = bridge.engine().grpcurl_config();
+    assert_eq!((loaded.path.as_deref(), loaded.expected_sha256), (Some("/opt/grpcurl"), Some("ab".repeat(32))));
+}
+
+#[tokio::test]
+async fn replaying_history_asks_for_redacted_values() {
+    let server = StandIn::start(|_| Reply::message(json!({}))).await;
+    let dir = tempfile::tempdir().unwrap();
+    let bridge = support::bridge(dir.path());
+    let mut p = params(&server.addr, r#"{"user":{"name":"ada","password":"pw"},"tags":["a"]}"#);
+    p.headers = Some(vec!["authorization: Bearer t".into(), "x-trace: 1".into()]);
+    let id = bridge.call(p).await.unwrap().history_id.unwrap();
+
+    match bridge.replay_params(id, Vec::new(), Default::default()) {
+        Err(BridgeError::RedactedPayload(fields)) => assert_eq!(fields, ["user.password"]),
+        other => panic!("expected RedactedPayload, got {:?}", other.map(|p| p.payload)),
+    }
+    let values = json!({ "user.password": "pw2" }).as_object().unwrap().clone();
+    let replay = bridge.replay_params(id, vec!["authorization: Bearer t2".into()], values).unwrap();
+    bridge.call(replay).await.unwrap();
+    let seen = &server.requests()[1];
+    assert_eq!(seen.json(), json!({ "user": { "name": "ada", "password": "pw2" }, "tags": ["a"] }));
+    assert_eq!(seen.headers(), ["x-trace: 1", "authorization: Bearer t2"]);
+
+    let unknown = json!({ "user.password": "pw2", "user.pin": 1 }).as_object().unwrap().clone();
+    assert!(matches!(bridge.replay_params(id, Vec::new(), unknown), Err(BridgeError::InvalidArgument(_))));
+    assert!(matches!(bridge.replay_params(id + 100, Vec::new(), Default::default()), Err(BridgeError::HistoryNotFound(_))));
+}
+
+#[tokio::test]
+async fn undefined_variables_fail_before_anything_is_sent() {
+    let server = StandIn::start(|_| Reply::message(json!({}))).await;
+    let dir = tempfile::tempdir().unwrap();
+    let bridge = support::bridge(dir.path());
+
+    let err = bridge.call(params(&server.addr, r#"{"id":"{{missing}}"}"#)).await.err().unwrap();
+    assert!(matches!(err, BridgeError::Template(ref issues) if issues.len() == 1));
+    assert!(server.requests().is_empty());
+    assert!(bridge.storage().list_history(&HistoryQuery::default()).unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn non_ok_status_is_reported_and_recorded() {
+    let server = StandIn::start(|_| Reply::status("NotFound", "no such user")).await;
+    let dir = tempfile::tempdir().unwrap();
+    let bridge = support::bridge(dir.path());
+
+    let done = bridge.call(params(&server.addr, "{}")).await.unwrap();
+    let outcome = done.result.as_ref().unwrap();
+    assert_eq!(outcome.status, "NotFound");
+    assert!(matches!(outcome.error(), Some(BridgeError::CallFailed { .. })));
+    assert_eq!(bridge.storage().get_history(done.history_id.unwrap()).unwrap().status, "NotFound");
+}
+
+#[tokio::test]
+async fn unreachable_server_is_a_dial_failure() {
+    let dir = tempfile::tempdir().unwrap();
+    let bridge = support::bridge(dir.path());
+
+    let done = bridge.call(params(&support::closed_addr().await, "{}")).await.unwrap();
+    match done.result.as_ref().unwrap().error() {
+        Some(BridgeError::CallFailed { kind, .. }) => assert_eq!(kind, CallFailureKind::DialFailure),
+        other => panic!("expected a dial failure, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn only_one_request_runs_at_a_time() {
+    let server = StandIn::start(|_| Reply::message(json!({}))).await;
+    let dir = tempfile::tempdir().unwrap();
+    let bridge = support::bridge(dir.path());
+
+    let busy = bridge.engine().acquire().unwrap();
+    assert!(matches!(bridge.call(params(&server.addr, "{}")).await, Err(BridgeError::RequestAlreadyRunning)));
+    assert!(matches!(bridge.engine().acquire(), Err(BridgeError::RequestAlreadyRunning)));
+    drop(busy);
+    assert!(bridge.call(params(&server.addr, "{}")).await.is_ok());
+}
+
+#[tokio::test]
+async fn collection_run_passes_extracted_values_and_checks_assertions() {
+    let server = StandIn::start(|req| match req.method() {
+        "demo.v1.Auth/Login" => Reply::message(json!({ "session": { "token": "t-42" } })),
+        _ if req.headers() == ["authorization: Bearer t-42"] => Reply::message(json!({ "user": { "id": "7", "email": "ada@example.com" } })),
+        _ => Reply::status("Unauthenticated", "missing token"),
+    }).await;
+    let dir = tempfile::tempdir().unwrap();
+    let bridge = support::bridge(dir.path());
+    let env = bridge.storage().create_environment("ci").unwrap();
+    bridge.workspace().set_environment_variables(&env.id, &[var("addr", &server.addr, false)]).unwrap();
+    let collection = bridge.storage().create_collection("smoke").unwrap();
+    let request = |name: &str, service: &str, method: &str, extra: serde_json::Value| -> SavedRequest {
+        let mut req = json!({
+            "id": "", "collection_id": collection.id, "folder_id": null, "name": name, "root_id": null,
+            "fq_service": service, "method": method, "target": "{{addr}}", "tls": null,
+        });
+        req.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        bridge.storage().save_request(&serde_json::from_value(req).unwrap()).unwrap()
+    };
+    request("1 login", "demo.v1.Auth", "Login", json!({
+        "extractions": [{ "variable": "token", "from": "body", "path": "$.session.token" }],
+    }));
+    request("2 profile", "demo.v1.Users", "Me", json!({
+        "headers": ["authorization: Bearer {{token}}"],
+        "assertions": [
+            { "kind": "status", "code": "OK" },
+            { "kind": "json_equals", "path": "$.user.id", "value": 7 },
+            { "kind": "json_matches", "path": "$.user.email", "pattern": "@example\\.com$" },
+            { "kind": "metadata_present", "key": "x-request-id" }
+        ],
+    }));
+    request("3 anonymous", "demo.v1.Users", "Me", json!({}));
+    request("4 wrong id", "demo.v1.Users", "Me", json!({
+        "headers": ["authorization: Bearer {{token}}"],
+        "assertions": [{ "kind": "json_equals", "path": "$.user.id", "value": 8 }],
+    }));
+
+    let mut streamed = Vec::new();
+    let summary = bridge.run_collection(&collection.id, None, Some(env.id.clone()), |step| streamed.push(step.name.clone())).await.unwrap();
+    assert_eq!(streamed, ["1 login", "2 profile", "3 anonymous", "4 wrong id"]);
+    assert_eq!((summary.total, summary.passed, summary.failed), (4, 2, 2));
+    assert_eq!(summary.steps[0].extracted, ["token"]);
+    assert!(summary.steps[1].assertions.iter().all(|a| a.passed));
+    // a non-OK status without a status assertion fails the step as an error
+    assert_eq!(summary.steps[2].status, "Unauthenticated");
+    assert!(summary.steps[2].error.is_some());
+    assert!(!summary.steps[3].assertions[0].passed);
+    let junit = summary.to_junit();
+    assert!(junit.contains("<error") && junit.contains("<failure"));
+    let env = bridge.storage().get_environment(&env.id).unwrap();
+    assert!(env.variables.iter().any(|v| v.key == "token" && v.value == "t-42"));
+}
+
+#[tokio::test]
+async fn reflection_lists_and_describes() {
+    let server = StandIn::start(|req| match req.method() {
+        "list" => Reply::text("demo.v1.Greeter\ngrpc.reflection.v1alpha.ServerReflection\n"),
+        symbol => Reply::text(&format!("{} is a service:\nservice Greeter {{}}\n", symbol)),
+    }).await;
+    let dir = tempfile::tempdir().unwrap();
+    let bridge = support::bridge(dir.path());
+
+    let services = bridge.reflect(&server.addr, None, &[], None, None).await.unwrap();
+    assert_eq!(services.lines().collect::<Vec<_>>(), ["demo.v1.Greeter", "grpc.reflection.v1alpha.ServerReflection"]);
+    let described = bridge.reflect(&server.addr, None, &[], None, Some("demo.v1.Greeter")).await.unwrap();
+    assert!(described.starts_with("demo.v1.Greeter is a service"));
+    assert_eq!(server.requests()[1].args[server.requests()[1].args.len() - 2], "describe");
+
+    let err = bridge.reflect(&server.addr, None, &[], None, Some("-plaintext")).await.err().unwrap();
+    assert!(matches!(err, BridgeError::InvalidArgument(_)));
+}
+
+#[tokio::test]
+async fn validation_stops_bad_payloads_before_the_network() {
+    let server = StandIn::start(|_| Reply::message(json!({ "message": "hi" }))).await;
+    let dir = tempfile::tempdir().unwrap();
+    support::write_protos(dir.path(), &[("greeter.proto", "syntax = \"proto3\";\npackage demo.v1;\nservice Greeter { rpc Hello (HelloRequest) returns (HelloReply); }\nmessage HelloRequest { string name = 1; }\nmessage HelloReply { string message = 1; }\n")]);
+    let bridge = support::bridge(dir.path());
+    let root = bridge.index().register_root(&dir.path().to_string_lossy()).unwrap();
+    bridge.index().scan_root(&root).await.unwrap();
+
+    let mut p = params(&format!("http://{}", server.addr), r#"{"nmae":"Ada"}"#);
+    p.validate = true;
+    match bridge.call(p.clone()).await {
+        Err(BridgeError::InvalidPayload(issues)) => assert_eq!(issues[0].path, "/nmae"),
+        other => panic!("expected invalid_payload, got {:?}", other.map(|d| d.raw)),
+    }
+    assert!(server.requests().is_empty());
+
+    p.payload = r#"{"name":"Ada"}"#.into();
+    assert!(bridge.call(p).await.unwrap().result.unwrap().is_ok());
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn fuzzing_saves_payloads_that_break_the_server() {
+    fn depth(v: &serde_json::Value) -> usize {
+        match v {
+            serde_json::Value::Object(m) => 1 + m.values().map(depth).max().unwrap_or(0),
+            serde_json::Value::Array(a) => 1 + a.iter().map(depth).max().unwrap_or(0),
+            _ => 0,
+        }
+    }
+    let server = StandIn::start(|req| {
+        let body = req.json();
+        if body["label"].as_str().is_some_and(|l| l.len() > 1000) { return Reply::status("Internal", "index out of range"); }
+        if depth(&body) > 50 { return Reply::status("Unknown", "stack overflow"); }
+        if body["label"].as_str().is_none_or(str::is_empty) { return Reply::status("InvalidArgument", "label is required"); }
+        Reply::message(json!({}))
+    }).await;
+    let dir = tempfile::tempdir().unwrap();
+    support::write_protos(dir.path(), &[("tree.proto", "syntax = \"proto3\";\npackage demo.v1;\nimport \"buf/validate/validate.proto\";\nservice Trees { rpc Plant (Node) returns (Node); }\nmessage Node {\n  string label = 1 [(buf.validate.field).required = true];\n  int32 weight = 2;\n  bytes blob = 3;\n  repeated Node children = 4;\n}\n")]);
+    let bridge = support::bridge(dir.path());
+    let root = bridge.index().register_root(&dir.path().to_string_lossy()).unwrap();
+    bridge.index().scan_root(&root).await.unwrap();
+
+    let params = FuzzParams {
+        target: format!("http://{}", server.addr),
+        service: "demo.v1.Trees".into(),
+        method: "Plant".into(),
+        proto_files: Vec::new(),
+        root_id: Some(root),
+        headers: None,
+        tls: None,
+        environment_id: None,
+        seed: Some(7),
+        max_cases: None,
+        collection_id: None,
+    };
+    let mut cases = Vec::new();
+    let report = bridge.fuzz(params.clone(), |case| cases.push(case.mutation.clone())).await.unwrap();
+    assert_eq!(report.cases, server.requests().len());
+    for expected in ["missing required field /label", "empty required field /label", "max int32 at /weight", "invalid UTF-8 bytes at /blob", "10000 items at /children", "100 levels of nesting"] {
+        assert!(cases.iter().any(|c| c == expected), "{} not in {:?}", expected, cases);
+    }
+    let found: Vec<(&str, &str)> = report.findings.iter().map(|f| (f.mutation.as_str(), f.status.as_str())).collect();
+    assert_eq!(found, [("1 MiB string at /label", "Internal"), ("100 levels of nesting", "Unknown")]);
+    assert_eq!(report.statuses["InvalidArgument"], 3);
+    assert!(bridge.storage().list_history(&HistoryQuery::default()).unwrap().is_empty());
+
+    let tree = bridge.storage().list_collections().unwrap().into_iter()
+        .find(|t| Some(&t.collection.id) == report.collection_id.as_ref())
+        .unwrap();
+    assert_eq!(tree.collection.name, "Fuzz findings: demo.v1.Trees/Plant");
+    let saved = bridge.storage().get_saved_request(report.findings[0].saved_request_id.as_deref().unwrap()).unwrap();
+    assert_eq!(saved.name, "1 MiB string at /label (Internal)");
+    assert_eq!(serde_json::from_str::<serde_json::Value>(&saved.payload).unwrap()["label"].as_str().unwrap().len(), 1 << 20);
+    assert_eq!(tree.requests.len(), 2);
+
+    // the same seed sends the same payloads
+    let again = bridge.fuzz(FuzzParams { max_cases: Some(3), ..params }, |_| {}).await.unwrap();
+    assert_eq!(again.cases, 3);
+    let sent = server.requests();
+    assert_eq!(sent[report.cases].json(), sent[0].json());
+    assert_eq!(sent[report.cases + 2].json(), sent[2].json());
+}
+
+#[tokio::test]
+async fn text_payloads_are_sent_as_json_and_responses_rendered_as_requested() {
+    let server = StandIn::start(|req| Reply::message(json!({ "message": format!("hello {}", req.json()["name"].as_str().unwrap()), "count": "3" }))).await;
+    let dir = tempfile::tempdir().unwrap();
+    support::write_protos(dir.path(), &[("greeter.proto", "syntax = \"proto3\";\npackage demo.v1;\nservice Greeter { rpc Hello (HelloRequest) returns (HelloReply); }\nmessage HelloRequest { string name = 1; }\nmessage HelloReply { string message = 1; int64 count = 2; }\n")]);
+    let bridge = support::bridge(dir.path());
+    let root = bridge.index().register_root(&dir.path().to_string_lossy()).unwrap();
+    bridge.index().scan_root(&root).await.unwrap();
+
+    let mut p = params(&format!("http://{}", server.addr), "name: \"Ada\"");
+    p.root_id = Some(root.clone());
+    p.payload_format = PayloadFormat::Text;
+    p.response_format = PayloadFormat::Text;
+    let done = bridge.call(p).await.unwrap();
+    assert_eq!(server.requests()[0].json(), json!({ "name": "Ada" }));
+    assert_eq!(done.parsed, Some(json!({ "message": "hello Ada", "count": "3" })));
+    assert_eq!(done.rendered.as_deref(), Some("message: \"hello Ada\"\ncount: 3\n"));
+
+    let mut p = params(&format!("http://{}", server.addr), "0a03426f62");
+    p.root_id = Some(root.clone());
+    p.payload_format = PayloadFormat::Hex;
+    p.response_format = PayloadFormat::Hex;
+    let done = bridge.call(p).await.unwrap();
+    assert_eq!(server.requests()[1].json(), json!({ "name": "Bob" }));
+    assert_eq!(done.rendered.as_deref(), Some("0a0968656c6c6f20426f621003"));
+
+    let mut p = params(&format!("http://{}", server.addr), "name: 1");
+    p.root_id = Some(root);
+    p.payload_format = PayloadFormat::Text;
+    let err = bridge.call(p).await.err().unwrap();
+    assert!(matches!(err, BridgeError::PayloadFormat { ref format, .. } if format == "text"), "{:?}", err);
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn json_options_reshape_the_parsed_response() {
+    let server = StandIn::start(|_| Reply::message(json!({ "message": "hi", "bigId": "9007199254740993", "smallId": "42", "mood": "MOOD_HAPPY" }))).await;
+    let dir = tempfile::tempdir().unwrap();
+    support::write_protos(dir.path(), &[("greeter.proto", "syntax = \"proto3\";\npackage demo.v1;\nservice Greeter { rpc Hello (HelloRequest) returns (HelloReply); }\nmessage HelloRequest { string name = 1; }\nenum Mood { MOOD_UNSPECIFIED = 0; MOOD_HAPPY = 1; }\nmessage HelloReply {\n  string message = 1;\n  int64 big_id = 2;\n  uint64 small_id = 3;\n  Mood mood = 4;\n  int32 retry_count = 5;\n  repeated string tags = 6;\n  optional string note = 7;\n}\n")]);
+    let bridge = support::bridge(dir.path());
+    let root = bridge.index().register_root(&dir.path().to_string_lossy()).unwrap();
+    bridge.index().scan_root(&root).await.unwrap();
+    let target = format!("http://{}", server.addr);
+
+    let mut p = params(&target, "{}");
+    p.root_id = Some(root.clone());
+    let options = JsonOptions { emit_defaults: true, proto_names: true, enums_as_numbers: true, int64: Int64Format::Lossless };
+    p.json_options = options;
+    let done = bridge.call(p).await.unwrap();
+    let parsed = done.parsed.unwrap();
+    assert_eq!(parsed, json!({ "message": "hi", "big_id": "9007199254740993", "small_id": 42, "mood": 1, "retry_count": 0, "tags": [] }));
+    assert_eq!(parsed.as_object().unwrap().keys().collect::<Vec<_>>(), ["message", "big_id", "small_id", "mood", "retry_count", "tags"]);
+    assert!(done.raw.contains("\"small_id\": 42"));
+    assert_eq!(done.render_error, None);
+    // replays and saved requests write the response the same way
+    assert_eq!(bridge.replay_params(done.history_id.unwrap(), Vec::new(), Default::default()).unwrap().json_options, options);
+    let collection = bridge.storage().create_collection("greeter").unwrap();
+    let saved = bridge.storage().save_request(&SavedRequest {
+        id: String::new(),
+        collection_id: collection.id,
+        folder_id: None,
+        name: "hello".into(),
+        root_id: Some(root.clone()),
+        fq_service: "demo.v1.Greeter".into(),
+        method: "Hello".into(),
+        target: target.clone(),
+        headers: Vec::new(),
+        payload: "{}".into(),
+        proto_files: Vec::new(),
+        tls: None,
+        extractions: Vec::new(),
+        assertions: Vec::new(),
+        payload_format: PayloadFormat::Json,
+        response_format: PayloadFormat::Json,
+        json_options: options,
+    }).unwrap();
+    let saved = bridge.storage().get_saved_request(&saved.id).unwrap();
+    assert_eq!(RunParams::from(saved).json_options, options);
+
+    let mut p = params(&target, "{}");
+    p.root_id = Some(root);
+    p.json_options.int64 = Int64Format::Number;
+    let parsed = bridge.call(p).await.unwrap().parsed.unwrap();
+    assert_eq!(parsed, json!({ "message": "hi", "bigId": 9007199254740993u64, "smallId": 42, "mood": "MOOD_HAPPY" }));
+
+    let done = bridge.call(params(&target, "{}")).await.unwrap();
+    assert_eq!(done.parsed, Some(json!({ "message": "hi", "bigId": "9007199254740993", "smallId": "42", "mood": "MOOD_HAPPY" })));
+}
+
+#[tokio::test]
+async fn fuzzing_stops_without_a_finding_once_the_server_is_gone() {
+    let server = StandIn::start(|req| if req.json()["weight"] == i32::MAX { Reply::crash() } else { Reply::message(json!({})) }).await;
+    let dir = tempfile::tempdir().unwrap();
+    support::write_protos(dir.path(), &[("tree.proto", "syntax = \"proto3\";\npackage demo.v1;\nservice Trees { rpc Plant (Node) returns (Node); }\nmessage Node {\n  string label = 1;\n  int32 weight = 2;\n}\n")]);
+    let bridge = support::bridge(dir.path());
+    let root = bridge.index().register_root(&dir.path().to_string_lossy()).unwrap();
+    bridge.index().scan_root(&root).await.unwrap();
+
+    let mut cases = Vec::new();
+    let report = bridge.fuzz(FuzzParams {
+        target: format!("http://{}", server.addr),
+        service: "demo.v1.Trees".into(),
+        method: "Plant".into(),
+        proto_files: Vec::new(),
+        root_id: Some(root),
+        headers: None,
+        tls: None,
+        environment_id: None,
+        seed: Some(7),
+        max_cases: None,
+        collection_id: None,
+    }, |case| cases.push((case.mutation.clone(), case.finding))).await.unwrap();
+
+    // the payload that took the server down is a finding, the refused case after it is not
+    let crashed = cases.iter().position(|(m, _)| m == "max int32 at /weight").unwrap();
+    assert_eq!(cases.len(), crashed + 2);
+    assert!(cases[crashed].1 && !cases[crashed + 1].1);
+    assert_eq!(server.requests().len(), crashed + 1);
+    assert!(report.stopped_early);
+    let found: Vec<(&str, &str)> = report.findings.iter().map(|f| (f.mutation.as_str(), f.status.as_str())).collect();
+    assert_eq!(found, [("max int32 at /weight", "Unavailable")]);
+    let tree = bridge.storage().list_collections().unwrap().into_iter()
+        .find(|t| Some(&t.collection.id) == report.collection_id.as_ref())
+        .unwrap();
+    assert_eq!(tree.requests.len(), 1);
+}
+
+#[tokio::test]
+async fn fuzzing_goes_on_when_the_server_reports_its_own_refused_connection() {
+    let server = StandIn::start(|req| if req.json()["weight"] == i32::MAX {
+        Reply::status("Internal", "dial tcp 10.0.0.5:5432: connect: connection refused")
+    } else { Reply::message(json!({})) }).await;
+    let dir = tempfile::tempdir().unwrap();
+    support::write_protos(dir.path(), &[("tree.proto", "syntax = \"proto3\";\npackage demo.v1;\nservice Trees { rpc Plant (Node) returns (Node); }\nmessage Node {\n  string label = 1;\n  int32 weight = 2;\n}\n")]);
+    let bridge = support::bridge(dir.path());
+    let root = bridge.index().register_root(&dir.path().to_string_lossy()).unwrap();
+    bridge.index().scan_root(&root).await.unwrap();
+
+    let mut cases = Vec::new();
+    let report = bridge.fuzz(FuzzParams {
+        target: format!("http://{}", server.addr),
+        service: "demo.v1.Trees".into(),
+        method: "Plant".into(),
+        proto_files: Vec::new(),
+        root_id: Some(root),
+        headers: None,
+        tls: None,
+        environment_id: None,
+        seed: Some(7),
+        max_cases: None,
+        collection_id: None,
+    }, |case| cases.push(case.mutation.clone())).await.unwrap();
+
+    let internal = cases.iter().position(|m| m == "max int32 at /weight").unwrap();
+    assert!(cases.len() > internal + 1);
+    assert!(!report.stopped_early);
+    assert_eq!(report.cases, server.requests().len());
+    let found: Vec<(&str, &str)> = report.findings.iter().map(|f| (f.mutation.as_str(), f.status.as_str())).collect();
+    assert_eq!(found, [("max int32 at /weight", "Internal")]);
+}
@@ -0,0 +1,263 @@
+mod support;
+
+use grpc_bridge_core::payload::raw;
+use grpc_bridge_core::storage::Storage;
+use grpc_bridge_core::{BridgeError, ProtoIndex};
+use std::sync::Arc;
+
+const GREETER: &str = r#"
+syntax = "proto3";
+package demo.v1;
+
+service Greeter {
+  rpc Hello (HelloRequest) returns (HelloReply);
+  rpc Chat (stream HelloRequest) returns (stream HelloReply) {}
+}
+message HelloRequest { string name = 1; }
+message HelloReply { string message = 1; }
+"#;
+
+const USERS: &str = r#"
+syntax = "proto3";
+package demo.v1;
+import "demo/v1/greeter.proto";
+
+service Users { rpc Get (GetRequest) returns (User); }
+message GetRequest { int64 id = 1; }
+message User { int64 id = 1; }
+"#;
+
+#[tokio::test]
+async fn scan_finds_services_with_relative_paths() {
+    let dir = tempfile::tempdir().unwrap();
+    support::write_protos(dir.path(), &[("demo/v1/greeter.proto", GREETER), ("demo/v1/users.proto", USERS), ("README.md", "# not a proto")]);
+    let index = ProtoIndex::load(Arc::new(Storage::open_in_memory().unwrap())).unwrap();
+    let root = index.register_root(&dir.path().to_string_lossy()).unwrap();
+
+    let scan = index.scan_root(&root).await.unwrap();
+    let mut files = scan.files.clone();
+    files.sort();
+    assert_eq!(files, ["demo/v1/greeter.proto", "demo/v1/users.proto"]);
+
+    let mut services = index.services(Some(&root));
+    services.sort_by(|a, b| a.fq_service.cmp(&b.fq_service));
+    assert_eq!(services.len(), 2);
+    assert_eq!(services[0].fq_service, "demo.v1.Greeter");
+    assert_eq!(services[0].file, "demo/v1/greeter.proto");
+    let chat = services[0].methods.iter().find(|m| m.name == "Chat").unwrap();
+    assert!(chat.streaming);
+    let hello = services[0].methods.iter().find(|m| m.name == "Hello").unwrap();
+    assert_eq!((hello.input_type.as_str(), hello.output_type.as_str(), hello.streaming), ("HelloRequest", "HelloReply", false));
+    assert_eq!(services[1].fq_service, "demo.v1.Users");
+    assert!(index.roots()[0].last_scan.is_some());
+}
+
+#[tokio::test]
+async fn scans_survive_a_reload() {
+    let dir = tempfile::tempdir().unwrap();
+    support::write_protos(dir.path(), &[("greeter.proto", GREETER)]);
+    let db = dir.path().join("index.sqlite3");
+    let root = {
+        let index = ProtoIndex::load(Arc::new(Storage::open(&db).unwrap())).unwrap();
+        let root = index.register_root(&dir.path().to_string_lossy()).unwrap();
+        index.scan_root(&root).await.unwrap();
+        root
+    };
+    let index = ProtoIndex::load(Arc::new(Storage::open(&db).unwrap())).unwrap();
+    assert_eq!(index.files(&root), ["greeter.proto"]);
+    assert_eq!(index.services(None)[0].fq_service, "demo.v1.Greeter");
+
+    index.remove_root(&root).unwrap();
+    assert!(index.services(None).is_empty());
+    let index = ProtoIndex::load(Arc::new(Storage::open(&db).unwrap())).unwrap();
+    assert!(index.roots().is_empty());
+}
+
+#[tokio::test]
+async fn unknown_roots_services_and_methods_are_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    support::write_protos(dir.path(), &[("greeter.proto", GREETER)]);
+    let index = ProtoIndex::load(Arc::new(Storage::open_in_memory().unwrap())).unwrap();
+    assert!(matches!(index.scan_root("missing").await, Err(BridgeError::RootNotFound(_))));
+
+    let root = index.register_root(&dir.path().to_string_lossy()).unwrap();
+    index.scan_root(&root).await.unwrap();
+    assert!(index.method_skeleton(None, "demo.v1.Greeter", "Hello", None).is_ok());
+    assert!(matches!(index.method_skeleton(None, "demo.v1.Nope", "Hello", None), Err(BridgeError::ServiceNotFound(_))));
+    assert!(matches!(index.method_skeleton(None, "demo.v1.Greeter", "Nope", None), Err(BridgeError::MethodNotFound { .. })));
+}
+
+#[tokio::test]
+async fn a_method_in_several_roots_resolves_to_the_preferred_root() {
+    let (a, b) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+    support::write_protos(a.path(), &[("greeter.proto", GREETER)]);
+    support::write_protos(b.path(), &[("greeter.proto", &GREETER.replace("string name = 1;", "string nickname = 1;"))]);
+    let index = ProtoIndex::load(Arc::new(Storage::open_in_memory().unwrap())).unwrap();
+    let mut roots = Vec::new();
+    for dir in [&a, &b] {
+        let root = index.register_root(&dir.path().to_string_lossy()).unwrap();
+        index.scan_root(&root).await.unwrap();
+        roots.push(root);
+    }
+    let field = |root: Option<&str>| {
+        let (schema, message) = index.method_input(root, "demo.v1.Greeter", "Hello").unwrap();
+        schema.message(&message).unwrap().fields[0].name.clone()
+    };
+    let fields = [(&roots[0], "name"), (&roots[1], "nickname")];
+    for (root, name) in fields {
+        assert_eq!(field(Some(root)), name);
+    }
+    // without a preference the root with the smallest id wins, every time
+    let first = fields.iter().min_by_key(|(root, _)| root.as_str()).unwrap().1;
+    for _ in 0..8 {
+        assert_eq!(field(None), first);
+    }
+    assert_eq!(field(Some("unknown")), first);
+}
+
+const ORDERS: &str = r#"
+syntax = "proto3";
+package shop.v1;
+import "google/protobuf/timestamp.proto";
+import "google/protobuf/wrappers.proto";
+
+service Orders { rpc Create (CreateOrderRequest) returns (Order); }
+enum Status { STATUS_UNSPECIFIED = 0; STATUS_OPEN = 1; }
+message Item { string sku = 1; uint32 quantity = 2; Item bundle = 3; }
+message CreateOrderRequest {
+  string customer_id = 1;
+  repeated Item items = 2;
+  map<string, int64> counters = 3;
+  Status status = 4;
+  oneof payment { string card_token = 5; bytes voucher = 6; }
+  google.protobuf.Timestamp deliver_at = 7;
+  google.protobuf.Int64Value budget = 8;
+  double discount = 9 [json_name = "rebate"];
+  message Note { string text = 1; }
+  Note note = 10;
+}
+message Order { string id = 1; }
+"#;
+
+#[tokio::test]
+async fn skeletons_follow_message_definitions() {
+    let dir = tempfile::tempdir().unwrap();
+    support::write_protos(dir.path(), &[("shop/v1/orders.proto", ORDERS)]);
+    let index = ProtoIndex::load(Arc::new(Storage::open_in_memory().unwrap())).unwrap();
+    let root = index.register_root(&dir.path().to_string_lossy()).unwrap();
+    index.scan_root(&root).await.unwrap();
+
+    let skeleton: serde_json::Value = serde_json::from_str(&index.method_skeleton(None, "shop.v1.Orders", "Create", None).unwrap()).unwrap();
+    assert_eq!(skeleton, serde_json::json!({
+        "customerId": "",
+        "items": [{ "sku": "", "quantity": 0, "bundle": { "sku": "", "quantity": 0, "bundle": {} } }],
+        "counters": { "key": "0" },
+        "status": "STATUS_UNSPECIFIED",
+        "cardToken": "",
+        "deliverAt": "1970-01-01T00:00:00Z",
+        "budget": "0",
+        "rebate": 0.0,
+        "note": { "text": "" }
+    }));
+    let keys: Vec<_> = skeleton.as_object().unwrap().keys().cloned().collect();
+    assert_eq!(keys[..3], ["customerId", "items", "counters"]);
+
+    let shallow: serde_json::Value = serde_json::from_str(&index.method_skeleton(None, "shop.v1.Orders", "Create", Some(1)).unwrap()).unwrap();
+    assert_eq!(shallow["items"], serde_json::json!([{}]));
+}
+
+const TREE: &str = r#"
+syntax = "proto3";
+package fs.v1;
+import "google/protobuf/timestamp.proto";
+
+enum Kind { KIND_UNSPECIFIED = 0; KIND_FILE = 1; }
+message Node {
+  // Name within the parent.
+  string name = 1;
+  repeated Node children = 2;
+  Kind kind = 3;
+  int64 size = 4;
+  bytes digest = 5;
+  map<uint32, string> attrs = 6;
+  google.protobuf.Timestamp modified = 7;
+  oneof target { string link = 8; string mount_point = 9; }
+  double weight = 10;
+  uint32 mode = 11;
+}
+"#;
+
+#[tokio::test]
+async fn json_schema_follows_the_proto3_json_mapping() {
+    let dir = tempfile::tempdir().unwrap();
+    support::write_protos(dir.path(), &[("fs.proto", TREE)]);
+    let index = ProtoIndex::load(Arc::new(Storage::open_in_memory().unwrap())).unwrap();
+    let root = index.register_root(&dir.path().to_string_lossy()).unwrap();
+    index.scan_root(&root).await.unwrap();
+
+    let schema = index.json_schema("fs.v1.Node").unwrap();
+    assert_eq!(schema["$schema"], "https://json-schema.org/draft/2020-12/schema");
+    assert_eq!(schema["$ref"], "#/$defs/fs.v1.Node");
+    let node = &schema["$defs"]["fs.v1.Node"];
+    let props = &node["properties"];
+    assert_eq!(props["name"], serde_json::json!({ "type": ["string", "null"], "description": "Name within the parent." }));
+    assert_eq!(props["children"], serde_json::json!({ "type": ["array", "null"], "items": { "$ref": "#/$defs/fs.v1.Node" } }));
+    assert_eq!(props["kind"]["anyOf"], serde_json::json!([{ "$ref": "#/$defs/fs.v1.Kind" }, { "type": "null" }]));
+    assert_eq!(schema["$defs"]["fs.v1.Kind"]["anyOf"][0]["enum"], serde_json::json!(["KIND_UNSPECIFIED", "KIND_FILE"]));
+    assert_eq!(props["size"]["type"], serde_json::json!(["string", "integer", "null"]));
+    // quoted numbers are accepted for every numeric type, as the payload check accepts them
+    assert_eq!(props["mode"]["type"], serde_json::json!(["string", "integer", "null"]));
+    assert_eq!((&props["mode"]["pattern"], &props["mode"]["maximum"]), (&serde_json::json!("^[0-9]+$"), &serde_json::json!(u32::MAX)));
+    let float = regex::Regex::new(props["weight"]["pattern"].as_str().unwrap()).unwrap();
+    assert!(["1.5", "-2e3", ".5", "7", "NaN", "-Infinity"].iter().all(|s| float.is_match(s)));
+    assert!(["abc", "1.5.2", "Inf", ""].iter().all(|s| !float.is_match(s)));
+    assert_eq!(props["digest"]["contentEncoding"], "base64");
+    assert_eq!(props["attrs"]["propertyNames"]["pattern"], "^[0-9]+$");
+    assert_eq!(props["modified"], serde_json::json!({ "type": ["string", "null"], "format": "date-time" }));
+    assert_eq!(props["mountPoint"], props["mount_point"]);
+    assert_eq!(node["additionalProperties"], false);
+    assert_eq!(node["allOf"], serde_json::json!([
+        { "not": { "required": ["mountPoint", "mount_point"] } },
+        { "not": { "anyOf": [{ "required": ["link", "mountPoint"] }, { "required": ["link", "mount_point"] }] } },
+    ]));
+
+    assert!(matches!(index.json_schema("fs.v1.Missing"), Err(BridgeError::MessageNotFound(_))));
+}
+
+#[tokio::test]
+async fn raw_bytes_decode_without_a_schema_and_suggest_indexed_types() {
+    let dir = tempfile::tempdir().unwrap();
+    support::write_protos(dir.path(), &[("fs.proto", TREE)]);
+    let index = ProtoIndex::load(Arc::new(Storage::open_in_memory().unwrap())).unwrap();
+    let root = index.register_root(&dir.path().to_string_lossy()).unwrap();
+    index.scan_root(&root).await.unwrap();
+
+    // name "a", one child { name "b", kind 1 }, size 300, digest ff 00
+    let node = [0x0a, 0x01, 0x61, 0x12, 0x05, 0x0a, 0x01, 0x62, 0x18, 0x01, 0x20, 0xac, 0x02, 0x2a, 0x02, 0xff, 0x00];
+    let decoding = index.decode_raw(&node, None).unwrap();
+    let tree = serde_json::to_value(&decoding.fields).unwrap();
+    assert_eq!(tree[0], serde_json::json!({ "number": 1, "wire_type": "length_delimited", "guess": "string", "value": "a", "alternatives": { "bytes": "YQ==" } }));
+    assert_eq!(tree[1]["guess"], "message");
+    assert_eq!(tree[1]["fields"][1]["guess"], "bool");
+    assert_eq!(tree[2]["value"], "300");
+    assert_eq!(tree[2]["alternatives"]["sint64"], "150");
+    assert_eq!(tree[3]["guess"], "bytes");
+    assert_eq!(decoding.candidates, ["fs.v1.Node"]);
+    assert_eq!(decoding.decoded, None);
+    assert_eq!(raw::print(&decoding.fields), "1: \"a\"\n2 {\n  1: \"b\"\n  3: 1\n}\n4: 300\n5: \"\\377\\000\"\n");
+
+    let typed = index.decode_raw(&node, Some("fs.v1.Node")).unwrap();
+    assert_eq!(typed.decoded, Some(serde_json::json!({ "name": "a", "children": [{ "name": "b", "kind": "KIND_FILE" }], "size": "300", "digest": "/wA=" })));
+
+    // double 1.0 as field 1, float 1.0 as field 2
+    let fixed = [0x09, 0, 0, 0, 0, 0, 0, 0xf0, 0x3f, 0x15, 0, 0, 0x80, 0x3f];
+    let decoding = index.decode_raw(&fixed, None).unwrap();
+    assert_eq!(decoding.fields[0].alternatives["double"], 1.0);
+    assert_eq!(decoding.fields[1].alternatives["float"], 1.0);
+    assert!(decoding.candidates.is_empty());
+    assert_eq!(raw::print(&decoding.fields), "1: 0x3ff0000000000000\n2: 0x3f800000\n");
+
+    assert!(matches!(index.decode_raw(&[0x0a, 0x05], None), Err(BridgeError::PayloadFormat { .. })));
+    assert!(matches!(index.decode_raw(&fixed, Some("fs.v1.Node")), Err(BridgeError::PayloadFormat { .. })));
+    assert!(matches!(index.decode_raw(&node, Some("fs.v1.Missing")), Err(BridgeError::MessageNotFound(_))));
+}
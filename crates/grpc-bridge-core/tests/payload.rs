@@ -0,0 +1,318 @@
+use grpc_bridge_core::payload::format::{from_json, to_json, PayloadFormat};
+use grpc_bridge_core::payload::json_output::{self, Int64Format, JsonOptions};
+use grpc_bridge_core::payload::complete::complete;
+use grpc_bridge_core::payload::sample::sample;
+use grpc_bridge_core::payload::validate::{validate, validate_str, IssueKind, PayloadIssue};
+use grpc_bridge_core::proto_index::schema::Schema;
+use serde_json::json;
+
+const ORDERS: &str = r#"
+syntax = "proto3";
+package shop.v1;
+import "google/protobuf/timestamp.proto";
+import "google/protobuf/duration.proto";
+
+enum Status { STATUS_UNSPECIFIED = 0; STATUS_OPEN = 1; }
+message Item { string sku = 1; uint32 quantity = 2; }
+message CreateOrderRequest {
+  string customer_id = 1;
+  repeated Item items = 2;
+  map<int32, string> labels = 3;
+  Status status = 4;
+  oneof payment { string card_token = 5; bytes voucher = 6; }
+  google.protobuf.Timestamp deliver_at = 7;
+  google.protobuf.Duration hold_for = 8;
+  int64 total_cents = 9;
+  bool gift = 10;
+}
+"#;
+
+fn check(payload: &str) -> Vec<PayloadIssue> {
+    validate_str(&Schema::parse([ORDERS]), "shop.v1.CreateOrderRequest", payload)
+}
+
+fn found(issues: &[PayloadIssue]) -> Vec<(&str, IssueKind)> {
+    issues.iter().map(|i| (i.path.as_str(), i.kind)).collect()
+}
+
+#[test]
+fn valid_payloads_have_no_issues() {
+    assert!(check("").is_empty());
+    assert!(check(r#"{
+        "customerId": "c-1",
+        "items": [{ "sku": "A", "quantity": 2 }],
+        "labels": { "7": "x" },
+        "status": "STATUS_OPEN",
+        "voucher": "AAEC",
+        "deliverAt": "2024-02-29T12:30:00.5+09:00",
+        "holdFor": "-1.25s",
+        "total_cents": "9223372036854775807",
+        "gift": null
+    }"#).is_empty());
+    assert!(check(r#"{"status": 1, "totalCents": 12}"#).is_empty());
+}
+
+#[test]
+fn issues_point_at_the_offending_value() {
+    let issues = check(r#"{
+        "customerID": "c-1",
+        "items": [{ "sku": 1, "quantity": -1 }, null],
+        "labels": { "x": "y" },
+        "status": "OPEN",
+        "cardToken": "t",
+        "voucher": "AAEC",
+        "deliverAt": "2023-02-29T00:00:00Z",
+        "holdFor": "90m",
+        "totalCents": "12.5",
+        "gift": "yes"
+    }"#);
+    assert_eq!(found(&issues), [
+        ("/customerID", IssueKind::UnknownField),
+        ("/items/0/sku", IssueKind::WrongType),
+        ("/items/0/quantity", IssueKind::OutOfRange),
+        ("/items/1", IssueKind::WrongType),
+        ("/labels/x", IssueKind::InvalidFormat),
+        ("/status", IssueKind::InvalidEnum),
+        ("/voucher", IssueKind::OneofConflict),
+        ("/deliverAt", IssueKind::InvalidFormat),
+        ("/holdFor", IssueKind::InvalidFormat),
+        ("/totalCents", IssueKind::InvalidFormat),
+        ("/gift", IssueKind::WrongType),
+    ]);
+    assert!(issues[5].message.contains("STATUS_OPEN"));
+}
+
+#[test]
+fn malformed_json_is_a_single_issue() {
+    let issues = check(r#"{"customerId": }"#);
+    assert_eq!(found(&issues), [("", IssueKind::InvalidJson)]);
+    assert_eq!(found(&check("[]")), [("", IssueKind::WrongType)]);
+}
+
+const SIGNUP: &str = r#"
+syntax = "proto3";
+package acct.v1;
+import "buf/validate/validate.proto";
+import "validate/validate.proto";
+
+enum Plan { PLAN_UNSPECIFIED = 0; PLAN_FREE = 1; PLAN_PRO = 2; }
+message Address { string city = 1; }
+message SignupRequest {
+  string username = 1 [(buf.validate.field).string = { min_len: 3, max_len: 16, pattern: "^[a-z][a-z0-9_]*$" }];
+  string email = 2 [(validate.rules).string.email = true];
+  int32 age = 3 [(buf.validate.field).int32 = { gte: 13, lt: 130 }];
+  Plan plan = 4 [(buf.validate.field).enum = { defined_only: true, not_in: [0] }];
+  repeated string tags = 5 [(buf.validate.field).repeated = { max_items: 2, unique: true, items: { string: { min_len: 1 } } }];
+  Address address = 6 [(buf.validate.field).required = true];
+  string referrer = 7 [(buf.validate.field).string.uuid = true, (buf.validate.field).ignore = IGNORE_IF_UNPOPULATED];
+  map<string, int64> quotas = 8 [(validate.rules).map.values.int64.lte = 100];
+  double score = 9 [(validate.rules).double = { gt: 1.0, lt: -1.0 }];
+}
+message Invite {
+  string name = 1 [(buf.validate.field).required = true];
+  string code = 2 [(buf.validate.field).string.min_len = 3, (buf.validate.field).ignore = IGNORE_IF_UNPOPULATED];
+  int64 inviter = 3 [(buf.validate.field).required = true];
+}
+"#;
+
+fn check_rules(payload: &str) -> Vec<PayloadIssue> {
+    validate_str(&Schema::parse([SIGNUP]), "acct.v1.SignupRequest", payload)
+}
+
+fn violated(issues: &[PayloadIssue]) -> Vec<(&str, &str)> {
+    issues.iter().inspect(|i| assert_eq!(i.kind, IssueKind::Constraint)).map(|i| (i.path.as_str(), i.rule.as_deref().unwrap())).collect()
+}
+
+#[test]
+fn constraints_from_field_options_are_enforced() {
+    assert!(check_rules(r#"{
+        "username": "ada_l", "email": "ada@example.com", "age": 36, "plan": "PLAN_PRO",
+        "tags": ["a", "b"], "address": {}, "quotas": { "cpu": "100" }, "score": 2
+    }"#).is_empty());
+
+    let issues = check_rules(r#"{
+        "username": "Ad", "email": "ada@", "age": 12, "plan": 7,
+        "tags": ["a", "a", ""], "referrer": "nope", "quotas": { "cpu": 101 }, "score": 0.5
+    }"#);
+    assert_eq!(violated(&issues), [
+        ("/username", "string.min_len"),
+        ("/username", "string.pattern"),
+        ("/email", "string.email"),
+        ("/age", "int32.gte"),
+        ("/plan", "enum.defined_only"),
+        ("/tags", "repeated.max_items"),
+        ("/tags", "repeated.unique"),
+        ("/tags/2", "repeated.items.string.min_len"),
+        ("/address", "required"),
+        ("/referrer", "string.uuid"),
+        ("/quotas/cpu", "map.values.int64.lte"),
+        ("/score", "double.gt"),
+    ]);
+    assert_eq!(issues[3].message, "must be greater than or equal to 13 and less than 130");
+
+    // "0" is only the default of 64-bit integers, which canonical JSON writes as strings
+    let invite = |payload| validate_str(&Schema::parse([SIGNUP]), "acct.v1.Invite", payload);
+    assert!(invite(r#"{"name": "0", "inviter": "7"}"#).is_empty());
+    assert_eq!(violated(&invite(r#"{"name": "ada", "code": "0", "inviter": "0"}"#)), [("/code", "string.min_len"), ("/inviter", "required")]);
+}
+
+#[test]
+fn unset_fields_are_checked_at_their_default_value() {
+    let issues = check_rules(r#"{"email": "a@b.co", "age": 20, "plan": "PLAN_FREE", "address": {"city": "x"}, "score": -3}"#);
+    assert_eq!(violated(&issues), [("/username", "string.min_len"), ("/username", "string.pattern")]);
+}
+
+#[test]
+fn patterns_that_do_not_compile_are_reported() {
+    let schema = Schema::parse([r#"syntax = "proto3";
+package acct.v1;
+import "buf/validate/validate.proto";
+message Tagged { repeated string tags = 1 [(buf.validate.field).repeated.items.string.pattern = "^(?=a)b"]; }
+"#]);
+    let issues = validate_str(&schema, "acct.v1.Tagged", r#"{"tags": ["ab"]}"#);
+    assert_eq!(violated(&issues), [("/tags/0", "repeated.items.string.pattern")]);
+    assert!(issues[0].message.starts_with("pattern \"^(?=a)b\" is not supported: look-around"), "{}", issues[0].message);
+}
+
+const COMPLETION: &str = r#"
+syntax = "proto3";
+package shop.v1;
+
+enum Status { STATUS_UNSPECIFIED = 0; STATUS_OPEN = 1; STATUS_CLOSED = 2; }
+message Item {
+  // Stock keeping unit.
+  string sku = 1;
+  bool gift_wrap = 2; // wrapped in paper
+}
+message Query {
+  string customer_id = 1;
+  repeated Item items = 2;
+  map<string, Status> by_region = 3;
+  oneof filter { Status status = 4; string text = 5; }
+}
+"#;
+
+fn suggest(payload_with_cursor: &str) -> (Vec<String>, Vec<String>, usize) {
+    let cursor = payload_with_cursor.find('|').unwrap();
+    let payload = payload_with_cursor.replace('|', "");
+    let out = complete(&Schema::parse([COMPLETION]), "shop.v1.Query", &payload, payload[..cursor].encode_utf16().count());
+    (out.items.iter().map(|i| i.label.clone()).collect(), out.items.iter().map(|i| i.insert_text.clone()).collect(), out.from)
+}
+
+#[test]
+fn completion_suggests_fields_of_the_message_at_the_cursor() {
+    let (labels, inserts, from) = suggest(r#"{ "customerId": "c", | }"#);
+    assert_eq!(labels, ["items", "byRegion", "status", "text"]);
+    assert_eq!(inserts[0], "\"items\": ");
+    assert_eq!(from, 21);
+
+    let (labels, _, _) = suggest(r#"{ "status": 1, "te|"#);
+    assert!(labels.is_empty(), "the oneof is already set");
+    let (labels, inserts, from) = suggest(r#"{ "items": [{ "sku": "", "g|"#);
+    assert_eq!((labels, inserts, from), (vec!["giftWrap".to_string()], vec!["giftWrap".to_string()], 26));
+
+    let items = complete(&Schema::parse([COMPLETION]), "shop.v1.Query", r#"{"items": [{"#, 12).items;
+    assert_eq!(items[0].detail.as_deref(), Some("string"));
+    assert_eq!(items[0].documentation.as_deref(), Some("Stock keeping unit."));
+    assert_eq!(items[1].documentation.as_deref(), Some("wrapped in paper"));
+}
+
+#[test]
+fn completion_suggests_values_for_the_field_at_the_cursor() {
+    let (labels, inserts, _) = suggest(r#"{ "status": |"#);
+    assert_eq!(labels, ["STATUS_UNSPECIFIED", "STATUS_OPEN", "STATUS_CLOSED"]);
+    assert_eq!(inserts[1], "\"STATUS_OPEN\"");
+    let (labels, inserts, _) = suggest(r#"{ "byRegion": { "eu": "STATUS_C|" } }"#);
+    assert_eq!((labels, inserts), (vec!["STATUS_CLOSED".to_string()], vec!["STATUS_CLOSED".to_string()]));
+    let (labels, _, _) = suggest(r#"{ "items": [{ "giftWrap": t|"#);
+    assert_eq!(labels, ["true"]);
+    let (labels, _, _) = suggest(r#"{ "items": [|"#);
+    assert_eq!(labels, ["null"]);
+    let (labels, _, _) = suggest(r#"{ "customerId": "{{id}}", "text": |"#);
+    assert!(labels.is_empty());
+}
+
+#[test]
+fn samples_are_valid_and_reproducible() {
+    for (source, message) in [(ORDERS, "shop.v1.CreateOrderRequest"), (SIGNUP, "acct.v1.SignupRequest"), (COMPLETION, "shop.v1.Query")] {
+        let schema = Schema::parse([source]);
+        for seed in 0..50 {
+            let payload = sample(&schema, message, seed, 3);
+            assert_eq!(validate(&schema, message, &payload), [], "seed {} gave {}", seed, payload);
+        }
+        assert_eq!(sample(&schema, message, 7, 3), sample(&schema, message, 7, 3));
+    }
+    let schema = Schema::parse([SIGNUP]);
+    assert_ne!(sample(&schema, "acct.v1.SignupRequest", 1, 3), sample(&schema, "acct.v1.SignupRequest", 2, 3));
+}
+
+#[test]
+fn payloads_convert_between_json_text_and_wire_formats() {
+    let schema = Schema::parse([ORDERS]);
+    let item = "shop.v1.Item";
+    assert_eq!(to_json(&schema, item, PayloadFormat::Hex, "0a 01 41 10 02").unwrap(), r#"{"sku":"A","quantity":2}"#);
+    assert_eq!(to_json(&schema, item, PayloadFormat::BinaryBase64, "CgFBEAI=").unwrap(), r#"{"sku":"A","quantity":2}"#);
+    assert_eq!(from_json(&schema, item, PayloadFormat::Hex, &json!({"sku": "A", "quantity": 2})).unwrap(), "0a01411002");
+    assert_eq!(from_json(&schema, item, PayloadFormat::Text, &json!({"sku": "A", "quantity": 2})).unwrap(), "sku: \"A\"\nquantity: 2\n");
+    assert!(to_json(&schema, item, PayloadFormat::Hex, "0a05").is_err());
+
+    let order = "shop.v1.CreateOrderRequest";
+    let text = r#"
+        customer_id: "c-1"  # comments are skipped
+        items { sku: "A" quantity: 2 }
+        items < sku: "B" >
+        labels [{ key: 7 value: "x" }]
+        status: STATUS_OPEN
+        voucher: "\x00\x01\002"
+        deliver_at { seconds: 1709209800 nanos: 500000000 }
+        hold_for { seconds: -1 nanos: -250000000 }
+        total_cents: 9223372036854775807
+    "#;
+    let value: serde_json::Value = serde_json::from_str(&to_json(&schema, order, PayloadFormat::Text, text).unwrap()).unwrap();
+    assert_eq!(value, json!({
+        "customerId": "c-1",
+        "items": [{ "sku": "A", "quantity": 2 }, { "sku": "B" }],
+        "labels": { "7": "x" },
+        "status": "STATUS_OPEN",
+        "voucher": "AAEC",
+        "deliverAt": "2024-02-29T12:30:00.500Z",
+        "holdFor": "-1.250s",
+        "totalCents": "9223372036854775807"
+    }));
+    for format in [PayloadFormat::Text, PayloadFormat::BinaryBase64, PayloadFormat::Hex] {
+        let rendered = from_json(&schema, order, format, &value).unwrap();
+        let back: serde_json::Value = serde_json::from_str(&to_json(&schema, order, format, &rendered).unwrap()).unwrap();
+        assert_eq!(back, value, "{} round trip", format);
+    }
+    assert!(to_json(&schema, order, PayloadFormat::Text, "customer_id: 1").is_err());
+    assert!(to_json(&schema, order, PayloadFormat::Text, "no_such_field: 1").is_err());
+}
+
+#[test]
+fn json_options_apply_through_nested_messages_maps_and_wrappers() {
+    let schema = Schema::parse([r#"
+        syntax = "proto3";
+        package stats.v1;
+        import "google/protobuf/wrappers.proto";
+        enum Unit { UNIT_UNSPECIFIED = 0; UNIT_BYTES = 1; }
+        message Sample { sint64 value_delta = 1; Unit unit = 2; }
+        message Report {
+          map<string, Sample> by_host = 1;
+          repeated Unit units = 2;
+          google.protobuf.Int64Value total = 3;
+          fixed64 checksum = 4;
+        }
+    "#]);
+    let canonical = json!({ "byHost": { "a": { "valueDelta": "-5", "unit": "UNIT_BYTES" } }, "units": ["UNIT_BYTES", 0], "total": "18446744073", "checksum": "18446744073709551615", "extra": true });
+    let options = JsonOptions { proto_names: true, enums_as_numbers: true, int64: Int64Format::Lossless, ..JsonOptions::default() };
+    assert_eq!(json_output::apply(&schema, "stats.v1.Report", &canonical, &options), json!({
+        "by_host": { "a": { "value_delta": -5, "unit": 1 } },
+        "units": [1, 0],
+        "total": 18446744073u64,
+        "checksum": "18446744073709551615",
+        "extra": true
+    }));
+    let named = json_output::apply(&schema, "stats.v1.Report", &json!({ "units": [1, 7] }), &JsonOptions { emit_defaults: true, ..JsonOptions::default() });
+    assert_eq!(named, json!({ "byHost": {}, "units": ["UNIT_BYTES", 7], "checksum": "0" }));
+    assert!(JsonOptions::default().is_canonical());
+}
@@ -0,0 +1,43 @@
+#!/usr/bin/env bash
+# Stands in for grpcurl in the integration tests: forwards the arguments and stdin to the
+# test server listening on the dialed address and replays its answer.
+if [ "$1" = "-version" ]; then echo "grpcurl v1.9.1"; exit 0; fi
+addr=""; prev=""; unix=""
+for a in "$@"; do
+    if [ "$prev" = "--" ]; then addr="$a"; fi
+    if [ "$a" = "-unix" ]; then unix=1; fi
+    prev="$a"
+done
+dial_failed() {
+    echo "Failed to dial target host \"$addr\": connection refused" >&2
+    exit 1
+}
+if [ -n "$unix" ]; then
+    # bash cannot open unix sockets itself, so perl relays between a coprocess pipe and the socket
+    [ -S "$addr" ] || dial_failed
+    coproc RELAY { perl -MIO::Socket::UNIX -e '
+        my $s = IO::Socket::UNIX->new(Peer => $ARGV[0]) or exit 1;
+        if (fork) { while (sysread($s, my $b, 4096)) { syswrite(STDOUT, $b) } }
+        else { close STDOUT; while (sysread(STDIN, my $b, 4096)) { syswrite($s, $b) } exit 0 }' "$addr"; }
+    exec 3>&"${RELAY[1]}" 4<&"${RELAY[0]}"
+else
+    { exec 3<>"/dev/tcp/${addr%:*}/${addr##*:}"; } 2>/dev/null || dial_failed
+    exec 4<&3
+fi
+for a in "$@"; do printf 'arg %s\n' "$a" >&3; done
+printf 'payload\n' >&3
+cat >&3
+echo >&3
+printf '.\n' >&3
+if ! IFS= read -r status <&4; then
+    # the server went away mid-call
+    printf 'ERROR:\n  Code: Unavailable\n  Message: error reading from server: EOF\n' >&2
+    exit 1
+fi
+while IFS= read -r line <&4; do
+    case "$line" in
+        "out "*) printf '%s\n' "${line#out }" ;;
+        "err "*) printf '%s\n' "${line#err }" >&2 ;;
+    esac
+done
+exit "${status#exit }"
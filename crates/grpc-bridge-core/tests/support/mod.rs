@@ -0,0 +1,195 @@
+//! A stand-in gRPC server for the integration tests. `grpcurl.sh` takes grpcurl's place and
+//! forwards each invocation over TCP or a unix socket to a [`StandIn`], whose handler decides what grpcurl would
+//! have printed.
+#![allow(dead_code)]
+
+use grpc_bridge_core::grpcurl::GrpcurlConfig;
+use grpc_bridge_core::secrets::MemoryStore;
+use grpc_bridge_core::storage::Storage;
+use grpc_bridge_core::Bridge;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, UnixListener};
+use tokio::sync::Notify;
+
+/// One grpcurl invocation as the server saw it.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub args: Vec<String>,
+    pub payload: String,
+}
+
+impl Request {
+    /// `package.Service/Method`, `list` or the described symbol.
+    pub fn method(&self) -> &str { self.args.last().map(String::as_str).unwrap_or_default() }
+
+    pub fn headers(&self) -> Vec<&str> {
+        self.args.windows(2).filter(|w| w[0] == "-H").map(|w| w[1].as_str()).collect()
+    }
+
+    pub fn json(&self) -> serde_json::Value { serde_json::from_str(&self.payload).unwrap() }
+}
+
+/// What grpcurl prints and how it exits.
+pub struct Reply {
+    exit: i32,
+    stdout: String,
+    stderr: String,
+    crash: bool,
+}
+
+impl Reply {
+    /// A successful unary response in grpcurl's verbose format.
+    pub fn message(body: serde_json::Value) -> Self {
+        Self::verbose(&[("x-request-id", "req-1")], Some(body), &[("x-served-by", "stand-in")])
+    }
+
+    pub fn verbose(headers: &[(&str, &str)], body: Option<serde_json::Value>, trailers: &[(&str, &str)]) -> Self {
+        let mut out = String::from("\nResolved method descriptor:\nrpc Call ( .Req ) returns ( .Resp );\n\nRequest metadata to send:\n(empty)\n\nResponse headers received:\n");
+        for (k, v) in headers { out.push_str(&format!("{}: {}\n", k, v)); }
+        if let Some(body) = &body {
+            out.push_str(&format!("\nResponse contents:\n{}\n", serde_json::to_string_pretty(body).unwrap()));
+        }
+        out.push_str("\nResponse trailers received:\n");
+        if trailers.is_empty() { out.push_str("(empty)\n"); }
+        for (k, v) in trailers { out.push_str(&format!("{}: {}\n", k, v)); }
+        out.push_str(&format!("Sent 1 request and received {} response\n", usize::from(body.is_some())));
+        Self { exit: 0, stdout: out, stderr: String::new(), crash: false }
+    }
+
+    /// The server dies mid-call: the call gets no answer and later calls cannot connect.
+    pub fn crash() -> Self {
+        Self { exit: 0, stdout: String::new(), stderr: String::new(), crash: true }
+    }
+
+    /// A non-OK gRPC status.
+    pub fn status(code: &str, message: &str) -> Self {
+        let mut reply = Self::verbose(&[], None, &[]);
+        reply.exit = 1;
+        reply.stderr = format!("ERROR:\n  Code: {}\n  Message: {}\n", code, message);
+        reply
+    }
+
+    /// Plain output, as printed for reflection queries.
+    pub fn text(out: &str) -> Self {
+        Self { exit: 0, stdout: out.to_string(), stderr: String::new(), crash: false }
+    }
+}
+
+pub struct StandIn {
+    pub addr: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+/// Raised by a [`Reply::crash`]; the accept loop answers once the listener is closed.
+#[derive(Default)]
+struct Crash {
+    raised: Notify,
+    closed: Notify,
+}
+
+impl StandIn {
+    pub async fn start(handler: impl Fn(&Request) -> Reply + Send + Sync + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let requests: Arc<Mutex<Vec<Request>>> = Default::default();
+        let (seen, handler, crash) = (requests.clone(), Arc::new(handler), Arc::new(Crash::default()));
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    accepted = listener.accept() => {
+                        let Ok((stream, _)) = accepted else { break; };
+                        let (read, write) = stream.into_split();
+                        tokio::spawn(serve(read, write, seen.clone(), handler.clone(), crash.clone()));
+                    }
+                    _ = crash.raised.notified() => break,
+                }
+            }
+            drop(listener);
+            crash.closed.notify_one();
+        });
+        Self { addr, requests }
+    }
+
+    /// Like [`Self::start`], listening on a unix socket at `path`, which becomes `addr`.
+    pub async fn start_unix(path: &Path, handler: impl Fn(&Request) -> Reply + Send + Sync + 'static) -> Self {
+        let listener = UnixListener::bind(path).unwrap();
+        let requests: Arc<Mutex<Vec<Request>>> = Default::default();
+        let (seen, handler, crash) = (requests.clone(), Arc::new(handler), Arc::new(Crash::default()));
+        let socket = path.to_path_buf();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    accepted = listener.accept() => {
+                        let Ok((stream, _)) = accepted else { break; };
+                        let (read, write) = stream.into_split();
+                        tokio::spawn(serve(read, write, seen.clone(), handler.clone(), crash.clone()));
+                    }
+                    _ = crash.raised.notified() => break,
+                }
+            }
+            drop(listener);
+            let _ = std::fs::remove_file(socket);
+            crash.closed.notify_one();
+        });
+        Self { addr: path.to_string_lossy().to_string(), requests }
+    }
+
+    pub fn requests(&self) -> Vec<Request> { self.requests.lock().unwrap().clone() }
+}
+
+/// Reads one invocation from the shim, records it and writes back the handler's reply.
+async fn serve<H: Fn(&Request) -> Reply>(read: impl AsyncRead + Unpin, mut write: impl AsyncWrite + Unpin, seen: Arc<Mutex<Vec<Request>>>, handler: Arc<H>, crash: Arc<Crash>) {
+    let mut lines = BufReader::new(read).lines();
+    let mut request = Request { args: Vec::new(), payload: String::new() };
+    let mut in_payload = false;
+    while let Ok(Some(line)) = lines.next_line().await {
+        match line.as_str() {
+            "." => break,
+            "payload" if !in_payload => in_payload = true,
+            l if in_payload => { request.payload.push_str(l); request.payload.push('\n'); }
+            l => request.args.push(l.strip_prefix("arg ").unwrap_or(l).to_string()),
+        }
+    }
+    let reply = handler(&request);
+    seen.lock().unwrap().push(request);
+    if reply.crash {
+        // stop listening before the shim sees the connection drop, so the next call is refused
+        crash.raised.notify_one();
+        crash.closed.notified().await;
+        return;
+    }
+    let mut out = format!("exit {}\n", reply.exit);
+    for l in reply.stdout.lines() { out.push_str(&format!("out {}\n", l)); }
+    for l in reply.stderr.lines() { out.push_str(&format!("err {}\n", l)); }
+    let _ = write.write_all(out.as_bytes()).await;
+    let _ = write.shutdown().await;
+}
+
+/// An address nothing listens on.
+pub async fn closed_addr() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    listener.local_addr().unwrap().to_string()
+}
+
+fn grpcurl_shim() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/support/grpcurl.sh")
+}
+
+/// A bridge with its database in `dir`, in-memory secrets and the grpcurl shim.
+pub fn bridge(dir: &Path) -> Bridge {
+    let storage = Storage::open(&dir.join("bridge.sqlite3")).unwrap();
+    let bridge = Bridge::load(storage, Box::<MemoryStore>::default(), None).unwrap();
+    bridge.engine().set_grpcurl_config(GrpcurlConfig { path: Some(grpcurl_shim().to_string_lossy().to_string()), expected_sha256: None });
+    bridge
+}
+
+/// Writes `files` (relative path, contents) below `dir`.
+pub fn write_protos(dir: &Path, files: &[(&str, &str)]) {
+    for (path, contents) in files {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+}
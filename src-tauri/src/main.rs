@@ -1,261 +1,471 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, sync::{Arc, Mutex}, path::PathBuf};
+use grpc_bridge_core::fuzz::{FuzzParams, FuzzReport};
+use grpc_bridge_core::grpcurl::GrpcurlConfig;
+use grpc_bridge_core::payload::complete::Completions;
+use grpc_bridge_core::payload::format::{self, PayloadFormat};
+use grpc_bridge_core::payload::raw::RawDecoding;
+use grpc_bridge_core::payload::sample::SamplePayload;
+use grpc_bridge_core::payload::validate::PayloadIssue;
+use grpc_bridge_core::proto_index::ProtoRoot;
+use grpc_bridge_core::redact::RedactionConfig;
+use grpc_bridge_core::runner::RunSummary;
+use grpc_bridge_core::storage::{Collection, CollectionTree, Environment, Folder, HistoryEntry, HistoryQuery, RetentionPolicy, SavedRequest, Storage, Variable};
+use grpc_bridge_core::{secrets, Bridge, BridgeError, BridgeResult, EnvironmentReport, RunParams, ServiceMeta};
+use serde::Serialize;
+use std::{path::{Path, PathBuf}, sync::Arc};
 use tauri::Emitter;
-use uuid::Uuid;
 use tracing::{info, error};
-mod proto_index;
-use proto_index::{scanner::find_proto_files, parser::parse_file, ParsedService};
-use tokio::process::Command;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct ProtoRoot { id: String, path: String, last_scan: Option<u64> }
+/// The Tauri commands are a thin layer over [`Bridge`]; what they add is event emission.
+type AppState = Arc<Bridge>;
 
-#[derive(Default, Clone)]
-struct AppState {
-    roots: Arc<Mutex<HashMap<String, ProtoRoot>>>,
-    services_by_root: Arc<Mutex<HashMap<String, Vec<ParsedService>>>>,
-    files_by_root: Arc<Mutex<HashMap<String, Vec<String>>>>,
-    active_req: Arc<Mutex<bool>>, // single unary guard
+fn emit<S: Serialize + Clone>(app: &tauri::AppHandle, event: &str, payload: S) -> BridgeResult<()> {
+    app.emit(event, payload).map_err(|e| BridgeError::Emit(e.to_string()))
 }
 
 #[tauri::command(rename_all = "snake_case")]
-async fn register_proto_root(state: tauri::State<'_, AppState>, path: String) -> Result<String, String> {
-    let id = Uuid::new_v4().to_string();
-    let root = ProtoRoot { id: id.clone(), path: path.clone(), last_scan: None };
-    state.roots.lock().unwrap().insert(id.clone(), root);
-    Ok(id)
+async fn register_proto_root(state: tauri::State<'_, AppState>, path: String) -> BridgeResult<String> {
+    state.index().register_root(&path)
 }
 
 #[tauri::command(rename_all = "snake_case")]
-async fn list_proto_roots(state: tauri::State<'_, AppState>) -> Result<Vec<ProtoRoot>, String> {
-    let roots = state.roots.lock().unwrap();
-    Ok(roots.values().cloned().collect())
+async fn list_proto_roots(state: tauri::State<'_, AppState>) -> BridgeResult<Vec<ProtoRoot>> {
+    Ok(state.index().roots())
 }
 
 #[tauri::command(rename_all = "snake_case")]
-async fn scan_proto_root(app: tauri::AppHandle, state: tauri::State<'_, AppState>, root_id: String) -> Result<(), String> {
-    app.emit("proto://index_start", serde_json::json!({"rootId": root_id}))
-        .map_err(|e| e.to_string())?;
-    let root_path = {
-        let roots = state.roots.lock().unwrap();
-        roots.get(&root_id).map(|r| r.path.clone()).ok_or_else(|| "root_not_found".to_string())?
-    };
-    let files_abs = find_proto_files(PathBuf::from(&root_path).as_path());
-    info!(?root_path, count = files_abs.len(), "scan proto root found files");
-    // convert to relative (fallback to absolute if strip fails)
-    let files: Vec<String> = files_abs.iter().map(|p| {
-        p.strip_prefix(&root_path).map(|rp| rp.to_string_lossy().to_string()).unwrap_or_else(|_| p.to_string_lossy().to_string())
-    }).collect();
-    let mut services: Vec<ParsedService> = Vec::new();
-    for (i, f_rel) in files.iter().enumerate() {
-        // need absolute path for parsing
-        let abs = files_abs.get(i).unwrap();
-    services.extend(parse_file(abs));
-    info!(file=?abs, "parsed proto file");
-        // adjust service.file to relative for UI consistency
-        for s in services.iter_mut().rev().take(10) { // small backward window; cheap heuristic
-            if s.file == abs.to_string_lossy() { s.file = f_rel.clone(); }
-        }
-    }
-    {
-        let mut map = state.services_by_root.lock().unwrap();
-        map.insert(root_id.clone(), services.clone());
-    }
-    {
-        let mut fmap = state.files_by_root.lock().unwrap();
-        fmap.insert(root_id.clone(), files.clone());
-    }
+async fn scan_proto_root(app: tauri::AppHandle, state: tauri::State<'_, AppState>, root_id: String) -> BridgeResult<()> {
+    emit(&app, "proto://index_start", serde_json::json!({"rootId": root_id}))?;
+    let scan = state.index().scan_root(&root_id).await?;
     let summary = serde_json::json!({
-        "files": files.len(),
-        "services": services.len()
+        "files": scan.files.len(),
+        "services": scan.services.len()
     });
-    app.emit("proto://index_done", serde_json::json!({
+    emit(&app, "proto://index_done", serde_json::json!({
         "rootId": root_id,
         "summary": summary,
-        "services": services,
-        "files": files
-    })).map_err(|e| e.to_string())?;
-    Ok(())
+        "services": scan.services,
+        "files": scan.files
+    }))
 }
 
 #[tauri::command(rename_all = "snake_case")]
-async fn list_proto_files(state: tauri::State<'_, AppState>, root_id: String) -> Result<Vec<String>, String> {
-    let map = state.files_by_root.lock().unwrap();
-    Ok(map.get(&root_id).cloned().unwrap_or_default())
+async fn list_proto_files(state: tauri::State<'_, AppState>, root_id: String) -> BridgeResult<Vec<String>> {
+    Ok(state.index().files(&root_id))
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct ServiceMeta { fq_service: String, file: String, methods: Vec<MethodMeta> }
-#[derive(Debug, Serialize, Deserialize)]
-struct MethodMeta { name: String, input_type: String, output_type: String, streaming: bool }
+#[tauri::command(rename_all = "snake_case")]
+async fn list_services(state: tauri::State<'_, AppState>, root_id: Option<String>) -> BridgeResult<Vec<ServiceMeta>> {
+    Ok(state.index().services(root_id.as_deref()))
+}
 
 #[tauri::command(rename_all = "snake_case")]
-async fn list_services(state: tauri::State<'_, AppState>, root_id: Option<String>) -> Result<Vec<ServiceMeta>, String> {
-    let map = state.services_by_root.lock().unwrap();
-    let roots_snapshot = state.roots.lock().unwrap();
-    let mut out = Vec::new();
-    let iter: Box<dyn Iterator<Item=(String, Vec<ParsedService>)>> = if let Some(rid) = root_id.clone() {
-        if let Some(list) = map.get(&rid) { Box::new(std::iter::once((rid, list.clone()))) } else { Box::new(std::iter::empty()) }
-    } else {
-        Box::new(map.iter().map(|(k,v)| (k.clone(), v.clone())))
-    };
-    for (rid, services) in iter {
-        if roots_snapshot.get(&rid).is_none() { continue; }
-        for ps in services {
-            out.push(ServiceMeta {
-                fq_service: ps.fq_service.clone(),
-                file: ps.file.clone(),
-                methods: ps.methods.iter().map(|m| MethodMeta { name: m.name.clone(), input_type: m.input_type.clone(), output_type: m.output_type.clone(), streaming: m.streaming }).collect()
-            });
-        }
-    }
-    Ok(out)
-}
-
-#[tauri::command(rename_all = "snake_case")]
-async fn get_method_skeleton(state: tauri::State<'_, AppState>, fq_service: String, method: String) -> Result<String, String> {
-    // Find method input type from cached services
-    let map = state.services_by_root.lock().unwrap();
-    for (_rid, services) in map.iter() {
-        if let Some(svc) = services.iter().find(|s| s.fq_service == fq_service) {
-            if let Some(m) = svc.methods.iter().find(|m| m.name == method) {
-                let mut skel = serde_json::json!({
-                    "//": format!("Skeleton for {} (streaming:{}). Add real fields.", m.input_type, m.streaming)
-                });
-                // Provide an empty object for root suggestion
-                if let serde_json::Value::Object(obj) = &mut skel { obj.insert("body".into(), serde_json::json!({})); }
-                return Ok(serde_json::to_string_pretty(&skel).unwrap());
-            }
-        }
-    }
-    Err("method_not_found".into())
+async fn get_method_skeleton(state: tauri::State<'_, AppState>, root_id: Option<String>, fq_service: String, method: String, max_depth: Option<usize>) -> BridgeResult<String> {
+    state.index().method_skeleton(root_id.as_deref(), &fq_service, &method, max_depth)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn generate_sample_payload(state: tauri::State<'_, AppState>, root_id: Option<String>, fq_service: String, method: String, seed: Option<u64>) -> BridgeResult<SamplePayload> {
+    state.index().sample_payload(root_id.as_deref(), &fq_service, &method, seed)
 }
 
-#[derive(Debug, Deserialize)]
-struct RunParams {
-    target: String,
-    service: String,
-    method: String,
-    payload: String,
-    proto_files: Vec<String>,
-    #[serde(alias = "rootId")] root_id: Option<String>,
-    headers: Option<Vec<String>>, // "Key: Value"
+#[tauri::command(rename_all = "snake_case")]
+async fn validate_payload(state: tauri::State<'_, AppState>, root_id: Option<String>, fq_service: String, method: String, payload: String) -> BridgeResult<Vec<PayloadIssue>> {
+    state.index().validate_payload(root_id.as_deref(), &fq_service, &method, &payload)
 }
 
 #[tauri::command(rename_all = "snake_case")]
-async fn run_grpc_call(app: tauri::AppHandle, state: tauri::State<'_, AppState>, params: RunParams) -> Result<(), String> {
-    // Acquire and set busy flag
-    let active_arc = {
-        let mut busy = state.active_req.lock().unwrap();
-        if *busy { return Err("request_already_running".into()); }
-        *busy = true;
-        state.active_req.clone()
-    };
-    let started = std::time::Instant::now();
-    // Sanitize target: remove surrounding whitespace, optional scheme, stray leading slashes
-    let mut target = params.target.trim().to_string();
-    if target.starts_with("http://") { target = target["http://".len()..].to_string(); }
-    if target.starts_with("https://") { target = target["https://".len()..].to_string(); }
-    // Remove any leading spaces again (in case after scheme removal) and single leading slash
-    target = target.trim().trim_start_matches('/').to_string();
-    // Remove trailing slash (but keep :port if present)
-    if target.ends_with('/') { target.pop(); }
-    // If accidental internal spaces exist, fail early with clearer message
-    if target.contains(' ') { return Err("invalid_target_whitespace".into()); }
-    let service = params.service.clone();
-    let method = params.method.clone();
-    let payload = params.payload.clone();
-    let headers = params.headers.clone().unwrap_or_default();
-    let proto_files = params.proto_files.clone();
-    let root_dir = if let Some(rid) = params.root_id.clone() {
-        let roots = state.roots.lock().unwrap();
-        roots.get(&rid).map(|r| r.path.clone())
-    } else { None };
+async fn complete_payload(state: tauri::State<'_, AppState>, root_id: Option<String>, fq_service: String, method: String, payload: String, cursor_offset: usize) -> BridgeResult<Completions> {
+    state.index().complete_payload(root_id.as_deref(), &fq_service, &method, &payload, cursor_offset)
+}
 
+#[tauri::command(rename_all = "snake_case")]
+async fn export_json_schema(state: tauri::State<'_, AppState>, fq_message: String) -> BridgeResult<serde_json::Value> {
+    state.index().json_schema(&fq_message)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn decode_raw(state: tauri::State<'_, AppState>, data: String, format: Option<PayloadFormat>, fq_message: Option<String>) -> BridgeResult<RawDecoding> {
+    let format = format.unwrap_or(PayloadFormat::BinaryBase64);
+    let bytes = format::decode_binary(format, &data).map_err(|reason| BridgeError::PayloadFormat { format: format.to_string(), reason })?;
+    state.index().decode_raw(&bytes, fq_message.as_deref())
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn run_grpc_call(app: tauri::AppHandle, state: tauri::State<'_, AppState>, params: RunParams) -> BridgeResult<()> {
+    start_call(app, state.inner().clone(), params).await
+}
+
+/// Validates `params`, then runs the call in the background and reports it through
+/// `grpc://response` / `grpc://error`. Every command that sends a single request goes through here.
+async fn start_call(app: tauri::AppHandle, state: AppState, params: RunParams) -> BridgeResult<()> {
+    let call = state.prepare_call(params).await?;
+    let busy = state.engine().acquire()?;
     tokio::spawn(async move {
-    let mut cmd = Command::new("grpcurl");
-    // Log sanitized target
-    info!(target = %target, "sanitized target for grpcurl");
-        if let Some(rp) = &root_dir { cmd.arg("-import-path").arg(rp); }
-        for f in &proto_files { cmd.arg("-proto").arg(f); }
-        for h in headers { cmd.arg("-H").arg(h); }
-        // 빈 payload인 경우 빈 JSON 객체 사용
-        let effective_payload = if payload.trim().is_empty() { "{}" } else { &payload };
-        cmd.arg("-d").arg(effective_payload);
-    cmd.arg(&target);
-        cmd.arg(format!("{}.{}", service, method));
-        info!(?cmd, "spawning grpcurl");
-        let emit_result = async {
-            match cmd.output().await {
-                Ok(out) => {
-                    if out.status.success() {
-                        let text = String::from_utf8_lossy(&out.stdout).to_string();
-                        let took = started.elapsed().as_millis();
-                        let json_attempt: Result<serde_json::Value, _> = serde_json::from_str(&text);
-                        let payload = serde_json::json!({
-                            "raw": text,
-                            "parsed": json_attempt.ok(),
-                            "took_ms": took
-                        });
-                        if let Err(e) = app.emit("grpc://response", payload) { error!(?e, "emit response failed"); }
-                    } else {
-                        let stderr_txt = String::from_utf8_lossy(&out.stderr).to_string();
-                        let took = started.elapsed().as_millis();
-                        let lowered = stderr_txt.to_lowercase();
-                        let kind = if lowered.contains("unknown service") { "unknown_service" }
-                            else if lowered.contains("unknown method") { "unknown_method" }
-                            else if lowered.contains("failed to dial") { "dial_failure" }
-                            else if lowered.contains("deadline exceeded") || lowered.contains("context deadline exceeded") { "timeout" }
-                            else if lowered.contains("permission denied") { "permission_denied" }
-                            else if lowered.contains("unauthenticated") { "unauthenticated" }
-                            else if lowered.contains("unavailable") { "unavailable" }
-                            else { "error" };
-                        let errp = serde_json::json!({
-                            "error": stderr_txt,
-                            "exit_code": out.status.code(),
-                            "took_ms": took,
-                            "kind": kind
-                        });
-                        let _ = app.emit("grpc://error", errp);
-                    }
+        let _busy = busy;
+        let done = state.perform_call(&call).await;
+        match &done.result {
+            Ok(outcome) => match outcome.error() {
+                None => {
+                    let payload = serde_json::json!({
+                        "raw": done.raw,
+                        "parsed": done.parsed,
+                        "took_ms": outcome.took_ms,
+                        "status": outcome.status,
+                        "headers": done.headers,
+                        "trailers": done.trailers,
+                        "history_id": done.history_id,
+                        "extracted": done.extracted,
+                        "extraction_errors": done.extraction_errors,
+                        "rendered": done.rendered,
+                        "render_error": done.render_error
+                    });
+                    if let Err(e) = app.emit("grpc://response", payload) { error!(?e, "emit response failed"); }
                 }
-                Err(e) => {
-                    let _ = app.emit("grpc://error", serde_json::json!({"error": e.to_string()}));
+                Some(err) => {
+                    let mut ev = error_event(&err, &outcome.stderr, outcome.took_ms);
+                    ev["status"] = outcome.status.clone().into();
+                    ev["trailers"] = serde_json::json!(done.trailers);
+                    ev["history_id"] = serde_json::json!(done.history_id);
+                    let _ = app.emit("grpc://error", ev);
                 }
+            },
+            Err(err) => {
+                let mut ev = error_event(err, &err.to_string(), done.took_ms);
+                ev["history_id"] = serde_json::json!(done.history_id);
+                let _ = app.emit("grpc://error", ev);
             }
-        };
-        emit_result.await;
-        // release busy flag
-        if let Ok(mut busy) = active_arc.lock() { *busy = false; }
+        }
     });
     Ok(())
 }
 
 #[tauri::command(rename_all = "snake_case")]
-async fn remove_proto_root(state: tauri::State<'_, AppState>, root_id: String) -> Result<(), String> {
-    // Remove from all related data structures
-    state.roots.lock().unwrap().remove(&root_id);
-    state.services_by_root.lock().unwrap().remove(&root_id);
-    state.files_by_root.lock().unwrap().remove(&root_id);
-    info!(root_id = %root_id, "removed proto root and associated data");
-    Ok(())
+async fn list_history(state: tauri::State<'_, AppState>, query: Option<HistoryQuery>) -> BridgeResult<Vec<HistoryEntry>> {
+    state.storage().list_history(&query.unwrap_or_default())
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn get_history_entry(state: tauri::State<'_, AppState>, id: i64) -> BridgeResult<HistoryEntry> {
+    state.storage().get_history(id)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn delete_history(state: tauri::State<'_, AppState>, ids: Vec<i64>) -> BridgeResult<usize> {
+    state.storage().delete_history(&ids)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn clear_history(state: tauri::State<'_, AppState>) -> BridgeResult<usize> {
+    state.storage().clear_history()
+}
+
+/// Sends a history entry again. Header values that were redacted when the entry was stored
+/// are dropped; pass them in `headers` (`"Key: Value"`, replacing entries with the same key).
+/// Redacted payload fields fail with `redacted_payload` until given in `payload_values`.
+#[tauri::command(rename_all = "snake_case")]
+async fn replay_history(app: tauri::AppHandle, state: tauri::State<'_, AppState>, id: i64, headers: Option<Vec<String>>, payload_values: Option<serde_json::Map<String, serde_json::Value>>) -> BridgeResult<()> {
+    let params = state.replay_params(id, headers.unwrap_or_default(), payload_values.unwrap_or_default())?;
+    start_call(app, state.inner().clone(), params).await
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn get_history_retention(state: tauri::State<'_, AppState>) -> BridgeResult<RetentionPolicy> {
+    state.storage().retention_policy()
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn set_history_retention(state: tauri::State<'_, AppState>, policy: RetentionPolicy) -> BridgeResult<()> {
+    state.storage().set_retention_policy(&policy)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn list_collections(state: tauri::State<'_, AppState>) -> BridgeResult<Vec<CollectionTree>> {
+    state.storage().list_collections()
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn create_collection(state: tauri::State<'_, AppState>, name: String) -> BridgeResult<Collection> {
+    state.storage().create_collection(&name)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn rename_collection(state: tauri::State<'_, AppState>, id: String, name: String) -> BridgeResult<()> {
+    state.storage().rename_collection(&id, &name)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn delete_collection(state: tauri::State<'_, AppState>, id: String) -> BridgeResult<()> {
+    state.storage().delete_collection(&id)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn create_folder(state: tauri::State<'_, AppState>, collection_id: String, parent_id: Option<String>, name: String) -> BridgeResult<Folder> {
+    state.storage().create_folder(&collection_id, parent_id.as_deref(), &name)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn rename_folder(state: tauri::State<'_, AppState>, id: String, name: String) -> BridgeResult<()> {
+    state.storage().rename_folder(&id, &name)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn move_folder(state: tauri::State<'_, AppState>, id: String, collection_id: String, parent_id: Option<String>) -> BridgeResult<()> {
+    state.storage().move_folder(&id, &collection_id, parent_id.as_deref())
+}
+
+/// Moves a folder to `index` among its siblings, which sets the order `run_collection` uses.
+#[tauri::command(rename_all = "snake_case")]
+async fn reorder_folder(state: tauri::State<'_, AppState>, id: String, index: usize) -> BridgeResult<()> {
+    state.storage().reorder_folder(&id, index)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn delete_folder(state: tauri::State<'_, AppState>, id: String) -> BridgeResult<()> {
+    state.storage().delete_folder(&id)
+}
+
+/// Creates a saved request (empty `id`) or updates an existing one.
+#[tauri::command(rename_all = "snake_case")]
+async fn save_request(state: tauri::State<'_, AppState>, request: SavedRequest) -> BridgeResult<SavedRequest> {
+    state.storage().save_request(&request)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn rename_request(state: tauri::State<'_, AppState>, id: String, name: String) -> BridgeResult<()> {
+    state.storage().rename_request(&id, &name)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn move_request(state: tauri::State<'_, AppState>, id: String, collection_id: String, folder_id: Option<String>) -> BridgeResult<()> {
+    state.storage().move_request(&id, &collection_id, folder_id.as_deref())
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn reorder_request(state: tauri::State<'_, AppState>, id: String, index: usize) -> BridgeResult<()> {
+    state.storage().reorder_request(&id, index)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn duplicate_request(state: tauri::State<'_, AppState>, id: String) -> BridgeResult<SavedRequest> {
+    state.storage().duplicate_request(&id)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn delete_request(state: tauri::State<'_, AppState>, id: String) -> BridgeResult<()> {
+    state.storage().delete_request(&id)
+}
+
+/// Sends a saved request through the same path as `run_grpc_call`.
+#[tauri::command(rename_all = "snake_case")]
+async fn run_saved_request(app: tauri::AppHandle, state: tauri::State<'_, AppState>, id: String, environment_id: Option<String>) -> BridgeResult<()> {
+    let req = state.storage().get_saved_request(&id)?;
+    let params = RunParams { environment_id, ..RunParams::from(req) };
+    start_call(app, state.inner().clone(), params).await
+}
+
+/// Runs the requests of a collection (or one folder of it) in order, emitting
+/// `collection://step` after each.
+#[tauri::command(rename_all = "snake_case")]
+async fn run_collection(app: tauri::AppHandle, state: tauri::State<'_, AppState>, collection_id: String, folder_id: Option<String>, environment_id: Option<String>) -> BridgeResult<RunSummary> {
+    state.run_collection(&collection_id, folder_id.as_deref(), environment_id, |step| {
+        if let Err(e) = app.emit("collection://step", step) { error!(?e, "emit step failed"); }
+    }).await
+}
+
+/// Sends mutated payloads to a method, emitting `fuzz://case` after each; reproducers of the
+/// findings are saved to a collection.
+#[tauri::command(rename_all = "snake_case")]
+async fn run_fuzz(app: tauri::AppHandle, state: tauri::State<'_, AppState>, params: FuzzParams) -> BridgeResult<FuzzReport> {
+    state.fuzz(params, |case| {
+        if let Err(e) = app.emit("fuzz://case", case) { error!(?e, "emit fuzz case failed"); }
+    }).await
+}
+
+/// Renders a `run_collection` summary as JUnit XML for CI.
+#[tauri::command(rename_all = "snake_case")]
+async fn export_junit(summary: RunSummary) -> BridgeResult<String> {
+    Ok(summary.to_junit())
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn list_environments(state: tauri::State<'_, AppState>) -> BridgeResult<Vec<Environment>> {
+    state.storage().list_environments()
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn create_environment(state: tauri::State<'_, AppState>, name: String) -> BridgeResult<Environment> {
+    state.storage().create_environment(&name)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn rename_environment(state: tauri::State<'_, AppState>, id: String, name: String) -> BridgeResult<()> {
+    state.storage().rename_environment(&id, &name)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn delete_environment(state: tauri::State<'_, AppState>, id: String) -> BridgeResult<()> {
+    state.workspace().delete_environment(&id)
+}
+
+/// Replaces all variables of an environment. Secret values go to the secret store; a secret
+/// sent with an empty value keeps the value stored earlier. Secrets are never returned.
+#[tauri::command(rename_all = "snake_case")]
+async fn set_environment_variables(state: tauri::State<'_, AppState>, id: String, variables: Vec<Variable>) -> BridgeResult<Environment> {
+    state.workspace().set_environment_variables(&id, &variables)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn get_active_environment(state: tauri::State<'_, AppState>) -> BridgeResult<Option<String>> {
+    state.storage().active_environment()
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn set_active_environment(state: tauri::State<'_, AppState>, id: Option<String>) -> BridgeResult<()> {
+    state.storage().set_active_environment(id.as_deref())
+}
+
+/// Directory holding Tauri sidecar binaries (next to the main executable).
+fn sidecar_dir() -> Option<PathBuf> {
+    std::env::current_exe().ok()?.parent().map(Path::to_path_buf)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn check_environment(state: tauri::State<'_, AppState>) -> BridgeResult<EnvironmentReport> {
+    state.engine().check_environment().await
+}
+
+/// Where saved data goes. `storage` is `"memory"` when the database could not be opened or
+/// its saved settings and scans could not be read; nothing saved then survives a restart, and
+/// the UI warns before the user relies on it.
+#[derive(Debug, Clone, Serialize)]
+struct StorageStatus {
+    storage: &'static str,
+    path: Option<String>,
+    error: Option<String>,
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn get_storage_status(status: tauri::State<'_, StorageStatus>) -> BridgeResult<StorageStatus> {
+    Ok(status.inner().clone())
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn get_grpcurl_config(state: tauri::State<'_, AppState>) -> BridgeResult<GrpcurlConfig> {
+    Ok(state.engine().grpcurl_config())
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn set_grpcurl_config(state: tauri::State<'_, AppState>, config: GrpcurlConfig) -> BridgeResult<()> {
+    state.set_grpcurl_config(config)
+}
+
+/// Payload of `grpc://error`: the serialized [`BridgeError`] plus the legacy `error`/`kind`/`took_ms` fields.
+fn error_event(err: &BridgeError, raw: &str, took_ms: u128) -> serde_json::Value {
+    let mut v = serde_json::to_value(err).unwrap_or_default();
+    if let serde_json::Value::Object(obj) = &mut v {
+        obj.insert("error".into(), raw.into());
+        obj.insert("kind".into(), err.code().into());
+        obj.insert("took_ms".into(), serde_json::json!(took_ms));
+        if let BridgeError::CallFailed { exit_code, .. } = err { obj.insert("exit_code".into(), serde_json::json!(exit_code)); }
+    }
+    v
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn get_redaction_config(state: tauri::State<'_, AppState>) -> BridgeResult<RedactionConfig> {
+    Ok(state.engine().redaction_config())
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn set_redaction_config(state: tauri::State<'_, AppState>, config: RedactionConfig) -> BridgeResult<()> {
+    state.set_redaction_config(config)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+async fn remove_proto_root(state: tauri::State<'_, AppState>, root_id: String) -> BridgeResult<()> {
+    state.index().remove_root(&root_id)
 }
 
 fn main() {
     tracing_subscriber::fmt().with_env_filter("info").init();
+    let path = Storage::default_path();
+    let open_secrets = || {
+        let store = secrets::open_default(path.as_deref().and_then(Path::parent), secrets::default_key_dir().as_deref());
+        info!(backend = store.name(), "secret store ready");
+        store
+    };
+    let loaded = path.as_deref()
+        .ok_or_else(|| BridgeError::Storage("no app data directory".into()))
+        .and_then(Storage::open)
+        .and_then(|storage| Bridge::load(storage, open_secrets(), sidecar_dir()));
+    let shown_path = path.as_ref().map(|p| p.display().to_string());
+    let (state, storage_status) = match loaded {
+        Ok(state) => (state, StorageStatus { storage: "disk", path: shown_path, error: None }),
+        Err(e) => {
+            error!(%e, "cannot open or load storage, falling back to an in-memory database");
+            let storage = Storage::open_in_memory().expect("in-memory sqlite");
+            let state = Bridge::load(storage, open_secrets(), sidecar_dir()).expect("empty in-memory state");
+            (state, StorageStatus { storage: "memory", path: shown_path, error: Some(e.to_string()) })
+        }
+    };
     tauri::Builder::default()
-        .manage(AppState { roots: Default::default(), services_by_root: Default::default(), files_by_root: Default::default(), active_req: Default::default() })
+        .manage(Arc::new(state))
+        .manage(storage_status)
         .invoke_handler(tauri::generate_handler![
             register_proto_root,
             list_proto_roots,
             scan_proto_root,
             list_services,
             get_method_skeleton,
+            generate_sample_payload,
+            validate_payload,
+            complete_payload,
+            export_json_schema,
+            decode_raw,
             run_grpc_call,
             remove_proto_root,
-            list_proto_files
+            list_proto_files,
+            get_redaction_config,
+            set_redaction_config,
+            check_environment,
+            get_storage_status,
+            get_grpcurl_config,
+            set_grpcurl_config,
+            list_history,
+            get_history_entry,
+            delete_history,
+            clear_history,
+            replay_history,
+            get_history_retention,
+            set_history_retention,
+            list_collections,
+            create_collection,
+            rename_collection,
+            delete_collection,
+            create_folder,
+            rename_folder,
+            move_folder,
+            reorder_folder,
+            delete_folder,
+            save_request,
+            rename_request,
+            move_request,
+            reorder_request,
+            duplicate_request,
+            delete_request,
+            run_saved_request,
+            list_environments,
+            create_environment,
+            rename_environment,
+            delete_environment,
+            set_environment_variables,
+            get_active_environment,
+            set_active_environment,
+            run_collection,
+            export_junit,
+            run_fuzz
         ])
     .run(tauri::generate_context!())
         .expect("error running tauri application");
@@ -9,6 +9,7 @@ import { useProtoFiles } from '@/state/protoFiles';
 import { invoke } from '@tauri-apps/api/core';
 import { ThemeProvider } from '@/context/ThemeContext';
 import '@/i18n'; // Initialize i18n
+import { formatBridgeError } from '@/lib/errors';
 
 const App: React.FC = () => {
   const setBusy = useRequestStore(s => s.setBusy);
@@ -39,7 +40,7 @@ const App: React.FC = () => {
     listen('grpc://error', (e: any) => {
       setBusy(false);
       setLastResponse({ ok: false, data: e.payload, at: Date.now() });
-      toast.error(e.payload.error || 'Request failed');
+      toast.error(formatBridgeError(e.payload) || 'Request failed');
       const p = e.payload as any;
       updatePendingHistory(false, p.took_ms);
     }).then(f => unlisten.push(f));
@@ -63,6 +64,15 @@ const App: React.FC = () => {
         toast.error('List services failed');
       }
     }).then(f => unlisten.push(f));
+    // nothing saved survives a restart when the database could not be opened
+    (async () => {
+      try {
+        const status = await invoke<any>('get_storage_status');
+        if (status.storage === 'memory') {
+          toast.error(`Storage unavailable, changes will be lost on exit: ${status.error}`, { duration: Infinity });
+        }
+      } catch {}
+    })();
     // initial roots fetch
     (async () => {
       try {
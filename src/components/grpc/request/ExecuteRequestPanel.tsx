@@ -7,6 +7,7 @@ import { useRequestStore } from '@/state/request';
 import { useHistoryStore } from '@/state/history';
 import { useProtoFiles } from '@/state/protoFiles';
 import toast from 'react-hot-toast';
+import { formatBridgeError } from '@/lib/errors';
 
 export const ExecuteRequestPanel: React.FC = () => {
   const { t } = useTranslation();
@@ -86,7 +87,7 @@ export const ExecuteRequestPanel: React.FC = () => {
         },
       });
     } catch (e: any) {
-      toast.error(e.toString());
+      toast.error(formatBridgeError(e));
       setBusy(false);
     }
   };
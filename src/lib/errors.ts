@@ -0,0 +1,22 @@
+import i18n from '@/i18n';
+
+// Shape of errors returned by Tauri commands and `grpc://error` events.
+export interface BridgeError {
+  code: string;
+  message: string;
+  details?: Record<string, unknown> | null;
+  retryable: boolean;
+}
+
+export function isBridgeError(e: unknown): e is BridgeError {
+  return typeof e === 'object' && e !== null && 'code' in e && 'message' in e;
+}
+
+// Localize a backend error by its stable code, falling back to the English message.
+export function formatBridgeError(e: unknown): string {
+  if (!isBridgeError(e)) return String(e);
+  return i18n.t(`errors.codes.${e.code}`, {
+    defaultValue: e.message,
+    ...(e.details ?? {}),
+  });
+}